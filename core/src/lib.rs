@@ -0,0 +1,14 @@
+// The part of the program an indexer or a browser needs to follow the canvas: building, signing
+// and decoding transactions and batches, applying them to accounts and checking what the proofs
+// commit to. Re-exported as is, so values move between this crate and `program` freely.
+
+pub use program::{
+    batch, builder, codec, commitment, config, edit, error, genesis, metadata, namespace, proof,
+    public_values, receipt, signing, smt, snapshot, undo, witness,
+};
+
+pub use program::{
+    decompress_transactions, recover_address_from_tx, Account, AccountDB, CanvasProcessor,
+    InMemoryDB, PublicValuesStruct, SignedTransaction, Transaction, TransactionEffect, WriteBatch,
+    MAX_SIZE, MAX_VALUE, TX_VERSION_LATEST,
+};
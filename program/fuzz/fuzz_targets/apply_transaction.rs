@@ -0,0 +1,177 @@
+#![no_main]
+
+use alloy_primitives::{Address, B256};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use program::{
+    commitment::CommitmentVersion, config::CanvasConfig, edit::EditOp, signing::signing_hash,
+    validator::AnyValue, Account, AccountDB, CanvasProcessor, Data, InMemoryDB, SignedTransaction,
+    Transaction, TX_VERSION_DOMAIN, TX_VERSION_LEGACY,
+};
+
+// Indices are kept small so most of them land in or near the data.
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Insert {
+        index: u16,
+        value: String,
+    },
+    Delete {
+        index: u16,
+        count: u16,
+    },
+    Replace {
+        index: u16,
+        count: u16,
+        value: String,
+    },
+    Append {
+        value: String,
+    },
+    Claim,
+    Copy {
+        own: bool,
+        index: u16,
+        count: u16,
+        at: u16,
+    },
+    Move {
+        own: bool,
+        index: u16,
+        count: u16,
+        at: u16,
+    },
+}
+
+#[derive(Debug, Arbitrary)]
+struct Case {
+    data: String,
+    source_data: String,
+    legacy: Vec<(u16, u16, String)>,
+    ops: Vec<Op>,
+    // Edits the sender's own account instead of another one.
+    own: bool,
+}
+
+const OTHER: Address = Address::with_last_byte(0xca);
+const SOURCE: Address = Address::with_last_byte(0x5e);
+
+fn op(op: Op, sender: Address) -> EditOp {
+    let source = |own| if own { sender } else { SOURCE };
+    match op {
+        Op::Insert { index, value } => EditOp::Insert {
+            index: index as usize,
+            value,
+        },
+        Op::Delete { index, count } => EditOp::Delete {
+            index: index as usize,
+            count: count as usize,
+        },
+        Op::Replace {
+            index,
+            count,
+            value,
+        } => EditOp::Replace {
+            index: index as usize,
+            count: count as usize,
+            value,
+        },
+        Op::Append { value } => EditOp::Append { value },
+        Op::Claim => EditOp::Claim,
+        Op::Copy {
+            own,
+            index,
+            count,
+            at,
+        } => EditOp::Copy {
+            source: source(own),
+            index: index as usize,
+            count: count as usize,
+            at: at as usize,
+        },
+        Op::Move {
+            own,
+            index,
+            count,
+            at,
+        } => EditOp::Move {
+            source: source(own),
+            index: index as usize,
+            count: count as usize,
+            at: at as usize,
+        },
+    }
+}
+
+// Applies a legacy transaction of the `Data` edits and then one of the ops, both signed, to
+// accounts holding arbitrary data. Either may fail; neither may panic, and a failed one must
+// leave the state as it was.
+fuzz_target!(|case: Case| {
+    let signer = PrivateKeySigner::from_bytes(&B256::with_last_byte(1)).unwrap();
+    let sender = signer.address();
+    let to = if case.own { sender } else { OTHER };
+
+    let db = InMemoryDB::default();
+    for (address, data) in [(to, case.data), (SOURCE, case.source_data)] {
+        let account = Account {
+            data,
+            ..Account::default()
+        };
+        db.set_account(&address, &account).unwrap();
+    }
+    // Any value, so the edit path is reached with every kind of char, under a commitment that
+    // binds owners so claims apply.
+    let config = CanvasConfig {
+        commitment: CommitmentVersion::V3,
+        ..CanvasConfig::default()
+    };
+    let mut processor = CanvasProcessor::with_config(&db, config).with_validator(AnyValue);
+
+    let legacy = Transaction {
+        to,
+        version: TX_VERSION_LEGACY,
+        data: case
+            .legacy
+            .into_iter()
+            .map(|(index, count, value)| Data {
+                index: index as usize,
+                count: count as usize,
+                value,
+            })
+            .collect(),
+        ops: Vec::new(),
+        nonce: 0,
+        extra: String::new(),
+        valid_until_batch: 0,
+        valid_until_timestamp: 0,
+        canvas: 0,
+        access_list: Vec::new(),
+    };
+    let ops = Transaction {
+        version: TX_VERSION_DOMAIN,
+        data: Vec::new(),
+        ops: case.ops.into_iter().map(|o| op(o, sender)).collect(),
+        ..legacy.clone()
+    };
+
+    for mut tx in [legacy, ops] {
+        tx.nonce = db.get_account(&sender).unwrap().nonce;
+        let signature = signer
+            .sign_hash_sync(&signing_hash(&tx, &CanvasConfig::default().domain))
+            .unwrap();
+        let signed = SignedTransaction {
+            tx,
+            r: signature.r(),
+            s: signature.s(),
+            odd_y_parity: signature.v().y_parity(),
+            sponsor: None,
+        };
+
+        let before = db.accounts.borrow().clone();
+        if processor.apply_transaction(&signed).is_err() {
+            assert_eq!(*db.accounts.borrow(), before);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use program::{codec::decode_transactions, config::CanvasConfig, recover_address_from_tx};
+
+// Whatever a batch submitter posts, decoding it and reading its transactions must fail with an
+// error rather than panic or run out of memory.
+fuzz_target!(|transaction_data: &[u8]| {
+    let Ok(transactions) = decode_transactions(transaction_data, None) else {
+        return;
+    };
+    let domain = CanvasConfig::default().domain;
+    for tx in &transactions {
+        let _ = tx.tx.edit_ops();
+        let _ = tx.tx.sources();
+        let _ = recover_address_from_tx(tx, &domain);
+    }
+});
@@ -0,0 +1,124 @@
+use alloy_primitives::Address;
+use std::fmt;
+
+use crate::commitment::CommitmentVersion;
+use crate::Account;
+
+/// Who may change an account. Unclaimed accounts (`owner` zero) stay open for anyone to edit, as
+/// every account was before, and are owned by their own address for schemas and permissions.
+/// Once claimed, only the owner and the writers it granted may edit the data.
+///
+/// An `owner` or `writers` the commitment version does not commit is not bound by the state
+/// root, so a prover could supply any; the checks read them as unset instead, and the ops that
+/// set them are rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    /// `sender` tried to claim an account that already has an owner, or whose own key has sent
+    /// transactions.
+    NotClaimable { account: Address, sender: Address },
+    /// `sender` tried to transfer or change the writers of an account it does not own.
+    NotOwner { account: Address, sender: Address },
+    /// `sender` tried to edit a claimed account it may not write.
+    NotWriter { account: Address, sender: Address },
+    /// A claim or transfer under a commitment version that does not commit owners.
+    OwnerNotCommitted,
+    /// A grant or revoke under a commitment version that does not commit writers.
+    WritersNotCommitted,
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::NotClaimable { account, sender } => {
+                write!(f, "{} cannot claim account {}", sender, account)
+            }
+            AccessError::NotOwner { account, sender } => {
+                write!(f, "{} does not own account {}", sender, account)
+            }
+            AccessError::NotWriter { account, sender } => {
+                write!(f, "{} may not write account {}", sender, account)
+            }
+            AccessError::OwnerNotCommitted => write!(f, "Owners need commitment version 1"),
+            AccessError::WritersNotCommitted => write!(f, "Writers need commitment version 3"),
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+/// The owner of `account` at `address`: its `owner`, or its own address while unclaimed.
+pub fn effective_owner(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+) -> Address {
+    match owner(version, account) {
+        Address::ZERO => *address,
+        owner => owner,
+    }
+}
+
+/// Makes `sender` the owner of the unclaimed `account`. An account whose own key has sent a
+/// transaction can only be claimed by that key.
+pub fn claim(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &mut Account,
+    sender: &Address,
+) -> Result<(), AccessError> {
+    if !version.commits_owner() {
+        return Err(AccessError::OwnerNotCommitted);
+    }
+    if account.owner != Address::ZERO || (account.nonce > 0 && sender != address) {
+        return Err(AccessError::NotClaimable {
+            account: *address,
+            sender: *sender,
+        });
+    }
+
+    account.owner = *sender;
+    Ok(())
+}
+
+pub fn check_owner(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+    sender: &Address,
+) -> Result<(), AccessError> {
+    if effective_owner(version, address, account) != *sender {
+        return Err(AccessError::NotOwner {
+            account: *address,
+            sender: *sender,
+        });
+    }
+    Ok(())
+}
+
+pub fn check_writer(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+    sender: &Address,
+) -> Result<(), AccessError> {
+    let owner = owner(version, account);
+    if owner == Address::ZERO
+        || owner == *sender
+        || (version.commits_writers() && account.writers.contains(sender))
+    {
+        return Ok(());
+    }
+
+    Err(AccessError::NotWriter {
+        account: *address,
+        sender: *sender,
+    })
+}
+
+// The `owner` of `account` as far as `version` commits it.
+fn owner(version: CommitmentVersion, account: &Account) -> Address {
+    match version.commits_owner() {
+        true => account.owner,
+        false => Address::ZERO,
+    }
+}
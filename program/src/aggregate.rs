@@ -0,0 +1,132 @@
+use alloy_primitives::{keccak256, B256};
+use alloy_sol_types::sol;
+use serde::{Deserialize, Serialize};
+
+use crate::public_values::{decode_public_values, ClaimedValues};
+
+sol! {
+    struct AggregatePublicValuesStruct {
+        bytes32 initialStateRoot;
+        bytes32 finalStateRoot;
+        bytes32 transactionCommit;
+        uint64 firstBatchIndex;
+        uint64 batchCount;
+        uint64 l1BlockNumber;
+        bytes32 l1BlockHash;
+        uint64 firstBatchTimestamp;
+        uint64 lastBatchTimestamp;
+        bytes32 previousBatchHash;
+        bytes32 batchHash;
+    }
+}
+
+/// What the aggregation program reads: the verifying key digest of the batch program and the
+/// public values of each batch proof it verifies, in batch order. The proofs themselves go to
+/// the zkVM alongside.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateInput {
+    pub vkey: [u32; 8],
+    pub public_values: Vec<Vec<u8>>,
+}
+
+/// Folds a batch's transaction commit into the commit of the batches before it, starting from
+/// zero: `keccak256(rolling || transaction_commit)`. The contract derives the same value from
+/// the posted transaction data.
+pub fn roll_commit(rolling: B256, transaction_commit: B256) -> B256 {
+    keccak256([rolling.as_slice(), transaction_commit.as_slice()].concat())
+}
+
+impl AggregateInput {
+    /// Checks that the batches follow each other, each starting from the root and building on
+    /// the transaction commit the one before ended with and none built before the one before
+    /// it, and returns what the aggregate proof commits. It is anchored to the L1 block of the
+    /// last batch, spans the timestamps of the first and last batch, and chains from the batch
+    /// hash the first batch chained from to the one the last batch ended with.
+    ///
+    /// Batches that consume deposits or forced transactions are rejected: the contract checks
+    /// those against its queues per batch, so they are submitted alone.
+    pub fn process(&self) -> eyre::Result<AggregatePublicValuesStruct> {
+        let mut batches = Vec::with_capacity(self.public_values.len());
+        for bytes in &self.public_values {
+            let claimed = decode_public_values(bytes)?;
+            if claimed.forced_count.is_none() {
+                return Err(eyre::eyre!("Batch proof predates aggregation"));
+            }
+            batches.push(claimed);
+        }
+
+        let (first, last) = match (batches.first(), batches.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(eyre::eyre!("No batches to aggregate")),
+        };
+        let first_index = first.batch_index.unwrap_or_default();
+
+        let mut previous: Option<&ClaimedValues> = None;
+        let mut rolling = B256::ZERO;
+        for (i, batch) in batches.iter().enumerate() {
+            let index = first_index + i as u64;
+            if batch.batch_index != Some(index) {
+                return Err(eyre::eyre!("Expected batch {}", index));
+            }
+            if batch.deposit_count != Some(0) || batch.forced_count != Some(0) {
+                return Err(eyre::eyre!(
+                    "Batch {} consumes L1 queue messages and has to be submitted alone",
+                    index
+                ));
+            }
+            if let Some(previous) = previous {
+                if batch.initial_state_root != previous.final_state_root {
+                    return Err(eyre::eyre!(
+                        "Batch {} does not start from the root batch {} ended with",
+                        index,
+                        index - 1
+                    ));
+                }
+                if batch.previous_batch != Some(previous.transaction_commit) {
+                    return Err(eyre::eyre!(
+                        "Batch {} does not build on batch {}",
+                        index,
+                        index - 1
+                    ));
+                }
+                if batch.previous_batch_hash.unwrap_or_default()
+                    != previous.batch_hash.unwrap_or_default()
+                {
+                    return Err(eyre::eyre!(
+                        "Batch {} does not chain from batch {}",
+                        index,
+                        index - 1
+                    ));
+                }
+                if batch.batch_timestamp < previous.batch_timestamp
+                    || batch
+                        .previous_batch_timestamp
+                        .is_some_and(|timestamp| Some(timestamp) != previous.batch_timestamp)
+                {
+                    return Err(eyre::eyre!(
+                        "Batch {} was built before batch {}",
+                        index,
+                        index - 1
+                    ));
+                }
+            }
+
+            rolling = roll_commit(rolling, batch.transaction_commit);
+            previous = Some(batch);
+        }
+
+        Ok(AggregatePublicValuesStruct {
+            initialStateRoot: first.initial_state_root,
+            finalStateRoot: last.final_state_root,
+            transactionCommit: rolling,
+            firstBatchIndex: first_index,
+            batchCount: batches.len() as u64,
+            l1BlockNumber: last.l1_block_number.unwrap_or_default(),
+            l1BlockHash: last.l1_block_hash.unwrap_or_default(),
+            firstBatchTimestamp: first.batch_timestamp.unwrap_or_default(),
+            lastBatchTimestamp: last.batch_timestamp.unwrap_or_default(),
+            previousBatchHash: first.previous_batch_hash.unwrap_or_default(),
+            batchHash: last.batch_hash.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,299 @@
+use alloy_primitives::{keccak256, Address, B256};
+use alloy_rlp::Encodable;
+use alloy_sol_types::SolValue;
+use serde::{Deserialize, Serialize};
+
+use crate::blob;
+use crate::codec::{decode_transactions, encode_transactions, Codec};
+use crate::cost::CycleModel;
+use crate::sequencer::SequencerSignature;
+use crate::SignedTransaction;
+
+/// Where a batch sits among the batches before it and on L1, when and by whom it was built. All
+/// of it is committed to in the public values, so the contract can require batches to land in
+/// order, to build on a block it can check with `blockhash`, and to come from a sequencer it
+/// accepts. The state the batch builds on is its `initialStateRoot`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchContext {
+    /// Number of batches before this one.
+    pub index: u64,
+    /// Transaction commit of the batch before this one, the seed of `Ordering::Shuffled`.
+    pub previous_batch: B256,
+    /// The L1 block the batch was built against, zero when it is not anchored.
+    pub l1_block_number: u64,
+    pub l1_block_hash: B256,
+    /// Unix time the batch was built at, zero when it has none. Transactions with a
+    /// `valid_until_timestamp` expire against it, so the contract has to keep it close to
+    /// `block.timestamp`.
+    pub timestamp: u64,
+    /// `batch_hash` of the batch before this one. Zero for the first batch, and for the first
+    /// batch proven with a chain on a deployment that started without one.
+    #[serde(default)]
+    pub previous_batch_hash: B256,
+    /// `timestamp` of the batch before this one. The program rejects a batch timestamped
+    /// before it, so batch time never runs backwards.
+    #[serde(default)]
+    pub previous_timestamp: u64,
+    /// The sequencer that built the batch, zero when it names none.
+    #[serde(default)]
+    pub sequencer: Address,
+    /// `sequencer`'s signature over the batch, posted with it. The program checks it whenever
+    /// it is given and requires it under `CanvasConfig::require_sequencer_signature`.
+    #[serde(default)]
+    pub sequencer_signature: Option<SequencerSignature>,
+}
+
+impl BatchContext {
+    pub fn anchored(self, l1_block_number: u64, l1_block_hash: B256) -> Self {
+        Self {
+            l1_block_number,
+            l1_block_hash,
+            ..self
+        }
+    }
+
+    pub fn timestamped(self, timestamp: u64) -> Self {
+        Self { timestamp, ..self }
+    }
+
+    pub fn sequenced_by(self, sequencer: Address) -> Self {
+        Self { sequencer, ..self }
+    }
+
+    pub fn signed(self, signature: SequencerSignature) -> Self {
+        Self {
+            sequencer_signature: Some(signature),
+            ..self
+        }
+    }
+}
+
+/// Links a batch to the chain of batches before it: keccak256 of the packed previous batch hash,
+/// index, transaction commit and final state root. The contract keeps the last one and accepts
+/// only a proof whose `previousBatchHash` equals it, so proofs land in order and exactly once.
+pub fn batch_hash(
+    previous_batch_hash: B256,
+    index: u64,
+    transaction_commit: B256,
+    final_state_root: B256,
+) -> B256 {
+    keccak256(
+        (
+            previous_batch_hash,
+            index,
+            transaction_commit,
+            final_state_root,
+        )
+            .abi_encode_packed(),
+    )
+}
+
+/// The `_transactionData` of `transactions` as posted on L1 and as the program encodes it again
+/// for the transaction commit. `dictionary` is only used by `Codec::Zstd`.
+pub fn encode_batch(
+    transactions: &[SignedTransaction],
+    codec: Codec,
+    dictionary: Option<&[u8]>,
+) -> eyre::Result<Vec<u8>> {
+    encode_transactions(transactions, codec, dictionary)
+}
+
+/// The transactions of posted `_transactionData` in any codec, within the default
+/// `DecodeLimits`.
+pub fn decode_batch(
+    transaction_data: &[u8],
+    dictionary: Option<&[u8]>,
+) -> eyre::Result<Vec<SignedTransaction>> {
+    decode_transactions(transaction_data, dictionary)
+}
+
+/// The transaction commit of a batch: the keccak256 of exactly the `_transactionData` bytes
+/// posted on L1, which the contract checks against the proven one.
+pub fn commit(transaction_data: &[u8]) -> B256 {
+    keccak256(transaction_data)
+}
+
+/// The commit of `transactions` as `encode_batch` posts them, as the program computes it.
+pub fn commit_transactions(
+    transactions: &[SignedTransaction],
+    codec: Codec,
+    dictionary: Option<&[u8]>,
+) -> eyre::Result<B256> {
+    Ok(commit(&encode_batch(transactions, codec, dictionary)?))
+}
+
+// Deflate never needs more than the input plus 5 bytes per stored block of up to 64 KiB, and
+// zstd and brotli need less per block; the rest covers the codec tag, the zlib header and
+// checksum or the zstd frame header, and a longer list header.
+fn stored_size(raw: usize) -> usize {
+    raw + 5 * (raw / 65_535 + 1) + 24
+}
+
+/// A batch ready to post: its transactions and their `_transactionData`, plus the transactions
+/// that did not fit, in the order they were offered.
+#[derive(Debug, Clone)]
+pub struct PackedBatch {
+    pub transactions: Vec<SignedTransaction>,
+    pub transaction_data: Vec<u8>,
+    pub leftover: Vec<SignedTransaction>,
+}
+
+impl PackedBatch {
+    /// `transaction_data` as blobs, for posting with `submitBatchWithBlobs`.
+    pub fn blobs(&self) -> eyre::Result<Vec<Vec<u8>>> {
+        blob::encode_blobs(&self.transaction_data)
+    }
+}
+
+/// Packs transactions greedily into a `_transactionData` payload of at most `budget` bytes. The
+/// first transaction that does not fit closes the builder.
+///
+/// The builder keeps the compressed size of the batch as of its last compression plus the raw
+/// size of what was added since, which bounds the real size from above. It only compresses
+/// again when that bound would exceed the budget, and each time roughly the remaining headroom
+/// shrinks by the compression ratio, so filling a batch compresses it a handful of times rather
+/// than once per transaction.
+///
+/// With a cycle budget, a transaction whose `CycleModel::transaction` estimate would take the
+/// batch past it closes the builder as well, so a batch is not found too big to prove only after
+/// proving it.
+pub struct BatchBuilder {
+    budget: usize,
+    codec: Codec,
+    dictionary: Option<Vec<u8>>,
+    transactions: Vec<SignedTransaction>,
+    compressed: usize,
+    raw_since: usize,
+    leftover: Vec<SignedTransaction>,
+    cycle_budget: u64,
+    model: CycleModel,
+    cycles: u64,
+}
+
+impl BatchBuilder {
+    pub fn new(budget: usize) -> Self {
+        Self::with_codec(budget, Codec::default(), None)
+    }
+
+    /// Packs for `codec`, with `dictionary` under `Codec::Zstd`.
+    pub fn with_codec(budget: usize, codec: Codec, dictionary: Option<Vec<u8>>) -> Self {
+        Self {
+            budget,
+            codec,
+            dictionary,
+            transactions: Vec::new(),
+            compressed: 0,
+            raw_since: 0,
+            leftover: Vec::new(),
+            cycle_budget: u64::MAX,
+            model: CycleModel::default(),
+            cycles: CycleModel::default().batch,
+        }
+    }
+
+    /// Keeps the batch within `budget` cycles as `model` estimates them.
+    pub fn with_cycle_budget(mut self, budget: u64, model: CycleModel) -> Self {
+        self.cycle_budget = budget;
+        self.model = model;
+        self.cycles = model.batch;
+        self
+    }
+
+    /// Packs a batch that fits in `blobs` blobs.
+    pub fn for_blobs(blobs: usize, codec: Codec, dictionary: Option<Vec<u8>>) -> Self {
+        Self::with_codec(blob::capacity(blobs), codec, dictionary)
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        !self.leftover.is_empty()
+    }
+
+    pub fn transactions(&self) -> &[SignedTransaction] {
+        &self.transactions
+    }
+
+    /// Estimated cycles of the batch so far.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Adds `tx` if the batch stays within the budgets with it. Otherwise, and once the builder
+    /// is closed, `tx` is kept as leftover and `false` returned.
+    pub fn push(&mut self, tx: SignedTransaction) -> eyre::Result<bool> {
+        if self.is_closed() {
+            self.leftover.push(tx);
+            return Ok(false);
+        }
+        let cycles = self.cycles.saturating_add(self.model.transaction(&tx));
+        if cycles > self.cycle_budget {
+            self.leftover.push(tx);
+            return Ok(false);
+        }
+
+        let raw = tx.length();
+        if self.compressed + stored_size(self.raw_since + raw) <= self.budget {
+            self.transactions.push(tx);
+            self.raw_since += raw;
+            self.cycles = cycles;
+            return Ok(true);
+        }
+
+        self.transactions.push(tx);
+        let compressed = self.encode(&self.transactions)?.len();
+        if compressed > self.budget {
+            let tx = self.transactions.pop().expect("just pushed");
+            self.leftover.push(tx);
+            return Ok(false);
+        }
+
+        self.compressed = compressed;
+        self.raw_since = 0;
+        self.cycles = cycles;
+        Ok(true)
+    }
+
+    /// Encodes the batch exactly as `encode_batch` does.
+    pub fn finish(mut self) -> eyre::Result<PackedBatch> {
+        let mut transactions = std::mem::take(&mut self.transactions);
+        let mut leftover = std::mem::take(&mut self.leftover);
+
+        // The bound is not a law of any codec, so the budget is checked once more on the real
+        // payload.
+        loop {
+            let transaction_data = self.encode(&transactions)?;
+            if transaction_data.len() <= self.budget || transactions.is_empty() {
+                return Ok(PackedBatch {
+                    transactions,
+                    transaction_data,
+                    leftover,
+                });
+            }
+            let last = transactions.pop().expect("batch is not empty");
+            leftover.insert(0, last);
+        }
+    }
+
+    fn encode(&self, transactions: &[SignedTransaction]) -> eyre::Result<Vec<u8>> {
+        encode_batch(transactions, self.codec, self.dictionary.as_deref())
+    }
+}
+
+/// Packs `transactions` in order into one batch of at most `budget` bytes.
+pub fn pack(
+    transactions: impl IntoIterator<Item = SignedTransaction>,
+    budget: usize,
+) -> eyre::Result<PackedBatch> {
+    let mut builder = BatchBuilder::new(budget);
+    for tx in transactions {
+        builder.push(tx)?;
+    }
+    builder.finish()
+}
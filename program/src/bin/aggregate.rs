@@ -0,0 +1,21 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+use program::aggregate::AggregateInput;
+
+use alloy_sol_types::SolValue;
+use sha2::{Digest, Sha256};
+
+pub fn main() {
+    let input = sp1_zkvm::io::read::<AggregateInput>();
+
+    // The host writes each batch proof next to the input; SP1 checks them against the digest
+    // of the public values they committed.
+    for public_values in &input.public_values {
+        let digest: [u8; 32] = Sha256::digest(public_values).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(&input.vkey, &digest);
+    }
+
+    let values = input.process().expect("Failed to aggregate batches");
+
+    sp1_zkvm::io::commit_slice(values.abi_encode().as_slice());
+}
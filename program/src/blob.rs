@@ -0,0 +1,80 @@
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+pub const BYTES_PER_FIELD_ELEMENT: usize = 32;
+pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
+/// Blobs one transaction can carry under the Cancun limits.
+pub const MAX_BLOBS_PER_TX: usize = 6;
+
+// Each field element keeps its first byte zero, which keeps it below the BLS12-381 modulus.
+const USABLE_BYTES: usize = BYTES_PER_FIELD_ELEMENT - 1;
+const PAYLOAD_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * USABLE_BYTES;
+const LENGTH_PREFIX: usize = 4;
+
+/// Most bytes of transaction data that fit in `blobs` blobs.
+pub fn capacity(blobs: usize) -> usize {
+    (blobs * PAYLOAD_PER_BLOB).saturating_sub(LENGTH_PREFIX)
+}
+
+/// Fewest blobs that hold `bytes` bytes of transaction data.
+pub fn blobs_needed(bytes: usize) -> usize {
+    (bytes + LENGTH_PREFIX).div_ceil(PAYLOAD_PER_BLOB)
+}
+
+/// Spreads `transaction_data` over the fewest blobs that hold it: behind a 4-byte big-endian
+/// length, 31 bytes in each field element after a zero byte, and zero padded. It commits and
+/// decodes exactly as the same data posted as calldata.
+pub fn encode_blobs(transaction_data: &[u8]) -> eyre::Result<Vec<Vec<u8>>> {
+    let blobs = blobs_needed(transaction_data.len());
+    if blobs > MAX_BLOBS_PER_TX {
+        return Err(eyre::eyre!(
+            "{} bytes need {} blobs, at most {} fit in a transaction",
+            transaction_data.len(),
+            blobs,
+            MAX_BLOBS_PER_TX
+        ));
+    }
+
+    let mut payload = (transaction_data.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(transaction_data);
+
+    let mut encoded = vec![vec![0u8; BYTES_PER_BLOB]; blobs];
+    for (i, chunk) in payload.chunks(USABLE_BYTES).enumerate() {
+        let blob = &mut encoded[i / FIELD_ELEMENTS_PER_BLOB];
+        let start = (i % FIELD_ELEMENTS_PER_BLOB) * BYTES_PER_FIELD_ELEMENT + 1;
+        blob[start..start + chunk.len()].copy_from_slice(chunk);
+    }
+
+    Ok(encoded)
+}
+
+/// The transaction data spread over `blobs`, in the order the transaction lists them.
+pub fn decode_blobs(blobs: &[impl AsRef<[u8]>]) -> eyre::Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(blobs.len() * PAYLOAD_PER_BLOB);
+    for blob in blobs {
+        let blob = blob.as_ref();
+        if blob.len() != BYTES_PER_BLOB {
+            return Err(eyre::eyre!("Blob is {} bytes", blob.len()));
+        }
+
+        for element in blob.chunks(BYTES_PER_FIELD_ELEMENT) {
+            if element[0] != 0 {
+                return Err(eyre::eyre!("Blob field element does not start with zero"));
+            }
+            payload.extend_from_slice(&element[1..]);
+        }
+    }
+
+    let length = match payload.get(..LENGTH_PREFIX) {
+        Some(prefix) => u32::from_be_bytes(prefix.try_into().expect("4 bytes")) as usize,
+        None => return Err(eyre::eyre!("No blobs")),
+    };
+    payload
+        .get(LENGTH_PREFIX..LENGTH_PREFIX + length)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "Blobs claim {} bytes but hold at most {}",
+                length,
+                capacity(blobs.len())
+            )
+        })
+}
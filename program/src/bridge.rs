@@ -0,0 +1,170 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+use alloy_rlp::Encodable;
+use alloy_sol_types::{sol, SolValue};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Account, SignedTransaction};
+
+sol! {
+    struct DepositMessage {
+        uint64 index;
+        address from;
+        address to;
+        uint256 amount;
+    }
+}
+
+/// An L1 → L2 message queued on the rollup contract: `amount` locked by `from` on L1, credited
+/// to the balance of `to` before the batch's transactions run. `index` is its position in the
+/// contract's queue, so the contract can check a batch consumes the queue in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deposit {
+    pub index: u64,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+/// What a batch takes from the rollup contract's queues besides its posted transactions.
+///
+/// Forced transactions were submitted to the contract directly, so a sequencer cannot censor
+/// them: they run before the sequencer's transactions, in queue order and whatever the batch's
+/// `Ordering`, and one that fails is skipped with a failed receipt under any `FailurePolicy`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inbox<'a> {
+    pub deposits: &'a [Deposit],
+    pub forced_txs: &'a [SignedTransaction],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeError {
+    /// A deposit would take the balance of `account` past `U256::MAX`.
+    BalanceOverflow(Address),
+    /// `account` tried to withdraw more than its balance.
+    InsufficientBalance {
+        account: Address,
+        balance: U256,
+        amount: U256,
+    },
+    /// A withdrawal was sent to `got` instead of the record address derived for it.
+    WrongRecord { expected: Address, got: Address },
+    /// A withdrawal under a commitment version that does not commit the balances it moves.
+    NotCommitted,
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeError::BalanceOverflow(account) => {
+                write!(f, "Deposit overflows the balance of {}", account)
+            }
+            BridgeError::InsufficientBalance {
+                account,
+                balance,
+                amount,
+            } => write!(
+                f,
+                "{} cannot withdraw {} with a balance of {}",
+                account, amount, balance
+            ),
+            BridgeError::WrongRecord { expected, got } => write!(
+                f,
+                "Withdrawal must be sent to its record {}, not {}",
+                expected, got
+            ),
+            BridgeError::NotCommitted => write!(f, "Withdrawals need commitment version 1"),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+/// What a batch commits to for its deposits: keccak256 of the ABI encoded `DepositMessage[]`,
+/// zero when there are none.
+pub fn deposits_commit(deposits: &[Deposit]) -> B256 {
+    if deposits.is_empty() {
+        return B256::ZERO;
+    }
+
+    let messages: Vec<DepositMessage> = deposits
+        .iter()
+        .map(|deposit| DepositMessage {
+            index: deposit.index,
+            from: deposit.from,
+            to: deposit.to,
+            amount: deposit.amount,
+        })
+        .collect();
+    keccak256(messages.abi_encode())
+}
+
+/// What a batch commits to for its forced transactions: keccak256 of the ABI encoded `bytes32[]`
+/// of the keccak256 of each one's RLP, zero when there are none.
+pub fn forced_commit(transactions: &[SignedTransaction]) -> B256 {
+    if transactions.is_empty() {
+        return B256::ZERO;
+    }
+
+    let hashes: Vec<B256> = transactions
+        .iter()
+        .map(|tx| {
+            let mut encoded = Vec::with_capacity(tx.length());
+            tx.encode(&mut encoded);
+            keccak256(encoded)
+        })
+        .collect();
+    keccak256(hashes.abi_encode())
+}
+
+pub fn credit(account: &mut Account, deposit: &Deposit) -> Result<(), BridgeError> {
+    account.balance = account
+        .balance
+        .checked_add(deposit.amount)
+        .ok_or(BridgeError::BalanceOverflow(deposit.to))?;
+    Ok(())
+}
+
+/// The account a withdrawal by `sender` at `nonce` is recorded in. Each withdrawal gets a fresh
+/// address, so the contract can pay each record out once by its address.
+pub fn withdrawal_address(sender: &Address, nonce: u64) -> Address {
+    let hash = keccak256(("canvas.withdrawal", *sender, nonce).abi_encode_packed());
+    Address::from_slice(&hash[12..])
+}
+
+/// Moves `amount` from the sender's balance into the withdrawal record at `address`, owned by
+/// the L1 `recipient`. The record's leaf can then be proven against the state root with
+/// `generate_proof`.
+pub fn withdraw(
+    sender: &Address,
+    nonce: u64,
+    sender_account: &mut Account,
+    address: &Address,
+    record: &mut Account,
+    recipient: &Address,
+    amount: U256,
+) -> Result<(), BridgeError> {
+    let expected = withdrawal_address(sender, nonce);
+    if *address != expected {
+        return Err(BridgeError::WrongRecord {
+            expected,
+            got: *address,
+        });
+    }
+
+    sender_account.balance =
+        sender_account
+            .balance
+            .checked_sub(amount)
+            .ok_or(BridgeError::InsufficientBalance {
+                account: *sender,
+                balance: sender_account.balance,
+                amount,
+            })?;
+    record.balance = record
+        .balance
+        .checked_add(amount)
+        .ok_or(BridgeError::BalanceOverflow(*address))?;
+    record.owner = *recipient;
+    Ok(())
+}
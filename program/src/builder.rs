@@ -0,0 +1,244 @@
+use alloy_primitives::{Address, B256, U256};
+
+use crate::edit::EditOp;
+use crate::namespace::DEFAULT_CANVAS;
+use crate::{
+    Data, Transaction, TX_VERSION_ACCESS_LIST, TX_VERSION_CANVAS, TX_VERSION_DOMAIN,
+    TX_VERSION_EXPIRY, TX_VERSION_LATEST, TX_VERSION_LEGACY,
+};
+
+/// Builds a `Transaction` for signing. Defaults to `TX_VERSION_DOMAIN`, nonce 0 and no extra.
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    to: Address,
+    version: u8,
+    nonce: u64,
+    ops: Vec<EditOp>,
+    extra: String,
+    valid_until_batch: u64,
+    valid_until_timestamp: u64,
+    canvas: u64,
+    access_list: Vec<Address>,
+}
+
+impl TxBuilder {
+    pub fn new(to: Address) -> Self {
+        Self {
+            to,
+            version: TX_VERSION_DOMAIN,
+            nonce: 0,
+            ops: Vec::new(),
+            extra: String::new(),
+            valid_until_batch: 0,
+            valid_until_timestamp: 0,
+            canvas: DEFAULT_CANVAS,
+            access_list: Vec::new(),
+        }
+    }
+
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn extra(mut self, extra: impl Into<String>) -> Self {
+        self.extra = extra.into();
+        self
+    }
+
+    /// Needs `TX_VERSION_EXPIRY` or later, as does `valid_until_timestamp`.
+    pub fn valid_until_batch(mut self, batch: u64) -> Self {
+        self.valid_until_batch = batch;
+        self
+    }
+
+    pub fn valid_until_timestamp(mut self, timestamp: u64) -> Self {
+        self.valid_until_timestamp = timestamp;
+        self
+    }
+
+    /// Needs `TX_VERSION_CANVAS` or later for any canvas but `DEFAULT_CANVAS`.
+    pub fn canvas(mut self, canvas: u64) -> Self {
+        self.canvas = canvas;
+        self
+    }
+
+    /// Declares the accounts the transaction touches; see `Transaction::access_list`. Needs
+    /// `TX_VERSION_ACCESS_LIST` or later.
+    pub fn access_list(mut self, accounts: impl IntoIterator<Item = Address>) -> Self {
+        self.access_list.extend(accounts);
+        self
+    }
+
+    pub fn op(mut self, op: EditOp) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    pub fn ops(mut self, ops: impl IntoIterator<Item = EditOp>) -> Self {
+        self.ops.extend(ops);
+        self
+    }
+
+    pub fn insert(self, index: usize, value: impl Into<String>) -> Self {
+        self.op(EditOp::Insert {
+            index,
+            value: value.into(),
+        })
+    }
+
+    pub fn delete(self, index: usize, count: usize) -> Self {
+        self.op(EditOp::Delete { index, count })
+    }
+
+    pub fn replace(self, index: usize, count: usize, value: impl Into<String>) -> Self {
+        self.op(EditOp::Replace {
+            index,
+            count,
+            value: value.into(),
+        })
+    }
+
+    pub fn append(self, value: impl Into<String>) -> Self {
+        self.op(EditOp::Append {
+            value: value.into(),
+        })
+    }
+
+    pub fn claim(self) -> Self {
+        self.op(EditOp::Claim)
+    }
+
+    pub fn transfer(self, owner: Address) -> Self {
+        self.op(EditOp::Transfer { owner })
+    }
+
+    pub fn grant(self, writer: Address) -> Self {
+        self.op(EditOp::Grant { writer })
+    }
+
+    pub fn revoke(self, writer: Address) -> Self {
+        self.op(EditOp::Revoke { writer })
+    }
+
+    /// Inserts at `at` the `count` units of `source`'s data from `index`.
+    pub fn copy(self, source: Address, index: usize, count: usize, at: usize) -> Self {
+        self.op(EditOp::Copy {
+            source,
+            index,
+            count,
+            at,
+        })
+    }
+
+    /// `copy` that also deletes the range from `source`.
+    pub fn move_range(self, source: Address, index: usize, count: usize, at: usize) -> Self {
+        self.op(EditOp::Move {
+            source,
+            index,
+            count,
+            at,
+        })
+    }
+
+    pub fn clear(self) -> Self {
+        self.op(EditOp::Clear)
+    }
+
+    /// Undoes the sender's last edit of the target, `tx_hash`; see `EditOp::RevertOwn`.
+    pub fn revert_own(self, tx_hash: B256) -> Self {
+        self.op(EditOp::RevertOwn { tx_hash })
+    }
+
+    /// Sets the target's display `name` and `uri`; see `EditOp::SetMetadata`.
+    pub fn set_metadata(self, name: impl Into<String>, uri: impl Into<String>) -> Self {
+        self.op(EditOp::SetMetadata {
+            name: name.into(),
+            uri: uri.into(),
+        })
+    }
+
+    /// For a transaction to `bridge::withdrawal_address` of the sender and nonce.
+    pub fn withdraw(self, recipient: Address, amount: U256) -> Self {
+        self.op(EditOp::Withdraw { recipient, amount })
+    }
+
+    /// Fails for a version after `TX_VERSION_LATEST`, for a `TX_VERSION_LEGACY` transaction with
+    /// edits other than inserts and deletes, which its `data` cannot express, and for an expiry,
+    /// canvas or access list on a version that does not encode it.
+    pub fn build(self) -> eyre::Result<Transaction> {
+        if self.version > TX_VERSION_LATEST {
+            return Err(eyre::eyre!("Unknown transaction version {}", self.version));
+        }
+        let expires = self.valid_until_batch != 0 || self.valid_until_timestamp != 0;
+        if expires && self.version < TX_VERSION_EXPIRY {
+            return Err(eyre::eyre!(
+                "Version {} transactions cannot expire",
+                self.version
+            ));
+        }
+        if self.canvas != DEFAULT_CANVAS && self.version < TX_VERSION_CANVAS {
+            return Err(eyre::eyre!(
+                "Version {} transactions cannot name a canvas",
+                self.version
+            ));
+        }
+        if !self.access_list.is_empty() && self.version < TX_VERSION_ACCESS_LIST {
+            return Err(eyre::eyre!(
+                "Version {} transactions cannot declare an access list",
+                self.version
+            ));
+        }
+
+        if self.version != TX_VERSION_LEGACY {
+            return Ok(Transaction {
+                to: self.to,
+                version: self.version,
+                data: Vec::new(),
+                ops: self.ops,
+                nonce: self.nonce,
+                extra: self.extra,
+                valid_until_batch: self.valid_until_batch,
+                valid_until_timestamp: self.valid_until_timestamp,
+                canvas: self.canvas,
+                access_list: self.access_list,
+            });
+        }
+
+        let data = self
+            .ops
+            .into_iter()
+            .map(|op| match op {
+                EditOp::Insert { index, value } => Ok(Data {
+                    index,
+                    count: 0,
+                    value,
+                }),
+                EditOp::Delete { index, count } if count > 0 => Ok(Data {
+                    index,
+                    count,
+                    value: String::new(),
+                }),
+                op => Err(eyre::eyre!("{:?} is not a legacy edit", op)),
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(Transaction {
+            to: self.to,
+            version: self.version,
+            data,
+            ops: Vec::new(),
+            nonce: self.nonce,
+            extra: self.extra,
+            valid_until_batch: 0,
+            valid_until_timestamp: 0,
+            canvas: DEFAULT_CANVAS,
+            access_list: Vec::new(),
+        })
+    }
+}
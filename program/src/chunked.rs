@@ -0,0 +1,275 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::{Account, InMemoryDB, Input};
+
+/// How account frames are encoded; every other frame is bincode. Decoding accounts is most of
+/// the guest's input cost, so they can use a cheaper format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Bincode,
+    /// Flat records of fixed-size fields, decoded without serde's visitors. Needs the `borsh`
+    /// feature on both sides.
+    Borsh,
+}
+
+/// Bytes of accounts `write_input` packs into one frame. The guest holds one
+/// frame at a time on top of what it has read so far, instead of the whole encoded input next to
+/// the decoded one.
+pub const FRAME_BYTES: usize = 256 * 1024;
+
+#[derive(Serialize, Deserialize)]
+enum Frame {
+    /// The input without its accounts, and how many follow. The posted batch stays in the
+    /// header; it is bounded by what fits in an L1 transaction.
+    Header {
+        input: Input,
+        accounts: u64,
+        encoding: Encoding,
+    },
+    Accounts(Vec<(Address, Account)>),
+}
+
+/// Writes `input` as a header frame and frames of accounts in address order, each behind its
+/// length as a little-endian `u32`.
+pub fn write_input(input: &Input, encoding: Encoding, mut out: impl Write) -> eyre::Result<()> {
+    let accounts = input.db.accounts.borrow();
+    let header = Input {
+        transaction_data: input.transaction_data.clone(),
+        db: InMemoryDB::default(),
+        config: input.config,
+        batch: input.batch,
+        deposits: input.deposits.clone(),
+        forced_txs: input.forced_txs.clone(),
+        limits: input.limits.clone(),
+        witness: input.witness.clone(),
+        dictionary: input.dictionary.clone(),
+    };
+    write_frame(
+        &mut out,
+        &Frame::Header {
+            input: header,
+            accounts: accounts.len() as u64,
+            encoding,
+        },
+    )?;
+
+    let mut sorted: Vec<_> = accounts.iter().collect();
+    sorted.sort_by_key(|(address, _)| **address);
+    let entries = sorted
+        .into_iter()
+        .map(|(address, account)| (*address, account.clone()));
+    write_chunks(&mut out, entries, |chunk| match encoding {
+        Encoding::Bincode => Ok(bincode::serialize(&Frame::Accounts(chunk))?),
+        Encoding::Borsh => encode_borsh(&chunk),
+    })
+}
+
+// Packs `items` into frames of about `FRAME_BYTES`.
+fn write_chunks<T: Serialize>(
+    out: &mut impl Write,
+    items: impl Iterator<Item = T>,
+    encode: impl Fn(Vec<T>) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<()> {
+    let mut chunk = Vec::new();
+    let mut size = 0;
+    for item in items {
+        size += bincode::serialized_size(&item)? as usize;
+        chunk.push(item);
+        if size >= FRAME_BYTES {
+            write_frame(out, &encode(std::mem::take(&mut chunk))?)?;
+            size = 0;
+        }
+    }
+    if !chunk.is_empty() {
+        write_frame(out, &encode(chunk)?)?;
+    }
+    Ok(())
+}
+
+fn write_frame(out: &mut impl Write, bytes: &[u8]) -> eyre::Result<()> {
+    out.write_all(&u32::try_from(bytes.len())?.to_le_bytes())?;
+    out.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_frame(reader: &mut impl Read) -> eyre::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let mut bytes = vec![0u8; u32::from_le_bytes(length) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_bincode_frame(reader: &mut impl Read) -> eyre::Result<Frame> {
+    Ok(bincode::deserialize(&read_frame(reader)?)?)
+}
+
+#[cfg(feature = "borsh")]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+struct BorshEntry {
+    address: [u8; 20],
+    nonce: u64,
+    data: String,
+    contributors: Vec<[u8; 20]>,
+    balance: [u8; 32],
+    owner: [u8; 20],
+    policy: [u8; 32],
+    writers: Vec<[u8; 20]>,
+    // Contributor, edits, inserted and deleted.
+    contributions: Vec<([u8; 20], u64, u64, u64)>,
+    canvas: u64,
+    // Editor and batch index.
+    last_edits: Vec<([u8; 20], u64)>,
+    // Editor, transaction hash, batch index, index, inserted and removed.
+    undos: Vec<([u8; 20], [u8; 32], u64, u64, u64, String)>,
+    // Name and URI.
+    metadata: (String, String),
+}
+
+#[cfg(feature = "borsh")]
+fn encode_borsh(chunk: &[(Address, Account)]) -> eyre::Result<Vec<u8>> {
+    let entries: Vec<BorshEntry> = chunk
+        .iter()
+        .map(|(address, account)| BorshEntry {
+            address: address.0 .0,
+            nonce: account.nonce,
+            data: account.data.clone(),
+            contributors: account.contributors.iter().map(|a| a.0 .0).collect(),
+            balance: account.balance.to_le_bytes(),
+            owner: account.owner.0 .0,
+            policy: account.policy.0,
+            writers: account.writers.iter().map(|a| a.0 .0).collect(),
+            contributions: account
+                .contributions
+                .iter()
+                .map(|(a, stats)| (a.0 .0, stats.edits, stats.inserted, stats.deleted))
+                .collect(),
+            canvas: account.canvas,
+            last_edits: account
+                .last_edits
+                .iter()
+                .map(|(a, batch)| (a.0 .0, *batch))
+                .collect(),
+            undos: account
+                .undos
+                .iter()
+                .map(|(a, undo)| {
+                    let crate::undo::Undo {
+                        tx_hash,
+                        batch_index,
+                        index,
+                        inserted,
+                        removed,
+                    } = undo.clone();
+                    (a.0 .0, tx_hash.0, batch_index, index, inserted, removed)
+                })
+                .collect(),
+            metadata: (account.metadata.name.clone(), account.metadata.uri.clone()),
+        })
+        .collect();
+    Ok(borsh::to_vec(&entries)?)
+}
+
+#[cfg(feature = "borsh")]
+fn decode_borsh(bytes: &[u8]) -> eyre::Result<Vec<(Address, Account)>> {
+    let entries: Vec<BorshEntry> = borsh::from_slice(bytes)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let account = Account {
+                nonce: entry.nonce,
+                data: entry.data,
+                contributors: entry.contributors.into_iter().map(Address::from).collect(),
+                balance: alloy_primitives::U256::from_le_bytes(entry.balance),
+                owner: Address::from(entry.owner),
+                policy: entry.policy.into(),
+                writers: entry.writers.into_iter().map(Address::from).collect(),
+                contributions: entry
+                    .contributions
+                    .into_iter()
+                    .map(|(a, edits, inserted, deleted)| {
+                        let stats = crate::ContributorStats {
+                            edits,
+                            inserted,
+                            deleted,
+                        };
+                        (Address::from(a), stats)
+                    })
+                    .collect(),
+                canvas: entry.canvas,
+                last_edits: entry
+                    .last_edits
+                    .into_iter()
+                    .map(|(a, batch)| (Address::from(a), batch))
+                    .collect(),
+                undos: entry
+                    .undos
+                    .into_iter()
+                    .map(|(a, tx_hash, batch_index, index, inserted, removed)| {
+                        let undo = crate::undo::Undo {
+                            tx_hash: tx_hash.into(),
+                            batch_index,
+                            index,
+                            inserted,
+                            removed,
+                        };
+                        (Address::from(a), undo)
+                    })
+                    .collect(),
+                metadata: crate::metadata::Metadata {
+                    name: entry.metadata.0,
+                    uri: entry.metadata.1,
+                },
+            };
+            (Address::from(entry.address), account)
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "borsh"))]
+fn encode_borsh(_chunk: &[(Address, Account)]) -> eyre::Result<Vec<u8>> {
+    Err(eyre::eyre!("Borsh input needs the `borsh` feature"))
+}
+
+#[cfg(not(feature = "borsh"))]
+fn decode_borsh(_bytes: &[u8]) -> eyre::Result<Vec<(Address, Account)>> {
+    Err(eyre::eyre!("Borsh input needs the `borsh` feature"))
+}
+
+/// Reads an input written by `write_input`, a frame at a time.
+pub fn read_input(mut reader: impl Read) -> eyre::Result<Input> {
+    let Frame::Header {
+        mut input,
+        accounts,
+        encoding,
+    } = read_bincode_frame(&mut reader)?
+    else {
+        return Err(eyre::eyre!("Chunked input does not start with a header"));
+    };
+
+    let mut read_accounts = 0;
+    while read_accounts < accounts {
+        let chunk = match encoding {
+            Encoding::Bincode => match read_bincode_frame(&mut reader)? {
+                Frame::Accounts(chunk) => chunk,
+                _ => return Err(eyre::eyre!("Chunked input is missing accounts")),
+            },
+            Encoding::Borsh => decode_borsh(&read_frame(&mut reader)?)?,
+        };
+        read_accounts += chunk.len() as u64;
+        input.db.accounts.get_mut().extend(chunk);
+    }
+
+    if read_accounts != accounts {
+        return Err(eyre::eyre!(
+            "Chunked input has {} accounts, its header claims {}",
+            read_accounts,
+            accounts
+        ));
+    }
+
+    Ok(input)
+}
@@ -0,0 +1,130 @@
+use alloy_primitives::{Address, B256, U256};
+use alloy_sol_types::sol;
+use rs_merkle::Hasher;
+use std::borrow::Borrow;
+
+use crate::commitment::{leaf_preimage, CommitmentVersion};
+use crate::namespace::{canvas_key, canvas_leaf, canvas_of};
+use crate::smt::{self, SparseProof};
+use crate::{CanvasProcessor, InMemoryDB, Keccak256Algorithm};
+
+sol! {
+    /// An account's leaf and its proof against the `finalStateRoot` of batch `batchIndex`, for
+    /// a contract to check before paying out a withdrawal or claim. The leaf is
+    /// `keccak256(leafPreimage)`, the `AccountCommit` of `commitmentVersion` that
+    /// `leaf_preimage` encodes, with the leaf and node prefixes of `HashScheme::SeparatedKeccak`
+    /// from `V10` on.
+    ///
+    /// Under a dense version `proof` holds the siblings from the leaf up, each pair hashed in
+    /// ascending order, and `bitmap` and the canvas fields are zero. Under a sparse version bit
+    /// `i` of `bitmap` says whether the sibling at height `i` is in `proof` or zero, and the path
+    /// follows the bits of `account`. From `V8` on that path leads to `canvasRoot`, which
+    /// `canvasBitmap` and `canvasProof` prove in turn, keyed by `canvas`, against the state root.
+    struct AccountClaim {
+        uint64 batchIndex;
+        uint8 commitmentVersion;
+        address account;
+        bytes leafPreimage;
+        uint256 bitmap;
+        bytes32[] proof;
+        uint64 canvas;
+        bytes32 canvasRoot;
+        uint256 canvasBitmap;
+        bytes32[] canvasProof;
+    }
+}
+
+impl<D: Borrow<InMemoryDB>, V> CanvasProcessor<D, V> {
+    /// The claim of the account of `address` against `generate_state_root`, as of the batch in
+    /// `batch`, the last one applied.
+    pub fn generate_claim(&self, address: &Address) -> eyre::Result<AccountClaim> {
+        let version = self.config.commitment;
+        let account = self.state().accounts.borrow().get(address).cloned();
+        let account = account.ok_or_else(|| eyre::eyre!("Address {} not found", address))?;
+        let siblings = |proof: Vec<[u8; 32]>| proof.into_iter().map(B256::from).collect();
+
+        let mut claim = AccountClaim {
+            batchIndex: self.batch.index,
+            commitmentVersion: version as u8,
+            account: *address,
+            leafPreimage: leaf_preimage(version, address, &account).into(),
+            bitmap: U256::ZERO,
+            proof: Vec::new(),
+            canvas: 0,
+            canvasRoot: B256::ZERO,
+            canvasBitmap: U256::ZERO,
+            canvasProof: Vec::new(),
+        };
+        if !version.is_sparse() {
+            claim.proof = siblings(self.generate_proof(address)?);
+            return Ok(claim);
+        }
+
+        let proof = self.generate_sparse_proof(address)?;
+        claim.bitmap = proof.bitmap;
+        claim.proof = siblings(proof.siblings);
+        if version.is_namespaced() {
+            let canvas = self.generate_canvas_proof(canvas_of(version, &account))?;
+            claim.canvas = canvas.canvas;
+            claim.canvasRoot = canvas.root.into();
+            claim.canvasBitmap = canvas.proof.bitmap;
+            claim.canvasProof = siblings(canvas.proof.siblings);
+        }
+        Ok(claim)
+    }
+}
+
+// The address an `AccountCommit` preimage commits to: the first field of the struct, after the
+// version byte and the offset word of the dynamic tuple.
+fn preimage_account(version: CommitmentVersion, preimage: &[u8]) -> Option<Address> {
+    let encoded = match version {
+        CommitmentVersion::V0 => preimage,
+        _ => preimage.get(1..)?,
+    };
+    encoded.get(44..64).map(Address::from_slice)
+}
+
+/// Checks `claim` against `state_root` as a Solidity verifier would: the preimage commits to
+/// `claim.account` in the version claimed, and the proof leads from its hash to the root.
+pub fn verify_claim(claim: &AccountClaim, state_root: &B256) -> bool {
+    let Some(version) = CommitmentVersion::from_u8(claim.commitmentVersion) else {
+        return false;
+    };
+    if preimage_account(version, &claim.leafPreimage) != Some(claim.account)
+        || (version != CommitmentVersion::V0 && claim.leafPreimage[0] != version as u8)
+    {
+        return false;
+    }
+    let scheme = version.hash_scheme();
+    let leaf = scheme.leaf(&claim.leafPreimage);
+    let siblings = |proof: &[B256]| proof.iter().map(|sibling| sibling.0).collect();
+
+    if !version.is_sparse() {
+        let root = claim.proof.iter().fold(leaf, |node, sibling| {
+            Keccak256Algorithm::concat_and_hash(&node, Some(&sibling.0))
+        });
+        return root == state_root.0;
+    }
+
+    let proof = SparseProof {
+        bitmap: claim.bitmap,
+        siblings: siblings(&claim.proof),
+    };
+    if !version.is_namespaced() {
+        return smt::verify_with(scheme, &state_root.0, &claim.account, Some(&leaf), &proof);
+    }
+
+    let canvas_proof = SparseProof {
+        bitmap: claim.canvasBitmap,
+        siblings: siblings(&claim.canvasProof),
+    };
+    let canvas_root = claim.canvasRoot.0;
+    smt::verify_with(scheme, &canvas_root, &claim.account, Some(&leaf), &proof)
+        && smt::verify_with(
+            scheme,
+            &state_root.0,
+            &canvas_key(claim.canvas),
+            Some(&canvas_leaf(scheme, claim.canvas, &canvas_root)),
+            &canvas_proof,
+        )
+}
@@ -0,0 +1,257 @@
+use alloy_rlp::{Decodable, Encodable};
+use eyre::WrapErr;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::SignedTransaction;
+
+#[cfg(feature = "codecs")]
+const ZSTD_LEVEL: i32 = 19;
+#[cfg(feature = "codecs")]
+const BROTLI_QUALITY: u32 = 11;
+#[cfg(feature = "codecs")]
+const BROTLI_WINDOW: u32 = 22;
+
+/// How a batch's `_transactionData` is compressed. Every codec but `Zlib` prefixes the payload
+/// with its tag byte. Zlib payloads start with a `0x78` header byte and stay unprefixed, so
+/// batches posted before codecs existed decode and commit as before.
+///
+/// The program encodes the batch again to derive the transaction commit, so host and guest must
+/// link the same version of each library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Codec {
+    #[default]
+    Zlib,
+    /// The RLP list as is.
+    None,
+    /// With the shared dictionary when one is given.
+    Zstd,
+    Brotli,
+}
+
+impl Codec {
+    pub fn tag(self) -> Option<u8> {
+        match self {
+            Codec::Zlib => None,
+            Codec::None => Some(0x00),
+            Codec::Zstd => Some(0x01),
+            Codec::Brotli => Some(0x02),
+        }
+    }
+
+    /// The codec `transaction_data` was encoded with.
+    pub fn of(transaction_data: &[u8]) -> eyre::Result<Self> {
+        match transaction_data.first() {
+            Some(0x00) => Ok(Codec::None),
+            Some(0x01) => Ok(Codec::Zstd),
+            Some(0x02) => Ok(Codec::Brotli),
+            Some(0x78) => Ok(Codec::Zlib),
+            Some(tag) => Err(eyre::eyre!("Unknown batch codec 0x{:02x}", tag)),
+            None => Err(eyre::eyre!("Empty transaction data")),
+        }
+    }
+}
+
+/// Bounds on what decoding a batch or admitting a transaction takes in from whoever sent it, so
+/// that a crafted payload fails with a `LimitError` instead of exhausting the node's memory. The
+/// defaults are far above anything that fits in an L1 block; a sequencer must not post more, or
+/// nodes stop at its batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeLimits {
+    /// Most bytes `_transactionData` decompresses to. Zstd decodes into a buffer of this
+    /// capacity.
+    pub max_decompressed: usize,
+    pub max_transactions: usize,
+    /// Most edit ops, or legacy `Data` edits, of one transaction.
+    pub max_ops: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_decompressed: 64 * 1024 * 1024,
+            max_transactions: 1 << 20,
+            max_ops: 8 * 1024,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Checks the parts of `tx` that are bounded on their own, for a transaction that arrives
+    /// outside a batch.
+    pub fn check_transaction(&self, tx: &SignedTransaction) -> Result<(), LimitError> {
+        let ops = tx.tx.data.len() + tx.tx.ops.len();
+        if ops > self.max_ops {
+            return Err(LimitError::TooManyOps {
+                ops,
+                max: self.max_ops,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// The payload decompresses to more than `max` bytes.
+    TooLarge {
+        max: usize,
+    },
+    TooManyTransactions {
+        count: usize,
+        max: usize,
+    },
+    /// A transaction has `ops` edits.
+    TooManyOps {
+        ops: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TooLarge { max } => {
+                write!(f, "Batch decompresses to more than {} bytes", max)
+            }
+            LimitError::TooManyTransactions { count, max } => write!(
+                f,
+                "Batch of {} transactions exceeds the maximum of {}",
+                count, max
+            ),
+            LimitError::TooManyOps { ops, max } => write!(
+                f,
+                "Transaction of {} edits exceeds the maximum of {}",
+                ops, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Encodes a batch as `_transactionData` with `codec`. `dictionary` is only used by `Zstd`.
+#[cfg_attr(not(feature = "codecs"), allow(unused_variables))]
+pub fn encode_transactions(
+    transactions: &[SignedTransaction],
+    codec: Codec,
+    dictionary: Option<&[u8]>,
+) -> eyre::Result<Vec<u8>> {
+    let mut encoded = Vec::<u8>::new();
+    transactions.encode(&mut encoded);
+
+    let mut out = Vec::from_iter(codec.tag());
+    match codec {
+        Codec::Zlib => {
+            let mut zlib = ZlibEncoder::new(out, Compression::default());
+            zlib.write_all(&encoded)?;
+            out = zlib.finish()?;
+        }
+        Codec::None => out.extend_from_slice(&encoded),
+        #[cfg(feature = "codecs")]
+        Codec::Zstd => {
+            let mut compressor = match dictionary {
+                Some(dictionary) => {
+                    zstd::bulk::Compressor::with_dictionary(ZSTD_LEVEL, dictionary)?
+                }
+                None => zstd::bulk::Compressor::new(ZSTD_LEVEL)?,
+            };
+            out.extend_from_slice(&compressor.compress(&encoded)?);
+        }
+        #[cfg(feature = "codecs")]
+        Codec::Brotli => {
+            let mut brotli =
+                brotli::CompressorWriter::new(&mut out, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+            brotli.write_all(&encoded)?;
+            brotli.into_inner();
+        }
+        #[cfg(not(feature = "codecs"))]
+        Codec::Zstd | Codec::Brotli => return Err(without_codecs(codec)),
+    }
+
+    Ok(out)
+}
+
+/// Decodes `_transactionData` in any codec within the default `DecodeLimits`. A batch compressed
+/// with a zstd dictionary needs the same `dictionary` to decode.
+pub fn decode_transactions(
+    transaction_data: &[u8],
+    dictionary: Option<&[u8]>,
+) -> eyre::Result<Vec<SignedTransaction>> {
+    decode_transactions_within(transaction_data, dictionary, &DecodeLimits::default())
+}
+
+/// `decode_transactions` within `limits`. Exceeding them fails with a `LimitError`.
+#[cfg_attr(not(feature = "codecs"), allow(unused_variables))]
+pub fn decode_transactions_within(
+    transaction_data: &[u8],
+    dictionary: Option<&[u8]>,
+    limits: &DecodeLimits,
+) -> eyre::Result<Vec<SignedTransaction>> {
+    let codec = Codec::of(transaction_data)?;
+    let _span = tracing::debug_span!("decode", ?codec, bytes = transaction_data.len()).entered();
+    let payload = match codec.tag() {
+        Some(_) => &transaction_data[1..],
+        None => transaction_data,
+    };
+
+    let mut bytes = Vec::new();
+    match codec {
+        Codec::Zlib => read_bounded(ZlibDecoder::new(payload), limits, &mut bytes)?,
+        Codec::None => bytes.extend_from_slice(payload),
+        #[cfg(feature = "codecs")]
+        Codec::Zstd => match dictionary {
+            Some(dictionary) => read_bounded(
+                zstd::stream::read::Decoder::with_dictionary(payload, dictionary)?,
+                limits,
+                &mut bytes,
+            )?,
+            None => read_bounded(
+                zstd::stream::read::Decoder::with_buffer(payload)?,
+                limits,
+                &mut bytes,
+            )?,
+        },
+        #[cfg(feature = "codecs")]
+        Codec::Brotli => {
+            read_bounded(brotli::Decompressor::new(payload, 4096), limits, &mut bytes)?
+        }
+        #[cfg(not(feature = "codecs"))]
+        Codec::Zstd | Codec::Brotli => return Err(without_codecs(codec)),
+    }
+
+    let transactions = Vec::<SignedTransaction>::decode(&mut bytes.as_slice())?;
+    if transactions.len() > limits.max_transactions {
+        return Err(LimitError::TooManyTransactions {
+            count: transactions.len(),
+            max: limits.max_transactions,
+        }
+        .into());
+    }
+    for (index, tx) in transactions.iter().enumerate() {
+        limits
+            .check_transaction(tx)
+            .wrap_err_with(|| format!("Transaction {} of the batch", index))?;
+    }
+    Ok(transactions)
+}
+
+#[cfg(not(feature = "codecs"))]
+fn without_codecs(codec: Codec) -> eyre::Report {
+    eyre::eyre!("{:?} batches need the `codecs` feature", codec)
+}
+
+// Reads all of `reader` into `bytes`, failing past `max_decompressed` so that a few bytes of
+// calldata cannot inflate into more memory than a node has.
+fn read_bounded(reader: impl Read, limits: &DecodeLimits, bytes: &mut Vec<u8>) -> eyre::Result<()> {
+    let max = limits.max_decompressed;
+    reader.take(max as u64 + 1).read_to_end(bytes)?;
+    if bytes.len() > max {
+        return Err(LimitError::TooLarge { max }.into());
+    }
+    Ok(())
+}
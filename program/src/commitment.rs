@@ -0,0 +1,444 @@
+use alloy_primitives::{keccak256, Address, U256};
+use alloy_sol_types::SolValue;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data_root::{data_root, DataRoots};
+use crate::hashing::HashScheme;
+use crate::{
+    Account, AccountCommit, AccountCommitV1, AccountCommitV11, AccountCommitV12, AccountCommitV3,
+    AccountCommitV6, AccountCommitV7, AccountCommitV9, Contribution, LastEdit, UndoCommit,
+};
+
+/// Encoding of account leaves. The first byte of every leaf preimage is the version: the legacy
+/// `V0` preimage is a bare ABI-encoded `AccountCommit`, whose leading offset word always starts
+/// with `0x00`, while later versions prepend their version byte explicitly. A verifier can
+/// therefore check a proof for either version while a deployment migrates between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum CommitmentVersion {
+    #[default]
+    V0 = 0,
+    V1 = 1,
+    /// `V1` leaves, and an empty state commits to `empty_root` instead of zero.
+    V2 = 2,
+    /// `V2` with the account's writers in its leaf.
+    V3 = 3,
+    /// `V3` leaves ordered by account address instead of by hash, so an account keeps its
+    /// position in the tree across edits and a range of addresses is a range of leaves.
+    V4 = 4,
+    /// `V3` leaves in a sparse Merkle tree keyed by address (`smt`), with proofs of constant
+    /// depth for present and absent accounts alike.
+    V5 = 5,
+    /// `V5` with the account's data committed by its `data_root` and length instead of in full,
+    /// so an edit re-hashes only the chunks it changed.
+    V6 = 6,
+    /// `V6` with the account's `contributions`, in contributor order.
+    V7 = 7,
+    /// `V7` leaves in one sparse tree per canvas, and the state root that of a sparse tree over
+    /// the canvas roots (`namespace`).
+    V8 = 8,
+    /// `V8` with the account's `last_edits`, in editor order, which edit cooldowns go by.
+    V9 = 9,
+    /// `V9` hashed under `HashScheme::SeparatedKeccak`: leaves and nodes of every tree are
+    /// domain separated.
+    V10 = 10,
+    /// `V10` with the account's `undos`, in editor order, which `EditOp::RevertOwn` goes by.
+    V11 = 11,
+    /// `V11` with the account's `metadata`, its name and URI.
+    V12 = 12,
+}
+
+impl CommitmentVersion {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CommitmentVersion::V0),
+            1 => Some(CommitmentVersion::V1),
+            2 => Some(CommitmentVersion::V2),
+            3 => Some(CommitmentVersion::V3),
+            4 => Some(CommitmentVersion::V4),
+            5 => Some(CommitmentVersion::V5),
+            6 => Some(CommitmentVersion::V6),
+            7 => Some(CommitmentVersion::V7),
+            8 => Some(CommitmentVersion::V8),
+            9 => Some(CommitmentVersion::V9),
+            10 => Some(CommitmentVersion::V10),
+            11 => Some(CommitmentVersion::V11),
+            12 => Some(CommitmentVersion::V12),
+            _ => None,
+        }
+    }
+
+    /// Whether the state tree orders its leaves by account address rather than by leaf hash.
+    pub fn sorts_by_address(self) -> bool {
+        matches!(self, CommitmentVersion::V4)
+    }
+
+    /// Whether the state is committed in a `smt::SparseMerkleTree` instead of a `StateTree`.
+    pub fn is_sparse(self) -> bool {
+        matches!(
+            self,
+            CommitmentVersion::V5
+                | CommitmentVersion::V6
+                | CommitmentVersion::V7
+                | CommitmentVersion::V8
+                | CommitmentVersion::V9
+                | CommitmentVersion::V10
+                | CommitmentVersion::V11
+                | CommitmentVersion::V12
+        )
+    }
+
+    /// Whether accounts are committed per canvas, in a sparse tree each.
+    pub fn is_namespaced(self) -> bool {
+        matches!(
+            self,
+            CommitmentVersion::V8
+                | CommitmentVersion::V9
+                | CommitmentVersion::V10
+                | CommitmentVersion::V11
+                | CommitmentVersion::V12
+        )
+    }
+
+    /// Whether leaves commit the `owner` that `EditOp::Claim` and `EditOp::Transfer` set.
+    pub fn commits_owner(self) -> bool {
+        self != CommitmentVersion::V0
+    }
+
+    /// Whether leaves commit the `policy` that `EditOp::SetSchema` sets.
+    pub fn commits_policy(self) -> bool {
+        self != CommitmentVersion::V0
+    }
+
+    /// Whether leaves commit the `balance` that fees, deposits and withdrawals move.
+    pub fn commits_balance(self) -> bool {
+        self != CommitmentVersion::V0
+    }
+
+    /// Whether leaves commit the `writers` that `EditOp::Grant` and `EditOp::Revoke` change.
+    pub fn commits_writers(self) -> bool {
+        !matches!(
+            self,
+            CommitmentVersion::V0 | CommitmentVersion::V1 | CommitmentVersion::V2
+        )
+    }
+
+    /// Whether leaves commit the `last_edits` that `CanvasConfig::edit_cooldown` goes by.
+    pub fn commits_last_edits(self) -> bool {
+        matches!(
+            self,
+            CommitmentVersion::V9
+                | CommitmentVersion::V10
+                | CommitmentVersion::V11
+                | CommitmentVersion::V12
+        )
+    }
+
+    /// Whether leaves commit the `undos` that `CanvasConfig::undo_window` keeps.
+    pub fn commits_undos(self) -> bool {
+        matches!(self, CommitmentVersion::V11 | CommitmentVersion::V12)
+    }
+
+    /// Whether leaves commit the `metadata` that `EditOp::SetMetadata` sets.
+    pub fn commits_metadata(self) -> bool {
+        matches!(self, CommitmentVersion::V12)
+    }
+
+    /// How the sparse trees of this version hash their leaves and nodes.
+    pub fn hash_scheme(self) -> HashScheme {
+        match self {
+            CommitmentVersion::V10 | CommitmentVersion::V11 | CommitmentVersion::V12 => {
+                HashScheme::SeparatedKeccak
+            }
+            _ => HashScheme::Keccak,
+        }
+    }
+
+    pub fn from_preimage(preimage: &[u8]) -> Option<Self> {
+        Self::from_u8(*preimage.first()?)
+    }
+}
+
+// Preimage of the empty leaf after its version byte. No account leaf is this short.
+const EMPTY_LEAF: &[u8] = b"empty";
+
+/// State root of a state without accounts. Up to `V1` it is zero, which contracts tend to read
+/// as no commitment at all; from `V2` on it is the hash of an empty leaf tagged with the
+/// version.
+pub fn empty_root(version: CommitmentVersion) -> [u8; 32] {
+    match version {
+        CommitmentVersion::V0 | CommitmentVersion::V1 => [0; 32],
+        CommitmentVersion::V2
+        | CommitmentVersion::V3
+        | CommitmentVersion::V4
+        | CommitmentVersion::V5
+        | CommitmentVersion::V6
+        | CommitmentVersion::V7
+        | CommitmentVersion::V8
+        | CommitmentVersion::V9
+        | CommitmentVersion::V10
+        | CommitmentVersion::V11
+        | CommitmentVersion::V12 => keccak256([&[version as u8][..], EMPTY_LEAF].concat()).into(),
+    }
+}
+
+/// Whether two state roots commit to the same state, treating the empty roots of every
+/// version as equal, so batches from before a deployment moved to `V2` still check out.
+pub fn same_state_root(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    let empty = |root: &[u8; 32]| {
+        *root == empty_root(CommitmentVersion::V0)
+            || *root == empty_root(CommitmentVersion::V2)
+            || *root == empty_root(CommitmentVersion::V3)
+            || *root == empty_root(CommitmentVersion::V4)
+            || *root == empty_root(CommitmentVersion::V5)
+            || *root == empty_root(CommitmentVersion::V6)
+            || *root == empty_root(CommitmentVersion::V7)
+            || *root == empty_root(CommitmentVersion::V8)
+            || *root == empty_root(CommitmentVersion::V9)
+            || *root == empty_root(CommitmentVersion::V10)
+            || *root == empty_root(CommitmentVersion::V11)
+            || *root == empty_root(CommitmentVersion::V12)
+    };
+    a == b || (empty(a) && empty(b))
+}
+
+pub fn leaf_preimage(version: CommitmentVersion, address: &Address, account: &Account) -> Vec<u8> {
+    match version {
+        CommitmentVersion::V0 => AccountCommit {
+            account: *address,
+            nonce: U256::from(account.nonce),
+            data: account.data.clone(),
+            contributors: account.contributors.clone(),
+        }
+        .abi_encode(),
+        CommitmentVersion::V1 | CommitmentVersion::V2 => {
+            let commit = AccountCommitV1 {
+                account: *address,
+                nonce: U256::from(account.nonce),
+                data: account.data.clone(),
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+            };
+
+            let mut preimage = vec![version as u8];
+            preimage.extend(commit.abi_encode());
+            preimage
+        }
+        CommitmentVersion::V3 | CommitmentVersion::V4 | CommitmentVersion::V5 => {
+            let commit = AccountCommitV3 {
+                account: *address,
+                nonce: U256::from(account.nonce),
+                data: account.data.clone(),
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+                writers: account.writers.clone(),
+            };
+
+            let mut preimage = vec![version as u8];
+            preimage.extend(commit.abi_encode());
+            preimage
+        }
+        CommitmentVersion::V6
+        | CommitmentVersion::V7
+        | CommitmentVersion::V8
+        | CommitmentVersion::V9
+        | CommitmentVersion::V10
+        | CommitmentVersion::V11
+        | CommitmentVersion::V12 => {
+            chunked_preimage(version, address, account, data_root(&account.data))
+        }
+    }
+}
+
+// The preimage of the versions that commit data by its root.
+fn chunked_preimage(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+    data_root: [u8; 32],
+) -> Vec<u8> {
+    let mut preimage = vec![version as u8];
+    let (account_address, nonce, data_length) = (
+        *address,
+        U256::from(account.nonce),
+        U256::from(account.data.len()),
+    );
+    if version == CommitmentVersion::V6 {
+        preimage.extend(
+            AccountCommitV6 {
+                account: account_address,
+                nonce,
+                dataRoot: data_root.into(),
+                dataLength: data_length,
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+                writers: account.writers.clone(),
+            }
+            .abi_encode(),
+        );
+        return preimage;
+    }
+
+    let contributions = account
+        .contributions
+        .iter()
+        .map(|(contributor, stats)| Contribution {
+            contributor: *contributor,
+            edits: stats.edits,
+            inserted: stats.inserted,
+            deleted: stats.deleted,
+        })
+        .collect();
+    if !version.commits_last_edits() {
+        preimage.extend(
+            AccountCommitV7 {
+                account: account_address,
+                nonce,
+                dataRoot: data_root.into(),
+                dataLength: data_length,
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+                writers: account.writers.clone(),
+                contributions,
+            }
+            .abi_encode(),
+        );
+        return preimage;
+    }
+
+    let last_edits = account
+        .last_edits
+        .iter()
+        .map(|(editor, batch_index)| LastEdit {
+            editor: *editor,
+            batchIndex: *batch_index,
+        })
+        .collect();
+    if !version.commits_undos() {
+        preimage.extend(
+            AccountCommitV9 {
+                account: account_address,
+                nonce,
+                dataRoot: data_root.into(),
+                dataLength: data_length,
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+                writers: account.writers.clone(),
+                contributions,
+                lastEdits: last_edits,
+            }
+            .abi_encode(),
+        );
+        return preimage;
+    }
+
+    let undos = account
+        .undos
+        .iter()
+        .map(|(editor, undo)| UndoCommit {
+            editor: *editor,
+            txHash: undo.tx_hash,
+            batchIndex: undo.batch_index,
+            index: undo.index,
+            inserted: undo.inserted,
+            removed: undo.removed.clone(),
+        })
+        .collect();
+    if !version.commits_metadata() {
+        preimage.extend(
+            AccountCommitV11 {
+                account: account_address,
+                nonce,
+                dataRoot: data_root.into(),
+                dataLength: data_length,
+                contributors: account.contributors.clone(),
+                balance: account.balance,
+                owner: account.owner,
+                policy: account.policy,
+                writers: account.writers.clone(),
+                contributions,
+                lastEdits: last_edits,
+                undos,
+            }
+            .abi_encode(),
+        );
+        return preimage;
+    }
+
+    preimage.extend(
+        AccountCommitV12 {
+            account: account_address,
+            nonce,
+            dataRoot: data_root.into(),
+            dataLength: data_length,
+            contributors: account.contributors.clone(),
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers.clone(),
+            contributions,
+            lastEdits: last_edits,
+            undos,
+            name: account.metadata.name.clone(),
+            uri: account.metadata.uri.clone(),
+        }
+        .abi_encode(),
+    );
+    preimage
+}
+
+pub fn leaf_hash(version: CommitmentVersion, address: &Address, account: &Account) -> [u8; 32] {
+    version
+        .hash_scheme()
+        .leaf(&leaf_preimage(version, address, account))
+}
+
+/// `leaf_hash`, taking the data root from `roots` from `V6` on so that only changed chunks are
+/// re-hashed.
+pub fn leaf_hash_cached(
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+    roots: &mut DataRoots,
+) -> [u8; 32] {
+    match version {
+        CommitmentVersion::V6
+        | CommitmentVersion::V7
+        | CommitmentVersion::V8
+        | CommitmentVersion::V9
+        | CommitmentVersion::V10
+        | CommitmentVersion::V11
+        | CommitmentVersion::V12 => {
+            let data_root = roots.root(address, &account.data);
+            version
+                .hash_scheme()
+                .leaf(&chunked_preimage(version, address, account, data_root))
+        }
+        _ => leaf_hash(version, address, account),
+    }
+}
+
+/// The leaf hash of each of `accounts`, in order: encoded and hashed in parallel under the
+/// `parallel` feature and one after the other in the zkVM.
+pub fn leaf_hashes(version: CommitmentVersion, accounts: &[(&Address, &Account)]) -> Vec<[u8; 32]> {
+    let hash = |(address, account): &(&Address, &Account)| leaf_hash(version, address, account);
+
+    #[cfg(feature = "parallel")]
+    let hashes = accounts.par_iter().map(hash).collect();
+    #[cfg(not(feature = "parallel"))]
+    let hashes = accounts.iter().map(hash).collect();
+
+    hashes
+}
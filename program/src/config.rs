@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use crate::codec::Codec;
+use crate::commitment::CommitmentVersion;
+use crate::fees::FeeSchedule;
+use crate::limits::RateLimit;
+use crate::shuffle::Ordering;
+use crate::signing::SigningDomain;
+use crate::MAX_SIZE;
+
+/// What to do with a transaction that would grow an account's data past `max_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SizePolicy {
+    #[default]
+    Reject,
+    Truncate,
+}
+
+/// What to do with a transaction that fails to execute in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FailurePolicy {
+    /// Fail the whole batch.
+    #[default]
+    Abort,
+    /// Leave the state untouched for that transaction and commit a failed receipt for it.
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanvasConfig {
+    pub commitment: CommitmentVersion,
+    /// Maximum length of an account's data, in chars.
+    pub max_size: usize,
+    pub size_policy: SizePolicy,
+    pub domain: SigningDomain,
+    /// Accept transactions signed without a domain (versions below `TX_VERSION_DOMAIN`). Turn
+    /// off once clients have migrated.
+    pub legacy_signatures: bool,
+    /// Accept sender and sponsor signatures with a high `s`, for replaying batches posted before
+    /// they were rejected. Off by default, so every transaction has one encoding.
+    pub high_s_signatures: bool,
+    pub failure_policy: FailurePolicy,
+    pub ordering: Ordering,
+    pub rate_limit: RateLimit,
+    /// Free by default.
+    pub fees: FeeSchedule,
+    /// How batches are compressed as `_transactionData`.
+    pub codec: Codec,
+    /// Batches a sender has to wait after editing an account's data before editing it again,
+    /// zero for no cooldown. Needs `CommitmentVersion::V9`.
+    pub edit_cooldown: u64,
+    /// Batches a sender can revert their last edit of an account's data for, zero to keep no
+    /// undos; see `undo`. Needs `CommitmentVersion::V11`.
+    #[serde(default)]
+    pub undo_window: u64,
+    /// Reject batches without a valid signature of their sequencer; see `sequencer`.
+    #[serde(default)]
+    pub require_sequencer_signature: bool,
+}
+
+impl Default for CanvasConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentVersion::default(),
+            max_size: MAX_SIZE,
+            size_policy: SizePolicy::default(),
+            domain: SigningDomain::default(),
+            legacy_signatures: true,
+            high_s_signatures: false,
+            failure_policy: FailurePolicy::default(),
+            ordering: Ordering::default(),
+            rate_limit: RateLimit::default(),
+            fees: FeeSchedule::default(),
+            codec: Codec::default(),
+            edit_cooldown: 0,
+            undo_window: 0,
+            require_sequencer_signature: false,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use alloy_primitives::Address;
+use hashbrown::HashSet;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::ops::Range;
+
+use crate::config::CanvasConfig;
+use crate::error::CanvasError;
+use crate::recovery::SenderCache;
+use crate::sponsor::recover_sponsor;
+use crate::validator::ValueValidator;
+use crate::{AccountDB, CanvasProcessor, SignedTransaction, TransactionEffect};
+
+/// Every account a transaction from `sender`, vouched for by `sponsor`, can read, in the order
+/// `execute_transaction` fetches them. It writes no others.
+pub fn reads(
+    config: &CanvasConfig,
+    input: &SignedTransaction,
+    sender: Address,
+    sponsor: Option<Address>,
+) -> Vec<Address> {
+    let mut reads = vec![sender, input.tx.target()];
+    reads.extend(input.tx.sources());
+    if !config.fees.fee(input).is_zero() {
+        reads.extend(sponsor);
+        reads.push(config.fees.recipient);
+    }
+    reads
+}
+
+/// What a transaction touches: the accounts it reads and the rate-limit counter it counts
+/// against, its sponsor's or its sender's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    pub sender: Address,
+    pub sponsor: Option<Address>,
+    pub reads: Vec<Address>,
+}
+
+impl Access {
+    /// `None` when the sender or sponsor cannot be recovered, as the transaction fails then. A
+    /// transaction that declares an `access_list` reads it: it fails before reading anything
+    /// else, so only its signatures are needed to place it.
+    pub fn of(
+        config: &CanvasConfig,
+        input: &SignedTransaction,
+        senders: &SenderCache,
+    ) -> Option<Self> {
+        let sender = senders.recover(input, &config.domain).ok()?;
+        let sponsor = recover_sponsor(input, &config.domain).ok()?;
+        let reads = match input.tx.access_list.is_empty() {
+            true => reads(config, input, sender, sponsor),
+            false => input.tx.access_list.clone(),
+        };
+        Some(Self {
+            sender,
+            sponsor,
+            reads,
+        })
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &Address> {
+        self.reads.iter().chain(&self.sponsor)
+    }
+}
+
+/// Splits transactions with `accesses` into consecutive waves whose transactions touch
+/// pairwise disjoint accounts and counters. Each transaction of a wave sees the state the
+/// waves before it left whether the others ran before it or not, so a wave can run at once and
+/// writing its effects in order gives what running it serially does. A transaction whose access
+/// is unknown is a wave of its own.
+pub fn waves(accesses: &[Option<Access>]) -> Vec<Range<usize>> {
+    let mut waves = Vec::new();
+    let mut start = 0;
+    let mut touched: HashSet<Address> = HashSet::new();
+
+    for (i, access) in accesses.iter().enumerate() {
+        let conflicts = match access {
+            Some(access) => access.keys().any(|key| touched.contains(key)),
+            None => true,
+        };
+        if conflicts && start < i {
+            waves.push(start..i);
+            start = i;
+            touched.clear();
+        }
+        match access {
+            Some(access) => touched.extend(access.keys().copied()),
+            None => {
+                waves.push(i..i + 1);
+                start = i + 1;
+            }
+        }
+    }
+    if start < accesses.len() {
+        waves.push(start..accesses.len());
+    }
+    waves
+}
+
+impl<D: AccountDB, V: ValueValidator> CanvasProcessor<D, V> {
+    // Executes `transactions` in order, handing `apply` the effect of each, or why it failed,
+    // once the ones before it are applied. Under the `parallel` feature each of their `waves`
+    // is executed at once on rayon; the zkVM executes them one by one.
+    pub(crate) fn execute_each<E: From<CanvasError>>(
+        &mut self,
+        transactions: &[&SignedTransaction],
+        mut apply: impl FnMut(
+            &mut Self,
+            &SignedTransaction,
+            Result<TransactionEffect, CanvasError>,
+        ) -> Result<(), E>,
+    ) -> Result<(), E> {
+        #[cfg(not(feature = "parallel"))]
+        for tx in transactions {
+            let effect = self.execute_transaction(tx);
+            apply(self, tx, effect)?;
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            let accesses: Vec<Option<Access>> = transactions
+                .iter()
+                .map(|tx| Access::of(&self.config, tx, &self.senders))
+                .collect();
+            for wave in waves(&accesses) {
+                let wave_transactions = &transactions[wave.clone()];
+                let effects = self.execute_wave(wave_transactions, &accesses[wave]);
+                for (tx, effect) in wave_transactions.iter().zip(effects) {
+                    apply(self, tx, effect)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Executes the transactions of a wave on the state before it, each over a copy of the
+    // accounts it reads, so neither the db nor the processor is shared between threads.
+    #[cfg(feature = "parallel")]
+    fn execute_wave(
+        &self,
+        wave: &[&SignedTransaction],
+        accesses: &[Option<Access>],
+    ) -> Vec<Result<TransactionEffect, CanvasError>> {
+        if wave.len() < 2 {
+            return wave.iter().map(|tx| self.execute_transaction(tx)).collect();
+        }
+
+        // A db that fails to read leaves the transaction to `execute_transaction`, for the
+        // error it would fail with one by one.
+        let inputs: Vec<Option<(Address, crate::InMemoryDB)>> = accesses
+            .iter()
+            .map(|access| {
+                let access = access.as_ref()?;
+                let accounts = self.db.get_accounts(&access.reads).ok()?;
+                let db = crate::InMemoryDB::default();
+                db.accounts
+                    .borrow_mut()
+                    .extend(access.reads.iter().copied().zip(accounts));
+                Some((access.sender, db))
+            })
+            .collect();
+        let (config, batch, limits, validator) =
+            (self.config, self.batch, &self.limits, &self.validator);
+
+        let effects: Vec<Option<Result<TransactionEffect, CanvasError>>> = inputs
+            .into_par_iter()
+            .zip(wave.par_iter())
+            .map(|(input, tx)| {
+                let (sender, db) = input?;
+                let mut processor = CanvasProcessor::with_config(db, config)
+                    .with_validator(validator)
+                    .with_limits(limits.clone());
+                processor.batch = batch;
+                processor.senders.insert(tx, &config.domain, sender);
+                Some(processor.execute_transaction(tx))
+            })
+            .collect();
+
+        effects
+            .into_iter()
+            .zip(wave)
+            .map(|(effect, tx)| effect.unwrap_or_else(|| self.execute_transaction(tx)))
+            .collect()
+    }
+}
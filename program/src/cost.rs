@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::blob::blobs_needed;
+use crate::edit::EditOp;
+use crate::SignedTransaction;
+
+/// A linear model of the zkVM cycles of a batch, from the work that dominates proving: recovering
+/// signatures, hashing account leaves and editing data. The defaults are a rough starting point;
+/// fit them to the cycle counts the prover reports for the batches a sequencer actually sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleModel {
+    /// Reading the input, the state roots before and after, and committing the public values.
+    pub batch: u64,
+    /// Recovering a sender or sponsor and checking nonce, expiry and rate limit, failed or not.
+    pub signature: u64,
+    /// Decoding and checking one edit op.
+    pub op: u64,
+    /// Per byte an op writes or deletes.
+    pub edited_byte: u64,
+    /// Hashing the leaf of an account a transaction writes and its path to the root, on top of
+    /// `data_byte` per byte of its data.
+    pub leaf: u64,
+    pub data_byte: u64,
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        Self {
+            batch: 2_000_000,
+            signature: 250_000,
+            op: 12_000,
+            edited_byte: 150,
+            leaf: 80_000,
+            data_byte: 40,
+        }
+    }
+}
+
+impl CycleModel {
+    pub fn estimate(
+        &self,
+        signatures: u64,
+        ops: u64,
+        edited_bytes: u64,
+        leaves: u64,
+        data_bytes: u64,
+    ) -> u64 {
+        self.batch
+            + self.signature * signatures
+            + self.op * ops
+            + self.edited_byte * edited_bytes
+            + self.leaf * leaves
+            + self.data_byte * data_bytes
+    }
+
+    /// What `tx` adds to a batch, judged from the transaction alone: its signatures, ops and the
+    /// bytes they write or delete, and a leaf for the sender, the target and every account its
+    /// ops read. The data of the accounts it writes is in the state, so it is not counted.
+    pub fn transaction(&self, tx: &SignedTransaction) -> u64 {
+        let ops = tx.tx.edit_ops();
+        let edited: usize = ops
+            .iter()
+            .map(|op| match op {
+                EditOp::Delete { count, .. } => *count,
+                EditOp::Replace { count, value, .. } => count + value.len(),
+                EditOp::Copy { count, .. } => *count,
+                EditOp::Move { count, .. } => count * 2,
+                op => op.value().map_or(0, str::len),
+            })
+            .sum();
+        let signatures = 1 + tx.sponsor.is_some() as u64;
+        let leaves = 2 + tx.tx.sources().len() as u64;
+
+        self.estimate(signatures, ops.len() as u64, edited as u64, leaves, 0) - self.batch
+    }
+}
+
+/// Calldata gas per zero and per nonzero byte, as of EIP-2028.
+pub const CALLDATA_ZERO_BYTE_GAS: u64 = 4;
+pub const CALLDATA_NONZERO_BYTE_GAS: u64 = 16;
+/// Blob gas per blob, as of EIP-4844.
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+
+/// How a batch's transaction data is posted to L1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PostingMode {
+    Calldata,
+    Blob,
+}
+
+/// What posting a batch's transaction data costs on L1. The base cost of the transaction and the
+/// proof and public values posted with it are not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Cost {
+    pub mode: PostingMode,
+    pub bytes: u64,
+    /// Zero bytes of the data, which calldata charges less for.
+    pub zero_bytes: u64,
+    /// Blobs the data takes, zero as calldata.
+    pub blobs: u64,
+    /// Calldata gas or blob gas.
+    pub gas: u64,
+    /// `gas` at the price it was estimated at, in wei.
+    pub wei: u128,
+}
+
+/// The L1 cost of posting `transaction_data` by `mode` at `gas_price` in wei: the execution gas
+/// price for calldata, the blob base fee for blobs. Blobs are charged whole, however little of
+/// the last one the data fills.
+pub fn estimate_l1_cost(transaction_data: &[u8], mode: PostingMode, gas_price: u128) -> L1Cost {
+    let bytes = transaction_data.len() as u64;
+    let zero_bytes = transaction_data.iter().filter(|byte| **byte == 0).count() as u64;
+    let (blobs, gas) = match mode {
+        PostingMode::Calldata => (
+            0,
+            zero_bytes * CALLDATA_ZERO_BYTE_GAS + (bytes - zero_bytes) * CALLDATA_NONZERO_BYTE_GAS,
+        ),
+        PostingMode::Blob => {
+            let blobs = blobs_needed(transaction_data.len()) as u64;
+            (blobs, blobs * GAS_PER_BLOB)
+        }
+    };
+
+    L1Cost {
+        mode,
+        bytes,
+        zero_bytes,
+        blobs,
+        gas,
+        wei: gas as u128 * gas_price,
+    }
+}
@@ -0,0 +1,80 @@
+use alloy_primitives::{keccak256, Address};
+use hashbrown::HashMap;
+
+/// Bytes of account data per chunk of the data tree.
+pub const CHUNK_BYTES: usize = 256;
+
+pub fn chunk_hash(chunk: &[u8]) -> [u8; 32] {
+    keccak256(chunk).into()
+}
+
+/// Root of the tree over the chunk hashes of an account's data, in chunk order: a parent is
+/// `keccak256(left || right)` and a last odd node moves up unchanged. Zero for empty data.
+pub fn root_of_chunks(hashes: &[[u8; 32]]) -> [u8; 32] {
+    let mut layer = hashes.to_vec();
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => keccak256([left.as_slice(), right.as_slice()].concat()).into(),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    layer.first().copied().unwrap_or_default()
+}
+
+/// `root_of_chunks` of `data` split into `CHUNK_BYTES` chunks, the last one possibly shorter.
+pub fn data_root(data: &str) -> [u8; 32] {
+    let hashes: Vec<[u8; 32]> = data
+        .as_bytes()
+        .chunks(CHUNK_BYTES)
+        .map(chunk_hash)
+        .collect();
+    root_of_chunks(&hashes)
+}
+
+#[derive(Debug, Clone, Default)]
+struct Cached {
+    data: String,
+    hashes: Vec<[u8; 32]>,
+    root: [u8; 32],
+}
+
+/// Data roots of the accounts hashed so far, with their chunk hashes and the data they were
+/// taken over. A new root re-hashes only the chunks whose bytes changed since, at the price of a
+/// copy of the data of every account in the cache.
+#[derive(Debug, Clone, Default)]
+pub struct DataRoots {
+    accounts: HashMap<Address, Cached>,
+}
+
+impl DataRoots {
+    pub fn root(&mut self, address: &Address, data: &str) -> [u8; 32] {
+        let cached = self.accounts.entry(*address).or_default();
+        if cached.data == data {
+            return cached.root;
+        }
+
+        let old: Vec<&[u8]> = cached.data.as_bytes().chunks(CHUNK_BYTES).collect();
+        let hashes: Vec<[u8; 32]> = data
+            .as_bytes()
+            .chunks(CHUNK_BYTES)
+            .enumerate()
+            .map(|(i, chunk)| match old.get(i) {
+                Some(previous) if *previous == chunk => cached.hashes[i],
+                _ => chunk_hash(chunk),
+            })
+            .collect();
+
+        cached.root = root_of_chunks(&hashes);
+        cached.hashes = hashes;
+        cached.data = data.to_string();
+        cached.root
+    }
+
+    pub fn remove(&mut self, address: &Address) {
+        self.accounts.remove(address);
+    }
+}
@@ -0,0 +1,95 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{Account, InMemoryDB};
+
+/// How one account differs between two states.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AccountChange {
+    Added {
+        address: Address,
+        account: Account,
+    },
+    Removed {
+        address: Address,
+        account: Account,
+    },
+    /// Contributors in `after` but not `before` and the other way round are listed apart, since a
+    /// reordered list changes the leaf as well.
+    #[serde(rename_all = "camelCase")]
+    Modified {
+        address: Address,
+        before: Account,
+        after: Account,
+        contributors_added: Vec<Address>,
+        contributors_removed: Vec<Address>,
+    },
+}
+
+impl AccountChange {
+    pub fn address(&self) -> &Address {
+        match self {
+            AccountChange::Added { address, .. }
+            | AccountChange::Removed { address, .. }
+            | AccountChange::Modified { address, .. } => address,
+        }
+    }
+}
+
+/// Every account that differs between two states, in address order. Comparing a host replay
+/// with the state a zkVM run started from and ended with shows where their roots diverge.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub changes: Vec<AccountChange>,
+}
+
+impl StateDiff {
+    pub fn between(before: &InMemoryDB, after: &InMemoryDB) -> Self {
+        let before = before.accounts.borrow();
+        let after = after.accounts.borrow();
+
+        let mut addresses: Vec<&Address> = before.keys().chain(after.keys()).collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let changes = addresses
+            .into_iter()
+            .filter_map(|address| {
+                let address = *address;
+                match (before.get(&address), after.get(&address)) {
+                    (None, Some(account)) => Some(AccountChange::Added {
+                        address,
+                        account: account.clone(),
+                    }),
+                    (Some(account), None) => Some(AccountChange::Removed {
+                        address,
+                        account: account.clone(),
+                    }),
+                    (Some(old), Some(new)) if old != new => Some(AccountChange::Modified {
+                        address,
+                        contributors_added: missing_from(&new.contributors, &old.contributors),
+                        contributors_removed: missing_from(&old.contributors, &new.contributors),
+                        before: old.clone(),
+                        after: new.clone(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+// The entries of `of` that are not in `other`.
+fn missing_from(of: &[Address], other: &[Address]) -> Vec<Address> {
+    of.iter()
+        .filter(|address| !other.contains(address))
+        .copied()
+        .collect()
+}
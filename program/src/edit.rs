@@ -0,0 +1,547 @@
+use alloy_primitives::{Address, B256, U256};
+use alloy_rlp::{BufMut, Decodable, Encodable, Header};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::gap_buffer::GapBuffer;
+use crate::schema::Schema;
+use crate::{Data, TX_VERSION_BYTES, TX_VERSION_GRAPHEMES};
+
+const INSERT: u8 = 0;
+const DELETE: u8 = 1;
+const REPLACE: u8 = 2;
+const APPEND: u8 = 3;
+const SET_SCHEMA: u8 = 4;
+const CLAIM: u8 = 5;
+const TRANSFER: u8 = 6;
+const GRANT: u8 = 7;
+const REVOKE: u8 = 8;
+const WITHDRAW: u8 = 9;
+const COPY: u8 = 10;
+const MOVE: u8 = 11;
+const CLEAR: u8 = 12;
+const REVERT_OWN: u8 = 13;
+const SET_METADATA: u8 = 14;
+
+/// A single edit of an account's data. Encoded as an RLP list whose first item is the op tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditOp {
+    Insert {
+        index: usize,
+        value: String,
+    },
+    Delete {
+        index: usize,
+        count: usize,
+    },
+    Replace {
+        index: usize,
+        count: usize,
+        value: String,
+    },
+    Append {
+        value: String,
+    },
+    /// Registers, or with `None` clears, the target account's `Schema`. Only its owner may.
+    SetSchema {
+        schema: Option<Schema>,
+    },
+    /// Makes the sender the owner of the unclaimed target account.
+    Claim,
+    /// Hands the target account to `owner`, or with zero leaves it unclaimed. Only its owner may.
+    Transfer {
+        owner: Address,
+    },
+    /// Lets `writer` edit the claimed target account. Only its owner may.
+    Grant {
+        writer: Address,
+    },
+    Revoke {
+        writer: Address,
+    },
+    /// Burns `amount` of the sender's balance for `recipient` on L1. Must target the record
+    /// address `bridge::withdrawal_address` derives from the sender and the transaction nonce.
+    Withdraw {
+        recipient: Address,
+        amount: U256,
+    },
+    /// Inserts at `at` the `count` units of `source`'s data from `index`, as the ops before it
+    /// left them. `source` is an account of the transaction's canvas and may be the target.
+    Copy {
+        source: Address,
+        index: usize,
+        count: usize,
+        at: usize,
+    },
+    /// `Copy` that also deletes the range from `source`, which the sender has to be allowed to
+    /// write. Within the target, `at` is addressed against the data with the range taken out.
+    Move {
+        source: Address,
+        index: usize,
+        count: usize,
+        at: usize,
+    },
+    /// Empties the target account's data and drops its schema, writers, contributors and
+    /// metadata. Only its owner may. An account left with nothing else, unclaimed and with no
+    /// nonce or balance, is removed from the state.
+    Clear,
+    /// Puts back what the sender's edit `tx_hash` of the target account replaced, if it is their
+    /// last edit of it and nobody changed what it wrote since; see `undo`. Must be the
+    /// transaction's only op.
+    RevertOwn {
+        tx_hash: B256,
+    },
+    /// Sets the target account's `Metadata`, or with both empty clears it. Only its owner may.
+    /// Within `metadata::MAX_NAME` and `metadata::MAX_URI`, and not checked against the palette.
+    SetMetadata {
+        name: String,
+        uri: String,
+    },
+}
+
+/// What the indices and counts of a transaction's edits count, set by its version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Addressing {
+    /// Rust `char`s, Unicode scalar values. Versions up to `TX_VERSION_DOMAIN`.
+    #[default]
+    Chars,
+    /// UTF-8 bytes, as `TextEncoder` counts in JS. `TX_VERSION_BYTES`.
+    Bytes,
+    /// Extended grapheme clusters, as `Intl.Segmenter` counts in JS. `TX_VERSION_GRAPHEMES`.
+    Graphemes,
+}
+
+impl Addressing {
+    pub fn of(version: u8) -> Self {
+        match version {
+            TX_VERSION_BYTES => Addressing::Bytes,
+            TX_VERSION_GRAPHEMES => Addressing::Graphemes,
+            _ => Addressing::Chars,
+        }
+    }
+
+    /// `index..index + count` of `data` in chars, as `(index, count)`.
+    pub fn range_to_chars(
+        self,
+        data: &GapBuffer,
+        index: usize,
+        count: usize,
+    ) -> Result<(usize, usize), EditError> {
+        if self == Addressing::Chars {
+            check_range(index, count, data.len())?;
+            return Ok((index, count));
+        }
+
+        let offsets = self.char_offsets(data);
+        check_range(index, count, offsets.len() - 1)?;
+        let offset = |at: usize| offsets[at].ok_or(EditError::NotCharBoundary(at));
+        let (start, end) = (offset(index)?, offset(index + count)?);
+        Ok((start, end - start))
+    }
+
+    // Char offset of every position `data` can be addressed at, `None` inside a UTF-8 sequence.
+    fn char_offsets(self, data: &GapBuffer) -> Vec<Option<usize>> {
+        match self {
+            Addressing::Chars => (0..=data.len()).map(Some).collect(),
+            Addressing::Bytes => {
+                let mut offsets = Vec::with_capacity(data.len() + 1);
+                for (i, c) in data.chars().enumerate() {
+                    offsets.push(Some(i));
+                    offsets.extend((1..c.len_utf8()).map(|_| None));
+                }
+                offsets.push(Some(data.len()));
+                offsets
+            }
+            Addressing::Graphemes => {
+                let text = String::from(data);
+                let mut offsets = Vec::new();
+                let mut chars = 0;
+                for grapheme in text.graphemes(true) {
+                    offsets.push(Some(chars));
+                    chars += grapheme.chars().count();
+                }
+                offsets.push(Some(chars));
+                offsets
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// `index..index + count` does not fit in data of length `len`, all in the transaction's
+    /// `Addressing`.
+    OutOfRange {
+        index: usize,
+        count: usize,
+        len: usize,
+    },
+    /// A byte-addressed edit starts or ends inside a UTF-8 sequence.
+    NotCharBoundary(usize),
+    /// The edited data would be `size` chars long, more than the configured `max`.
+    TooLarge { size: usize, max: usize },
+    /// The value contains a character the processor's `ValueValidator` does not allow.
+    InvalidValue(char),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfRange { index, count, len } => write!(
+                f,
+                "Edit at index {} with count {} is out of bounds for data of length {}",
+                index, count, len
+            ),
+            EditError::TooLarge { size, max } => write!(
+                f,
+                "Edited data of length {} exceeds the maximum size of {}",
+                size, max
+            ),
+            EditError::NotCharBoundary(index) => {
+                write!(f, "Byte index {} is not on a char boundary", index)
+            }
+            EditError::InvalidValue(c) => write!(f, "Invalid character {:?} in edit value", c),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+fn check_range(index: usize, count: usize, len: usize) -> Result<(), EditError> {
+    match index.checked_add(count) {
+        Some(end) if end <= len => Ok(()),
+        _ => Err(EditError::OutOfRange { index, count, len }),
+    }
+}
+
+impl EditOp {
+    /// The same edit with its index and count in chars, as `apply` takes them. `Copy` and
+    /// `Move` are addressed against two datas, so the processor converts them itself.
+    pub fn to_chars(&self, data: &GapBuffer, addressing: Addressing) -> Result<Self, EditError> {
+        let (index, count) = match self {
+            EditOp::Insert { index, .. } => (*index, 0),
+            EditOp::Delete { index, count } | EditOp::Replace { index, count, .. } => {
+                (*index, *count)
+            }
+            _ => return Ok(self.clone()),
+        };
+        let (start, count) = addressing.range_to_chars(data, index, count)?;
+
+        Ok(match self {
+            EditOp::Insert { value, .. } => EditOp::Insert {
+                index: start,
+                value: value.clone(),
+            },
+            EditOp::Delete { .. } => EditOp::Delete {
+                index: start,
+                count,
+            },
+            EditOp::Replace { value, .. } => EditOp::Replace {
+                index: start,
+                count,
+                value: value.clone(),
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    /// The account a `Copy` or `Move` reads, named as in the transaction.
+    pub fn source(&self) -> Option<&Address> {
+        match self {
+            EditOp::Copy { source, .. } | EditOp::Move { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Applies the edit, leaving `data` untouched if it is out of range.
+    pub fn apply(&self, data: &mut GapBuffer) -> Result<(), EditError> {
+        match self {
+            EditOp::Insert { index, value } => {
+                check_range(*index, 0, data.len())?;
+                data.insert(*index, value);
+            }
+            EditOp::Delete { index, count } => {
+                check_range(*index, *count, data.len())?;
+                data.delete(*index, *count);
+            }
+            EditOp::Replace {
+                index,
+                count,
+                value,
+            } => {
+                check_range(*index, *count, data.len())?;
+                data.delete(*index, *count);
+                data.insert(*index, value);
+            }
+            EditOp::Append { value } => {
+                data.push_str(value);
+            }
+            EditOp::Clear => {
+                data.truncate(0);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The text the edit writes, if any.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            EditOp::Insert { value, .. }
+            | EditOp::Replace { value, .. }
+            | EditOp::Append { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Whether the op changes the data, which claimed accounts only let their writers do.
+    pub fn is_data_edit(&self) -> bool {
+        matches!(
+            self,
+            EditOp::Insert { .. }
+                | EditOp::Delete { .. }
+                | EditOp::Replace { .. }
+                | EditOp::Append { .. }
+                | EditOp::Copy { .. }
+                | EditOp::Move { .. }
+                | EditOp::RevertOwn { .. }
+        )
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            EditOp::Insert { .. } => INSERT,
+            EditOp::Delete { .. } => DELETE,
+            EditOp::Replace { .. } => REPLACE,
+            EditOp::Append { .. } => APPEND,
+            EditOp::SetSchema { .. } => SET_SCHEMA,
+            EditOp::Claim => CLAIM,
+            EditOp::Transfer { .. } => TRANSFER,
+            EditOp::Grant { .. } => GRANT,
+            EditOp::Revoke { .. } => REVOKE,
+            EditOp::Withdraw { .. } => WITHDRAW,
+            EditOp::Copy { .. } => COPY,
+            EditOp::Move { .. } => MOVE,
+            EditOp::Clear => CLEAR,
+            EditOp::RevertOwn { .. } => REVERT_OWN,
+            EditOp::SetMetadata { .. } => SET_METADATA,
+        }
+    }
+
+    fn payload_length(&self) -> usize {
+        self.tag().length()
+            + match self {
+                EditOp::Insert { index, value } => index.length() + value.length(),
+                EditOp::Delete { index, count } => index.length() + count.length(),
+                EditOp::Replace {
+                    index,
+                    count,
+                    value,
+                } => index.length() + count.length() + value.length(),
+                EditOp::Append { value } => value.length(),
+                EditOp::SetSchema { schema } => policy(schema).length(),
+                EditOp::Claim | EditOp::Clear => 0,
+                EditOp::Transfer { owner } => owner.length(),
+                EditOp::Grant { writer } | EditOp::Revoke { writer } => writer.length(),
+                EditOp::Withdraw { recipient, amount } => recipient.length() + amount.length(),
+                EditOp::Copy {
+                    source,
+                    index,
+                    count,
+                    at,
+                }
+                | EditOp::Move {
+                    source,
+                    index,
+                    count,
+                    at,
+                } => source.length() + index.length() + count.length() + at.length(),
+                EditOp::RevertOwn { tx_hash } => tx_hash.length(),
+                EditOp::SetMetadata { name, uri } => name.length() + uri.length(),
+            }
+    }
+}
+
+// Schemas are encoded as the account policy word they are stored as.
+fn policy(schema: &Option<Schema>) -> B256 {
+    schema.map_or(B256::ZERO, |schema| schema.to_policy())
+}
+
+// Legacy (v0) edits: a zero count inserts `value` at `index`, anything else deletes.
+impl From<&Data> for EditOp {
+    fn from(data: &Data) -> Self {
+        match data.count {
+            0 => EditOp::Insert {
+                index: data.index,
+                value: data.value.clone(),
+            },
+            count => EditOp::Delete {
+                index: data.index,
+                count,
+            },
+        }
+    }
+}
+
+impl Encodable for EditOp {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+
+        self.tag().encode(out);
+        match self {
+            EditOp::Insert { index, value } => {
+                index.encode(out);
+                value.encode(out);
+            }
+            EditOp::Delete { index, count } => {
+                index.encode(out);
+                count.encode(out);
+            }
+            EditOp::Replace {
+                index,
+                count,
+                value,
+            } => {
+                index.encode(out);
+                count.encode(out);
+                value.encode(out);
+            }
+            EditOp::Append { value } => {
+                value.encode(out);
+            }
+            EditOp::SetSchema { schema } => {
+                policy(schema).encode(out);
+            }
+            EditOp::Claim | EditOp::Clear => {}
+            EditOp::Transfer { owner } => {
+                owner.encode(out);
+            }
+            EditOp::Grant { writer } | EditOp::Revoke { writer } => {
+                writer.encode(out);
+            }
+            EditOp::Withdraw { recipient, amount } => {
+                recipient.encode(out);
+                amount.encode(out);
+            }
+            EditOp::Copy {
+                source,
+                index,
+                count,
+                at,
+            }
+            | EditOp::Move {
+                source,
+                index,
+                count,
+                at,
+            } => {
+                source.encode(out);
+                index.encode(out);
+                count.encode(out);
+                at.encode(out);
+            }
+            EditOp::RevertOwn { tx_hash } => {
+                tx_hash.encode(out);
+            }
+            EditOp::SetMetadata { name, uri } => {
+                name.encode(out);
+                uri.encode(out);
+            }
+        }
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for EditOp {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let Header {
+            list,
+            payload_length,
+        } = Header::decode(buf)?;
+        if !list {
+            return Err(alloy_rlp::Error::UnexpectedString);
+        }
+
+        let started_len = buf.len();
+        if started_len < payload_length {
+            return Err(alloy_rlp::Error::InputTooShort);
+        }
+
+        let op = match u8::decode(buf)? {
+            INSERT => EditOp::Insert {
+                index: Decodable::decode(buf)?,
+                value: Decodable::decode(buf)?,
+            },
+            DELETE => EditOp::Delete {
+                index: Decodable::decode(buf)?,
+                count: Decodable::decode(buf)?,
+            },
+            REPLACE => EditOp::Replace {
+                index: Decodable::decode(buf)?,
+                count: Decodable::decode(buf)?,
+                value: Decodable::decode(buf)?,
+            },
+            APPEND => EditOp::Append {
+                value: Decodable::decode(buf)?,
+            },
+            SET_SCHEMA => EditOp::SetSchema {
+                schema: Schema::from_policy(&Decodable::decode(buf)?)
+                    .map_err(|_| alloy_rlp::Error::Custom("unknown schema"))?,
+            },
+            CLAIM => EditOp::Claim,
+            TRANSFER => EditOp::Transfer {
+                owner: Decodable::decode(buf)?,
+            },
+            GRANT => EditOp::Grant {
+                writer: Decodable::decode(buf)?,
+            },
+            REVOKE => EditOp::Revoke {
+                writer: Decodable::decode(buf)?,
+            },
+            WITHDRAW => EditOp::Withdraw {
+                recipient: Decodable::decode(buf)?,
+                amount: Decodable::decode(buf)?,
+            },
+            COPY => EditOp::Copy {
+                source: Decodable::decode(buf)?,
+                index: Decodable::decode(buf)?,
+                count: Decodable::decode(buf)?,
+                at: Decodable::decode(buf)?,
+            },
+            MOVE => EditOp::Move {
+                source: Decodable::decode(buf)?,
+                index: Decodable::decode(buf)?,
+                count: Decodable::decode(buf)?,
+                at: Decodable::decode(buf)?,
+            },
+            CLEAR => EditOp::Clear,
+            REVERT_OWN => EditOp::RevertOwn {
+                tx_hash: Decodable::decode(buf)?,
+            },
+            SET_METADATA => EditOp::SetMetadata {
+                name: Decodable::decode(buf)?,
+                uri: Decodable::decode(buf)?,
+            },
+            _ => return Err(alloy_rlp::Error::Custom("unknown edit op")),
+        };
+
+        let consumed = started_len - buf.len();
+        if consumed != payload_length {
+            return Err(alloy_rlp::Error::ListLengthMismatch {
+                expected: payload_length,
+                got: consumed,
+            });
+        }
+
+        Ok(op)
+    }
+}
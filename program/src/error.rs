@@ -0,0 +1,77 @@
+use alloy_primitives::{Address, SignatureError};
+use thiserror::Error;
+
+use crate::access::AccessError;
+use crate::bridge::BridgeError;
+use crate::edit::EditError;
+use crate::expiry::ExpiryError;
+use crate::fees::FeeError;
+use crate::limits::LimitError;
+use crate::metadata::MetadataError;
+use crate::nonce::NonceError;
+use crate::schema::SchemaError;
+use crate::undo::UndoError;
+
+/// Why a transaction, a db access or a checkpoint operation failed. Callers that only report
+/// errors can keep using `?` into `eyre`; the rest can match on the variant.
+#[derive(Debug, Error)]
+pub enum CanvasError {
+    /// A transaction of a version after `TX_VERSION_LATEST`, e.g. one built in JSON.
+    #[error("Unknown transaction version {0}")]
+    UnknownVersion(u8),
+    #[error("Legacy signed transactions (version {0}) are no longer accepted")]
+    LegacySignature(u8),
+    /// A sender or sponsor signature with a high `s`; see `signing::is_low_s`.
+    #[error("Signature is malleable: s is in the upper half of the curve order")]
+    MalleableSignature,
+    #[error("Failed to recover the sender: {0}")]
+    Recovery(#[from] SignatureError),
+    #[error("Failed to recover the sponsor: {0}")]
+    SponsorRecovery(SignatureError),
+    #[error(transparent)]
+    Nonce(#[from] NonceError),
+    #[error(transparent)]
+    Expiry(#[from] ExpiryError),
+    #[error(transparent)]
+    Fee(#[from] FeeError),
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+    #[error(transparent)]
+    Edit(#[from] EditError),
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    #[error(transparent)]
+    Access(#[from] AccessError),
+    #[error(transparent)]
+    Bridge(#[from] BridgeError),
+    #[error(transparent)]
+    Undo(#[from] UndoError),
+    #[error(transparent)]
+    Metadata(#[from] MetadataError),
+    /// A `WitnessDB` was asked for an account its witness does not cover.
+    #[error("Account {0} is not in the witness")]
+    NotInWitness(Address),
+    /// A db kept outside the process, e.g. in a SQL database, failed to read or write.
+    #[error("Account db failed: {0}")]
+    Db(String),
+    /// A transaction touched an account its `access_list` does not declare.
+    #[error("Account {0} is not in the transaction's access list")]
+    Undeclared(Address),
+    #[error("Checkpoint was already reverted to or committed")]
+    StaleCheckpoint,
+    /// A transaction named a canvas other than the default one under a commitment version
+    /// without canvases.
+    #[error("Canvas {0} needs commitment version 8")]
+    NotNamespaced(u64),
+    /// `sender` edited `account` within the last `CanvasConfig::edit_cooldown` batches.
+    #[error("{sender} cannot edit {account} again before batch {until}")]
+    Cooldown {
+        account: Address,
+        sender: Address,
+        until: u64,
+    },
+    /// An edit cooldown is configured under a commitment version that does not commit the
+    /// edits it goes by.
+    #[error("Edit cooldowns need commitment version 9")]
+    CooldownNotCommitted,
+}
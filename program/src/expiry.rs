@@ -0,0 +1,56 @@
+use std::fmt;
+
+use crate::batch::BatchContext;
+use crate::{Transaction, TX_VERSION_EXPIRY};
+
+/// A transaction reached a batch past the window its sender signed for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpiryError {
+    /// Valid up to batch `valid_until`, included in batch `batch`.
+    Batch { valid_until: u64, batch: u64 },
+    /// Valid up to `valid_until`, included in a batch built at `timestamp`.
+    Timestamp { valid_until: u64, timestamp: u64 },
+}
+
+impl fmt::Display for ExpiryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpiryError::Batch { valid_until, batch } => write!(
+                f,
+                "Transaction expired after batch {}, included in batch {}",
+                valid_until, batch
+            ),
+            ExpiryError::Timestamp {
+                valid_until,
+                timestamp,
+            } => write!(
+                f,
+                "Transaction expired at {}, included in a batch built at {}",
+                valid_until, timestamp
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExpiryError {}
+
+/// Checks that `tx` may still be included in `batch`. Transactions before `TX_VERSION_EXPIRY`
+/// never expire: their window is not signed, so whoever relays them could set it.
+pub fn check(tx: &Transaction, batch: &BatchContext) -> Result<(), ExpiryError> {
+    if tx.version < TX_VERSION_EXPIRY {
+        return Ok(());
+    }
+    if tx.valid_until_batch != 0 && batch.index > tx.valid_until_batch {
+        return Err(ExpiryError::Batch {
+            valid_until: tx.valid_until_batch,
+            batch: batch.index,
+        });
+    }
+    if tx.valid_until_timestamp != 0 && batch.timestamp > tx.valid_until_timestamp {
+        return Err(ExpiryError::Timestamp {
+            valid_until: tx.valid_until_timestamp,
+            timestamp: batch.timestamp,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use alloy_primitives::{Address, U256};
+use alloy_rlp::Encodable;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Account, SignedTransaction};
+
+/// What a transaction pays out of its sender's balance to `recipient`. Zero rates charge nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Charged per edit op.
+    pub per_op: U256,
+    /// Charged per byte of the signed transaction's RLP encoding, the data posted for it.
+    pub per_byte: U256,
+    /// Credited with every fee; an account in the state like any other.
+    pub recipient: Address,
+}
+
+impl FeeSchedule {
+    pub fn is_free(&self) -> bool {
+        self.per_op.is_zero() && self.per_byte.is_zero()
+    }
+
+    /// Saturates rather than overflows, which no balance covers either.
+    pub fn fee(&self, tx: &SignedTransaction) -> U256 {
+        let ops = U256::from(tx.tx.edit_ops().len());
+        let bytes = U256::from(tx.length());
+        self.per_op
+            .saturating_mul(ops)
+            .saturating_add(self.per_byte.saturating_mul(bytes))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeeError {
+    /// `account` cannot pay `fee` with a balance of `balance`.
+    InsufficientBalance {
+        account: Address,
+        balance: U256,
+        fee: U256,
+    },
+    /// Crediting a fee would take the balance of the recipient past `U256::MAX`.
+    BalanceOverflow(Address),
+    /// A fee schedule that charges anything under a commitment version that does not commit
+    /// the balances it moves.
+    NotCommitted,
+}
+
+impl fmt::Display for FeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeError::InsufficientBalance {
+                account,
+                balance,
+                fee,
+            } => write!(
+                f,
+                "{} cannot pay a fee of {} with a balance of {}",
+                account, fee, balance
+            ),
+            FeeError::BalanceOverflow(account) => {
+                write!(f, "Fee overflows the balance of {}", account)
+            }
+            FeeError::NotCommitted => write!(f, "Fees need commitment version 1"),
+        }
+    }
+}
+
+impl std::error::Error for FeeError {}
+
+/// Takes `fee` from the balance of `account` at `address`.
+pub fn debit(address: &Address, account: &mut Account, fee: U256) -> Result<(), FeeError> {
+    account.balance = account
+        .balance
+        .checked_sub(fee)
+        .ok_or(FeeError::InsufficientBalance {
+            account: *address,
+            balance: account.balance,
+            fee,
+        })?;
+    Ok(())
+}
+
+/// Adds `fee` to the balance of the recipient's `account`.
+pub fn credit(recipient: &Address, account: &mut Account, fee: U256) -> Result<(), FeeError> {
+    account.balance = account
+        .balance
+        .checked_add(fee)
+        .ok_or(FeeError::BalanceOverflow(*recipient))?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::iter;
+
+// Chars the gap grows by at least, so a run of short inserts does not reallocate every time.
+const MIN_GAP: usize = 64;
+
+/// Account data while a transaction edits it. The chars are kept with a gap at the last edit, so
+/// an edit only moves the chars between it and the previous one instead of everything after it,
+/// and the data is only turned back into a `String` once the transaction is done.
+#[derive(Debug, Clone, Default)]
+pub struct GapBuffer {
+    chars: Vec<char>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl GapBuffer {
+    pub fn new(data: &str) -> Self {
+        let chars: Vec<char> = data.chars().collect();
+        let len = chars.len();
+
+        Self {
+            chars,
+            gap_start: len,
+            gap_end: len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len() - self.gap_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts `value` before the char at `index`. `index` must be at most `len`.
+    pub fn insert(&mut self, index: usize, value: &str) {
+        let count = value.chars().count();
+        self.move_gap(index);
+        self.reserve(count);
+
+        for (slot, c) in self.chars[self.gap_start..].iter_mut().zip(value.chars()) {
+            *slot = c;
+        }
+        self.gap_start += count;
+    }
+
+    /// Removes `count` chars from `index`. The range must be within `len`.
+    pub fn delete(&mut self, index: usize, count: usize) {
+        self.move_gap(index);
+        self.gap_end += count;
+    }
+
+    pub fn push_str(&mut self, value: &str) {
+        self.insert(self.len(), value);
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.delete(len, self.len() - len);
+        }
+    }
+
+    /// The chars in order, with the gap moved out of the way.
+    pub fn as_slice(&mut self) -> &[char] {
+        self.move_gap(self.len());
+        &self.chars[..self.gap_start]
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars[..self.gap_start]
+            .iter()
+            .chain(&self.chars[self.gap_end..])
+            .copied()
+    }
+
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
+
+    fn move_gap(&mut self, index: usize) {
+        if index < self.gap_start {
+            let moved = self.gap_start - index;
+            self.chars
+                .copy_within(index..self.gap_start, self.gap_end - moved);
+            self.gap_start = index;
+            self.gap_end -= moved;
+        } else if index > self.gap_start {
+            let moved = index - self.gap_start;
+            self.chars
+                .copy_within(self.gap_end..self.gap_end + moved, self.gap_start);
+            self.gap_start = index;
+            self.gap_end += moved;
+        }
+    }
+
+    // Makes the gap at least `count` chars long.
+    fn reserve(&mut self, count: usize) {
+        if self.gap_len() >= count {
+            return;
+        }
+
+        let grow = count.max(self.len() / 2).max(MIN_GAP);
+        self.chars
+            .splice(self.gap_end..self.gap_end, iter::repeat('\0').take(grow));
+        self.gap_end += grow;
+    }
+}
+
+impl From<&GapBuffer> for String {
+    fn from(buffer: &GapBuffer) -> Self {
+        buffer.chars().collect()
+    }
+}
@@ -0,0 +1,60 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::commitment::{empty_root, leaf_hashes, CommitmentVersion};
+use crate::namespace::{canvas_of, namespace_tree};
+use crate::smt::SparseMerkleTree;
+use crate::state_tree::{state_root_of, Leaf};
+use crate::Account;
+
+/// The state batch 0 runs on. Operators that start from the same genesis file agree on the
+/// state root before the first batch, which is the `initialStateRoot` the contract is deployed
+/// with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Genesis {
+    /// Account fields left out default as in an account that was never written.
+    #[serde(default)]
+    pub accounts: BTreeMap<Address, Account>,
+}
+
+impl Genesis {
+    /// The root `generate_state_root` gives right after `InMemoryDB::from_genesis`, computed
+    /// without building the tree: the leaf hash of every account under `version`, ordered as the
+    /// version orders leaves and hashed pairwise up to the root, or the empty root of `version`
+    /// for no accounts. Sparse versions put the leaves in a `SparseMerkleTree` instead, one per
+    /// canvas from `CommitmentVersion::V8` on.
+    pub fn state_root(&self, version: CommitmentVersion) -> [u8; 32] {
+        if self.accounts.is_empty() {
+            return empty_root(version);
+        }
+
+        let accounts: Vec<_> = self.accounts.iter().collect();
+        let hashes = leaf_hashes(version, &accounts).into_iter().zip(&accounts);
+        if version.is_sparse() {
+            let mut trees = BTreeMap::<u64, SparseMerkleTree>::new();
+            for (hash, (address, account)) in hashes {
+                trees
+                    .entry(canvas_of(version, account))
+                    .or_insert_with(|| SparseMerkleTree::with_scheme(version.hash_scheme()))
+                    .insert(address, hash);
+            }
+            let mut roots = trees
+                .into_iter()
+                .map(|(canvas, mut tree)| (canvas, tree.root()));
+            return match version.is_namespaced() {
+                true => namespace_tree(version.hash_scheme(), roots).root(),
+                false => roots.next().map_or([0; 32], |(_, root)| root),
+            };
+        }
+
+        let leaves = hashes
+            .map(|(hash, (address, _))| Leaf {
+                hash,
+                account: **address,
+            })
+            .collect();
+        state_root_of(version, leaves).unwrap_or(empty_root(version))
+    }
+}
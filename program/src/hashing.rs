@@ -0,0 +1,38 @@
+use alloy_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+
+/// First byte of every leaf preimage under `HashScheme::SeparatedKeccak`.
+pub const LEAF_PREFIX: u8 = 0x00;
+/// First byte of every node preimage under `HashScheme::SeparatedKeccak`.
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// How the sparse state trees hash leaves and the nodes above them, picked by the commitment
+/// version. Another hash function, such as one cheaper to prove in the zkVM, is another scheme
+/// here and a new commitment version to switch to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HashScheme {
+    /// keccak256 of the leaf preimage, and of the two children concatenated.
+    #[default]
+    Keccak,
+    /// `Keccak` with `LEAF_PREFIX` before every leaf preimage and `NODE_PREFIX` before every
+    /// pair of children, so that a node can never be passed off as a leaf or a leaf as a node.
+    SeparatedKeccak,
+}
+
+impl HashScheme {
+    pub fn leaf(self, preimage: &[u8]) -> [u8; 32] {
+        match self {
+            HashScheme::Keccak => keccak256(preimage).into(),
+            HashScheme::SeparatedKeccak => keccak256([&[LEAF_PREFIX], preimage].concat()).into(),
+        }
+    }
+
+    pub fn node(self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        match self {
+            HashScheme::Keccak => keccak256([left.as_slice(), right.as_slice()].concat()).into(),
+            HashScheme::SeparatedKeccak => {
+                keccak256([&[NODE_PREFIX], left.as_slice(), right.as_slice()].concat()).into()
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use alloy_primitives::Address;
+
+use crate::error::CanvasError;
+use crate::limits::RateLimitState;
+use crate::Account;
+
+/// A state `CanvasProcessor::revert_to` can roll back to, from `CanvasProcessor::checkpoint`.
+/// It stays valid until it, or a checkpoint taken before it, is reverted to or committed.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    depth: usize,
+    len: usize,
+    limits: RateLimitState,
+}
+
+impl Checkpoint {
+    pub(crate) fn limits(&self) -> &RateLimitState {
+        &self.limits
+    }
+}
+
+/// The previous value of every account written while a transaction or a checkpoint is open,
+/// oldest first. `None` where the address had no account.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    entries: Vec<(Address, Option<Account>)>,
+    depth: usize,
+}
+
+impl Journal {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn record(&mut self, address: Address, previous: Option<Account>) {
+        self.entries.push((address, previous));
+    }
+
+    pub fn checkpoint(&mut self, limits: &RateLimitState) -> Checkpoint {
+        self.depth += 1;
+        Checkpoint {
+            depth: self.depth,
+            len: self.entries.len(),
+            limits: limits.clone(),
+        }
+    }
+
+    /// Removes the entries recorded since `len`, newest first, for undoing.
+    pub fn unwind(&mut self, len: usize) -> Vec<(Address, Option<Account>)> {
+        let mut entries = self.entries.split_off(len);
+        entries.reverse();
+        entries
+    }
+
+    /// Closes `checkpoint` and every later one, returning the entries to undo.
+    pub fn revert(
+        &mut self,
+        checkpoint: &Checkpoint,
+    ) -> Result<Vec<(Address, Option<Account>)>, CanvasError> {
+        self.close(checkpoint)?;
+        let entries = self.unwind(checkpoint.len);
+        self.settle();
+        Ok(entries)
+    }
+
+    /// Closes `checkpoint` and every later one, keeping their entries for earlier checkpoints.
+    pub fn commit(&mut self, checkpoint: &Checkpoint) -> Result<(), CanvasError> {
+        self.close(checkpoint)?;
+        self.settle();
+        Ok(())
+    }
+
+    fn close(&mut self, checkpoint: &Checkpoint) -> Result<(), CanvasError> {
+        if checkpoint.depth > self.depth || checkpoint.len > self.entries.len() {
+            return Err(CanvasError::StaleCheckpoint);
+        }
+        self.depth = checkpoint.depth - 1;
+        Ok(())
+    }
+
+    /// Forgets the entries once no checkpoint needs them.
+    pub fn settle(&mut self) {
+        if self.depth == 0 {
+            self.entries.clear();
+        }
+    }
+}
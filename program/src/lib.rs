@@ -1,25 +1,147 @@
-use alloy_primitives::Address;
 use alloy_primitives::{keccak256, Signature, U256};
-use alloy_rlp::{Encodable, RlpDecodable, RlpEncodable};
+use alloy_primitives::{Address, B256};
+use alloy_rlp::{BufMut, Decodable, Encodable, Header, RlpDecodable, RlpEncodable};
 use alloy_sol_types::sol;
-use alloy_sol_types::SolValue;
-use eyre::Result;
-use flate2::write::ZlibEncoder;
-use flate2::Compression;
-use hashbrown::HashMap;
-use rs_merkle::{Hasher, MerkleTree};
+use eyre::WrapErr;
+use hashbrown::{HashMap, HashSet};
+use rs_merkle::Hasher;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::{cell::RefCell, ops::Deref};
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+pub mod access;
+pub mod aggregate;
+pub mod batch;
+pub mod blob;
+pub mod bridge;
+pub mod builder;
+pub mod chunked;
+pub mod claim;
+pub mod codec;
+pub mod commitment;
+pub mod config;
+pub mod conflicts;
+pub mod cost;
+pub mod data_root;
+#[cfg(feature = "host")]
+pub mod diff;
+pub mod edit;
+pub mod error;
+pub mod expiry;
+pub mod fees;
+pub mod gap_buffer;
+pub mod genesis;
+pub mod hashing;
+pub mod journal;
+pub mod light;
+pub mod limits;
+#[cfg(feature = "host")]
+pub mod mempool;
+pub mod metadata;
+pub mod namespace;
+pub mod nonce;
+#[cfg(feature = "host")]
+pub mod ordering;
+#[cfg(feature = "host")]
+pub mod overlay;
+pub mod proof;
+pub mod public_values;
+pub mod receipt;
+pub mod recovery;
+pub mod schema;
+pub mod sequencer;
+pub mod shuffle;
+pub mod signing;
+#[cfg(feature = "host")]
+pub mod simulate;
+pub mod smt;
+pub mod snapshot;
+pub mod sponsor;
+pub mod state_tree;
+pub mod undo;
+pub mod validator;
+pub mod verify;
+pub mod witness;
+
+use access::AccessError;
+use batch::BatchContext;
+use bridge::{BridgeError, Deposit, Inbox};
+use codec::Codec;
+use commitment::{empty_root, leaf_hash_cached, leaf_hashes};
+use config::{CanvasConfig, FailurePolicy, SizePolicy};
+use data_root::DataRoots;
+use edit::{Addressing, EditError, EditOp};
+use error::CanvasError;
+use fees::FeeError;
+use gap_buffer::GapBuffer;
+use genesis::Genesis;
+use journal::{Checkpoint, Journal};
+use limits::RateLimitState;
+use metadata::{Metadata, MetadataError};
+use namespace::{canvas_address, canvas_of, namespace_tree, CanvasProof, DEFAULT_CANVAS};
+use nonce::NonceError;
+use proof::{AddressIndex, ExclusionProof, MultiProof};
+use receipt::{receipts_root, BatchReport, Receipt, ReceiptStatus};
+use recovery::SenderCache;
+use schema::{Schema, SchemaError};
+use shuffle::execution_order;
+use signing::{is_legacy_signed, is_low_s, signing_hash, SigningDomain};
+use smt::{SparseMerkleTree, SparseProof};
+use sponsor::SponsorSignature;
+use state_tree::StateTree;
+use undo::{Undo, UndoError};
+use validator::{HexNibbleValidator, ValueValidator};
+use witness::Witness;
 
 pub const MAX_SIZE: usize = 9800;
 pub const MAX_VALUE: u8 = 15;
 
+/// Transactions carrying legacy `Data` edits.
+pub const TX_VERSION_LEGACY: u8 = 0;
+/// Transactions carrying typed `EditOp`s.
+pub const TX_VERSION_OPS: u8 = 1;
+/// Typed `EditOp`s, signed over EIP-712 typed data bound to a `SigningDomain`.
+pub const TX_VERSION_DOMAIN: u8 = 2;
+/// `TX_VERSION_DOMAIN` with indices and counts in UTF-8 bytes, which must fall on char
+/// boundaries.
+pub const TX_VERSION_BYTES: u8 = 3;
+/// `TX_VERSION_DOMAIN` with indices and counts in extended grapheme clusters.
+pub const TX_VERSION_GRAPHEMES: u8 = 4;
+/// `TX_VERSION_DOMAIN` with `valid_until_batch` and `valid_until_timestamp` encoded after
+/// `extra`.
+pub const TX_VERSION_EXPIRY: u8 = 5;
+/// `TX_VERSION_EXPIRY` with the `canvas` the transaction edits encoded last.
+pub const TX_VERSION_CANVAS: u8 = 6;
+/// `TX_VERSION_CANVAS` with the `access_list` the transaction declares encoded last.
+pub const TX_VERSION_ACCESS_LIST: u8 = 7;
+/// The newest version this build knows. Later versions are rejected when decoded and applied,
+/// rather than read with the layout of this one: a new version gets its own constant, and the
+/// fields it adds are encoded after those of the version before it.
+pub const TX_VERSION_LATEST: u8 = TX_VERSION_ACCESS_LIST;
+
 sol! {
     struct PublicValuesStruct {
         bytes32 initialStateRoot;
         bytes32 finalStateRoot;
         bytes32 transaction_commit;
+        bytes32 receiptsRoot;
+        bytes32 accountsRoot;
+        bytes32 previousBatch;
+        bytes32 limitsRoot;
+        uint64 batchIndex;
+        uint64 l1BlockNumber;
+        bytes32 l1BlockHash;
+        bytes32 depositsCommit;
+        uint64 depositCount;
+        bytes32 forcedCommit;
+        uint64 forcedCount;
+        uint64 batchTimestamp;
+        bytes32 previousBatchHash;
+        bytes32 batchHash;
+        uint64 publicValuesVersion;
+        uint64 previousBatchTimestamp;
+        address sequencer;
     }
 
     struct AccountCommit {
@@ -28,18 +150,175 @@ sol! {
         string data;
         address[] contributors;
     }
-}
 
-#[derive(Debug, Clone)]
-struct Leaf {
-    hash: [u8; 32],
-    account: Address,
+    struct AccountCommitV1 {
+        address account;
+        uint256 nonce;
+        string data;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+    }
+
+    struct AccountCommitV3 {
+        address account;
+        uint256 nonce;
+        string data;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+    }
+
+    struct AccountCommitV6 {
+        address account;
+        uint256 nonce;
+        bytes32 dataRoot;
+        uint256 dataLength;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+    }
+
+    struct Contribution {
+        address contributor;
+        uint64 edits;
+        uint64 inserted;
+        uint64 deleted;
+    }
+
+    struct AccountCommitV7 {
+        address account;
+        uint256 nonce;
+        bytes32 dataRoot;
+        uint256 dataLength;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+        Contribution[] contributions;
+    }
+
+    struct LastEdit {
+        address editor;
+        uint64 batchIndex;
+    }
+
+    struct AccountCommitV9 {
+        address account;
+        uint256 nonce;
+        bytes32 dataRoot;
+        uint256 dataLength;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+        Contribution[] contributions;
+        LastEdit[] lastEdits;
+    }
+
+    struct UndoCommit {
+        address editor;
+        bytes32 txHash;
+        uint64 batchIndex;
+        uint64 index;
+        uint64 inserted;
+        string removed;
+    }
+
+    struct AccountCommitV11 {
+        address account;
+        uint256 nonce;
+        bytes32 dataRoot;
+        uint256 dataLength;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+        Contribution[] contributions;
+        LastEdit[] lastEdits;
+        UndoCommit[] undos;
+    }
+
+    struct AccountCommitV12 {
+        address account;
+        uint256 nonce;
+        bytes32 dataRoot;
+        uint256 dataLength;
+        address[] contributors;
+        uint256 balance;
+        address owner;
+        bytes32 policy;
+        address[] writers;
+        Contribution[] contributions;
+        LastEdit[] lastEdits;
+        UndoCommit[] undos;
+        string name;
+        string uri;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Input {
-    pub transactions: Vec<SignedTransaction>,
+    /// The batch's `_transactionData` exactly as posted. The program decodes the transactions
+    /// from it and commits to its keccak256, so the commit matches the L1 calldata whichever
+    /// encoder produced it.
+    pub transaction_data: Vec<u8>,
     pub db: InMemoryDB,
+    pub config: CanvasConfig,
+    #[serde(default)]
+    pub batch: BatchContext,
+    /// L1 messages the batch consumes, credited before its transactions.
+    #[serde(default)]
+    pub deposits: Vec<Deposit>,
+    /// Transactions queued on L1 that the batch has to include; see `Inbox`.
+    #[serde(default)]
+    pub forced_txs: Vec<SignedTransaction>,
+    /// Rate-limit counters before the batch.
+    #[serde(default)]
+    pub limits: RateLimitState,
+    /// When set, the batch runs against the witness and `db` is ignored, so it can be left empty.
+    #[serde(default)]
+    pub witness: Option<Witness>,
+    /// The zstd dictionary of `Codec::Zstd` batches, if they use one.
+    #[serde(default)]
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl Input {
+    pub fn process(self) -> eyre::Result<BatchReport> {
+        let inbox = Inbox {
+            deposits: &self.deposits,
+            forced_txs: &self.forced_txs,
+        };
+
+        match self.witness {
+            Some(witness) => witness.process_batch(
+                &self.transaction_data,
+                self.config,
+                self.batch,
+                inbox,
+                self.limits,
+                self.dictionary,
+            ),
+            None => CanvasProcessor::with_config(self.db, self.config)
+                .with_limits(self.limits)
+                .with_dictionary(self.dictionary)
+                .process_posted_batch(&self.transaction_data, self.batch, inbox),
+        }
+    }
+
+    /// The transactions of `transaction_data`, as the program decodes them.
+    pub fn transactions(&self) -> eyre::Result<Vec<SignedTransaction>> {
+        batch::decode_batch(&self.transaction_data, self.dictionary.as_deref())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
@@ -49,137 +328,1354 @@ pub struct Data {
     pub value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
+/// Only one of `data` and `ops` is part of the encoding: `data` for `TX_VERSION_LEGACY`, `ops`
+/// for every later version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     pub to: Address,
     pub version: u8,
     pub data: Vec<Data>,
+    pub ops: Vec<EditOp>,
     pub nonce: u64,
     pub extra: String,
+    /// Last batch index the transaction may be included in, zero for no limit. Encoded, and so
+    /// enforced, from `TX_VERSION_EXPIRY` on, as is `valid_until_timestamp`.
+    #[serde(default)]
+    pub valid_until_batch: u64,
+    /// Latest `BatchContext::timestamp` the transaction may be included at, zero for no limit.
+    #[serde(default)]
+    pub valid_until_timestamp: u64,
+    /// The canvas `to` is edited in; see `namespace`. Encoded from `TX_VERSION_CANVAS` on,
+    /// earlier versions edit `DEFAULT_CANVAS`.
+    #[serde(default)]
+    pub canvas: u64,
+    /// The accounts the transaction may read and write, by the address they are stored under,
+    /// so those in a canvas by their key in it; empty to declare none. Touching an account it
+    /// does not declare fails the transaction, so which accounts it touches is known before it
+    /// runs. Encoded from `TX_VERSION_ACCESS_LIST` on.
+    #[serde(default)]
+    pub access_list: Vec<Address>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
+impl Transaction {
+    /// Where the account `to` edits is stored: `to` itself in `DEFAULT_CANVAS`.
+    pub fn target(&self) -> Address {
+        canvas_address(self.canvas, &self.to)
+    }
+
+    /// Where the accounts its `Copy` and `Move` ops read are stored, in the target's canvas.
+    pub fn sources(&self) -> Vec<Address> {
+        self.edit_ops()
+            .iter()
+            .filter_map(|op| op.source())
+            .map(|source| canvas_address(self.canvas, source))
+            .collect()
+    }
+
+    /// Whether the transaction may touch `address`: every address when it declares none.
+    pub fn declares(&self, address: &Address) -> bool {
+        self.access_list.is_empty() || self.access_list.contains(address)
+    }
+
+    pub fn edit_ops(&self) -> Vec<EditOp> {
+        if self.version == TX_VERSION_LEGACY {
+            self.data.iter().map(EditOp::from).collect()
+        } else {
+            self.ops.clone()
+        }
+    }
+
+    fn payload_length(&self) -> usize {
+        let edits_length = if self.version == TX_VERSION_LEGACY {
+            self.data.length()
+        } else {
+            self.ops.length()
+        };
+
+        let expiry_length = if self.version >= TX_VERSION_EXPIRY {
+            self.valid_until_batch.length() + self.valid_until_timestamp.length()
+        } else {
+            0
+        };
+
+        let canvas_length = if self.version >= TX_VERSION_CANVAS {
+            self.canvas.length()
+        } else {
+            0
+        };
+
+        let access_list_length = if self.version >= TX_VERSION_ACCESS_LIST {
+            self.access_list.length()
+        } else {
+            0
+        };
+
+        self.to.length()
+            + self.version.length()
+            + edits_length
+            + self.nonce.length()
+            + self.extra.length()
+            + expiry_length
+            + canvas_length
+            + access_list_length
+    }
+}
+
+impl Encodable for Transaction {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+
+        self.to.encode(out);
+        self.version.encode(out);
+        if self.version == TX_VERSION_LEGACY {
+            self.data.encode(out);
+        } else {
+            self.ops.encode(out);
+        }
+        self.nonce.encode(out);
+        self.extra.encode(out);
+        if self.version >= TX_VERSION_EXPIRY {
+            self.valid_until_batch.encode(out);
+            self.valid_until_timestamp.encode(out);
+        }
+        if self.version >= TX_VERSION_CANVAS {
+            self.canvas.encode(out);
+        }
+        if self.version >= TX_VERSION_ACCESS_LIST {
+            self.access_list.encode(out);
+        }
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let Header {
+            list,
+            payload_length,
+        } = Header::decode(buf)?;
+        if !list {
+            return Err(alloy_rlp::Error::UnexpectedString);
+        }
+
+        let started_len = buf.len();
+        if started_len < payload_length {
+            return Err(alloy_rlp::Error::InputTooShort);
+        }
+
+        let to = Address::decode(buf)?;
+        let version = u8::decode(buf)?;
+        if version > TX_VERSION_LATEST {
+            return Err(alloy_rlp::Error::Custom("Unknown transaction version"));
+        }
+        let (data, ops) = if version == TX_VERSION_LEGACY {
+            (Vec::<Data>::decode(buf)?, Vec::new())
+        } else {
+            (Vec::new(), Vec::<EditOp>::decode(buf)?)
+        };
+
+        let nonce = u64::decode(buf)?;
+        let extra = String::decode(buf)?;
+        let (valid_until_batch, valid_until_timestamp) = if version >= TX_VERSION_EXPIRY {
+            (u64::decode(buf)?, u64::decode(buf)?)
+        } else {
+            (0, 0)
+        };
+        let canvas = if version >= TX_VERSION_CANVAS {
+            u64::decode(buf)?
+        } else {
+            DEFAULT_CANVAS
+        };
+        let access_list = if version >= TX_VERSION_ACCESS_LIST {
+            Vec::<Address>::decode(buf)?
+        } else {
+            Vec::new()
+        };
+
+        let tx = Transaction {
+            to,
+            version,
+            data,
+            ops,
+            nonce,
+            extra,
+            valid_until_batch,
+            valid_until_timestamp,
+            canvas,
+            access_list,
+        };
+
+        let consumed = started_len - buf.len();
+        if consumed != payload_length {
+            return Err(alloy_rlp::Error::ListLengthMismatch {
+                expected: payload_length,
+                got: consumed,
+            });
+        }
+
+        Ok(tx)
+    }
+}
+
+/// `sponsor` is encoded after the sender's signature only when set, so unsponsored transactions
+/// encode as they always have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignedTransaction {
     pub tx: Transaction,
     pub r: U256,
     pub s: U256,
     pub odd_y_parity: bool,
+    #[serde(default)]
+    pub sponsor: Option<SponsorSignature>,
+}
+
+impl SignedTransaction {
+    /// keccak256 of the RLP encoding, signatures included, which identifies the transaction in
+    /// the mempool, receipts lookups and RPC. Each signature has one accepted encoding, so an
+    /// edit has one hash.
+    pub fn hash(&self) -> B256 {
+        let mut encoded = Vec::with_capacity(self.length());
+        self.encode(&mut encoded);
+        keccak256(encoded)
+    }
+
+    fn payload_length(&self) -> usize {
+        self.tx.length()
+            + self.r.length()
+            + self.s.length()
+            + self.odd_y_parity.length()
+            + self.sponsor.as_ref().map_or(0, |sponsor| sponsor.length())
+    }
+}
+
+impl Encodable for SignedTransaction {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+
+        self.tx.encode(out);
+        self.r.encode(out);
+        self.s.encode(out);
+        self.odd_y_parity.encode(out);
+        if let Some(sponsor) = &self.sponsor {
+            sponsor.encode(out);
+        }
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for SignedTransaction {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let Header {
+            list,
+            payload_length,
+        } = Header::decode(buf)?;
+        if !list {
+            return Err(alloy_rlp::Error::UnexpectedString);
+        }
+
+        let started_len = buf.len();
+        if started_len < payload_length {
+            return Err(alloy_rlp::Error::InputTooShort);
+        }
+
+        let tx = Transaction::decode(buf)?;
+        let r = U256::decode(buf)?;
+        let s = U256::decode(buf)?;
+        let odd_y_parity = bool::decode(buf)?;
+        let sponsor = match started_len - buf.len() < payload_length {
+            true => Some(SponsorSignature::decode(buf)?),
+            false => None,
+        };
+
+        let consumed = started_len - buf.len();
+        if consumed != payload_length {
+            return Err(alloy_rlp::Error::ListLengthMismatch {
+                expected: payload_length,
+                got: consumed,
+            });
+        }
+
+        Ok(SignedTransaction {
+            tx,
+            r,
+            s,
+            odd_y_parity,
+            sponsor,
+        })
+    }
 }
 
-pub struct CanvasProcessor<D> {
+pub struct CanvasProcessor<D, V = HexNibbleValidator> {
     pub db: D,
+    pub config: CanvasConfig,
+    pub validator: V,
+    pub limits: RateLimitState,
+    /// Senders recovered during the current batch.
+    pub senders: SenderCache,
+    /// The zstd dictionary batches are encoded with under `Codec::Zstd`.
+    pub dictionary: Option<Vec<u8>>,
+    /// The batch being applied, which transactions expire against. Set by `apply_batch_with`;
+    /// callers applying transactions one at a time set it themselves.
+    pub batch: BatchContext,
+    journal: Journal,
+    tree: RefCell<StateTree>,
+    // One sparse tree per canvas, only `DEFAULT_CANVAS` before `CommitmentVersion::V8`.
+    smts: RefCell<BTreeMap<u64, SparseMerkleTree>>,
+    data_roots: RefCell<DataRoots>,
+}
+
+impl<D> CanvasProcessor<D> {
+    pub fn new(db: D) -> Self {
+        Self::with_config(db, CanvasConfig::default())
+    }
+
+    pub fn with_config(db: D, config: CanvasConfig) -> Self {
+        Self {
+            db,
+            config,
+            validator: HexNibbleValidator,
+            limits: RateLimitState::default(),
+            senders: SenderCache::default(),
+            dictionary: None,
+            batch: BatchContext::default(),
+            journal: Journal::default(),
+            tree: RefCell::new(StateTree::new(config.commitment)),
+            smts: RefCell::new(BTreeMap::new()),
+            data_roots: RefCell::new(DataRoots::default()),
+        }
+    }
 }
 
-impl<D: AccountDB> CanvasProcessor<&D> {
-    pub fn apply_transaction(&mut self, input: &SignedTransaction) -> Result<()> {
+impl<D, V> CanvasProcessor<D, V> {
+    pub fn with_validator<W: ValueValidator>(self, validator: W) -> CanvasProcessor<D, W> {
+        CanvasProcessor {
+            db: self.db,
+            config: self.config,
+            validator,
+            limits: self.limits,
+            senders: self.senders,
+            dictionary: self.dictionary,
+            batch: self.batch,
+            journal: self.journal,
+            tree: self.tree,
+            smts: self.smts,
+            data_roots: self.data_roots,
+        }
+    }
+
+    /// Continues from the rate-limit counters left by an earlier batch.
+    pub fn with_limits(mut self, limits: RateLimitState) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_dictionary(mut self, dictionary: Option<Vec<u8>>) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+}
+
+/// The accounts a transaction writes, in write order. When `from == to` both hold the same
+/// account.
+#[derive(Debug, Clone)]
+pub struct TransactionEffect {
+    pub from: Address,
+    pub from_account: Account,
+    pub to: Address,
+    pub to_account: Account,
+    /// The fee payer and recipient with their accounts, when a fee was charged and they are
+    /// neither `from` nor `to`.
+    pub others: Vec<(Address, Account)>,
+    /// Who vouched for the transaction, paying its fee and taking it on their rate limit.
+    pub sponsor: Option<Address>,
+    /// Whether `to` is removed rather than written: a `Clear` left it with nothing to keep.
+    pub removes_to: bool,
+    pub receipt: Receipt,
+}
+
+impl<D: AccountDB, V: ValueValidator> CanvasProcessor<D, V> {
+    pub fn apply_transaction(&mut self, input: &SignedTransaction) -> Result<Receipt, CanvasError> {
+        let effect = self.execute_transaction(input)?;
+        self.write_effect(effect)
+    }
+
+    /// Applies `transactions` in order under `policy`, without committing to anything. Run over
+    /// an `OverlayDB` this simulates a batch. Under the `parallel` feature transactions that
+    /// touch disjoint accounts are executed at once; see `conflicts::waves`.
+    pub fn apply_transactions<'a>(
+        &mut self,
+        transactions: impl IntoIterator<Item = &'a SignedTransaction>,
+        policy: FailurePolicy,
+    ) -> Result<Vec<Receipt>, CanvasError> {
+        let transactions: Vec<&SignedTransaction> = transactions.into_iter().collect();
+        let mut receipts = Vec::with_capacity(transactions.len());
+        self.execute_each(&transactions, |processor, tx, effect| {
+            let receipt = match (effect, policy) {
+                (Ok(effect), _) => processor.write_effect(effect),
+                (Err(err), FailurePolicy::Skip) => {
+                    tracing::debug!(error = %err, "Skipping failed transaction");
+                    Ok(Receipt::failed(tx, &processor.config.domain))
+                }
+                (Err(err), FailurePolicy::Abort) => Err(err),
+            };
+            receipts.push(receipt?);
+            Ok::<_, CanvasError>(())
+        })?;
+        Ok(receipts)
+    }
+
+    /// Writes accounts computed elsewhere, e.g. an `OverlayDB`'s `into_writes`, and marks them
+    /// for re-hashing. `None` removes the account.
+    pub fn apply_writes(
+        &mut self,
+        writes: impl IntoIterator<Item = (Address, Option<Account>)>,
+    ) -> Result<(), CanvasError> {
+        self.write_all(writes.into_iter().collect())?;
+        self.journal.settle();
+        Ok(())
+    }
+
+    /// Credits each of `deposits` to its recipient, in order.
+    pub fn apply_deposits(&mut self, deposits: &[Deposit]) -> Result<(), CanvasError> {
+        for deposit in deposits {
+            let mut account = self.db.get_account(&deposit.to)?;
+            bridge::credit(&mut account, deposit)?;
+            self.write(&deposit.to, Some(&account))?;
+        }
+        self.journal.settle();
+        Ok(())
+    }
+
+    /// Marks the current state, rate-limit counters included, for `revert_to`. Checkpoints nest
+    /// and span transactions and batches; writes are journaled while any is open.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.journal.checkpoint(&self.limits)
+    }
+
+    /// Undoes every write since `checkpoint` was taken and closes it and every later one.
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) -> Result<(), CanvasError> {
+        let entries = self.journal.revert(&checkpoint)?;
+        self.undo(entries)?;
+        self.limits = checkpoint.limits().clone();
+        Ok(())
+    }
+
+    /// Keeps every write since `checkpoint` was taken and closes it and every later one.
+    pub fn commit_checkpoint(&mut self, checkpoint: Checkpoint) -> Result<(), CanvasError> {
+        self.journal.commit(&checkpoint)
+    }
+
+    // Writes every account of the effect or none.
+    fn write_effect(&mut self, effect: TransactionEffect) -> Result<Receipt, CanvasError> {
+        let mut writes = WriteBatch::default();
+        writes.set(effect.from, effect.from_account);
+        match effect.removes_to {
+            true => writes.remove(effect.to),
+            false => writes.set(effect.to, effect.to_account),
+        }
+        for (address, account) in effect.others {
+            writes.set(address, account);
+        }
+
+        let start = self.journal.len();
+        if let Err(err) = self.write_all(writes) {
+            let entries = self.journal.unwind(start);
+            self.undo(entries)?;
+            return Err(err);
+        }
+        self.journal.settle();
+
+        self.limits.record(
+            &self.config.rate_limit,
+            effect.sponsor.unwrap_or(effect.from),
+            effect.receipt.ops,
+        );
+
+        Ok(effect.receipt)
+    }
+
+    fn write(&mut self, address: &Address, account: Option<&Account>) -> Result<(), CanvasError> {
+        self.journal
+            .record(*address, self.db.find_account(address)?);
+        match account {
+            Some(account) => self.db.set_account(address, account)?,
+            None => self.db.remove_account(address)?,
+        }
+        Ok(())
+    }
+
+    // `write` of every account in `writes`, reading what they replace and writing them each in
+    // one call to the db.
+    fn write_all(&mut self, writes: WriteBatch) -> Result<(), CanvasError> {
+        let addresses = writes.addresses();
+        for (address, previous) in addresses.iter().zip(self.db.find_accounts(&addresses)?) {
+            self.journal.record(*address, previous);
+        }
+        self.db.apply_writes(writes)
+    }
+
+    fn undo(&mut self, entries: Vec<(Address, Option<Account>)>) -> Result<(), CanvasError> {
+        self.db.apply_writes(entries.into_iter().collect())
+    }
+
+    /// Computes what `apply_transaction` would write without touching the db.
+    pub fn execute_transaction(
+        &self,
+        input: &SignedTransaction,
+    ) -> Result<TransactionEffect, CanvasError> {
         let tx = input.tx.clone();
+        let span = tracing::debug_span!(
+            "transaction",
+            tx_hash = tracing::field::Empty,
+            sender = tracing::field::Empty,
+            to = %tx.to,
+            nonce = tx.nonce,
+        );
+        // Hashing costs cycles in the zkVM, where nothing listens.
+        if !span.is_disabled() {
+            span.record("tx_hash", tracing::field::display(input.hash()));
+        }
+        let _span = span.entered();
 
-        let from_address = recover_address_from_tx(input)?;
-        let to_address = tx.to;
+        if tx.version > TX_VERSION_LATEST {
+            return Err(CanvasError::UnknownVersion(tx.version));
+        }
+        if is_legacy_signed(&tx) && !self.config.legacy_signatures {
+            return Err(CanvasError::LegacySignature(tx.version));
+        }
+        if !self.config.high_s_signatures && !has_low_s(input) {
+            return Err(CanvasError::MalleableSignature);
+        }
+        expiry::check(&tx, &self.batch)?;
+        if tx.canvas != DEFAULT_CANVAS && !self.config.commitment.is_namespaced() {
+            return Err(CanvasError::NotNamespaced(tx.canvas));
+        }
+
+        let from_address = self.senders.recover(input, &self.config.domain)?;
+        let sponsor = sponsor::recover_sponsor(input, &self.config.domain)
+            .map_err(CanvasError::SponsorRecovery)?;
+        // Owners, writers and schemas go by the address the sender named; the account itself is
+        // stored under its key in the canvas.
+        let owner_address = tx.to;
+        let to_address = tx.target();
+        tracing::Span::current().record("sender", tracing::field::display(from_address));
+
+        if !self.config.fees.is_free() && !self.config.commitment.commits_balance() {
+            return Err(FeeError::NotCommitted.into());
+        }
+
+        // Every account the transaction can read, fetched in one call to the db.
+        let fee = self.config.fees.fee(input);
+        let reads = conflicts::reads(&self.config, input, from_address, sponsor);
+        if let Some(address) = reads.iter().find(|address| !tx.declares(address)) {
+            return Err(CanvasError::Undeclared(*address));
+        }
+        let mut read: HashMap<Address, Account> = reads
+            .iter()
+            .copied()
+            .zip(self.db.get_accounts(&reads)?)
+            .collect();
 
-        let mut from_account = self.db.get_account(&from_address)?;
-        let mut to_account = self.db.get_account(&to_address)?;
+        let mut from_account = read[&from_address].clone();
 
-        if tx.nonce < from_account.nonce {
-            return Err(eyre::eyre!(format!(
-                "Invalid nonce for {:?}, current nonce is: {:?}",
-                from_address, from_account.nonce
-            )));
+        if tx.nonce != from_account.nonce {
+            return Err(NonceError {
+                address: from_address,
+                expected: from_account.nonce,
+                got: tx.nonce,
+            }
+            .into());
         }
 
+        let ops = tx.edit_ops();
+        self.limits.check(
+            &self.config.rate_limit,
+            &sponsor.unwrap_or(from_address),
+            ops.len() as u64,
+        )?;
+
         from_account.nonce += 1;
 
-        let mut data_chars: Vec<char> = to_account.data.chars().collect();
+        // Editing your own account must not write back the pre-bump nonce.
+        let mut to_account = if to_address == from_address {
+            from_account.clone()
+        } else {
+            read[&to_address].clone()
+        };
+        to_account.canvas = tx.canvas;
+
+        let commitment = self.config.commitment;
+        let cooldown = self.config.edit_cooldown;
+        let cooled = cooldown != 0 && ops.iter().any(|op| op.is_data_edit());
+        if cooled {
+            if !self.config.commitment.commits_last_edits() {
+                return Err(CanvasError::CooldownNotCommitted);
+            }
+            if let Some(last) = to_account.last_edits.get(&from_address) {
+                let until = last.saturating_add(cooldown);
+                if self.batch.index < until {
+                    return Err(CanvasError::Cooldown {
+                        account: owner_address,
+                        sender: from_address,
+                        until,
+                    });
+                }
+            }
+        }
+
+        let window = self.config.undo_window;
+        let undoable = window != 0 && ops.iter().any(|op| op.is_data_edit());
+        if undoable && !self.config.commitment.commits_undos() {
+            return Err(UndoError::NotCommitted.into());
+        }
+
+        let mut data = GapBuffer::new(&to_account.data);
+        let (mut inserted, mut deleted) = (0, 0);
+        let mut cleared = false;
+        let addressing = Addressing::of(tx.version);
+        // Accounts other than the target that `Copy` and `Move` read, with their data as the
+        // ops left it and how many chars were moved out of them.
+        let mut sources: Vec<(Address, Account, GapBuffer, usize)> = Vec::new();
+
+        for op in &ops {
+            // Each op is addressed against the data as the ops before it left it. A copy or move
+            // is an insert of the text it takes.
+            let op = &match op {
+                EditOp::Copy {
+                    source,
+                    index,
+                    count,
+                    at,
+                }
+                | EditOp::Move {
+                    source,
+                    index,
+                    count,
+                    at,
+                } => {
+                    let moves = matches!(op, EditOp::Move { .. });
+                    let key = canvas_address(tx.canvas, source);
+                    let value = if key == to_address {
+                        let (index, count) = addressing.range_to_chars(&data, *index, *count)?;
+                        let value: String = data.chars().skip(index).take(count).collect();
+                        if moves {
+                            data.delete(index, count);
+                            deleted += count;
+                        }
+                        value
+                    } else {
+                        let position = match sources.iter().position(|(a, ..)| *a == key) {
+                            Some(position) => position,
+                            None => {
+                                let account = match key == from_address {
+                                    true => from_account.clone(),
+                                    false => read[&key].clone(),
+                                };
+                                let buffer = GapBuffer::new(&account.data);
+                                sources.push((key, account, buffer, 0));
+                                sources.len() - 1
+                            }
+                        };
+                        let (_, account, buffer, removed) = &mut sources[position];
+                        let (index, count) = addressing.range_to_chars(buffer, *index, *count)?;
+                        let value: String = buffer.chars().skip(index).take(count).collect();
+                        if moves {
+                            access::check_writer(commitment, source, account, &from_address)?;
+                            buffer.delete(index, count);
+                            *removed += count;
+                        }
+                        value
+                    };
+                    EditOp::Insert { index: *at, value }.to_chars(&data, addressing)?
+                }
+                op => op.to_chars(&data, addressing)?,
+            };
+            if let Some(value) = op.value() {
+                self.validator.validate(value)?;
+                inserted += value.chars().count();
+            }
+            if let EditOp::Delete { count, .. } | EditOp::Replace { count, .. } = op {
+                deleted += count;
+            }
+            if op.is_data_edit() {
+                access::check_writer(commitment, &owner_address, &to_account, &from_address)?;
+            }
+            match op {
+                EditOp::SetSchema { schema } => {
+                    if !commitment.commits_policy() {
+                        return Err(SchemaError::NotCommitted.into());
+                    }
+                    if access::effective_owner(commitment, &owner_address, &to_account)
+                        != from_address
+                    {
+                        return Err(SchemaError::NotOwner {
+                            account: owner_address,
+                            sender: from_address,
+                        }
+                        .into());
+                    }
+                    to_account.policy = schema.map_or(B256::ZERO, |schema| schema.to_policy());
+                }
+                EditOp::Claim => {
+                    access::claim(commitment, &owner_address, &mut to_account, &from_address)?
+                }
+                EditOp::Transfer { owner } => {
+                    if !commitment.commits_owner() {
+                        return Err(AccessError::OwnerNotCommitted.into());
+                    }
+                    access::check_owner(commitment, &owner_address, &to_account, &from_address)?;
+                    to_account.owner = *owner;
+                }
+                EditOp::Grant { writer } => {
+                    if !commitment.commits_writers() {
+                        return Err(AccessError::WritersNotCommitted.into());
+                    }
+                    access::check_owner(commitment, &owner_address, &to_account, &from_address)?;
+                    if !to_account.writers.contains(writer) {
+                        to_account.writers.push(*writer);
+                    }
+                }
+                EditOp::Revoke { writer } => {
+                    if !commitment.commits_writers() {
+                        return Err(AccessError::WritersNotCommitted.into());
+                    }
+                    access::check_owner(commitment, &owner_address, &to_account, &from_address)?;
+                    to_account.writers.retain(|w| w != writer);
+                }
+                EditOp::Clear => {
+                    access::check_owner(commitment, &owner_address, &to_account, &from_address)?;
+                    deleted += data.len();
+                    to_account.policy = B256::ZERO;
+                    to_account.writers.clear();
+                    to_account.contributors.clear();
+                    to_account.contributions.clear();
+                    to_account.undos.clear();
+                    to_account.metadata = Metadata::default();
+                    cleared = true;
+                }
+                // The undo is addressed against the data before the transaction, which no other
+                // op may have changed.
+                EditOp::RevertOwn { tx_hash } => {
+                    if ops.len() != 1 {
+                        return Err(UndoError::NotAlone.into());
+                    }
+                    let undo = undo::find(
+                        &to_account.undos,
+                        &from_address,
+                        tx_hash,
+                        self.batch.index,
+                        window,
+                    )?;
+                    EditOp::Replace {
+                        index: undo.index as usize,
+                        count: undo.inserted as usize,
+                        value: undo.removed.clone(),
+                    }
+                    .apply(&mut data)?;
+                    inserted += undo.removed.chars().count();
+                    deleted += undo.inserted as usize;
+                }
+                EditOp::SetMetadata { name, uri } => {
+                    access::check_owner(commitment, &owner_address, &to_account, &from_address)?;
+                    if !self.config.commitment.commits_metadata() {
+                        return Err(MetadataError::NotCommitted.into());
+                    }
+                    metadata::check(name, uri)?;
+                    to_account.metadata = Metadata {
+                        name: name.clone(),
+                        uri: uri.clone(),
+                    };
+                }
+                EditOp::Withdraw { recipient, amount } => {
+                    if !commitment.commits_balance() {
+                        return Err(BridgeError::NotCommitted.into());
+                    }
+                    bridge::withdraw(
+                        &from_address,
+                        tx.nonce,
+                        &mut from_account,
+                        &to_address,
+                        &mut to_account,
+                        recipient,
+                        *amount,
+                    )?
+                }
+                _ => {}
+            }
+            op.apply(&mut data)?;
+        }
 
-        for data in tx.data.clone() {
-            let index = data.index;
-            match data.count {
-                0 => {
-                    data_chars.splice(index..index, data.value.chars());
+        if data.len() > self.config.max_size {
+            match self.config.size_policy {
+                SizePolicy::Reject => {
+                    return Err(EditError::TooLarge {
+                        size: data.len(),
+                        max: self.config.max_size,
+                    }
+                    .into());
                 }
-                _ => {
-                    data_chars.drain(index..index + data.count);
+                SizePolicy::Truncate => {
+                    deleted += data.len() - self.config.max_size;
+                    data.truncate(self.config.max_size);
                 }
             }
         }
 
-        to_account.data = data_chars.into_iter().collect();
+        // Checked on the final data, so a transaction may register a schema together with the
+        // edits that make the data conform to it.
+        if let Some(schema) = Schema::of(commitment, &to_account)? {
+            schema.check(data.as_slice())?;
+        }
+
+        let before = std::mem::replace(&mut to_account.data, String::from(&data));
+        // Undos follow the chars they put back, and with a window the edit becomes the sender's.
+        if undoable || !to_account.undos.is_empty() {
+            undo::update(
+                &mut to_account.undos,
+                &before,
+                &to_account.data,
+                self.batch.index,
+                window,
+                undoable.then(|| (from_address, input.hash())),
+            );
+        }
+
+        let mut others = Vec::new();
+        for (address, mut account, mut buffer, removed) in sources {
+            if removed == 0 {
+                continue;
+            }
+            if let Some(schema) = Schema::of(commitment, &account)? {
+                schema.check(buffer.as_slice())?;
+            }
+            let source = match address == from_address {
+                true => &mut from_account,
+                false => &mut account,
+            };
+            let before = std::mem::replace(&mut source.data, String::from(&buffer));
+            if !source.undos.is_empty() {
+                let (after, index) = (&source.data, self.batch.index);
+                undo::update(&mut source.undos, &before, after, index, window, None);
+            }
+            if !source.contributors.contains(&from_address) {
+                source.contributors.push(from_address);
+            }
+            let stats = source.contributions.entry(from_address).or_default();
+            stats.edits += 1;
+            stats.deleted += removed as u64;
+            if address != from_address {
+                others.push((address, account));
+            }
+        }
 
         if !to_account.contributors.contains(&from_address) {
             to_account.contributors.push(from_address);
         }
+        let stats = to_account.contributions.entry(from_address).or_default();
+        stats.edits += 1;
+        stats.inserted += inserted as u64;
+        stats.deleted += deleted as u64;
 
-        self.db.set_account(&from_address, &from_account)?;
-        self.db.set_account(&to_address, &to_account)?;
+        // Edits that can no longer hold their sender back are dropped, so the map stays as small
+        // as the number of senders within the cooldown.
+        if cooled {
+            let index = self.batch.index;
+            to_account
+                .last_edits
+                .retain(|_, last| index < last.saturating_add(cooldown));
+            to_account.last_edits.insert(from_address, index);
+        }
 
-        Ok(())
+        let mut effect = TransactionEffect {
+            from: from_address,
+            from_account,
+            to: to_address,
+            to_account,
+            others,
+            sponsor,
+            removes_to: false,
+            receipt: Receipt {
+                from: from_address,
+                to: to_address,
+                ops: ops.len() as u64,
+                inserted: inserted as u64,
+                deleted: deleted as u64,
+                status: ReceiptStatus::Success,
+            },
+        };
+
+        // Paid out of the balance the ops left, so a withdrawal cannot spend the fee.
+        if !fee.is_zero() {
+            let payer = sponsor.unwrap_or(from_address);
+            fees::debit(&payer, written(&payer, &mut effect, &mut read), fee)?;
+            let recipient = self.config.fees.recipient;
+            fees::credit(&recipient, written(&recipient, &mut effect, &mut read), fee)?;
+        }
+
+        // Decided after the fee, which may be paid to the target. Only an account that holds
+        // nothing is removed, so dropping its leaf loses no balance and no nonce to replay
+        // against; it reads back as a default account, as if never written.
+        effect.removes_to = cleared && effect.to_account.is_vacant();
+
+        Ok(effect)
     }
 }
 
-impl CanvasProcessor<&InMemoryDB> {
-    pub fn generate_state_root(&self) -> eyre::Result<[u8; 32]> {
-        let accounts = self.db.accounts.borrow();
+// The account `effect` writes at `address`, taken from `read` into `others` the first time.
+// `to_account` is written last, so it is the one to change when `to == from`.
+fn written<'a>(
+    address: &Address,
+    effect: &'a mut TransactionEffect,
+    read: &mut HashMap<Address, Account>,
+) -> &'a mut Account {
+    if *address == effect.to {
+        return &mut effect.to_account;
+    }
+    if *address == effect.from {
+        return &mut effect.from_account;
+    }
+
+    let index = match effect.others.iter().position(|(other, _)| other == address) {
+        Some(index) => index,
+        None => {
+            let account = read.remove(address).unwrap_or_default();
+            effect.others.push((*address, account));
+            effect.others.len() - 1
+        }
+    };
+    &mut effect.others[index].1
+}
 
-        if accounts.len() < 1 {
-            return Ok([0; 32]);
+impl<D: AccountDB, V: ValueValidator> CanvasProcessor<D, V> {
+    /// `apply_batch` committing to `transaction_commit`, with the state root and the final
+    /// `AddressIndex` root computed by `state_root` and `accounts_root`, for dbs that commit to
+    /// their state some other way than a full `InMemoryDB`.
+    pub fn apply_batch_with(
+        &mut self,
+        transactions: &[SignedTransaction],
+        transaction_commit: B256,
+        policy: FailurePolicy,
+        batch: BatchContext,
+        inbox: Inbox,
+        state_root: impl Fn(&Self) -> eyre::Result<[u8; 32]>,
+        accounts_root: impl Fn(&Self) -> eyre::Result<[u8; 32]>,
+    ) -> eyre::Result<BatchReport> {
+        let _span = tracing::info_span!(
+            "batch",
+            index = batch.index,
+            transactions = transactions.len(),
+            deposits = inbox.deposits.len(),
+            forced = inbox.forced_txs.len(),
+        )
+        .entered();
+        if batch.index == 0 && batch.previous_batch_hash != B256::ZERO {
+            return Err(eyre::eyre!("The first batch cannot follow another batch"));
+        }
+        if batch.sequencer_signature.is_some() || self.config.require_sequencer_signature {
+            sequencer::verify_sequencer_signature(&batch, transaction_commit, &self.config.domain)?;
+        }
+        if batch.timestamp < batch.previous_timestamp {
+            return Err(eyre::eyre!(
+                "Batch timestamp {} is before the previous batch's {}",
+                batch.timestamp,
+                batch.previous_timestamp
+            ));
         }
+        self.batch = batch;
+        let initial_state_root =
+            state_root(self).wrap_err("Failed to generate inital state root")?;
+
+        self.apply_deposits(inbox.deposits)
+            .wrap_err("Failed to apply deposit")?;
+
+        self.senders.prefetch(inbox.forced_txs, &self.config.domain);
+        let mut receipts = self
+            .apply_transactions(inbox.forced_txs, FailurePolicy::Skip)
+            .wrap_err("Failed to apply forced transaction")?;
+
+        self.senders.prefetch(transactions, &self.config.domain);
+        let order = execution_order(
+            &self.config,
+            transactions,
+            &batch.previous_batch,
+            &self.senders,
+        );
+        receipts.extend(
+            self.apply_transactions(order.into_iter().map(|i| &transactions[i]), policy)
+                .wrap_err("Failed to apply transaction")?,
+        );
+        self.limits.end_batch(&self.config.rate_limit);
+        self.senders.clear();
 
-        let (tree, _) = self.get_merkle_tree()?;
+        let final_state_root = state_root(self).wrap_err("Failed to generate final state root")?;
+        let accounts_root = accounts_root(self).wrap_err("Failed to generate accounts root")?;
+        let batch_hash = batch::batch_hash(
+            batch.previous_batch_hash,
+            batch.index,
+            transaction_commit,
+            final_state_root.into(),
+        );
+
+        Ok(BatchReport {
+            public_values: PublicValuesStruct {
+                initialStateRoot: initial_state_root.into(),
+                finalStateRoot: final_state_root.into(),
+                transaction_commit,
+                receiptsRoot: receipts_root(&receipts).into(),
+                accountsRoot: accounts_root.into(),
+                previousBatch: batch.previous_batch,
+                limitsRoot: self.limits.root().into(),
+                batchIndex: batch.index,
+                l1BlockNumber: batch.l1_block_number,
+                l1BlockHash: batch.l1_block_hash,
+                depositsCommit: bridge::deposits_commit(inbox.deposits),
+                depositCount: inbox.deposits.len() as u64,
+                forcedCommit: bridge::forced_commit(inbox.forced_txs),
+                forcedCount: inbox.forced_txs.len() as u64,
+                batchTimestamp: batch.timestamp,
+                previousBatchHash: batch.previous_batch_hash,
+                batchHash: batch_hash,
+                publicValuesVersion: public_values::PUBLIC_VALUES_VERSION,
+                previousBatchTimestamp: batch.previous_timestamp,
+                sequencer: batch.sequencer,
+            },
+            receipts,
+        })
+    }
+}
+
+impl<D: Borrow<InMemoryDB>, V> CanvasProcessor<D, V> {
+    /// Runs a whole batch the way the zkVM program does, with the configured `FailurePolicy`,
+    /// and returns the values it commits.
+    pub fn process_batch(&mut self, transactions: &[SignedTransaction]) -> eyre::Result<BatchReport>
+    where
+        D: AccountDB,
+        V: ValueValidator,
+    {
+        self.process_batch_after(transactions, BatchContext::default(), Inbox::default())
+    }
+
+    /// `process_batch` for a batch other than the first, one anchored to an L1 block, or one
+    /// that consumes L1 messages.
+    pub fn process_batch_after(
+        &mut self,
+        transactions: &[SignedTransaction],
+        batch: BatchContext,
+        inbox: Inbox,
+    ) -> eyre::Result<BatchReport>
+    where
+        D: AccountDB,
+        V: ValueValidator,
+    {
+        let transaction_commit = self
+            .generate_transaction_commit(transactions)
+            .wrap_err("Failed to generate transaction commit")?;
+        self.apply_batch_with(
+            transactions,
+            transaction_commit.into(),
+            self.config.failure_policy,
+            batch,
+            inbox,
+            Self::generate_state_root,
+            Self::generate_accounts_root,
+        )
+    }
+
+    /// `process_batch_after` for a batch as posted on L1: decodes `transaction_data` itself and
+    /// commits to exactly those bytes, which re-encoding the transactions need not reproduce.
+    pub fn process_posted_batch(
+        &mut self,
+        transaction_data: &[u8],
+        batch: BatchContext,
+        inbox: Inbox,
+    ) -> eyre::Result<BatchReport>
+    where
+        D: AccountDB,
+        V: ValueValidator,
+    {
+        let transactions = batch::decode_batch(transaction_data, self.dictionary.as_deref())
+            .wrap_err("Failed to decode batch")?;
+        self.apply_batch_with(
+            &transactions,
+            batch::commit(transaction_data),
+            self.config.failure_policy,
+            batch,
+            inbox,
+            Self::generate_state_root,
+            Self::generate_accounts_root,
+        )
+    }
+
+    pub fn apply_batch(
+        &mut self,
+        transactions: &[SignedTransaction],
+        policy: FailurePolicy,
+    ) -> eyre::Result<BatchReport>
+    where
+        D: AccountDB,
+        V: ValueValidator,
+    {
+        let transaction_commit = self
+            .generate_transaction_commit(transactions)
+            .wrap_err("Failed to generate transaction commit")?;
+        self.apply_batch_with(
+            transactions,
+            transaction_commit.into(),
+            policy,
+            BatchContext::default(),
+            Inbox::default(),
+            Self::generate_state_root,
+            Self::generate_accounts_root,
+        )
+    }
+
+    pub fn generate_state_root(&self) -> eyre::Result<[u8; 32]> {
+        let _span = tracing::debug_span!("state_root").entered();
+        if self.state().accounts.borrow().len() < 1 {
+            return Ok(empty_root(self.config.commitment));
+        }
+        if self.config.commitment.is_sparse() {
+            let scheme = self.config.commitment.hash_scheme();
+            let mut smts = self.sync_sparse();
+            if self.config.commitment.is_namespaced() {
+                let roots = smts.iter_mut().map(|(canvas, smt)| (*canvas, smt.root()));
+                return Ok(namespace_tree(scheme, roots).root());
+            }
+            return Ok(smts
+                .entry(DEFAULT_CANVAS)
+                .or_insert_with(|| SparseMerkleTree::with_scheme(scheme))
+                .root());
+        }
+
+        let tree = self.sync_tree();
 
         let root = tree.root().expect("Could not get merkle root");
         Ok(root)
     }
 
+    /// The accounts written since the state root was last computed, which the next root
+    /// re-hashes. Set, removed and restored accounts all count, whether or not their value
+    /// changed.
+    pub fn touched_accounts(&self) -> HashSet<Address> {
+        self.state().dirty()
+    }
+
     pub fn generate_proof(&self, address: &Address) -> eyre::Result<Vec<[u8; 32]>> {
-        let (tree, leaves) = self.get_merkle_tree()?;
-        let idx = leaves.iter().position(|l| l.account == *address);
+        self.check_dense()?;
+        let tree = self.sync_tree();
+        let idx = tree.position(address);
 
         if idx.is_none() {
             return Err(eyre::eyre!("Address not found"));
         }
 
-        let proof = tree.proof(&[idx.unwrap()]);
-        Ok(proof.proof_hashes().to_vec())
+        Ok(tree.proof(idx.unwrap()))
+    }
+
+    /// One proof of every account in `addresses` against `generate_state_root`, sharing the
+    /// siblings the accounts have in common.
+    pub fn generate_multiproof(&self, addresses: &[Address]) -> eyre::Result<MultiProof> {
+        self.check_dense()?;
+        let tree = self.sync_tree();
+
+        let mut indices = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let idx = tree.position(address);
+            indices.push(idx.ok_or_else(|| eyre::eyre!("Address {} not found", address))?);
+        }
+
+        let mut sorted = indices.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != indices.len() {
+            return Err(eyre::eyre!("Duplicate address in multiproof request"));
+        }
+
+        Ok(MultiProof {
+            proof: tree.multiproof(&sorted),
+            leaf_count: tree.leaves().len(),
+            indices,
+        })
+    }
+
+    /// Proves the account of `address`, or that it has none, against `generate_state_root` under
+    /// a sparse commitment version. From `CommitmentVersion::V8` on the proof is against the root
+    /// of the account's canvas, `DEFAULT_CANVAS` for an address without one, which
+    /// `generate_canvas_proof` proves in turn.
+    pub fn generate_sparse_proof(&self, address: &Address) -> eyre::Result<SparseProof> {
+        if !self.config.commitment.is_sparse() {
+            return Err(eyre::eyre!(
+                "Commitment version {} has no sparse proofs",
+                self.config.commitment as u8
+            ));
+        }
+        let canvas = self
+            .state()
+            .accounts
+            .borrow()
+            .get(address)
+            .map_or(DEFAULT_CANVAS, |account| {
+                canvas_of(self.config.commitment, account)
+            });
+        let scheme = self.config.commitment.hash_scheme();
+        Ok(self
+            .sync_sparse()
+            .entry(canvas)
+            .or_insert_with(|| SparseMerkleTree::with_scheme(scheme))
+            .proof(address))
+    }
+
+    /// Proves the root of `canvas`, zero for a canvas without accounts, against
+    /// `generate_state_root` under `CommitmentVersion::V8`.
+    pub fn generate_canvas_proof(&self, canvas: u64) -> eyre::Result<CanvasProof> {
+        if !self.config.commitment.is_namespaced() {
+            return Err(eyre::eyre!(
+                "Commitment version {} has no canvases",
+                self.config.commitment as u8
+            ));
+        }
+        let mut smts = self.sync_sparse();
+        let roots: Vec<(u64, [u8; 32])> = smts
+            .iter_mut()
+            .map(|(canvas, smt)| (*canvas, smt.root()))
+            .collect();
+        let root = roots
+            .iter()
+            .find(|(id, _)| *id == canvas)
+            .map_or([0; 32], |(_, root)| *root);
+
+        Ok(CanvasProof {
+            canvas,
+            root,
+            proof: namespace_tree(self.config.commitment.hash_scheme(), roots)
+                .proof(&namespace::canvas_key(canvas)),
+        })
+    }
+
+    fn check_dense(&self) -> eyre::Result<()> {
+        match self.config.commitment.is_sparse() {
+            true => Err(eyre::eyre!(
+                "Commitment version {} only has sparse proofs",
+                self.config.commitment as u8
+            )),
+            false => Ok(()),
+        }
+    }
+
+    pub fn generate_accounts_root(&self) -> eyre::Result<[u8; 32]> {
+        Ok(self.address_index().root())
+    }
+
+    /// Proves `address` has no account, against `generate_accounts_root`.
+    pub fn generate_exclusion_proof(&self, address: &Address) -> eyre::Result<ExclusionProof> {
+        self.address_index()
+            .exclusion_proof(address)
+            .ok_or_else(|| eyre::eyre!("Address {} is in the state", address))
+    }
+
+    fn address_index(&self) -> AddressIndex {
+        AddressIndex::new(self.state().accounts.borrow().keys().copied())
     }
 
     pub fn generate_transaction_commit(
         &self,
-        transactions: &Vec<SignedTransaction>,
+        transactions: &[SignedTransaction],
     ) -> eyre::Result<[u8; 32]> {
-        let mut transactions_encoded = Vec::<u8>::new();
-        transactions.encode(&mut transactions_encoded);
-
-        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
-        zlib.write_all(&transactions_encoded)?;
-        let transactions_compressed = zlib.finish()?;
+        let commit = batch::commit_transactions(
+            transactions,
+            self.config.codec,
+            self.dictionary.as_deref(),
+        )?;
+        Ok(commit.into())
+    }
 
-        Ok(keccak256(transactions_compressed).into())
+    fn state(&self) -> &InMemoryDB {
+        self.db.borrow()
     }
 
-    fn get_merkle_tree(&self) -> eyre::Result<(MerkleTree<Keccak256Algorithm>, Vec<Leaf>)> {
-        let accounts = self.db.accounts.borrow();
+    // Brings the cached tree up to date with the db: a full build the first time, afterwards
+    // only the accounts the db reports dirty are re-hashed, whoever wrote them.
+    fn sync_tree(&self) -> std::cell::Ref<'_, StateTree> {
+        {
+            let dirty = self.state().take_dirty();
+            let accounts = self.state().accounts.borrow();
+            let mut tree = self.tree.borrow_mut();
+            for address in &dirty {
+                tree.mark_dirty(address);
+            }
 
-        let mut leaves: Vec<Leaf> = Vec::new();
-        accounts.iter().for_each(|(k, v)| {
-            let commit = AccountCommit {
-                account: *k,
-                nonce: U256::from(v.nonce),
-                data: v.data.clone(),
-                contributors: v.contributors.clone(),
-            };
-            let hash = keccak256(commit.abi_encode());
-            leaves.push(Leaf {
-                hash: hash.into(),
-                account: *k,
-            });
-        });
+            if !tree.is_initialized() {
+                tree.build(accounts.iter());
+            } else {
+                tree.update(|addresses| {
+                    self.state()
+                        .find_accounts(addresses)
+                        .expect("InMemoryDB reads cannot fail")
+                });
+            }
+        }
 
-        leaves.sort_by(|a, b| a.hash.cmp(&b.hash));
-        let hashes: Vec<[u8; 32]> = leaves.clone().into_iter().map(|l| l.hash).collect();
+        self.tree.borrow()
+    }
+
+    // `sync_tree` for the sparse trees. Accounts re-hashed after the first build keep their data
+    // chunks cached, so from `CommitmentVersion::V6` on their next edit re-hashes only the chunks
+    // it changed.
+    fn sync_sparse(&self) -> std::cell::RefMut<'_, BTreeMap<u64, SparseMerkleTree>> {
+        let version = self.config.commitment;
+        let empty_tree = || SparseMerkleTree::with_scheme(version.hash_scheme());
+        let dirty = self.state().take_dirty();
+        let accounts = self.state().accounts.borrow();
+        let mut smts = self.smts.borrow_mut();
+        let mut data_roots = self.data_roots.borrow_mut();
 
-        let tree: MerkleTree<Keccak256Algorithm> = MerkleTree::from_leaves(&hashes);
+        if smts.is_empty() {
+            let accounts: Vec<_> = accounts.iter().collect();
+            for (leaf, (address, account)) in
+                leaf_hashes(version, &accounts).into_iter().zip(&accounts)
+            {
+                smts.entry(canvas_of(version, account))
+                    .or_insert_with(empty_tree)
+                    .insert(address, leaf);
+            }
+        } else {
+            for address in dirty {
+                match accounts.get(&address) {
+                    Some(account) => smts
+                        .entry(canvas_of(version, account))
+                        .or_insert_with(empty_tree)
+                        .insert(
+                            &address,
+                            leaf_hash_cached(version, &address, account, &mut data_roots),
+                        ),
+                    None => {
+                        // A removed account no longer says which canvas it was in.
+                        for smt in smts.values_mut() {
+                            smt.remove(&address);
+                        }
+                        data_roots.remove(&address);
+                    }
+                }
+            }
+        }
 
-        Ok((tree, leaves))
+        smts
     }
 }
 
@@ -210,42 +1706,222 @@ impl Hasher for Keccak256Algorithm {
     }
 }
 
-pub fn recover_address_from_tx(input: &SignedTransaction) -> eyre::Result<Address> {
+/// Encodes a batch the way it is posted as `_transactionData` on L1 under the default
+/// `Codec::Zlib`.
+pub fn compress_transactions(transactions: &[SignedTransaction]) -> eyre::Result<Vec<u8>> {
+    batch::encode_batch(transactions, Codec::Zlib, None)
+}
+
+/// Decodes a batch posted as `_transactionData` in any codec but zstd with a dictionary.
+pub fn decompress_transactions(transaction_data: &[u8]) -> eyre::Result<Vec<SignedTransaction>> {
+    batch::decode_batch(transaction_data, None)
+}
+
+/// The sender of `input`. A signature with a high `s` is rejected: it recovers the same sender
+/// as its low-s twin, so one edit would have two encodings and two transaction commits.
+pub fn recover_address_from_tx(
+    input: &SignedTransaction,
+    domain: &SigningDomain,
+) -> Result<Address, CanvasError> {
+    if !is_low_s(&input.s) {
+        return Err(CanvasError::MalleableSignature);
+    }
+    recover_address_any_s(input, domain)
+}
+
+/// `recover_address_from_tx` accepting either `s`. The processor recovers senders this way and
+/// checks `s` itself against `CanvasConfig::high_s_signatures`.
+pub fn recover_address_any_s(
+    input: &SignedTransaction,
+    domain: &SigningDomain,
+) -> Result<Address, CanvasError> {
     let signature = Signature::from_rs_and_parity(input.r, input.s, input.odd_y_parity)?;
 
-    let mut encoded = Vec::<u8>::new();
-    input.tx.encode(&mut encoded);
+    Ok(signature.recover_address_from_prehash(&signing_hash(&input.tx, domain))?)
+}
 
-    Ok(signature.recover_address_from_msg(keccak256(encoded))?)
+// Whether the sender's signature and the sponsor's, if any, both have a low `s`.
+fn has_low_s(input: &SignedTransaction) -> bool {
+    is_low_s(&input.s)
+        && input
+            .sponsor
+            .as_ref()
+            .map_or(true, |sponsor| is_low_s(&sponsor.s))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Account {
     pub nonce: u64,
     pub data: String,
     pub contributors: Vec<Address>,
+    /// Only committed from `CommitmentVersion::V1` on, as are `owner` and `policy`.
+    pub balance: U256,
+    /// Zero while unclaimed; see `access`.
+    pub owner: Address,
+    pub policy: B256,
+    /// Who besides the owner may edit a claimed account. Committed from `CommitmentVersion::V3`
+    /// on.
+    pub writers: Vec<Address>,
+    /// What each contributor did to the account, counted from when nodes started keeping
+    /// them. Committed from `CommitmentVersion::V7` on.
+    pub contributions: BTreeMap<Address, ContributorStats>,
+    /// The canvas the account is in; see `namespace`. Committed from `CommitmentVersion::V8` on,
+    /// by the tree the account's leaf is in.
+    pub canvas: u64,
+    /// The batch each sender last edited the account's data in, kept while
+    /// `CanvasConfig::edit_cooldown` is set and only for as long as it can still hold a sender
+    /// back. Committed from `CommitmentVersion::V9` on.
+    pub last_edits: BTreeMap<Address, u64>,
+    /// Each sender's last edit of the account's data that they can still revert, kept while
+    /// `CanvasConfig::undo_window` is set. Committed from `CommitmentVersion::V11` on.
+    pub undos: BTreeMap<Address, Undo>,
+    /// Its owner's display name and URI for it. Committed from `CommitmentVersion::V12` on.
+    pub metadata: Metadata,
+}
+
+impl Account {
+    /// Whether the account has no data, nonce, balance, owner, schema, writers or metadata, so
+    /// removing it loses nothing but its contributor history.
+    pub fn is_vacant(&self) -> bool {
+        self.nonce == 0
+            && self.data.is_empty()
+            && self.balance.is_zero()
+            && self.owner == Address::ZERO
+            && self.policy == B256::ZERO
+            && self.writers.is_empty()
+            && self.metadata.is_empty()
+    }
+}
+
+/// One contributor's successful transactions on an account and the units they inserted and
+/// deleted, as in their receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct ContributorStats {
+    pub edits: u64,
+    pub inserted: u64,
+    pub deleted: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InMemoryDB {
     pub accounts: RefCell<HashMap<Address, Account>>,
+    // Accounts written through `AccountDB` since the processor's state tree last hashed them.
+    #[serde(skip)]
+    dirty: RefCell<HashSet<Address>>,
 }
 
 impl Default for InMemoryDB {
     fn default() -> Self {
         Self {
             accounts: RefCell::new(HashMap::new()),
+            dirty: RefCell::new(HashSet::new()),
         }
     }
 }
 
+/// Account writes for `AccountDB::apply_writes` to make together, in order. `None` removes the
+/// account.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    pub writes: Vec<(Address, Option<Account>)>,
+}
+
+impl WriteBatch {
+    pub fn set(&mut self, address: Address, account: Account) {
+        self.writes.push((address, Some(account)));
+    }
+
+    pub fn remove(&mut self, address: Address) {
+        self.writes.push((address, None));
+    }
+
+    /// The address of each write, in order.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.writes.iter().map(|(address, _)| *address).collect()
+    }
+}
+
+impl FromIterator<(Address, Option<Account>)> for WriteBatch {
+    fn from_iter<I: IntoIterator<Item = (Address, Option<Account>)>>(writes: I) -> Self {
+        Self {
+            writes: writes.into_iter().collect(),
+        }
+    }
+}
+
+/// Where the processor keeps accounts. The bulk methods default to one call per account; a db
+/// behind a network or on disk overrides them to read or write the accounts of a transaction,
+/// and those the state tree re-hashes, in one round trip.
 pub trait AccountDB {
-    fn get_account(&self, address: &Address) -> eyre::Result<Account>;
-    fn set_account(&self, address: &Address, account: &Account) -> eyre::Result<()>;
+    /// The default account for an address without one.
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError>;
+    /// `None` for an address without an account.
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError>;
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError>;
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError>;
+
+    /// `find_account` of each of `addresses`, in order.
+    fn find_accounts(&self, addresses: &[Address]) -> Result<Vec<Option<Account>>, CanvasError> {
+        addresses
+            .iter()
+            .map(|address| self.find_account(address))
+            .collect()
+    }
+
+    /// `get_account` of each of `addresses`, in order.
+    fn get_accounts(&self, addresses: &[Address]) -> Result<Vec<Account>, CanvasError> {
+        Ok(self
+            .find_accounts(addresses)?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect())
+    }
+
+    /// Makes the writes of `batch` in order, so a later write of an address wins.
+    fn apply_writes(&self, batch: WriteBatch) -> Result<(), CanvasError> {
+        for (address, account) in batch.writes {
+            match account {
+                Some(account) => self.set_account(&address, &account)?,
+                None => self.remove_account(&address)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: AccountDB + ?Sized> AccountDB for &T {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
+        (**self).get_account(address)
+    }
+
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        (**self).find_account(address)
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
+        (**self).set_account(address, account)
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        (**self).remove_account(address)
+    }
+
+    fn find_accounts(&self, addresses: &[Address]) -> Result<Vec<Option<Account>>, CanvasError> {
+        (**self).find_accounts(addresses)
+    }
+
+    fn get_accounts(&self, addresses: &[Address]) -> Result<Vec<Account>, CanvasError> {
+        (**self).get_accounts(addresses)
+    }
+
+    fn apply_writes(&self, batch: WriteBatch) -> Result<(), CanvasError> {
+        (**self).apply_writes(batch)
+    }
 }
 
 impl AccountDB for InMemoryDB {
-    fn get_account(&self, address: &Address) -> eyre::Result<Account> {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
         if let Some(account) = self.accounts.borrow().get(address) {
             Ok(account.clone())
         } else {
@@ -253,19 +1929,81 @@ impl AccountDB for InMemoryDB {
         }
     }
 
-    fn set_account(&self, address: &Address, account: &Account) -> eyre::Result<()> {
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        Ok(self.accounts.borrow().get(address).cloned())
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
         self.accounts.borrow_mut().insert(*address, account.clone());
+        self.dirty.borrow_mut().insert(*address);
+        Ok(())
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        self.accounts.borrow_mut().remove(address);
+        self.dirty.borrow_mut().insert(*address);
         Ok(())
     }
 }
 
 impl InMemoryDB {
+    /// Serializes the accounts in address order, so equal states give identical snapshots.
     pub fn snapshot_accounts(&self) -> eyre::Result<Vec<u8>> {
-        Ok(bincode::serialize(&self.accounts.borrow().deref())?)
+        Ok(snapshot::encode(&self.accounts.borrow())?)
     }
 
+    /// Restores a snapshot of any version, migrating older account layouts. Errors are
+    /// `SnapshotError`s.
     pub fn from_snapshot(snapshot: &[u8]) -> eyre::Result<InMemoryDB> {
-        let db: InMemoryDB = bincode::deserialize(snapshot)?;
-        Ok(db)
+        let accounts = snapshot::decode(snapshot)?;
+        Ok(InMemoryDB {
+            accounts: RefCell::new(accounts),
+            ..Default::default()
+        })
+    }
+
+    /// The accounts as pretty JSON in address order: addresses and hashes as hex, data as UTF-8
+    /// text. The layout is a JSON genesis file's, so an export can start another chain.
+    pub fn export_json(&self) -> eyre::Result<String> {
+        let genesis = Genesis {
+            accounts: self
+                .accounts
+                .borrow()
+                .iter()
+                .map(|(address, account)| (*address, account.clone()))
+                .collect(),
+        };
+        Ok(serde_json::to_string_pretty(&genesis)?)
+    }
+
+    /// Restores the accounts of `export_json`, or of any JSON genesis file. Fields left out
+    /// default as in `Genesis`.
+    pub fn import_json(json: &str) -> eyre::Result<InMemoryDB> {
+        let genesis: Genesis = serde_json::from_str(json)?;
+        Ok(Self::from_genesis(&genesis))
+    }
+
+    /// The state before batch 0, with the accounts of `genesis`.
+    pub fn from_genesis(genesis: &Genesis) -> InMemoryDB {
+        let accounts = genesis
+            .accounts
+            .iter()
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        InMemoryDB {
+            accounts: RefCell::new(accounts),
+            ..Default::default()
+        }
+    }
+
+    /// The accounts written through `AccountDB` since the last call. Writes straight to
+    /// `accounts` are not tracked, so a processor's cached leaf hashes only see them on a rebuild.
+    pub fn take_dirty(&self) -> HashSet<Address> {
+        std::mem::take(&mut *self.dirty.borrow_mut())
+    }
+
+    /// `take_dirty` without clearing them.
+    pub fn dirty(&self) -> HashSet<Address> {
+        self.dirty.borrow().clone()
     }
 }
@@ -0,0 +1,146 @@
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+use crate::commitment::{leaf_hash, CommitmentVersion};
+use crate::namespace::{canvas_of, CanvasProof, DEFAULT_CANVAS};
+use crate::proof::verify_proof;
+use crate::public_values::decode_public_values;
+use crate::smt::{self, SparseProof};
+use crate::{Account, CanvasProcessor, InMemoryDB};
+
+/// What proves an account against a state root, by the commitment version's tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StateProof {
+    /// A `generate_proof` path, which only proves accounts that exist.
+    Dense(Vec<[u8; 32]>),
+    /// A `generate_sparse_proof` of the account or its absence, and under a namespaced version
+    /// the proof of the canvas root it is against.
+    Sparse {
+        proof: SparseProof,
+        canvas: Option<CanvasProof>,
+    },
+}
+
+/// An account as a node serves it to light clients, with the proof of it against the node's
+/// state root. `account` is `None` where the node claims there is none.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountWithProof {
+    pub address: Address,
+    pub account: Option<Account>,
+    pub state_root: B256,
+    pub proof: StateProof,
+}
+
+impl<D: Borrow<InMemoryDB>, V> CanvasProcessor<D, V> {
+    /// The account of `address` with its proof against `generate_state_root`. Under a dense
+    /// commitment version an address without an account is an error, as only sparse trees prove
+    /// absence.
+    pub fn account_with_proof(&self, address: &Address) -> eyre::Result<AccountWithProof> {
+        let version = self.config.commitment;
+        let account = self.state().accounts.borrow().get(address).cloned();
+        let proof = match version.is_sparse() {
+            false => StateProof::Dense(self.generate_proof(address)?),
+            true => StateProof::Sparse {
+                proof: self.generate_sparse_proof(address)?,
+                canvas: match version.is_namespaced() {
+                    true => Some(
+                        self.generate_canvas_proof(
+                            account
+                                .as_ref()
+                                .map_or(DEFAULT_CANVAS, |account| canvas_of(version, account)),
+                        )?,
+                    ),
+                    false => None,
+                },
+            },
+        };
+
+        Ok(AccountWithProof {
+            address: *address,
+            account,
+            state_root: self.generate_state_root()?.into(),
+            proof,
+        })
+    }
+}
+
+/// Checks accounts served by an untrusted node against a state root the rollup contract
+/// accepted with a validity proof, so reading the state takes neither a replay nor trust in the
+/// node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightClient {
+    pub state_root: B256,
+    pub version: CommitmentVersion,
+}
+
+impl LightClient {
+    pub fn new(state_root: B256, version: CommitmentVersion) -> Self {
+        Self {
+            state_root,
+            version,
+        }
+    }
+
+    /// Trusts the final state root of a batch's `_publicValuesBytes`, as posted to L1 with the
+    /// proof of it, under the commitment version the batch ran with.
+    pub fn from_public_values(
+        public_values: &[u8],
+        version: CommitmentVersion,
+    ) -> eyre::Result<Self> {
+        let claimed = decode_public_values(public_values)?;
+        Ok(Self::new(claimed.final_state_root, version))
+    }
+
+    /// The account `response` serves, `None` for one proven not to exist, if its proof holds
+    /// against the trusted state root. A node at another state fails even with a valid proof.
+    pub fn verify(&self, response: &AccountWithProof) -> eyre::Result<Option<Account>> {
+        if response.state_root != self.state_root {
+            return Err(eyre::eyre!(
+                "Node proves against state root {}, not {}",
+                response.state_root,
+                self.state_root
+            ));
+        }
+
+        let root = self.state_root.0;
+        let address = &response.address;
+        let account = response.account.as_ref();
+        let scheme = self.version.hash_scheme();
+        let valid = match (&response.proof, account) {
+            (StateProof::Dense(proof), Some(account)) => {
+                !self.version.is_sparse()
+                    && verify_proof(&root, self.version, address, account, proof)
+            }
+            (StateProof::Dense(_), None) => false,
+            (StateProof::Sparse { proof, canvas }, _) => {
+                let leaf = account.map(|account| leaf_hash(self.version, address, account));
+                match (self.version.is_namespaced(), canvas) {
+                    (true, Some(canvas)) => {
+                        let expected = account
+                            .map_or(DEFAULT_CANVAS, |account| canvas_of(self.version, account));
+                        canvas.canvas == expected
+                            && canvas.verify_with(scheme, &root)
+                            && smt::verify_with(scheme, &canvas.root, address, leaf.as_ref(), proof)
+                    }
+                    (false, None) => {
+                        self.version.is_sparse()
+                            && smt::verify_with(scheme, &root, address, leaf.as_ref(), proof)
+                    }
+                    _ => false,
+                }
+            }
+        };
+
+        match valid {
+            true => Ok(response.account.clone()),
+            false => Err(eyre::eyre!(
+                "Proof of {} does not hold against state root {}",
+                address,
+                self.state_root
+            )),
+        }
+    }
+}
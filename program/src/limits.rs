@@ -0,0 +1,166 @@
+use alloy_primitives::{keccak256, Address};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::state_tree::root_of;
+
+/// Per-sender quotas. The state has no clock, so time is counted in batches. Zero fields are
+/// unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Most edit ops a sender may apply per window.
+    pub max_ops: u64,
+    /// Batches per window, at least one.
+    pub window: u64,
+    /// Batches a sender has to wait after a transaction before sending the next one.
+    pub cooldown: u64,
+}
+
+impl RateLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_ops == 0 && self.cooldown == 0
+    }
+
+    fn window(&self) -> u64 {
+        self.window.max(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Counter {
+    pub window_start: u64,
+    pub ops: u64,
+    pub last_batch: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// `address` already applied `used` ops this window and `ops` more would exceed `max`.
+    OpsExceeded {
+        address: Address,
+        used: u64,
+        ops: u64,
+        max: u64,
+    },
+    /// `address` may not send again before batch `until`.
+    CoolingDown { address: Address, until: u64 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::OpsExceeded {
+                address,
+                used,
+                ops,
+                max,
+            } => write!(
+                f,
+                "{:?} used {} of {} ops this window, {} more exceed the limit",
+                address, used, max, ops
+            ),
+            LimitError::CoolingDown { address, until } => {
+                write!(f, "{:?} is cooling down until batch {}", address, until)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// The rate-limit counters, kept next to the accounts and committed as `limitsRoot` after every
+/// batch. Counters that can no longer limit anything are pruned at the end of each batch.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RateLimitState {
+    /// Number of the batch being applied, counting from the first one applied under a limit.
+    pub batch: u64,
+    pub counters: BTreeMap<Address, Counter>,
+}
+
+impl RateLimitState {
+    pub fn check(&self, limit: &RateLimit, address: &Address, ops: u64) -> Result<(), LimitError> {
+        let counter = self.counters.get(address);
+
+        if let Some(counter) = counter {
+            let until = counter.last_batch + limit.cooldown;
+            if limit.cooldown > 0 && self.batch < until {
+                return Err(LimitError::CoolingDown {
+                    address: *address,
+                    until,
+                });
+            }
+        }
+
+        let used = counter.map_or(0, |counter| self.used(limit, counter));
+        if limit.max_ops > 0 && used + ops > limit.max_ops {
+            return Err(LimitError::OpsExceeded {
+                address: *address,
+                used,
+                ops,
+                max: limit.max_ops,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, limit: &RateLimit, address: Address, ops: u64) {
+        if limit.is_unlimited() {
+            return;
+        }
+
+        let batch = self.batch;
+        let counter = self.counters.entry(address).or_insert(Counter {
+            window_start: batch,
+            ops: 0,
+            last_batch: batch,
+        });
+        if batch >= counter.window_start + limit.window() {
+            counter.window_start = batch;
+            counter.ops = 0;
+        }
+        counter.ops += ops;
+        counter.last_batch = batch;
+    }
+
+    /// Only counts batches while a limit is set, so without one the root stays the same and a
+    /// verifier does not need to know how many batches came before.
+    pub fn end_batch(&mut self, limit: &RateLimit) {
+        if limit.is_unlimited() {
+            return;
+        }
+        self.batch += 1;
+
+        let batch = self.batch;
+        self.counters.retain(|_, counter| {
+            batch < counter.window_start + limit.window()
+                || batch < counter.last_batch + limit.cooldown
+        });
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let leaves = self
+            .counters
+            .iter()
+            .map(|(address, counter)| {
+                let mut preimage = address.to_vec();
+                preimage.extend(counter.window_start.to_be_bytes());
+                preimage.extend(counter.ops.to_be_bytes());
+                preimage.extend(counter.last_batch.to_be_bytes());
+                keccak256(preimage).into()
+            })
+            .collect();
+
+        let counters_root: [u8; 32] = root_of(leaves).unwrap_or([0; 32]);
+        keccak256([&self.batch.to_be_bytes()[..], &counters_root].concat()).into()
+    }
+
+    fn used(&self, limit: &RateLimit, counter: &Counter) -> u64 {
+        if self.batch >= counter.window_start + limit.window() {
+            0
+        } else {
+            counter.ops
+        }
+    }
+}
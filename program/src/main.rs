@@ -1,37 +1,35 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
-use program::{CanvasProcessor, Input, PublicValuesStruct};
+use program::chunked;
 
 use alloy_sol_types::SolValue;
+use std::io::Read;
 
-pub fn main() {
-    let input = sp1_zkvm::io::read::<Input>();
-
-    let mut canvas = CanvasProcessor { db: &input.db };
-
-    let initial_state_root = canvas
-        .generate_state_root()
-        .expect("Failed to generate inital state root");
-
-    let transaction_commit = canvas
-        .generate_transaction_commit(&input.transactions)
-        .expect("Failed to generate transaction commit");
+// The buffers the host wrote to stdin as one stream, holding one buffer at a time.
+#[derive(Default)]
+struct Stdin {
+    buffer: Vec<u8>,
+    position: usize,
+}
 
-    for tx in input.transactions {
-        canvas
-            .apply_transaction(&tx)
-            .expect("Failed to apply transaction");
+impl Read for Stdin {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.buffer.len() {
+            self.buffer = sp1_zkvm::io::read_vec();
+            self.position = 0;
+        }
+
+        let n = out.len().min(self.buffer.len() - self.position);
+        out[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
     }
+}
 
-    let final_state_root = canvas
-        .generate_state_root()
-        .expect("Failed to generate final state root");
+pub fn main() {
+    let input = chunked::read_input(Stdin::default()).expect("Failed to read input");
 
-    let public_values = PublicValuesStruct {
-        initialStateRoot: initial_state_root.into(),
-        finalStateRoot: final_state_root.into(),
-        transaction_commit: transaction_commit.into(),
-    };
+    let report = input.process().expect("Failed to process batch");
 
-    sp1_zkvm::io::commit_slice(public_values.abi_encode().as_slice());
+    sp1_zkvm::io::commit_slice(report.public_values.abi_encode().as_slice());
 }
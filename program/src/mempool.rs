@@ -0,0 +1,674 @@
+use alloy_primitives::{Address, B256};
+use alloy_rlp::Encodable;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::batch::{BatchBuilder, BatchContext};
+use crate::codec::{Codec, DecodeLimits};
+use crate::config::CanvasConfig;
+use crate::cost::CycleModel;
+use crate::expiry;
+use crate::fees::FeeError;
+use crate::nonce::NonceError;
+use crate::ordering::{Fifo, OrderingPolicy};
+use crate::sponsor::recover_sponsor;
+use crate::{recover_address_from_tx, AccountDB, SignedTransaction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolConfig {
+    /// Seconds a transaction may wait before it is evicted.
+    pub ttl: u64,
+    /// Most transactions held at once.
+    pub max_size: usize,
+    /// Most RLP-encoded bytes of a single transaction.
+    pub max_tx_size: usize,
+    /// How far past the sender's nonce in state a transaction may be queued.
+    pub max_nonce_gap: u64,
+    /// Most compressed bytes of a batch from `drain_batch`, its `_transactionData`.
+    pub max_batch_size: usize,
+    /// Most zkVM cycles of a batch from `drain_batch` as `cycle_model` estimates them. A
+    /// transaction that would not fit in an empty batch is not admitted.
+    pub max_batch_cycles: u64,
+    pub cycle_model: CycleModel,
+    /// The limits a transaction is held to, as when it is decoded from a batch.
+    pub decode_limits: DecodeLimits,
+    /// Seconds over which a sender's admitted transactions count towards `max_sender_txs` and
+    /// `max_sender_bytes`.
+    pub sender_window: u64,
+    /// Most transactions admitted from one sender per `sender_window`, zero for no limit.
+    pub max_sender_txs: usize,
+    /// Most RLP-encoded bytes admitted from one sender per `sender_window`, zero for no limit.
+    pub max_sender_bytes: usize,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 600,
+            max_size: 10_000,
+            max_tx_size: 16 * 1024,
+            max_nonce_gap: 16,
+            max_batch_size: 96 * 1024,
+            max_batch_cycles: 2_000_000_000,
+            cycle_model: CycleModel::default(),
+            decode_limits: DecodeLimits::default(),
+            sender_window: 60,
+            max_sender_txs: 120,
+            max_sender_bytes: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MempoolError {
+    /// The encoded transaction is `size` bytes, more than `max`.
+    TooLarge { size: usize, max: usize },
+    /// `nonce` is more than `max_nonce_gap` past the sender's next nonce `next`.
+    NonceTooFar {
+        address: Address,
+        next: u64,
+        nonce: u64,
+    },
+    /// The sender already has a transaction with this nonce pending.
+    AlreadyKnown { address: Address, nonce: u64 },
+    /// The very same transaction is already pending.
+    Duplicate { hash: B256 },
+    /// A batch of just the transaction is estimated at `cycles`, more than `max`.
+    TooExpensive { cycles: u64, max: u64 },
+    /// The sender already had `max` transactions admitted in the last `window` seconds.
+    SenderTxQuota {
+        address: Address,
+        max: usize,
+        window: u64,
+    },
+    /// The transaction's `size` bytes would take the sender past `max` bytes admitted in the
+    /// last `window` seconds, of which it already used `used`.
+    SenderByteQuota {
+        address: Address,
+        size: usize,
+        used: usize,
+        max: usize,
+        window: u64,
+    },
+    /// The pool is full and every pending transaction has a priority above `priority`, the
+    /// lowest being `lowest`.
+    PoolFull { priority: u64, lowest: u64 },
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::TooLarge { size, max } => write!(
+                f,
+                "Transaction of {} bytes exceeds the maximum size of {}",
+                size, max
+            ),
+            MempoolError::NonceTooFar {
+                address,
+                next,
+                nonce,
+            } => write!(
+                f,
+                "Nonce {} of {:?} is too far ahead of its next nonce {}",
+                nonce, address, next
+            ),
+            MempoolError::AlreadyKnown { address, nonce } => write!(
+                f,
+                "{:?} already has a pending transaction with nonce {}",
+                address, nonce
+            ),
+            MempoolError::Duplicate { hash } => {
+                write!(f, "Transaction {} is already pending", hash)
+            }
+            MempoolError::TooExpensive { cycles, max } => write!(
+                f,
+                "Transaction of an estimated {} cycles exceeds the batch budget of {}",
+                cycles, max
+            ),
+            MempoolError::SenderTxQuota {
+                address,
+                max,
+                window,
+            } => write!(
+                f,
+                "{:?} already submitted {} transactions in the last {} seconds",
+                address, max, window
+            ),
+            MempoolError::SenderByteQuota {
+                address,
+                size,
+                used,
+                max,
+                window,
+            } => write!(
+                f,
+                "Transaction of {} bytes would take {:?} past {} bytes in the last {} seconds, \
+                 of which it used {}",
+                size, address, max, window, used
+            ),
+            MempoolError::PoolFull { priority, lowest } => write!(
+                f,
+                "Mempool is full and priority {} is below the lowest pending, {}",
+                priority, lowest
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+#[derive(Debug, Clone)]
+pub struct PendingTransaction {
+    pub tx: SignedTransaction,
+    /// `SignedTransaction::hash` of `tx`.
+    pub hash: B256,
+    pub sender: Address,
+    /// Unix time, in seconds, the transaction was admitted.
+    pub received: u64,
+    /// Higher is kept longer when the pool is full.
+    pub priority: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvictionReason {
+    Expired,
+    PoolFull,
+    /// An earlier transaction of the same sender was evicted, so this one can no longer apply.
+    NonceGap,
+    /// The sender's nonce in state moved past it.
+    Stale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eviction {
+    pub sender: Address,
+    pub nonce: u64,
+    pub reason: EvictionReason,
+}
+
+/// Pending transactions in admission order. Expired transactions and, once the pool is over
+/// `max_size`, the lowest-priority and then oldest ones are evicted; every eviction is sent to
+/// the subscribers. Times are passed in by the caller so eviction stays deterministic.
+///
+/// Each sender is held to `max_sender_txs` and `max_sender_bytes` per `sender_window`, counting
+/// every transaction admitted whether or not it is still pending.
+///
+/// A sender's transactions may arrive out of nonce order; one past a gap waits until the gap is
+/// filled, or expires. Batches take them as the `OrderingPolicy` picks, `Fifo` by default.
+///
+/// Once `restore` gives it a `MempoolLog`, every transaction `submit` admits is logged there.
+/// The pool itself does no I/O; the node decides where the log lives.
+#[derive(Debug)]
+pub struct Mempool {
+    pub config: MempoolConfig,
+    codec: Codec,
+    dictionary: Option<Vec<u8>>,
+    pending: Vec<PendingTransaction>,
+    subscribers: Vec<Sender<Eviction>>,
+    // When each sender's transactions were admitted within the last `sender_window`, with their
+    // sizes, oldest first.
+    admitted: HashMap<Address, VecDeque<(u64, usize)>>,
+    ordering: Box<dyn OrderingPolicy>,
+    log: Option<Box<dyn MempoolLog>>,
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new(MempoolConfig::default())
+    }
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            codec: Codec::default(),
+            dictionary: None,
+            pending: Vec::new(),
+            subscribers: Vec::new(),
+            admitted: HashMap::new(),
+            ordering: Box::new(Fifo),
+            log: None,
+        }
+    }
+
+    /// Fills the batches from `drain_batch` in the order `ordering` picks.
+    pub fn with_ordering(mut self, ordering: Box<dyn OrderingPolicy>) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Sizes the batches from `drain_batch` as `codec` encodes them.
+    pub fn with_codec(mut self, codec: Codec, dictionary: Option<Vec<u8>>) -> Self {
+        self.codec = codec;
+        self.dictionary = dictionary;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> &[PendingTransaction] {
+        &self.pending
+    }
+
+    /// The pending transaction with `SignedTransaction::hash` `hash`.
+    pub fn get(&self, hash: &B256) -> Option<&PendingTransaction> {
+        self.pending.iter().find(|pending| pending.hash == *hash)
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<Eviction> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Checks `tx` against the state in `db` and admits it as received at `now`. It must not be
+    /// pending already, it must be within `decode_limits` and the cycle budget, the signature
+    /// must recover, the sender must be within its quotas, the nonce must be the sender's next
+    /// one in state or at most `max_nonce_gap` past it, the sender must not have a transaction
+    /// with that nonce pending already, and the balance of its sponsor, or else its sender, must
+    /// cover the fee. A full pool only admits it if its `priority` is at least the lowest
+    /// pending one, evicting the oldest transaction of that priority. An admitted transaction
+    /// is in the log, if there is one, before this returns.
+    pub fn submit<D: AccountDB>(
+        &mut self,
+        db: &D,
+        config: &CanvasConfig,
+        tx: SignedTransaction,
+        now: u64,
+        priority: u64,
+    ) -> eyre::Result<Vec<Eviction>> {
+        self.admit(db, config, tx, now, priority, false)
+    }
+
+    /// Admits the transactions `log` held before a restart again, as received and with the
+    /// priority they were logged with, and logs there every transaction admitted from then on.
+    /// Their senders' quotas counted them when they were first admitted, so they are not checked
+    /// again. Those the state in `db` no longer admits, such as ones a batch included since, are
+    /// dropped, and the log is rewritten without them. Returns how many were admitted.
+    pub fn restore<D: AccountDB>(
+        &mut self,
+        db: &D,
+        config: &CanvasConfig,
+        logged: Vec<LoggedTransaction>,
+        mut log: Box<dyn MempoolLog>,
+    ) -> eyre::Result<usize> {
+        self.log = None;
+        let restored = logged
+            .into_iter()
+            .filter(|logged| {
+                self.admit(
+                    db,
+                    config,
+                    logged.tx.clone(),
+                    logged.received,
+                    logged.priority,
+                    true,
+                )
+                .is_ok()
+            })
+            .count();
+        log.compact(&self.pending)?;
+        self.log = Some(log);
+        Ok(restored)
+    }
+
+    /// Rewrites the log, if there is one, with the transactions still pending.
+    pub fn compact_log(&mut self) -> eyre::Result<()> {
+        match &mut self.log {
+            Some(log) => log.compact(&self.pending),
+            None => Ok(()),
+        }
+    }
+
+    // `submit`, or with `restoring` a transaction logged before a restart, whose admission its
+    // sender's quota already counted.
+    fn admit<D: AccountDB>(
+        &mut self,
+        db: &D,
+        config: &CanvasConfig,
+        tx: SignedTransaction,
+        now: u64,
+        priority: u64,
+        restoring: bool,
+    ) -> eyre::Result<Vec<Eviction>> {
+        let size = tx.length();
+        if size > self.config.max_tx_size {
+            return Err(MempoolError::TooLarge {
+                size,
+                max: self.config.max_tx_size,
+            }
+            .into());
+        }
+        let hash = tx.hash();
+        if self.get(&hash).is_some() {
+            return Err(MempoolError::Duplicate { hash }.into());
+        }
+        self.config.decode_limits.check_transaction(&tx)?;
+        let model = self.config.cycle_model;
+        let cycles = model.batch.saturating_add(model.transaction(&tx));
+        if cycles > self.config.max_batch_cycles {
+            return Err(MempoolError::TooExpensive {
+                cycles,
+                max: self.config.max_batch_cycles,
+            }
+            .into());
+        }
+
+        let sender = recover_address_from_tx(&tx, &config.domain)?;
+        if !restoring {
+            self.check_quota(&sender, size, now)?;
+        }
+        let account = db.get_account(&sender)?;
+        let (next, nonce) = (account.nonce, tx.tx.nonce);
+        if nonce < next {
+            return Err(NonceError {
+                address: sender,
+                expected: next,
+                got: nonce,
+            }
+            .into());
+        }
+        if nonce - next > self.config.max_nonce_gap {
+            return Err(MempoolError::NonceTooFar {
+                address: sender,
+                next,
+                nonce,
+            }
+            .into());
+        }
+        if self
+            .pending
+            .iter()
+            .any(|pending| pending.sender == sender && pending.tx.tx.nonce == nonce)
+        {
+            return Err(MempoolError::AlreadyKnown {
+                address: sender,
+                nonce,
+            }
+            .into());
+        }
+        let fee = config.fees.fee(&tx);
+        let (payer, balance) = match recover_sponsor(&tx, &config.domain)? {
+            Some(sponsor) if sponsor != sender => (sponsor, db.get_account(&sponsor)?.balance),
+            _ => (sender, account.balance),
+        };
+        if balance < fee {
+            return Err(FeeError::InsufficientBalance {
+                account: payer,
+                balance,
+                fee,
+            }
+            .into());
+        }
+        if self.pending.len() >= self.config.max_size {
+            let lowest = self.pending.iter().map(|pending| pending.priority).min();
+            if let Some(lowest) = lowest.filter(|lowest| priority < *lowest) {
+                return Err(MempoolError::PoolFull { priority, lowest }.into());
+            }
+        }
+
+        self.admitted
+            .entry(sender)
+            .or_default()
+            .push_back((now, size));
+        let evicted = self.insert(PendingTransaction {
+            tx,
+            hash,
+            sender,
+            received: now,
+            priority,
+        });
+        self.log_admission(hash, sender, &evicted)?;
+        Ok(evicted)
+    }
+
+    /// Adds `tx` and evicts down to `max_size`, which may evict `tx` itself.
+    pub fn insert(&mut self, tx: PendingTransaction) -> Vec<Eviction> {
+        self.pending.push(tx);
+
+        let mut evicted = Vec::new();
+        while self.pending.len() > self.config.max_size {
+            // `min_by_key` keeps the first of equal keys, which is the oldest.
+            let (index, _) = self
+                .pending
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, tx)| tx.priority)
+                .expect("pool is not empty");
+            evicted.extend(self.evict(index, EvictionReason::PoolFull));
+        }
+
+        self.notify(&evicted);
+        evicted
+    }
+
+    /// Evicts every transaction received more than `ttl` seconds before `now`, or whose
+    /// `valid_until_timestamp` is before `now`.
+    pub fn evict_expired(&mut self, now: u64) -> Vec<Eviction> {
+        let batch = BatchContext::default().timestamped(now);
+        let mut evicted = Vec::new();
+        while let Some(index) = self.pending.iter().position(|pending| {
+            now.saturating_sub(pending.received) > self.config.ttl
+                || expiry::check(&pending.tx.tx, &batch).is_err()
+        }) {
+            evicted.extend(self.evict(index, EvictionReason::Expired));
+        }
+        let window = self.config.sender_window;
+        self.admitted.retain(|_, admitted| {
+            admitted
+                .back()
+                .is_some_and(|(received, _)| now.saturating_sub(*received) < window)
+        });
+
+        self.notify(&evicted);
+        evicted
+    }
+
+    /// Removes and returns up to `max` transactions, oldest first.
+    pub fn drain(&mut self, max: usize) -> Vec<PendingTransaction> {
+        let count = max.min(self.pending.len());
+        self.pending.drain(..count).collect()
+    }
+
+    /// Removes the next batch: transactions whose nonces follow on from their sender's nonce in
+    /// `db`, in the order the `OrderingPolicy` picks otherwise, until the compressed batch would
+    /// exceed `max_batch_size` or its estimated cycles `max_batch_cycles`. Transactions behind a
+    /// gap stay queued; ones the state has moved past are evicted as `Stale`.
+    pub fn drain_batch<D: AccountDB>(&mut self, db: &D) -> eyre::Result<Vec<SignedTransaction>> {
+        let mut next = HashMap::new();
+        for pending in &self.pending {
+            if !next.contains_key(&pending.sender) {
+                next.insert(pending.sender, db.get_account(&pending.sender)?.nonce);
+            }
+        }
+
+        let stale: Vec<Eviction> = self
+            .pending
+            .iter()
+            .filter(|pending| pending.tx.tx.nonce < next[&pending.sender])
+            .map(|pending| Eviction {
+                sender: pending.sender,
+                nonce: pending.tx.tx.nonce,
+                reason: EvictionReason::Stale,
+            })
+            .collect();
+        self.pending
+            .retain(|pending| pending.tx.tx.nonce >= next[&pending.sender]);
+        self.notify(&stale);
+
+        let mut builder = BatchBuilder::with_codec(
+            self.config.max_batch_size,
+            self.codec,
+            self.dictionary.clone(),
+        )
+        .with_cycle_budget(self.config.max_batch_cycles, self.config.cycle_model);
+        let mut taken = vec![false; self.pending.len()];
+        let mut order = Vec::new();
+        let mut per_sender = HashMap::new();
+        loop {
+            // The next transaction of each sender that has one; a sender has one per nonce at
+            // most.
+            let ready: Vec<usize> = (0..self.pending.len())
+                .filter(|index| {
+                    let pending = &self.pending[*index];
+                    !taken[*index] && pending.tx.tx.nonce == next[&pending.sender]
+                })
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            let candidates: Vec<&PendingTransaction> =
+                ready.iter().map(|index| &self.pending[*index]).collect();
+            let index = ready[self.ordering.pick(&candidates, &per_sender)];
+
+            let pending = &self.pending[index];
+            if !builder.push(pending.tx.clone())? {
+                break;
+            }
+            taken[index] = true;
+            order.push(index);
+            *next.get_mut(&pending.sender).expect("sender seen above") += 1;
+            *per_sender.entry(pending.sender).or_insert(0) += 1;
+        }
+
+        // Transactions `finish` could not fit stay queued. They are the last ones taken, so no
+        // later nonce of their senders was.
+        let batch = builder.finish()?.transactions;
+        for index in &order[batch.len()..] {
+            taken[*index] = false;
+        }
+        let mut taken = taken.into_iter();
+        self.pending.retain(|_| !taken.next().unwrap_or(false));
+        Ok(batch)
+    }
+
+    // Drops the sender's admissions that fell out of the window and checks a transaction of
+    // `size` bytes against what is left.
+    fn check_quota(&mut self, sender: &Address, size: usize, now: u64) -> Result<(), MempoolError> {
+        let window = self.config.sender_window;
+        let Some(admitted) = self.admitted.get_mut(sender) else {
+            return Ok(());
+        };
+        while admitted
+            .front()
+            .is_some_and(|(received, _)| now.saturating_sub(*received) >= window)
+        {
+            admitted.pop_front();
+        }
+
+        let max = self.config.max_sender_txs;
+        if max != 0 && admitted.len() >= max {
+            return Err(MempoolError::SenderTxQuota {
+                address: *sender,
+                max,
+                window,
+            });
+        }
+        let used: usize = admitted.iter().map(|(_, size)| size).sum();
+        let max = self.config.max_sender_bytes;
+        if max != 0 && used + size > max {
+            return Err(MempoolError::SenderByteQuota {
+                address: *sender,
+                size,
+                used,
+                max,
+                window,
+            });
+        }
+        Ok(())
+    }
+
+    // Removes the transaction at `index` and the sender's later ones, which now have a gap.
+    fn evict(&mut self, index: usize, reason: EvictionReason) -> Vec<Eviction> {
+        let removed = self.pending.remove(index);
+        let (sender, nonce) = (removed.sender, removed.tx.tx.nonce);
+
+        let mut evicted = vec![Eviction {
+            sender,
+            nonce,
+            reason,
+        }];
+        self.pending.retain(|tx| {
+            if tx.sender == sender && tx.tx.tx.nonce > nonce {
+                evicted.push(Eviction {
+                    sender,
+                    nonce: tx.tx.tx.nonce,
+                    reason: EvictionReason::NonceGap,
+                });
+                return false;
+            }
+            true
+        });
+
+        evicted
+    }
+
+    // Logs the transaction `submit` just admitted as `hash` from `sender`, or rewrites the log if
+    // admitting it evicted others. A transaction that could not be logged is not admitted after
+    // all, as a crash would lose it, nor counted against the sender's quota.
+    fn log_admission(
+        &mut self,
+        hash: B256,
+        sender: Address,
+        evicted: &[Eviction],
+    ) -> eyre::Result<()> {
+        let Some(log) = &mut self.log else {
+            return Ok(());
+        };
+        let logged = if evicted.is_empty() {
+            log.append(self.pending.last().expect("just inserted"))
+        } else {
+            log.compact(&self.pending)
+        };
+        if let Err(err) = logged {
+            self.pending.retain(|pending| pending.hash != hash);
+            if let Some(admitted) = self.admitted.get_mut(&sender) {
+                admitted.pop_back();
+                if admitted.is_empty() {
+                    self.admitted.remove(&sender);
+                }
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn notify(&mut self, evicted: &[Eviction]) {
+        if evicted.is_empty() {
+            return;
+        }
+        self.subscribers
+            .retain(|subscriber| evicted.iter().all(|e| subscriber.send(*e).is_ok()));
+    }
+}
+
+/// Where a `Mempool` keeps its pending transactions so that `Mempool::restore` can queue them
+/// again after a restart. `append` has to make the transaction durable before it returns, as
+/// `Mempool::submit` confirms the transaction once it does.
+pub trait MempoolLog: fmt::Debug + Send {
+    /// Logs `tx`, admitted after those logged so far.
+    fn append(&mut self, tx: &PendingTransaction) -> eyre::Result<()>;
+    /// Rewrites the log with `pending` alone.
+    fn compact(&mut self, pending: &[PendingTransaction]) -> eyre::Result<()>;
+}
+
+/// A transaction read back from a `MempoolLog`, with what `Mempool::restore` admits it again
+/// under.
+#[derive(Debug, Clone)]
+pub struct LoggedTransaction {
+    pub tx: SignedTransaction,
+    /// `PendingTransaction::received`.
+    pub received: u64,
+    /// `PendingTransaction::priority`.
+    pub priority: u64,
+}
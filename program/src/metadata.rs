@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Most UTF-8 bytes of a `Metadata::name`.
+pub const MAX_NAME: usize = 64;
+/// Most UTF-8 bytes of a `Metadata::uri`.
+pub const MAX_URI: usize = 256;
+
+/// What an account's owner says about it, set with `EditOp::SetMetadata`. Neither field is
+/// validated beyond its size, and neither counts against the account's data or its palette.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    /// A display name.
+    pub name: String,
+    /// Where more about the account is, an avatar for instance.
+    pub uri: String,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.uri.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataError {
+    /// `field` is `len` bytes, over its `max`.
+    TooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    /// Metadata set under a commitment version that does not commit it.
+    NotCommitted,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::TooLong { field, len, max } => {
+                write!(f, "Metadata {} of {} bytes exceeds {}", field, len, max)
+            }
+            MetadataError::NotCommitted => write!(f, "Metadata needs commitment version 12"),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+/// Checks `name` and `uri` against `MAX_NAME` and `MAX_URI`.
+pub fn check(name: &str, uri: &str) -> Result<(), MetadataError> {
+    for (field, value, max) in [("name", name, MAX_NAME), ("uri", uri, MAX_URI)] {
+        if value.len() > max {
+            return Err(MetadataError::TooLong {
+                field,
+                len: value.len(),
+                max,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use alloy_primitives::{keccak256, Address, B256};
+use alloy_sol_types::SolValue;
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::CommitmentVersion;
+use crate::hashing::HashScheme;
+use crate::smt::{self, SparseMerkleTree, SparseProof};
+use crate::Account;
+
+/// The canvas transactions before `TX_VERSION_CANVAS` edit. Its accounts are stored under their
+/// own address, and so are every sender's nonce and balance, whichever canvas it edits.
+pub const DEFAULT_CANVAS: u64 = 0;
+
+/// Where the account `address` of `canvas` is stored: `address` itself in `DEFAULT_CANVAS`,
+/// else a key derived from both, so the same address holds independent data in every canvas.
+pub fn canvas_address(canvas: u64, address: &Address) -> Address {
+    if canvas == DEFAULT_CANVAS {
+        return *address;
+    }
+    let hash = keccak256(("canvas.namespace", canvas, *address).abi_encode_packed());
+    Address::from_slice(&hash[12..])
+}
+
+/// The canvas whose tree holds `account` under `version`. Before `CommitmentVersion::V8` every
+/// account is in the one tree of `DEFAULT_CANVAS`.
+pub fn canvas_of(version: CommitmentVersion, account: &Account) -> u64 {
+    match version.is_namespaced() {
+        true => account.canvas,
+        false => DEFAULT_CANVAS,
+    }
+}
+
+/// Where `canvas` sits in the namespace tree.
+pub fn canvas_key(canvas: u64) -> Address {
+    Address::left_padding_from(&canvas.to_be_bytes())
+}
+
+/// The leaf of `canvas` in the namespace tree, committing to the root of its accounts, hashed
+/// under `scheme`. A canvas without accounts has root zero and no leaf.
+pub fn canvas_leaf(scheme: HashScheme, canvas: u64, root: &[u8; 32]) -> [u8; 32] {
+    if *root == [0; 32] {
+        return [0; 32];
+    }
+    scheme.leaf(&(canvas, B256::from(*root)).abi_encode_packed())
+}
+
+/// The `CommitmentVersion::V8` tree over canvases: a sparse Merkle tree keyed by `canvas_key`
+/// whose leaves are the `canvas_leaf`s of `roots`, hashed under `scheme`. Its root is the state
+/// root.
+pub fn namespace_tree(
+    scheme: HashScheme,
+    roots: impl IntoIterator<Item = (u64, [u8; 32])>,
+) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::with_scheme(scheme);
+    for (canvas, root) in roots {
+        if root != [0; 32] {
+            tree.insert(&canvas_key(canvas), canvas_leaf(scheme, canvas, &root));
+        }
+    }
+    tree
+}
+
+/// The root of one canvas's sparse tree and its proof against the state root. An account's
+/// `SparseProof` proves it against `root`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanvasProof {
+    pub canvas: u64,
+    pub root: [u8; 32],
+    pub proof: SparseProof,
+}
+
+impl CanvasProof {
+    pub fn verify(&self, state_root: &[u8; 32]) -> bool {
+        self.verify_with(HashScheme::Keccak, state_root)
+    }
+
+    /// `verify` for a state hashed under `scheme`.
+    pub fn verify_with(&self, scheme: HashScheme, state_root: &[u8; 32]) -> bool {
+        smt::verify_with(
+            scheme,
+            state_root,
+            &canvas_key(self.canvas),
+            Some(&canvas_leaf(scheme, self.canvas, &self.root)),
+            &self.proof,
+        )
+    }
+}
@@ -0,0 +1,108 @@
+use alloy_primitives::Address;
+use hashbrown::HashMap;
+use std::fmt;
+
+use crate::AccountDB;
+
+/// A transaction's nonce is not exactly the sender's next nonce: either a replay (`got` below
+/// `expected`) or a gap (`got` above it).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceError {
+    pub address: Address,
+    pub expected: u64,
+    pub got: u64,
+}
+
+impl fmt::Display for NonceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid nonce for {:?}: expected {}, got {}",
+            self.address, self.expected, self.got
+        )
+    }
+}
+
+impl std::error::Error for NonceError {}
+
+/// In-memory index of the next nonce each sender is expected to use, so admitting a transaction
+/// only reads the db the first time a sender is seen.
+///
+/// `committed` mirrors the nonce stored in state after the last applied batch, `pending` counts
+/// the sender's transactions admitted but not yet applied.
+#[derive(Debug, Clone, Default)]
+pub struct NonceCache {
+    committed: HashMap<Address, u64>,
+    pending: HashMap<Address, u64>,
+}
+
+impl NonceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_nonce<D: AccountDB>(&mut self, db: &D, address: &Address) -> eyre::Result<u64> {
+        let committed = match self.committed.get(address) {
+            Some(nonce) => *nonce,
+            None => {
+                let nonce = db.get_account(address)?.nonce;
+                self.committed.insert(*address, nonce);
+                nonce
+            }
+        };
+
+        Ok(committed + self.pending.get(address).copied().unwrap_or(0))
+    }
+
+    /// Checks `nonce` against the expected next nonce with the same rule as `apply_transaction`.
+    pub fn check<D: AccountDB>(
+        &mut self,
+        db: &D,
+        address: &Address,
+        nonce: u64,
+    ) -> eyre::Result<()> {
+        let expected = self.next_nonce(db, address)?;
+
+        if nonce != expected {
+            return Err(NonceError {
+                address: *address,
+                expected,
+                got: nonce,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub fn admit(&mut self, address: &Address) {
+        *self.pending.entry(*address).or_default() += 1;
+    }
+
+    /// Records that an admitted transaction was applied and the sender's nonce in state is now
+    /// `nonce`.
+    pub fn applied(&mut self, address: &Address, nonce: u64) {
+        self.release(address);
+        self.committed.insert(*address, nonce);
+    }
+
+    /// Records that an admitted transaction was dropped without being applied.
+    pub fn dropped(&mut self, address: &Address) {
+        self.release(address);
+    }
+
+    /// Forgets everything cached, e.g. after the state was replaced.
+    pub fn clear(&mut self) {
+        self.committed.clear();
+        self.pending.clear();
+    }
+
+    fn release(&mut self, address: &Address) {
+        if let Some(pending) = self.pending.get_mut(address) {
+            *pending -= 1;
+            if *pending == 0 {
+                self.pending.remove(address);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use alloy_primitives::Address;
+use hashbrown::HashMap;
+use std::fmt;
+
+use crate::mempool::PendingTransaction;
+
+/// Which queued transaction `Mempool::drain_batch` offers the batch next. Each sender's
+/// transactions are offered in nonce order whatever the policy, so it only chooses between
+/// senders.
+pub trait OrderingPolicy: fmt::Debug + Send {
+    /// The index in `ready` of the transaction to offer next. `ready` holds the next transaction
+    /// of every sender that has one queued, in admission order, and is never empty. `taken`
+    /// counts the transactions of each sender already in the batch.
+    fn pick(&self, ready: &[&PendingTransaction], taken: &HashMap<Address, usize>) -> usize;
+}
+
+/// The transaction admitted first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl OrderingPolicy for Fifo {
+    fn pick(&self, _ready: &[&PendingTransaction], _taken: &HashMap<Address, usize>) -> usize {
+        0
+    }
+}
+
+/// The transaction with the highest `PendingTransaction::priority`, the first admitted of equal
+/// ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeePriority;
+
+impl OrderingPolicy for FeePriority {
+    fn pick(&self, ready: &[&PendingTransaction], _taken: &HashMap<Address, usize>) -> usize {
+        ready
+            .iter()
+            .enumerate()
+            .max_by_key(|(index, pending)| (pending.priority, std::cmp::Reverse(*index)))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+/// The transaction of the sender with the fewest in the batch so far, the first admitted of
+/// equal ones, so one busy sender cannot fill a batch while others wait.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl OrderingPolicy for RoundRobin {
+    fn pick(&self, ready: &[&PendingTransaction], taken: &HashMap<Address, usize>) -> usize {
+        // `min_by_key` keeps the first of equal keys.
+        ready
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, pending)| taken.get(&pending.sender).copied().unwrap_or(0))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+/// The policy named `name`: `fifo`, `fee` or `round-robin`.
+pub fn policy(name: &str) -> Option<Box<dyn OrderingPolicy>> {
+    match name {
+        "fifo" => Some(Box::new(Fifo)),
+        "fee" => Some(Box::new(FeePriority)),
+        "round-robin" => Some(Box::new(RoundRobin)),
+        _ => None,
+    }
+}
@@ -0,0 +1,93 @@
+use alloy_primitives::Address;
+use hashbrown::HashMap;
+use std::cell::RefCell;
+
+use crate::error::CanvasError;
+use crate::{Account, AccountDB, WriteBatch};
+
+/// Buffers writes in memory over a base db that is only ever read, for simulating
+/// transactions against the latest state. `commit` writes the buffer through, `discard` drops
+/// it. A removed account is buffered as `None`.
+pub struct OverlayDB<D> {
+    base: D,
+    writes: RefCell<HashMap<Address, Option<Account>>>,
+}
+
+impl<D: AccountDB> OverlayDB<D> {
+    pub fn new(base: D) -> Self {
+        Self {
+            base,
+            writes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn base(&self) -> &D {
+        &self.base
+    }
+
+    /// Addresses written since the overlay was created.
+    pub fn written(&self) -> Vec<Address> {
+        self.writes.borrow().keys().copied().collect()
+    }
+
+    /// Writes the buffered accounts to the base. A base backing a `CanvasProcessor` should be
+    /// committed with `into_writes` and `CanvasProcessor::apply_writes` instead, so the
+    /// processor re-hashes them.
+    pub fn commit(self) -> Result<D, CanvasError> {
+        self.base
+            .apply_writes(self.writes.into_inner().into_iter().collect())?;
+        Ok(self.base)
+    }
+
+    pub fn discard(self) -> D {
+        self.base
+    }
+
+    pub fn into_writes(self) -> HashMap<Address, Option<Account>> {
+        self.writes.into_inner()
+    }
+}
+
+impl<D: AccountDB> AccountDB for OverlayDB<D> {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
+        Ok(self.find_account(address)?.unwrap_or_default())
+    }
+
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        match self.writes.borrow().get(address) {
+            Some(account) => Ok(account.clone()),
+            None => self.base.find_account(address),
+        }
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
+        self.writes
+            .borrow_mut()
+            .insert(*address, Some(account.clone()));
+        Ok(())
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        self.writes.borrow_mut().insert(*address, None);
+        Ok(())
+    }
+
+    // The accounts not written yet are read from the base together.
+    fn find_accounts(&self, addresses: &[Address]) -> Result<Vec<Option<Account>>, CanvasError> {
+        let writes = self.writes.borrow();
+        let unwritten: Vec<Address> = addresses
+            .iter()
+            .filter(|address| !writes.contains_key(*address))
+            .copied()
+            .collect();
+        let mut base = self.base.find_accounts(&unwritten)?.into_iter();
+
+        Ok(addresses
+            .iter()
+            .map(|address| match writes.get(address) {
+                Some(account) => account.clone(),
+                None => base.next().flatten(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,216 @@
+use alloy_primitives::{keccak256, Address};
+use rs_merkle::{Hasher, MerkleProof};
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::{leaf_hash, CommitmentVersion};
+use crate::{Account, Keccak256Algorithm};
+
+/// Checks a `generate_proof` proof of `account` at `address` against a state root.
+pub fn verify_proof(
+    root: &[u8; 32],
+    version: CommitmentVersion,
+    address: &Address,
+    account: &Account,
+    proof: &[[u8; 32]],
+) -> bool {
+    let leaf = leaf_hash(version, address, account);
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        Keccak256Algorithm::concat_and_hash(&node, Some(sibling))
+    });
+
+    computed == *root
+}
+
+/// A `generate_multiproof` proof: the rs_merkle helper hashes for the leaves at `indices` of a
+/// tree of `leaf_count` leaves. `indices` are in the order the addresses were requested.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiProof {
+    pub indices: Vec<usize>,
+    pub leaf_count: usize,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Checks a `MultiProof` of `accounts`, given in the order their addresses were requested.
+pub fn verify_multiproof(
+    root: &[u8; 32],
+    version: CommitmentVersion,
+    accounts: &[(Address, Account)],
+    proof: &MultiProof,
+) -> bool {
+    if accounts.is_empty() || accounts.len() != proof.indices.len() {
+        return false;
+    }
+
+    let leaves: Vec<[u8; 32]> = accounts
+        .iter()
+        .map(|(address, account)| leaf_hash(version, address, account))
+        .collect();
+
+    MerkleProof::<Keccak256Algorithm>::new(proof.proof.clone()).verify(
+        *root,
+        &proof.indices,
+        &leaves,
+        proof.leaf_count,
+    )
+}
+
+/// Path from the leaf at `index` to the root of an `AddressIndex` with `count` leaves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexProof {
+    pub index: u64,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Proves an address is not in the state: the addresses right below and above it are adjacent
+/// leaves of the `AddressIndex`. `lower` is `None` when the address sorts before every account,
+/// `upper` when it sorts after every account, and both when the state is empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionProof {
+    pub count: u64,
+    pub lower: Option<(Address, IndexProof)>,
+    pub upper: Option<(Address, IndexProof)>,
+}
+
+/// Merkle tree over the addresses in the state in ascending order, committed as `accountsRoot`.
+///
+/// State tree leaves are sorted by hash and its pairs are hashed unordered, so a proof there
+/// shows membership but not position. Here pairs are hashed in order and the leaf count is part
+/// of the root, which lets two proofs show that two addresses are neighbours.
+pub struct AddressIndex {
+    addresses: Vec<Address>,
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl AddressIndex {
+    pub fn new(addresses: impl IntoIterator<Item = Address>) -> Self {
+        let mut addresses: Vec<Address> = addresses.into_iter().collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let mut layers = vec![addresses.iter().map(index_leaf).collect::<Vec<_>>()];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = &layers[layers.len() - 1];
+            let parents = layer
+                .chunks(2)
+                .map(|pair| index_node(&pair[0], pair.get(1)))
+                .collect();
+            layers.push(parents);
+        }
+
+        Self { addresses, layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let top = self.layers.last().and_then(|l| l.first()).copied();
+        index_root(self.addresses.len() as u64, top.unwrap_or([0; 32]))
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.addresses.binary_search(address).is_ok()
+    }
+
+    pub fn proof(&self, index: usize) -> IndexProof {
+        let mut siblings = Vec::new();
+        let mut idx = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(idx ^ 1) {
+                siblings.push(*sibling);
+            }
+            idx /= 2;
+        }
+
+        IndexProof {
+            index: index as u64,
+            siblings,
+        }
+    }
+
+    /// `None` if `address` is in the index.
+    pub fn exclusion_proof(&self, address: &Address) -> Option<ExclusionProof> {
+        let upper = self.addresses.binary_search(address).err()?;
+        let lower = upper.checked_sub(1);
+
+        Some(ExclusionProof {
+            count: self.addresses.len() as u64,
+            lower: lower.map(|i| (self.addresses[i], self.proof(i))),
+            upper: self
+                .addresses
+                .get(upper)
+                .map(|address| (*address, self.proof(upper))),
+        })
+    }
+}
+
+/// Checks that `address` is in the `AddressIndex` committed as `accounts_root`.
+pub fn verify_index_proof(
+    accounts_root: &[u8; 32],
+    count: u64,
+    address: &Address,
+    proof: &IndexProof,
+) -> bool {
+    if proof.index >= count {
+        return false;
+    }
+
+    let (mut idx, mut len) = (proof.index, count);
+    let mut node = index_leaf(address);
+    let mut siblings = proof.siblings.iter();
+
+    while len > 1 {
+        if idx ^ 1 < len {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            node = if idx % 2 == 0 {
+                index_node(&node, Some(sibling))
+            } else {
+                index_node(sibling, Some(&node))
+            };
+        }
+        idx /= 2;
+        len = len.div_ceil(2);
+    }
+
+    siblings.next().is_none() && index_root(count, node) == *accounts_root
+}
+
+/// Checks that `address` is not in the `AddressIndex` committed as `accounts_root`.
+pub fn verify_exclusion(
+    accounts_root: &[u8; 32],
+    address: &Address,
+    proof: &ExclusionProof,
+) -> bool {
+    let count = proof.count;
+    let bound = |bound: &(Address, IndexProof)| {
+        verify_index_proof(accounts_root, count, &bound.0, &bound.1)
+    };
+
+    match (&proof.lower, &proof.upper) {
+        (None, None) => count == 0 && index_root(0, [0; 32]) == *accounts_root,
+        (Some(lower), None) => lower.0 < *address && lower.1.index + 1 == count && bound(lower),
+        (None, Some(upper)) => *address < upper.0 && upper.1.index == 0 && bound(upper),
+        (Some(lower), Some(upper)) => {
+            lower.0 < *address
+                && *address < upper.0
+                && lower.1.index + 1 == upper.1.index
+                && bound(lower)
+                && bound(upper)
+        }
+    }
+}
+
+// Leaves hash 20 bytes and nodes 64, so neither can pass for the other.
+fn index_leaf(address: &Address) -> [u8; 32] {
+    keccak256(address).into()
+}
+
+fn index_node(left: &[u8; 32], right: Option<&[u8; 32]>) -> [u8; 32] {
+    match right {
+        Some(right) => keccak256([left.as_slice(), right.as_slice()].concat()).into(),
+        None => *left,
+    }
+}
+
+fn index_root(count: u64, top: [u8; 32]) -> [u8; 32] {
+    keccak256([&count.to_be_bytes()[..], top.as_slice()].concat()).into()
+}
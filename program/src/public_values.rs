@@ -0,0 +1,148 @@
+use alloy_primitives::{Address, B256};
+use alloy_sol_types::SolValue;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::PublicValuesStruct;
+
+/// Words in the public values of each layout, oldest first: version `n` has
+/// `LAYOUT_WORDS[n - 1]`. Values only ever get appended, so each layout extends the one before.
+pub const LAYOUT_WORDS: [usize; 12] = [3, 4, 5, 6, 7, 10, 12, 14, 15, 17, 18, 20];
+
+/// The layout this program commits, as `publicValuesVersion`. Earlier layouts do not carry their
+/// version and are told apart by their length.
+pub const PUBLIC_VALUES_VERSION: u64 = LAYOUT_WORDS.len() as u64;
+
+/// The layout of public values `length` bytes long, if any.
+pub fn layout_version(length: usize) -> Option<u64> {
+    if length % 32 != 0 {
+        return None;
+    }
+    LAYOUT_WORDS
+        .iter()
+        .position(|words| *words == length / 32)
+        .map(|index| index as u64 + 1)
+}
+
+/// What a batch submission claimed. Earlier versions of the program committed fewer values, so
+/// the later fields are `None` for older submissions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimedValues {
+    /// The layout the values were committed in; see `LAYOUT_WORDS`.
+    pub version: u64,
+    pub initial_state_root: B256,
+    pub final_state_root: B256,
+    pub transaction_commit: B256,
+    pub receipts_root: Option<B256>,
+    pub accounts_root: Option<B256>,
+    pub previous_batch: Option<B256>,
+    pub limits_root: Option<B256>,
+    pub batch_index: Option<u64>,
+    pub l1_block_number: Option<u64>,
+    pub l1_block_hash: Option<B256>,
+    pub deposits_commit: Option<B256>,
+    pub deposit_count: Option<u64>,
+    pub forced_commit: Option<B256>,
+    pub forced_count: Option<u64>,
+    pub batch_timestamp: Option<u64>,
+    pub previous_batch_hash: Option<B256>,
+    pub batch_hash: Option<B256>,
+    pub previous_batch_timestamp: Option<u64>,
+    pub sequencer: Option<Address>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicValuesError {
+    pub length: usize,
+}
+
+impl fmt::Display for PublicValuesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes of public values do not match any known layout",
+            self.length
+        )
+    }
+}
+
+impl std::error::Error for PublicValuesError {}
+
+impl From<PublicValuesStruct> for ClaimedValues {
+    fn from(values: PublicValuesStruct) -> Self {
+        Self {
+            version: values.publicValuesVersion,
+            initial_state_root: values.initialStateRoot,
+            final_state_root: values.finalStateRoot,
+            transaction_commit: values.transaction_commit,
+            receipts_root: Some(values.receiptsRoot),
+            accounts_root: Some(values.accountsRoot),
+            previous_batch: Some(values.previousBatch),
+            limits_root: Some(values.limitsRoot),
+            batch_index: Some(values.batchIndex),
+            l1_block_number: Some(values.l1BlockNumber),
+            l1_block_hash: Some(values.l1BlockHash),
+            deposits_commit: Some(values.depositsCommit),
+            deposit_count: Some(values.depositCount),
+            forced_commit: Some(values.forcedCommit),
+            forced_count: Some(values.forcedCount),
+            batch_timestamp: Some(values.batchTimestamp),
+            previous_batch_hash: Some(values.previousBatchHash),
+            batch_hash: Some(values.batchHash),
+            previous_batch_timestamp: Some(values.previousBatchTimestamp),
+            sequencer: Some(values.sequencer),
+        }
+    }
+}
+
+/// Decodes `_publicValuesBytes` as committed by this program or any earlier version of it.
+pub fn decode_public_values(bytes: &[u8]) -> Result<ClaimedValues, PublicValuesError> {
+    // Every layout is a static struct of words, appended to over time. Values were added
+    // alone up to the limits root and in pairs since: the batch index with the L1 block number
+    // (the block hash follows alone), then each commit of an L1 queue with its count. The batch
+    // timestamp came alone again, the batch hash with the one before it, then the version, and
+    // the previous batch's timestamp with the sequencer after it.
+    let error = || PublicValuesError {
+        length: bytes.len(),
+    };
+    let version = layout_version(bytes.len()).ok_or_else(error)?;
+    if version == PUBLIC_VALUES_VERSION {
+        let values = PublicValuesStruct::abi_decode(bytes, true).map_err(|_| error())?;
+        if values.publicValuesVersion != PUBLIC_VALUES_VERSION {
+            return Err(error());
+        }
+        return Ok(values.into());
+    }
+
+    let words: Vec<B256> = bytes.chunks(32).map(B256::from_slice).collect();
+    // A uint64 word decodes from its low 8 bytes.
+    let number = |i: usize| {
+        words
+            .get(i)
+            .map(|word| u64::from_be_bytes(word[24..].try_into().unwrap()))
+    };
+
+    Ok(ClaimedValues {
+        version,
+        initial_state_root: words[0],
+        final_state_root: words[1],
+        transaction_commit: words[2],
+        receipts_root: words.get(3).copied(),
+        accounts_root: words.get(4).copied(),
+        previous_batch: words.get(5).copied(),
+        limits_root: words.get(6).copied(),
+        batch_index: number(7),
+        l1_block_number: number(8),
+        l1_block_hash: words.get(9).copied(),
+        deposits_commit: words.get(10).copied(),
+        deposit_count: number(11),
+        forced_commit: words.get(12).copied(),
+        forced_count: number(13),
+        batch_timestamp: number(14),
+        previous_batch_hash: words.get(15).copied(),
+        batch_hash: words.get(16).copied(),
+        previous_batch_timestamp: number(18),
+        sequencer: words.get(19).map(|word| Address::from_word(*word)),
+    })
+}
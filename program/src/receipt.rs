@@ -0,0 +1,103 @@
+use alloy_primitives::{keccak256, Address};
+use alloy_sol_types::{sol, SolValue};
+use rs_merkle::MerkleTree;
+use serde::{Deserialize, Serialize};
+
+use crate::signing::SigningDomain;
+use crate::{recover_address_any_s, Keccak256Algorithm, PublicValuesStruct, SignedTransaction};
+
+sol! {
+    struct ReceiptCommit {
+        address from;
+        address to;
+        uint64 ops;
+        uint64 inserted;
+        uint64 deleted;
+        uint8 status;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ReceiptStatus {
+    Success = 0,
+    Failed = 1,
+}
+
+/// What a transaction changed. `to` is the target's `Transaction::target`, and `inserted` and
+/// `deleted` count chars of its data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub from: Address,
+    pub to: Address,
+    pub ops: u64,
+    pub inserted: u64,
+    pub deleted: u64,
+    pub status: ReceiptStatus,
+}
+
+impl Receipt {
+    /// Receipt of a skipped transaction. `from` is zero if the signature does not recover, and
+    /// the signer of a rejected high-s signature otherwise.
+    pub fn failed(tx: &SignedTransaction, domain: &SigningDomain) -> Self {
+        Self {
+            from: recover_address_any_s(tx, domain).unwrap_or(Address::ZERO),
+            to: tx.tx.target(),
+            ops: 0,
+            inserted: 0,
+            deleted: 0,
+            status: ReceiptStatus::Failed,
+        }
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(
+            ReceiptCommit {
+                from: self.from,
+                to: self.to,
+                ops: self.ops,
+                inserted: self.inserted,
+                deleted: self.deleted,
+                status: self.status as u8,
+            }
+            .abi_encode(),
+        )
+        .into()
+    }
+}
+
+/// Merkle root over receipt hashes in transaction order, zero for an empty batch.
+pub fn receipts_root(receipts: &[Receipt]) -> [u8; 32] {
+    let leaves: Vec<[u8; 32]> = receipts.iter().map(Receipt::hash).collect();
+
+    MerkleTree::<Keccak256Algorithm>::from_leaves(&leaves)
+        .root()
+        .unwrap_or([0; 32])
+}
+
+/// The outcome of `process_batch`: the values the program commits and a receipt per transaction.
+pub struct BatchReport {
+    pub public_values: PublicValuesStruct,
+    pub receipts: Vec<Receipt>,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.receipts
+            .iter()
+            .filter(|r| r.status == ReceiptStatus::Success)
+            .count()
+    }
+
+    pub fn ops(&self) -> u64 {
+        self.receipts.iter().map(|r| r.ops).sum()
+    }
+
+    pub fn inserted(&self) -> u64 {
+        self.receipts.iter().map(|r| r.inserted).sum()
+    }
+
+    pub fn deleted(&self) -> u64 {
+        self.receipts.iter().map(|r| r.deleted).sum()
+    }
+}
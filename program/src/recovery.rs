@@ -0,0 +1,100 @@
+use alloy_primitives::{Address, B256};
+use hashbrown::HashMap;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::cell::RefCell;
+
+use crate::error::CanvasError;
+use crate::signing::SigningDomain;
+use crate::{recover_address_any_s, SignedTransaction};
+
+/// Recovers the sender of each of `transactions`, in parallel under the `parallel` feature and
+/// one after the other in the zkVM. Like `recover_address_any_s`, accepts either `s`.
+pub fn recover_addresses_batch(
+    transactions: &[SignedTransaction],
+    domain: &SigningDomain,
+) -> Vec<Result<Address, CanvasError>> {
+    recover_each(&transactions.iter().collect::<Vec<_>>(), domain)
+}
+
+pub(crate) fn recover_each(
+    transactions: &[&SignedTransaction],
+    domain: &SigningDomain,
+) -> Vec<Result<Address, CanvasError>> {
+    let recover = |tx: &&SignedTransaction| recover_address_any_s(tx, domain);
+
+    #[cfg(feature = "parallel")]
+    let senders = transactions.par_iter().map(recover).collect();
+    #[cfg(not(feature = "parallel"))]
+    let senders = transactions.iter().map(recover).collect();
+
+    senders
+}
+
+/// Senders recovered so far, by the keccak256 of a signed transaction's RLP and the domain it
+/// was recovered under. Only successful recoveries are kept, of either `s`.
+#[derive(Debug, Clone, Default)]
+pub struct SenderCache {
+    senders: RefCell<HashMap<(B256, SigningDomain), Address>>,
+}
+
+impl SenderCache {
+    pub fn recover(
+        &self,
+        tx: &SignedTransaction,
+        domain: &SigningDomain,
+    ) -> Result<Address, CanvasError> {
+        let key = (tx.hash(), *domain);
+        if let Some(sender) = self.senders.borrow().get(&key) {
+            return Ok(*sender);
+        }
+
+        let sender = recover_address_any_s(tx, domain)?;
+        self.senders.borrow_mut().insert(key, sender);
+        Ok(sender)
+    }
+
+    /// Caches `sender` as the sender of `tx`, e.g. one recovered by another cache.
+    pub fn insert(&self, tx: &SignedTransaction, domain: &SigningDomain, sender: Address) {
+        self.senders
+            .borrow_mut()
+            .insert((tx.hash(), *domain), sender);
+    }
+
+    /// Recovers the senders of `transactions` not cached yet with `recover_addresses_batch`.
+    pub fn prefetch(&self, transactions: &[SignedTransaction], domain: &SigningDomain) {
+        let missing: Vec<(B256, &SignedTransaction)> = {
+            let senders = self.senders.borrow();
+            transactions
+                .iter()
+                .map(|tx| (tx.hash(), tx))
+                .filter(|(hash, _)| !senders.contains_key(&(*hash, *domain)))
+                .collect()
+        };
+        if missing.is_empty() {
+            return;
+        }
+
+        let transactions: Vec<&SignedTransaction> = missing.iter().map(|(_, tx)| *tx).collect();
+        let recovered = recover_each(&transactions, domain);
+
+        let mut senders = self.senders.borrow_mut();
+        for ((hash, _), sender) in missing.into_iter().zip(recovered) {
+            if let Ok(sender) = sender {
+                senders.insert((hash, *domain), sender);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.senders.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.borrow().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.senders.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,184 @@
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::commitment::CommitmentVersion;
+use crate::{Account, MAX_VALUE};
+
+// First byte of the `policy` word of an account with a schema. A zero policy has none.
+const SCHEMA_TAG: u8 = 1;
+
+/// Characters an account's data may contain, line breaks aside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Charset {
+    #[default]
+    Any = 0,
+    /// Hex digits up to `MAX_VALUE`, one palette entry per character.
+    Palette = 1,
+    /// Printable ASCII.
+    Ascii = 2,
+}
+
+/// A validation rule an account's owner registers for its data, checked after every edit of it.
+/// Zero fields are unconstrained. Lines are separated by `'\n'`.
+///
+/// The schema is packed into the account's `policy` word, so it is part of the leaf from
+/// `CommitmentVersion::V1` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Schema {
+    pub max_line_length: u32,
+    /// With `height`, a fixed grid: exactly `height` lines of exactly `width` chars.
+    pub width: u16,
+    pub height: u16,
+    pub charset: Charset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// Line `line` is `length` chars long, more than `max`.
+    LineTooLong {
+        line: usize,
+        length: usize,
+        max: u32,
+    },
+    /// The data is not a `width` by `height` grid.
+    WrongDimensions {
+        width: u16,
+        height: u16,
+    },
+    InvalidChar(char),
+    /// `sender` tried to set the schema of an account it does not own.
+    NotOwner {
+        account: Address,
+        sender: Address,
+    },
+    /// The account's policy is not a schema this version understands.
+    UnknownPolicy(B256),
+    /// A schema set under a commitment version that does not commit the policy it is kept in.
+    NotCommitted,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::LineTooLong { line, length, max } => write!(
+                f,
+                "Line {} is {} chars long, the schema allows {}",
+                line, length, max
+            ),
+            SchemaError::WrongDimensions { width, height } => {
+                write!(f, "Data is not a {}x{} grid", width, height)
+            }
+            SchemaError::InvalidChar(c) => {
+                write!(f, "Character {:?} is not in the schema's charset", c)
+            }
+            SchemaError::NotOwner { account, sender } => write!(
+                f,
+                "{:?} cannot set the schema of {:?}, it is not the owner",
+                sender, account
+            ),
+            SchemaError::UnknownPolicy(policy) => write!(f, "Unknown account policy {}", policy),
+            SchemaError::NotCommitted => write!(f, "Schemas need commitment version 1"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl Charset {
+    pub fn allows(&self, c: char) -> bool {
+        match self {
+            Charset::Any => true,
+            Charset::Palette => c.to_digit(16).is_some_and(|d| d <= MAX_VALUE as u32),
+            Charset::Ascii => c.is_ascii_graphic() || c == ' ',
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Charset::Any),
+            1 => Some(Charset::Palette),
+            2 => Some(Charset::Ascii),
+            _ => None,
+        }
+    }
+}
+
+impl Schema {
+    /// The schema `account` holds its data to. None where `version` does not commit the
+    /// `policy`, which a prover could otherwise supply to reject any edit.
+    pub fn of(version: CommitmentVersion, account: &Account) -> Result<Option<Self>, SchemaError> {
+        match version.commits_policy() {
+            true => Self::from_policy(&account.policy),
+            false => Ok(None),
+        }
+    }
+
+    /// The schema stored in `policy`, if any.
+    pub fn from_policy(policy: &B256) -> Result<Option<Self>, SchemaError> {
+        if policy.is_zero() {
+            return Ok(None);
+        }
+
+        let p = policy.as_slice();
+        let charset = Charset::from_u8(p[9]);
+        match charset {
+            Some(charset) if p[0] == SCHEMA_TAG && p[10..].iter().all(|b| *b == 0) => {
+                Ok(Some(Schema {
+                    max_line_length: u32::from_be_bytes([p[1], p[2], p[3], p[4]]),
+                    width: u16::from_be_bytes([p[5], p[6]]),
+                    height: u16::from_be_bytes([p[7], p[8]]),
+                    charset,
+                }))
+            }
+            _ => Err(SchemaError::UnknownPolicy(*policy)),
+        }
+    }
+
+    pub fn to_policy(&self) -> B256 {
+        let mut p = [0u8; 32];
+        p[0] = SCHEMA_TAG;
+        p[1..5].copy_from_slice(&self.max_line_length.to_be_bytes());
+        p[5..7].copy_from_slice(&self.width.to_be_bytes());
+        p[7..9].copy_from_slice(&self.height.to_be_bytes());
+        p[9] = self.charset as u8;
+        p.into()
+    }
+
+    pub fn check(&self, data: &[char]) -> Result<(), SchemaError> {
+        let grid = self.width > 0 && self.height > 0;
+        let mut lines = 0;
+
+        for (line, chars) in data.split(|c| *c == '\n').enumerate() {
+            lines += 1;
+
+            if self.max_line_length > 0 && chars.len() > self.max_line_length as usize {
+                return Err(SchemaError::LineTooLong {
+                    line,
+                    length: chars.len(),
+                    max: self.max_line_length,
+                });
+            }
+            if grid && chars.len() != self.width as usize {
+                return Err(self.wrong_dimensions());
+            }
+            if let Some(c) = chars.iter().find(|c| !self.charset.allows(**c)) {
+                return Err(SchemaError::InvalidChar(*c));
+            }
+        }
+
+        if grid && lines != self.height as usize {
+            return Err(self.wrong_dimensions());
+        }
+
+        Ok(())
+    }
+
+    fn wrong_dimensions(&self) -> SchemaError {
+        SchemaError::WrongDimensions {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use alloy_primitives::{Address, Signature, SignatureError, B256, U256};
+use alloy_sol_types::{sol, SolStruct};
+use serde::{Deserialize, Serialize};
+
+use crate::batch::BatchContext;
+use crate::signing::{is_low_s, SigningDomain};
+
+sol! {
+    /// EIP-712 statement of a sequencer that it built batch `index` on top of the batch with
+    /// `previousBatchHash`, at `timestamp`, out of exactly the transaction data committed to by
+    /// `transactionCommit`. Two of them by one key for the same index that differ otherwise
+    /// prove the key equivocated.
+    struct SequencedBatch {
+        uint64 index;
+        bytes32 previousBatchHash;
+        uint64 timestamp;
+        bytes32 transactionCommit;
+    }
+}
+
+/// The sequencer's signature over its `SequencedBatch`, posted next to the batch so that anyone
+/// can attribute the batch to the key that built it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencerSignature {
+    pub r: U256,
+    pub s: U256,
+    pub odd_y_parity: bool,
+}
+
+impl From<Signature> for SequencerSignature {
+    fn from(signature: Signature) -> Self {
+        Self {
+            r: signature.r(),
+            s: signature.s(),
+            odd_y_parity: signature.v().y_parity(),
+        }
+    }
+}
+
+impl SequencerSignature {
+    /// The 65 bytes `r ‖ s ‖ v` a contract passes to `ecrecover`, `v` being 27 or 28.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0; 65];
+        bytes[..32].copy_from_slice(&self.r.to_be_bytes::<32>());
+        bytes[32..64].copy_from_slice(&self.s.to_be_bytes::<32>());
+        bytes[64] = 27 + self.odd_y_parity as u8;
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignatureError> {
+        Ok(Signature::try_from(bytes)?.into())
+    }
+}
+
+pub fn sequenced_batch(batch: &BatchContext, transaction_commit: B256) -> SequencedBatch {
+    SequencedBatch {
+        index: batch.index,
+        previousBatchHash: batch.previous_batch_hash,
+        timestamp: batch.timestamp,
+        transactionCommit: transaction_commit,
+    }
+}
+
+/// The prehash a sequencer's key signs for `batch`, with `transaction_commit` the
+/// `batch::commit` of its posted transaction data.
+pub fn sequenced_batch_hash(
+    batch: &BatchContext,
+    transaction_commit: B256,
+    domain: &SigningDomain,
+) -> B256 {
+    sequenced_batch(batch, transaction_commit).eip712_signing_hash(&domain.eip712_domain())
+}
+
+/// The key that signed `statement`, if `signature` has a low `s`.
+pub fn recover_sequencer(
+    statement: &SequencedBatch,
+    signature: &SequencerSignature,
+    domain: &SigningDomain,
+) -> eyre::Result<Address> {
+    if !is_low_s(&signature.s) {
+        return Err(eyre::eyre!("Sequencer signature has a high s"));
+    }
+    let signature =
+        Signature::from_rs_and_parity(signature.r, signature.s, signature.odd_y_parity)?;
+
+    Ok(signature
+        .recover_address_from_prehash(&statement.eip712_signing_hash(&domain.eip712_domain()))?)
+}
+
+/// Checks that `batch.sequencer_signature` is `batch.sequencer`'s over the batch and
+/// `transaction_commit`.
+pub fn verify_sequencer_signature(
+    batch: &BatchContext,
+    transaction_commit: B256,
+    domain: &SigningDomain,
+) -> eyre::Result<()> {
+    let signature = batch
+        .sequencer_signature
+        .ok_or_else(|| eyre::eyre!("Batch {} is not signed by its sequencer", batch.index))?;
+    let signer = recover_sequencer(
+        &sequenced_batch(batch, transaction_commit),
+        &signature,
+        domain,
+    )?;
+    if batch.sequencer == Address::ZERO || signer != batch.sequencer {
+        return Err(eyre::eyre!(
+            "Batch {} is signed by {}, not its sequencer {}",
+            batch.index,
+            signer,
+            batch.sequencer
+        ));
+    }
+    Ok(())
+}
+
+/// The key that signed both `a` and `b`, two different batches at the same index, if one did.
+/// Either statement with its signature is enough to hold the key to the batch it signed.
+pub fn equivocator(
+    a: (&SequencedBatch, &SequencerSignature),
+    b: (&SequencedBatch, &SequencerSignature),
+    domain: &SigningDomain,
+) -> Option<Address> {
+    let (first, second) = (a.0, b.0);
+    if first.index != second.index
+        || (first.previousBatchHash == second.previousBatchHash
+            && first.timestamp == second.timestamp
+            && first.transactionCommit == second.transactionCommit)
+    {
+        return None;
+    }
+
+    let signer = recover_sequencer(a.0, a.1, domain).ok()?;
+    (recover_sequencer(b.0, b.1, domain).ok()? == signer).then_some(signer)
+}
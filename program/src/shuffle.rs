@@ -0,0 +1,59 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::CanvasConfig;
+use crate::recovery::SenderCache;
+use crate::SignedTransaction;
+
+/// Order in which a batch's transactions are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Ordering {
+    /// As posted.
+    #[default]
+    Posted,
+    /// Shuffled with the previous batch's transaction commit as the seed, so the sequencer
+    /// cannot pick which of two edits of the same region lands last. Each sender's transactions
+    /// keep their posted order, so consecutive nonces still apply.
+    Shuffled,
+}
+
+/// Indices into `transactions` in the order they are executed. Senders are recovered through
+/// `senders`.
+pub fn execution_order(
+    config: &CanvasConfig,
+    transactions: &[SignedTransaction],
+    seed: &B256,
+    senders: &SenderCache,
+) -> Vec<usize> {
+    let count = transactions.len();
+    let mut order: Vec<usize> = (0..count).collect();
+    if config.ordering == Ordering::Posted {
+        return order;
+    }
+
+    // Fisher-Yates, drawing the i-th swap from keccak256(seed || i).
+    for i in (1..count).rev() {
+        let word = keccak256([seed.as_slice(), &(i as u64).to_be_bytes()].concat());
+        let j = U256::from_be_bytes(word.0) % U256::from(i + 1);
+        order.swap(i, j.to::<usize>());
+    }
+
+    // Hand the slots each sender drew back to its transactions in posted order. Transactions
+    // whose sender does not recover fail wherever they land.
+    let mut slots: HashMap<Address, Vec<usize>> = HashMap::new();
+    for (slot, &index) in order.iter().enumerate() {
+        if let Ok(sender) = senders.recover(&transactions[index], &config.domain) {
+            slots.entry(sender).or_default().push(slot);
+        }
+    }
+    for slots in slots.values() {
+        let mut indices: Vec<usize> = slots.iter().map(|&slot| order[slot]).collect();
+        indices.sort_unstable();
+        for (&slot, index) in slots.iter().zip(indices) {
+            order[slot] = index;
+        }
+    }
+
+    order
+}
@@ -0,0 +1,86 @@
+use alloy_primitives::{eip191_hash_message, keccak256, uint, Address, B256, U256};
+use alloy_rlp::Encodable;
+use alloy_sol_types::{sol, Eip712Domain, SolStruct};
+use serde::{Deserialize, Serialize};
+
+use crate::{Transaction, TX_VERSION_DOMAIN};
+
+sol! {
+    /// EIP-712 view of a transaction. `payloadHash` is keccak256 of its RLP encoding.
+    struct CanvasTransaction {
+        address to;
+        uint8 version;
+        uint64 nonce;
+        bytes32 payloadHash;
+    }
+}
+
+/// Identifies the rollup deployment a transaction is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SigningDomain {
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl Default for SigningDomain {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            verifying_contract: Address::ZERO,
+        }
+    }
+}
+
+impl SigningDomain {
+    pub fn eip712_domain(&self) -> Eip712Domain {
+        Eip712Domain::new(
+            Some("Canvas".into()),
+            Some("1".into()),
+            Some(U256::from(self.chain_id)),
+            Some(self.verifying_contract),
+            None,
+        )
+    }
+}
+
+// Half the order of secp256k1. Every signature with `s` above it has a twin with `n - s` and the
+// other parity that recovers the same key.
+const SECP256K1_HALF_ORDER: U256 =
+    uint!(0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0_U256);
+
+/// Whether `s` is in the lower half of the curve order, the one form EIP-2 accepts of each
+/// signature.
+pub fn is_low_s(s: &U256) -> bool {
+    *s <= SECP256K1_HALF_ORDER
+}
+
+/// Whether `tx` is signed with the pre-domain scheme, a personal-sign of keccak256 of its RLP.
+pub fn is_legacy_signed(tx: &Transaction) -> bool {
+    tx.version < TX_VERSION_DOMAIN
+}
+
+/// keccak256 of the RLP encoding of `tx`, the message a legacy signature personal-signs.
+pub fn payload_hash(tx: &Transaction) -> B256 {
+    let mut encoded = Vec::<u8>::new();
+    tx.encode(&mut encoded);
+    keccak256(encoded)
+}
+
+/// The typed data a domain signature signs for `tx`.
+pub fn typed_transaction(tx: &Transaction) -> CanvasTransaction {
+    CanvasTransaction {
+        to: tx.to,
+        version: tx.version,
+        nonce: tx.nonce,
+        payloadHash: payload_hash(tx),
+    }
+}
+
+/// The prehash the sender's key signs for `tx`.
+pub fn signing_hash(tx: &Transaction, domain: &SigningDomain) -> B256 {
+    if is_legacy_signed(tx) {
+        return eip191_hash_message(payload_hash(tx));
+    }
+
+    typed_transaction(tx).eip712_signing_hash(&domain.eip712_domain())
+}
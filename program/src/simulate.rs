@@ -0,0 +1,253 @@
+use alloy_primitives::{Address, B256};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::iter;
+
+use crate::batch::encode_batch;
+use crate::codec::Codec;
+use crate::commitment::{empty_root, leaf_hash};
+use crate::cost::{estimate_l1_cost, CycleModel, PostingMode};
+use crate::error::CanvasError;
+use crate::namespace::{canvas_of, namespace_tree, DEFAULT_CANVAS};
+use crate::overlay::OverlayDB;
+use crate::receipt::Receipt;
+use crate::shuffle::execution_order;
+use crate::smt::{PartialTree, SparseMerkleTree};
+use crate::validator::ValueValidator;
+use crate::{
+    Account, AccountDB, CanvasProcessor, InMemoryDB, SignedTransaction, TransactionEffect,
+};
+
+/// How one transaction of a simulated batch went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionSimulation {
+    /// Position of the transaction in the batch as given, not in execution order.
+    pub index: usize,
+    pub tx_hash: B256,
+    pub receipt: Receipt,
+    /// Why the transaction failed, `None` if it succeeded.
+    pub error: Option<String>,
+    /// Bytes of account data that differ after the transaction, counted per account it wrote.
+    pub changed_bytes: u64,
+}
+
+/// What `CanvasProcessor::simulate_batch` found, without the state having changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulationResult {
+    /// In execution order.
+    pub transactions: Vec<TransactionSimulation>,
+    /// The state root after the batch.
+    pub state_root: B256,
+    pub changed_bytes: u64,
+    /// Accounts the batch wrote.
+    pub accounts: u64,
+    /// `CycleModel::default`'s estimate of the batch.
+    pub cycles: u64,
+    /// The batch's transaction data under the default codec, and the L1 gas of posting it as
+    /// calldata and as blobs, as `estimate_l1_cost` counts it.
+    pub transaction_data_bytes: u64,
+    pub calldata_gas: u64,
+    pub blob_gas: u64,
+}
+
+impl SimulationResult {
+    pub fn succeeded(&self) -> usize {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.error.is_none())
+            .count()
+    }
+}
+
+impl<D: Borrow<InMemoryDB>, V: ValueValidator> CanvasProcessor<D, V> {
+    /// Runs `transactions` as a batch on top of the current state, in the order the batch would
+    /// execute them and skipping those that fail, over an `OverlayDB` that is dropped afterwards.
+    /// Expiry and rate limits go by the current `batch` and `limits`; deposits and forced
+    /// transactions are not simulated.
+    pub fn simulate_batch(
+        &self,
+        transactions: &[SignedTransaction],
+    ) -> eyre::Result<SimulationResult> {
+        let mut pending = CanvasProcessor::with_config(OverlayDB::new(self.state()), self.config)
+            .with_validator(&self.validator)
+            .with_limits(self.limits.clone());
+        pending.batch = self.batch;
+        pending.senders.prefetch(transactions, &self.config.domain);
+        let order = execution_order(
+            &self.config,
+            transactions,
+            &self.batch.previous_batch,
+            &pending.senders,
+        );
+
+        let ordered: Vec<&SignedTransaction> = order.iter().map(|&i| &transactions[i]).collect();
+        let mut simulated = Vec::with_capacity(transactions.len());
+        pending.execute_each(&ordered, |pending, tx, effect| {
+            let (receipt, error, changed_bytes) = match effect {
+                Ok(effect) => {
+                    let changed_bytes = bytes_changed(&pending.db, &effect)?;
+                    (pending.write_effect(effect)?, None, changed_bytes)
+                }
+                Err(err) => (
+                    Receipt::failed(tx, &self.config.domain),
+                    Some(format!("{:#}", err)),
+                    0,
+                ),
+            };
+            simulated.push(TransactionSimulation {
+                index: order[simulated.len()],
+                tx_hash: tx.hash(),
+                receipt,
+                error,
+                changed_bytes,
+            });
+            Ok::<_, eyre::Report>(())
+        })?;
+
+        let writes = pending.db.into_writes();
+        let changed_bytes = simulated.iter().map(|tx| tx.changed_bytes).sum();
+        let ops = simulated.iter().map(|tx| tx.receipt.ops).sum();
+        let signatures = transactions
+            .iter()
+            .map(|tx| 1 + tx.sponsor.is_some() as u64)
+            .sum();
+        let data_bytes = writes
+            .values()
+            .flatten()
+            .map(|account| account.data.len() as u64)
+            .sum();
+        let cycles = CycleModel::default().estimate(
+            signatures,
+            ops,
+            changed_bytes,
+            writes.len() as u64,
+            data_bytes,
+        );
+
+        let transaction_data = encode_batch(
+            &transactions.to_vec(),
+            Codec::default(),
+            self.dictionary.as_deref(),
+        )?;
+
+        Ok(SimulationResult {
+            state_root: self.state_root_with(&writes)?.into(),
+            accounts: writes.len() as u64,
+            transactions: simulated,
+            changed_bytes,
+            cycles,
+            transaction_data_bytes: transaction_data.len() as u64,
+            calldata_gas: estimate_l1_cost(&transaction_data, PostingMode::Calldata, 0).gas,
+            blob_gas: estimate_l1_cost(&transaction_data, PostingMode::Blob, 0).gas,
+        })
+    }
+
+    // `generate_state_root` as if `writes` were applied. Under a sparse version only the paths
+    // of the written accounts are re-hashed, from their proofs in the cached trees.
+    fn state_root_with(
+        &self,
+        writes: &HashMap<Address, Option<Account>>,
+    ) -> eyre::Result<[u8; 32]> {
+        let version = self.config.commitment;
+        if !version.is_sparse() {
+            let mut tree = self.sync_tree().clone();
+            for address in writes.keys() {
+                tree.mark_dirty(address);
+            }
+            let accounts = self.state().accounts.borrow();
+            tree.update(|addresses| {
+                addresses
+                    .iter()
+                    .map(|address| match writes.get(address) {
+                        Some(account) => account.clone(),
+                        None => accounts.get(address).cloned(),
+                    })
+                    .collect()
+            });
+            return Ok(tree.root().unwrap_or_else(|| empty_root(version)));
+        }
+
+        let mut smts = self.sync_sparse();
+        let accounts = self.state().accounts.borrow();
+        let mut changed = BTreeMap::<u64, Vec<(Address, [u8; 32], [u8; 32])>>::new();
+        for (address, account) in writes {
+            let before = accounts.get(address);
+            let canvas = account
+                .as_ref()
+                .or(before)
+                .map_or(DEFAULT_CANVAS, |account| canvas_of(version, account));
+            let leaf = |account: Option<&Account>| {
+                account.map_or([0; 32], |account| leaf_hash(version, address, account))
+            };
+            changed.entry(canvas).or_default().push((
+                *address,
+                leaf(before),
+                leaf(account.as_ref()),
+            ));
+        }
+
+        let mut roots: BTreeMap<u64, [u8; 32]> = smts
+            .iter_mut()
+            .map(|(canvas, smt)| (*canvas, smt.root()))
+            .collect();
+        for (canvas, leaves) in changed {
+            let smt = smts
+                .entry(canvas)
+                .or_insert_with(|| SparseMerkleTree::with_scheme(version.hash_scheme()));
+            let proofs: Vec<_> = leaves
+                .iter()
+                .map(|(address, before, _)| (address, *before, smt.proof(address)))
+                .collect();
+            let mut tree = PartialTree::from_proofs_with(
+                version.hash_scheme(),
+                proofs
+                    .iter()
+                    .map(|(address, before, proof)| (*address, *before, proof)),
+            )?;
+            for (address, _, after) in &leaves {
+                tree.set(address, *after)?;
+            }
+            roots.insert(canvas, tree.root());
+        }
+
+        if roots.values().all(|root| *root == [0; 32]) {
+            return Ok(empty_root(version));
+        }
+        Ok(match version.is_namespaced() {
+            true => namespace_tree(version.hash_scheme(), roots).root(),
+            false => roots.get(&DEFAULT_CANVAS).copied().unwrap_or_default(),
+        })
+    }
+}
+
+// Bytes of account data `effect` changes in `db`, per account it writes. An edit's changed
+// bytes are what is left of the longer of the two datas without their common prefix and suffix.
+fn bytes_changed(db: &impl AccountDB, effect: &TransactionEffect) -> Result<u64, CanvasError> {
+    // Later writes of the same address win, as in `write_effect`.
+    let writes: HashMap<&Address, &Account> = iter::once((&effect.from, &effect.from_account))
+        .chain(iter::once((&effect.to, &effect.to_account)))
+        .chain(
+            effect
+                .others
+                .iter()
+                .map(|(address, account)| (address, account)),
+        )
+        .collect();
+
+    let mut changed = 0;
+    for (address, account) in writes {
+        let before = db.get_account(address)?.data;
+        let (before, after) = (before.as_bytes(), account.data.as_bytes());
+        let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+        let suffix = before[prefix..]
+            .iter()
+            .rev()
+            .zip(after[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        changed += (before.len().max(after.len()) - prefix - suffix) as u64;
+    }
+    Ok(changed)
+}
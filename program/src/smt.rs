@@ -0,0 +1,313 @@
+use alloy_primitives::{aliases::U160, Address, U256};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::hashing::HashScheme;
+
+/// Height of the tree: one level per address bit, so every path has `DEPTH` siblings.
+pub const DEPTH: usize = 160;
+
+/// Inclusion or exclusion proof of an address: the siblings on its path from the leaf up, with
+/// the empty ones left out. Bit `i` of `bitmap` is set when the sibling at height `i` is in
+/// `siblings`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseProof {
+    pub bitmap: U256,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+fn key(address: &Address) -> U160 {
+    U160::from_be_bytes(address.0 .0)
+}
+
+// The lowest `height` bits, which tell apart the leaves below a node at `height`.
+fn low_bits(height: usize) -> U160 {
+    match height >= DEPTH {
+        true => U160::MAX,
+        false => (U160::from(1) << height) - U160::from(1),
+    }
+}
+
+// The node at `height` on the path of `key`, and its sibling.
+fn prefix(key: &U160, height: usize) -> U160 {
+    *key & !low_bits(height)
+}
+
+fn sibling(key: &U160, height: usize) -> U160 {
+    prefix(key, height) ^ (U160::from(1) << height)
+}
+
+/// Parent of two nodes: `keccak256(left || right)`, except that two empty children give an
+/// empty (zero) parent, so an empty subtree is zero at every height.
+pub fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    parent_with(HashScheme::Keccak, left, right)
+}
+
+/// `parent` with the node hash of `scheme`.
+pub fn parent_with(scheme: HashScheme, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if *left == [0; 32] && *right == [0; 32] {
+        return [0; 32];
+    }
+    scheme.node(left, right)
+}
+
+// Hashes `node`, at `height` on the path of `key`, one level up. Bit `height` of the key set
+// means `node` is the right child.
+fn hash_up(
+    scheme: HashScheme,
+    key: &U160,
+    height: usize,
+    node: &[u8; 32],
+    sibling: &[u8; 32],
+) -> [u8; 32] {
+    match key.bit(height) {
+        true => parent_with(scheme, sibling, node),
+        false => parent_with(scheme, node, sibling),
+    }
+}
+
+// The siblings of `proof` at every height, or `None` if the bitmap and siblings disagree.
+fn expand(proof: &SparseProof) -> Option<Vec<[u8; 32]>> {
+    if proof.bitmap >> DEPTH != U256::ZERO {
+        return None;
+    }
+
+    let mut siblings = proof.siblings.iter();
+    let expanded = (0..DEPTH)
+        .map(|height| match proof.bitmap.bit(height) {
+            true => siblings.next().copied(),
+            false => Some([0; 32]),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    siblings.next().is_none().then_some(expanded)
+}
+
+/// The root `proof` gives for `leaf` at `address`, the zero leaf standing for no account, or
+/// `None` for a malformed proof.
+pub fn compute_root(address: &Address, leaf: &[u8; 32], proof: &SparseProof) -> Option<[u8; 32]> {
+    compute_root_with(HashScheme::Keccak, address, leaf, proof)
+}
+
+/// `compute_root` for a tree hashed under `scheme`.
+pub fn compute_root_with(
+    scheme: HashScheme,
+    address: &Address,
+    leaf: &[u8; 32],
+    proof: &SparseProof,
+) -> Option<[u8; 32]> {
+    let key = key(address);
+    let siblings = expand(proof)?;
+
+    Some(
+        siblings
+            .iter()
+            .enumerate()
+            .fold(*leaf, |node, (height, sibling)| {
+                hash_up(scheme, &key, height, &node, sibling)
+            }),
+    )
+}
+
+/// Checks that `root` holds the account with leaf hash `leaf` at `address`, or for `None` that
+/// it holds no account there.
+pub fn verify(
+    root: &[u8; 32],
+    address: &Address,
+    leaf: Option<&[u8; 32]>,
+    proof: &SparseProof,
+) -> bool {
+    verify_with(HashScheme::Keccak, root, address, leaf, proof)
+}
+
+/// `verify` for a tree hashed under `scheme`.
+pub fn verify_with(
+    scheme: HashScheme,
+    root: &[u8; 32],
+    address: &Address,
+    leaf: Option<&[u8; 32]>,
+    proof: &SparseProof,
+) -> bool {
+    compute_root_with(scheme, address, leaf.unwrap_or(&[0; 32]), proof).as_ref() == Some(root)
+}
+
+/// Sparse Merkle tree of `DEPTH` levels over account leaf hashes, keyed by address. Each path
+/// from the root follows the address bits from the most significant down.
+///
+/// Nodes with at least two leaves below them are cached and dropped along the path of every
+/// change, so the root after a few writes re-hashes only those paths. A node with one leaf
+/// below it is hashed from the leaf when needed.
+#[derive(Debug, Clone, Default)]
+pub struct SparseMerkleTree {
+    leaves: BTreeMap<U160, [u8; 32]>,
+    branches: HashMap<(usize, U160), [u8; 32]>,
+    scheme: HashScheme,
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A tree that hashes its nodes under `scheme`. Its leaves have to be hashed under the same.
+    pub fn with_scheme(scheme: HashScheme) -> Self {
+        Self {
+            scheme,
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn insert(&mut self, address: &Address, leaf: [u8; 32]) {
+        let key = key(address);
+        self.leaves.insert(key, leaf);
+        self.invalidate(&key);
+    }
+
+    pub fn remove(&mut self, address: &Address) {
+        let key = key(address);
+        self.leaves.remove(&key);
+        self.invalidate(&key);
+    }
+
+    fn invalidate(&mut self, key: &U160) {
+        for height in 1..=DEPTH {
+            self.branches.remove(&(height, prefix(key, height)));
+        }
+    }
+
+    /// Zero for a tree without leaves.
+    pub fn root(&mut self) -> [u8; 32] {
+        self.node(DEPTH, U160::ZERO)
+    }
+
+    /// Proves the leaf at `address`, or that there is none.
+    pub fn proof(&mut self, address: &Address) -> SparseProof {
+        let key = key(address);
+        let mut proof = SparseProof::default();
+        for height in 0..DEPTH {
+            let node = self.node(height, sibling(&key, height));
+            if node != [0; 32] {
+                proof.bitmap.set_bit(height, true);
+                proof.siblings.push(node);
+            }
+        }
+        proof
+    }
+
+    // The node at `height` over the leaves whose keys start with `prefix`.
+    fn node(&mut self, height: usize, prefix: U160) -> [u8; 32] {
+        if height == 0 {
+            return self.leaves.get(&prefix).copied().unwrap_or_default();
+        }
+        if let Some(hash) = self.branches.get(&(height, prefix)) {
+            return *hash;
+        }
+
+        let mut below = self.leaves.range(prefix..=prefix | low_bits(height));
+        let first = below.next().map(|(key, leaf)| (*key, *leaf));
+        let more = below.next().is_some();
+
+        let hash = match (first, more) {
+            (None, _) => return [0; 32],
+            (Some((key, leaf)), false) => {
+                return (0..height).fold(leaf, |node, h| {
+                    hash_up(self.scheme, &key, h, &node, &[0; 32])
+                })
+            }
+            (Some(_), true) => {
+                let right = prefix | (U160::from(1) << (height - 1));
+                let (left, right) = (self.node(height - 1, prefix), self.node(height - 1, right));
+                parent_with(self.scheme, &left, &right)
+            }
+        };
+        self.branches.insert((height, prefix), hash);
+        hash
+    }
+}
+
+/// The nodes of a sparse Merkle tree that a set of proofs reveal: enough to recompute the root
+/// after any of the proven leaves changes, without the rest of the tree. The guest keeps this
+/// instead of the whole state.
+#[derive(Debug, Clone, Default)]
+pub struct PartialTree {
+    nodes: HashMap<(usize, U160), [u8; 32]>,
+    scheme: HashScheme,
+}
+
+impl PartialTree {
+    /// Takes in each proof with the address and leaf it proves, the zero leaf for no account.
+    /// Errors unless they all prove the same root.
+    pub fn from_proofs<'a>(
+        proofs: impl IntoIterator<Item = (&'a Address, [u8; 32], &'a SparseProof)>,
+    ) -> eyre::Result<Self> {
+        Self::from_proofs_with(HashScheme::Keccak, proofs)
+    }
+
+    /// `from_proofs` for a tree hashed under `scheme`.
+    pub fn from_proofs_with<'a>(
+        scheme: HashScheme,
+        proofs: impl IntoIterator<Item = (&'a Address, [u8; 32], &'a SparseProof)>,
+    ) -> eyre::Result<Self> {
+        let mut tree = Self {
+            scheme,
+            ..Self::default()
+        };
+        let mut root = None;
+        for (address, leaf, proof) in proofs {
+            let siblings =
+                expand(proof).ok_or_else(|| eyre::eyre!("Malformed proof of {}", address))?;
+            let key = key(address);
+            for (height, node) in siblings.into_iter().enumerate() {
+                tree.nodes
+                    .entry((height, sibling(&key, height)))
+                    .or_insert(node);
+            }
+            tree.set(address, leaf)?;
+
+            let proved = tree.root();
+            if *root.get_or_insert(proved) != proved {
+                return Err(eyre::eyre!("Proof of {} is against another root", address));
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Replaces the leaf at `address` and re-hashes its path. Errors for an address whose
+    /// path no proof revealed.
+    pub fn set(&mut self, address: &Address, leaf: [u8; 32]) -> eyre::Result<()> {
+        let key = key(address);
+        let siblings = (0..DEPTH)
+            .map(|height| self.nodes.get(&(height, sibling(&key, height))).copied())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| eyre::eyre!("No proof covers {}", address))?;
+
+        let mut node = leaf;
+        for (height, sibling) in siblings.iter().enumerate() {
+            self.nodes.insert((height, prefix(&key, height)), node);
+            node = hash_up(self.scheme, &key, height, &node, sibling);
+        }
+        self.nodes.insert((DEPTH, U160::ZERO), node);
+        Ok(())
+    }
+
+    pub fn scheme(&self) -> HashScheme {
+        self.scheme
+    }
+
+    /// Zero before any proof is added.
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes
+            .get(&(DEPTH, U160::ZERO))
+            .copied()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,347 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+use bincode::Options;
+use hashbrown::HashMap;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::metadata::Metadata;
+use crate::namespace::DEFAULT_CANVAS;
+use crate::undo::Undo;
+use crate::{Account, ContributorStats};
+
+/// First bytes of every enveloped snapshot. Unversioned snapshots start with the bincode length
+/// of the accounts map instead, which never matches.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"CVSN";
+
+/// Unversioned, accounts without `balance`, `owner` and `policy`.
+pub const SNAPSHOT_V0: u16 = 0;
+/// Unversioned, accounts without `writers`.
+pub const SNAPSHOT_V1: u16 = 1;
+/// Magic, version, keccak256 checksum of the payload, then the payload. Accounts without
+/// `writers`.
+pub const SNAPSHOT_V2: u16 = 2;
+/// `SNAPSHOT_V2` with accounts without `contributions`.
+pub const SNAPSHOT_V3: u16 = 3;
+/// `SNAPSHOT_V2` with accounts without `canvas`.
+pub const SNAPSHOT_V4: u16 = 4;
+/// `SNAPSHOT_V2` with accounts without `last_edits`.
+pub const SNAPSHOT_V5: u16 = 5;
+/// `SNAPSHOT_V2` with accounts without `undos`.
+pub const SNAPSHOT_V6: u16 = 6;
+/// `SNAPSHOT_V2` with accounts without `metadata`.
+pub const SNAPSHOT_V7: u16 = 7;
+/// `SNAPSHOT_V2` with current accounts.
+pub const SNAPSHOT_V8: u16 = 8;
+pub const SNAPSHOT_VERSION: u16 = SNAPSHOT_V8;
+
+const HEADER_LEN: usize = 4 + 2 + 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The envelope is shorter than its header.
+    Truncated,
+    UnsupportedVersion(u16),
+    ChecksumMismatch,
+    /// The payload does not decode as its version's layout.
+    Corrupt(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Truncated => write!(f, "Snapshot is truncated"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version {}", version)
+            }
+            SnapshotError::ChecksumMismatch => write!(f, "Snapshot checksum does not match"),
+            SnapshotError::Corrupt(err) => write!(f, "Corrupt snapshot: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+// The account layout of `SNAPSHOT_V0`.
+#[derive(Deserialize)]
+struct AccountV0 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+}
+
+impl From<AccountV0> for Account {
+    fn from(account: AccountV0) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            ..Default::default()
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V1` and `SNAPSHOT_V2`.
+#[derive(Deserialize)]
+struct AccountV1 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+}
+
+impl From<AccountV1> for Account {
+    fn from(account: AccountV1) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            ..Default::default()
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V3`.
+#[derive(Deserialize)]
+struct AccountV3 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+    writers: Vec<Address>,
+}
+
+impl From<AccountV3> for Account {
+    fn from(account: AccountV3) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers,
+            contributions: BTreeMap::new(),
+            canvas: DEFAULT_CANVAS,
+            last_edits: BTreeMap::new(),
+            undos: BTreeMap::new(),
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V4`, from before canvases.
+#[derive(Deserialize)]
+struct AccountV4 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+    writers: Vec<Address>,
+    contributions: BTreeMap<Address, ContributorStats>,
+}
+
+impl From<AccountV4> for Account {
+    fn from(account: AccountV4) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers,
+            contributions: account.contributions,
+            canvas: DEFAULT_CANVAS,
+            last_edits: BTreeMap::new(),
+            undos: BTreeMap::new(),
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V5`, from before edit cooldowns.
+#[derive(Deserialize)]
+struct AccountV5 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+    writers: Vec<Address>,
+    contributions: BTreeMap<Address, ContributorStats>,
+    canvas: u64,
+}
+
+impl From<AccountV5> for Account {
+    fn from(account: AccountV5) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers,
+            contributions: account.contributions,
+            canvas: account.canvas,
+            last_edits: BTreeMap::new(),
+            undos: BTreeMap::new(),
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V6`, from before undos.
+#[derive(Deserialize)]
+struct AccountV6 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+    writers: Vec<Address>,
+    contributions: BTreeMap<Address, ContributorStats>,
+    canvas: u64,
+    last_edits: BTreeMap<Address, u64>,
+}
+
+impl From<AccountV6> for Account {
+    fn from(account: AccountV6) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers,
+            contributions: account.contributions,
+            canvas: account.canvas,
+            last_edits: account.last_edits,
+            undos: BTreeMap::new(),
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+// The account layout of `SNAPSHOT_V7`, from before metadata.
+#[derive(Deserialize)]
+struct AccountV7 {
+    nonce: u64,
+    data: String,
+    contributors: Vec<Address>,
+    balance: U256,
+    owner: Address,
+    policy: B256,
+    writers: Vec<Address>,
+    contributions: BTreeMap<Address, ContributorStats>,
+    canvas: u64,
+    last_edits: BTreeMap<Address, u64>,
+    undos: BTreeMap<Address, Undo>,
+}
+
+impl From<AccountV7> for Account {
+    fn from(account: AccountV7) -> Self {
+        Account {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors,
+            balance: account.balance,
+            owner: account.owner,
+            policy: account.policy,
+            writers: account.writers,
+            contributions: account.contributions,
+            canvas: account.canvas,
+            last_edits: account.last_edits,
+            undos: account.undos,
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+pub fn encode(accounts: &HashMap<Address, Account>) -> Result<Vec<u8>, SnapshotError> {
+    let sorted: BTreeMap<&Address, &Account> = accounts.iter().collect();
+    let payload = bincode::serialize(&sorted).map_err(|e| SnapshotError::Corrupt(e.to_string()))?;
+
+    let mut snapshot = Vec::with_capacity(HEADER_LEN + payload.len());
+    snapshot.extend(SNAPSHOT_MAGIC);
+    snapshot.extend(SNAPSHOT_VERSION.to_be_bytes());
+    snapshot.extend(keccak256(&payload));
+    snapshot.extend(payload);
+    Ok(snapshot)
+}
+
+/// Decodes a snapshot of any version, migrating it to the current account layout.
+pub fn decode(snapshot: &[u8]) -> Result<HashMap<Address, Account>, SnapshotError> {
+    if !snapshot.starts_with(&SNAPSHOT_MAGIC) {
+        // Unversioned: the layout is only known by which one the bytes fit exactly.
+        return migrate(SNAPSHOT_V1, snapshot).or_else(|_| migrate(SNAPSHOT_V0, snapshot));
+    }
+    if snapshot.len() < HEADER_LEN {
+        return Err(SnapshotError::Truncated);
+    }
+
+    let version = u16::from_be_bytes([snapshot[4], snapshot[5]]);
+    let (checksum, payload) = snapshot[6..].split_at(32);
+    if keccak256(payload).as_slice() != checksum {
+        return Err(SnapshotError::ChecksumMismatch);
+    }
+
+    migrate(version, payload)
+}
+
+fn migrate(version: u16, payload: &[u8]) -> Result<HashMap<Address, Account>, SnapshotError> {
+    match version {
+        SNAPSHOT_V0 => Ok(strict::<HashMap<Address, AccountV0>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V1 | SNAPSHOT_V2 => Ok(strict::<HashMap<Address, AccountV1>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V3 => Ok(strict::<HashMap<Address, AccountV3>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V4 => Ok(strict::<HashMap<Address, AccountV4>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V5 => Ok(strict::<HashMap<Address, AccountV5>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V6 => Ok(strict::<HashMap<Address, AccountV6>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V7 => Ok(strict::<HashMap<Address, AccountV7>>(payload)?
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect()),
+        SNAPSHOT_V8 => strict(payload),
+        version => Err(SnapshotError::UnsupportedVersion(version)),
+    }
+}
+
+// bincode's default encoding, but failing on trailing bytes so a layout only matches exactly.
+fn strict<T: DeserializeOwned>(payload: &[u8]) -> Result<T, SnapshotError> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(payload)
+        .map_err(|e| SnapshotError::Corrupt(e.to_string()))
+}
@@ -0,0 +1,53 @@
+use alloy_primitives::{Address, Signature, SignatureError, B256, U256};
+use alloy_rlp::{RlpDecodable, RlpEncodable};
+use alloy_sol_types::{sol, SolStruct};
+use serde::{Deserialize, Serialize};
+
+use crate::signing::{signing_hash, SigningDomain};
+use crate::{SignedTransaction, Transaction};
+
+sol! {
+    /// EIP-712 approval of a transaction by its sponsor. `transaction` is the prehash its sender
+    /// signs, so the approval holds for that transaction on that deployment only.
+    struct Sponsorship {
+        bytes32 transaction;
+    }
+}
+
+/// A second signature on a transaction, over its `Sponsorship`. The sponsor pays the fee and the
+/// transaction counts against the sponsor's rate limit instead of the sender's.
+///
+/// The sender's signature does not cover it, so a relayer can drop it and leave the fee to the
+/// sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
+pub struct SponsorSignature {
+    pub r: U256,
+    pub s: U256,
+    pub odd_y_parity: bool,
+}
+
+pub fn sponsorship(tx: &Transaction, domain: &SigningDomain) -> Sponsorship {
+    Sponsorship {
+        transaction: signing_hash(tx, domain),
+    }
+}
+
+/// The prehash a sponsor's key signs for `tx`.
+pub fn sponsorship_hash(tx: &Transaction, domain: &SigningDomain) -> B256 {
+    sponsorship(tx, domain).eip712_signing_hash(&domain.eip712_domain())
+}
+
+/// The sponsor of `input`, `None` for a transaction without one.
+pub fn recover_sponsor(
+    input: &SignedTransaction,
+    domain: &SigningDomain,
+) -> Result<Option<Address>, SignatureError> {
+    let Some(sponsor) = &input.sponsor else {
+        return Ok(None);
+    };
+    let signature = Signature::from_rs_and_parity(sponsor.r, sponsor.s, sponsor.odd_y_parity)?;
+
+    Ok(Some(signature.recover_address_from_prehash(
+        &sponsorship_hash(&input.tx, domain),
+    )?))
+}
@@ -0,0 +1,279 @@
+use alloy_primitives::Address;
+use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rs_merkle::Hasher;
+use std::cmp::Ordering;
+
+use crate::commitment::{leaf_hash, leaf_hashes, CommitmentVersion};
+use crate::{Account, Keccak256Algorithm};
+
+// Below this many nodes the thread pool costs more than it saves.
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct Leaf {
+    pub hash: [u8; 32],
+    pub account: Address,
+}
+
+/// Merkle tree over the account leaves that keeps every layer cached between calls.
+///
+/// Only accounts marked dirty are re-hashed on `update`, and only the nodes covering the range
+/// of leaf positions that actually changed are recomputed. The layout (leaves sorted by hash, or
+/// by address from `CommitmentVersion::V4` on, sorted-pair hashing, odd nodes promoted) is
+/// identical to `rs_merkle::MerkleTree`, so roots and proofs match a full rebuild.
+#[derive(Debug, Clone, Default)]
+pub struct StateTree {
+    version: CommitmentVersion,
+    hashes: HashMap<Address, [u8; 32]>,
+    leaves: Vec<Leaf>,
+    layers: Vec<Vec<[u8; 32]>>,
+    dirty: HashSet<Address>,
+    initialized: bool,
+}
+
+impl StateTree {
+    pub fn new(version: CommitmentVersion) -> Self {
+        Self {
+            version,
+            ..Default::default()
+        }
+    }
+
+    pub fn version(&self) -> CommitmentVersion {
+        self.version
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    pub fn mark_dirty(&mut self, address: &Address) {
+        if self.initialized {
+            self.dirty.insert(*address);
+        }
+    }
+
+    pub fn dirty(&self) -> impl Iterator<Item = &Address> {
+        self.dirty.iter()
+    }
+
+    pub fn build<'a>(&mut self, accounts: impl Iterator<Item = (&'a Address, &'a Account)>) {
+        self.hashes.clear();
+        self.leaves.clear();
+        self.dirty.clear();
+
+        let accounts: Vec<(&Address, &Account)> = accounts.collect();
+        self.leaves = leaf_hashes(self.version, &accounts)
+            .into_iter()
+            .zip(&accounts)
+            .map(|(hash, (address, _))| Leaf {
+                hash,
+                account: **address,
+            })
+            .collect();
+
+        let version = self.version;
+        #[cfg(feature = "parallel")]
+        self.leaves
+            .par_sort_unstable_by(|a, b| order(version, a, b));
+        #[cfg(not(feature = "parallel"))]
+        self.leaves.sort_unstable_by(|a, b| order(version, a, b));
+
+        self.hashes = self.leaves.iter().map(|l| (l.account, l.hash)).collect();
+
+        self.layers = vec![self.leaves.iter().map(|l| l.hash).collect()];
+        self.recompute_from(0, usize::MAX);
+        self.initialized = true;
+    }
+
+    /// Re-hashes the dirty accounts, looking them all up with one call to `get`, which returns
+    /// the account of each address it is given in order. An account for which `get` returns
+    /// `None` is removed from the tree.
+    pub fn update(&mut self, get: impl FnOnce(&[Address]) -> Vec<Option<Account>>) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        let previous: Vec<[u8; 32]> = self.layers[0].clone();
+
+        let dirty: Vec<Address> = self.dirty.drain().collect();
+        let accounts = get(&dirty);
+        for (address, account) in dirty.into_iter().zip(accounts) {
+            if let Some(old) = self.hashes.remove(&address) {
+                if let Ok(pos) = self.search(&Leaf {
+                    hash: old,
+                    account: address,
+                }) {
+                    self.leaves.remove(pos);
+                }
+            }
+
+            if let Some(account) = account {
+                let leaf = Leaf {
+                    hash: leaf_hash(self.version, &address, &account),
+                    account: address,
+                };
+                let pos = self.search(&leaf).unwrap_or_else(|p| p);
+                self.hashes.insert(address, leaf.hash);
+                self.leaves.insert(pos, leaf);
+            }
+        }
+
+        let current: Vec<[u8; 32]> = self.leaves.iter().map(|l| l.hash).collect();
+
+        let lo = previous
+            .iter()
+            .zip(current.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(previous.len().min(current.len()));
+
+        let hi = if previous.len() == current.len() {
+            match previous
+                .iter()
+                .zip(current.iter())
+                .rposition(|(a, b)| a != b)
+            {
+                Some(hi) => hi,
+                None => return,
+            }
+        } else {
+            usize::MAX
+        };
+
+        self.layers[0] = current;
+        self.recompute_from(lo, hi);
+    }
+
+    pub fn root(&self) -> Option<[u8; 32]> {
+        self.layers.last().and_then(|l| l.first()).copied()
+    }
+
+    pub fn leaves(&self) -> &[Leaf] {
+        &self.leaves
+    }
+
+    pub fn position(&self, address: &Address) -> Option<usize> {
+        let hash = self.hashes.get(address)?;
+        self.search(&Leaf {
+            hash: *hash,
+            account: *address,
+        })
+        .ok()
+    }
+
+    pub fn proof(&self, index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        let mut idx = index;
+        for layer in &self.layers[..self.layers.len().saturating_sub(1)] {
+            if let Some(sibling) = layer.get(idx ^ 1) {
+                proof.push(*sibling);
+            }
+            idx /= 2;
+        }
+        proof
+    }
+
+    /// The helper hashes of an rs_merkle multiproof of the leaves at `indices`, which must be
+    /// sorted and distinct.
+    pub fn multiproof(&self, indices: &[usize]) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        let mut current = indices.to_vec();
+        for layer in &self.layers[..self.layers.len().saturating_sub(1)] {
+            for sibling in current.iter().map(|idx| idx ^ 1) {
+                if current.binary_search(&sibling).is_err() {
+                    proof.extend(layer.get(sibling));
+                }
+            }
+            current = current.iter().map(|idx| idx / 2).collect();
+            current.dedup();
+        }
+        proof
+    }
+
+    fn search(&self, leaf: &Leaf) -> Result<usize, usize> {
+        self.leaves
+            .binary_search_by(|probe| order(self.version, probe, leaf))
+    }
+
+    // Recomputes parent nodes covering leaf positions `lo..=hi` (`hi == usize::MAX` meaning
+    // everything from `lo` onwards, used when the number of leaves changed).
+    fn recompute_from(&mut self, lo: usize, hi: usize) {
+        let (mut lo, mut hi) = (lo, hi);
+        let mut depth = 0;
+
+        while self.layers[depth].len() > 1 {
+            let len = (self.layers[depth].len() + 1) / 2;
+            if self.layers.len() <= depth + 1 {
+                self.layers.push(Vec::new());
+            }
+
+            lo /= 2;
+            hi = if hi == usize::MAX { hi } else { hi / 2 };
+
+            let (below, above) = self.layers.split_at_mut(depth + 1);
+            let (nodes, parents) = (&below[depth], &mut above[0]);
+            parents.resize(len, [0; 32]);
+
+            let end = len.min(hi.saturating_add(1));
+            if lo < end {
+                hash_range(nodes, &mut parents[lo..end], lo);
+            }
+
+            depth += 1;
+        }
+
+        self.layers.truncate(depth + 1);
+    }
+}
+
+// Leaf order of the tree under `version`.
+fn order(version: CommitmentVersion, a: &Leaf, b: &Leaf) -> Ordering {
+    match version.sorts_by_address() {
+        true => a.account.cmp(&b.account),
+        false => a.hash.cmp(&b.hash),
+    }
+}
+
+/// Root of the state tree of `version` over `leaves`, in any order, with the same layout as
+/// `StateTree`.
+pub fn state_root_of(version: CommitmentVersion, mut leaves: Vec<Leaf>) -> Option<[u8; 32]> {
+    leaves.sort_unstable_by(|a, b| order(version, a, b));
+    root_of_ordered(leaves.into_iter().map(|leaf| leaf.hash).collect())
+}
+
+/// Root of the tree over `leaves`, in any order, sorted by hash as in `StateTree` up to
+/// `CommitmentVersion::V3`.
+pub fn root_of(mut leaves: Vec<[u8; 32]>) -> Option<[u8; 32]> {
+    leaves.sort_unstable();
+    root_of_ordered(leaves)
+}
+
+fn root_of_ordered(leaves: Vec<[u8; 32]>) -> Option<[u8; 32]> {
+    let mut layer = leaves;
+    while layer.len() > 1 {
+        let mut parents = vec![[0; 32]; layer.len().div_ceil(2)];
+        hash_range(&layer, &mut parents, 0);
+        layer = parents;
+    }
+
+    layer.first().copied()
+}
+
+// Fills `parents` with the parents of `nodes`, the first of them being parent number `offset`.
+fn hash_range(nodes: &[[u8; 32]], parents: &mut [[u8; 32]], offset: usize) {
+    let hash = |(j, parent): (usize, &mut [u8; 32])| {
+        let i = (offset + j) * 2;
+        *parent = Keccak256Algorithm::concat_and_hash(&nodes[i], nodes.get(i + 1));
+    };
+
+    #[cfg(feature = "parallel")]
+    if parents.len() >= PARALLEL_THRESHOLD {
+        parents.par_iter_mut().enumerate().for_each(hash);
+        return;
+    }
+
+    parents.iter_mut().enumerate().for_each(hash);
+}
@@ -0,0 +1,127 @@
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What undoes a sender's last edit of an account's data: the `inserted` chars it left at
+/// `index` go back to the text they replaced. Kept while `CanvasConfig::undo_window` is set,
+/// until the window passes or another edit changes those chars.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Undo {
+    /// `SignedTransaction::hash` of the edit, which `EditOp::RevertOwn` names.
+    pub tx_hash: B256,
+    pub batch_index: u64,
+    /// Where the chars the edit left start in the data as it is now.
+    pub index: u64,
+    pub inserted: u64,
+    pub removed: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoError {
+    /// The sender has nothing to undo under `tx_hash`: it is not their last edit of the
+    /// account, the undo window passed, or the chars it left were edited since.
+    NotUndoable(B256),
+    /// `RevertOwn` with other ops, while it can only be addressed against the data before them.
+    NotAlone,
+    /// An undo window under a commitment version that does not commit undos.
+    NotCommitted,
+}
+
+impl fmt::Display for UndoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UndoError::NotUndoable(tx_hash) => {
+                write!(f, "Edit {} can no longer be reverted", tx_hash)
+            }
+            UndoError::NotAlone => write!(f, "RevertOwn must be the transaction's only op"),
+            UndoError::NotCommitted => write!(f, "Undo windows need commitment version 11"),
+        }
+    }
+}
+
+impl std::error::Error for UndoError {}
+
+/// The undo of `sender`'s edit `tx_hash`, if it is their last one and still within `window`
+/// batches at `batch_index`.
+pub fn find<'a>(
+    undos: &'a BTreeMap<Address, Undo>,
+    sender: &Address,
+    tx_hash: &B256,
+    batch_index: u64,
+    window: u64,
+) -> Result<&'a Undo, UndoError> {
+    match undos.get(sender) {
+        Some(undo)
+            if undo.tx_hash == *tx_hash
+                && batch_index < undo.batch_index.saturating_add(window) =>
+        {
+            Ok(undo)
+        }
+        _ => Err(UndoError::NotUndoable(*tx_hash)),
+    }
+}
+
+/// Carries `undos` over a change of the data from `before` to `after`: those whose chars come
+/// after the changed range move with it, those whose chars it touches are dropped, as are those
+/// older than `window` batches. With `edit`, the change is recorded as that sender's last edit,
+/// in place of the one before.
+pub fn update(
+    undos: &mut BTreeMap<Address, Undo>,
+    before: &str,
+    after: &str,
+    batch_index: u64,
+    window: u64,
+    edit: Option<(Address, B256)>,
+) {
+    undos.retain(|_, undo| batch_index < undo.batch_index.saturating_add(window));
+
+    let (index, removed, inserted) = changed_range(before, after);
+    let removed_len = removed.chars().count();
+    undos.retain(|_, undo| {
+        let (start, end) = (undo.index as usize, (undo.index + undo.inserted) as usize);
+        if index + removed_len <= start {
+            undo.index = (start - removed_len + inserted) as u64;
+            true
+        } else {
+            index >= end
+        }
+    });
+
+    if let Some((sender, tx_hash)) = edit {
+        undos.insert(
+            sender,
+            Undo {
+                tx_hash,
+                batch_index,
+                index: index as u64,
+                inserted: inserted as u64,
+                removed,
+            },
+        );
+    }
+}
+
+// The one range of chars in which `before` and `after` differ, as where it starts, the text of
+// `before` in it and how many chars `after` has in its place. Empty at 0 when they are equal.
+fn changed_range(before: &str, after: &str) -> (usize, String, usize) {
+    let before: Vec<char> = before.chars().collect();
+    let after: Vec<char> = after.chars().collect();
+    let prefix = before
+        .iter()
+        .zip(&after)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == before.len() && prefix == after.len() {
+        return (0, String::new(), 0);
+    }
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let removed = before[prefix..before.len() - suffix].iter().collect();
+    (prefix, removed, after.len() - prefix - suffix)
+}
@@ -0,0 +1,41 @@
+use crate::edit::EditError;
+use crate::MAX_VALUE;
+
+/// Decides which characters an edit may write into an account's data. Shared by the threads
+/// that execute a batch's transactions under the `parallel` feature.
+pub trait ValueValidator: Sync {
+    fn validate(&self, value: &str) -> Result<(), EditError>;
+}
+
+/// Accepts hex digits (either case) whose value is at most `MAX_VALUE`, one palette entry per
+/// character.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HexNibbleValidator;
+
+impl ValueValidator for HexNibbleValidator {
+    fn validate(&self, value: &str) -> Result<(), EditError> {
+        match value
+            .chars()
+            .find(|c| !c.to_digit(16).is_some_and(|d| d <= MAX_VALUE as u32))
+        {
+            Some(c) => Err(EditError::InvalidValue(c)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Accepts any value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnyValue;
+
+impl ValueValidator for AnyValue {
+    fn validate(&self, _value: &str) -> Result<(), EditError> {
+        Ok(())
+    }
+}
+
+impl<V: ValueValidator + ?Sized> ValueValidator for &V {
+    fn validate(&self, value: &str) -> Result<(), EditError> {
+        (**self).validate(value)
+    }
+}
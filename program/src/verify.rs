@@ -0,0 +1,191 @@
+use serde::Serialize;
+
+use crate::batch::BatchContext;
+use crate::bridge::Inbox;
+use crate::commitment::same_state_root;
+use crate::config::CanvasConfig;
+use crate::limits::RateLimitState;
+use crate::public_values::{decode_public_values, ClaimedValues};
+use crate::receipt::Receipt;
+use crate::{CanvasProcessor, InMemoryDB};
+
+/// Outcome of re-executing a posted batch. `computed` holds the values this program derives,
+/// committing to the posted bytes as the contract does.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub claimed: ClaimedValues,
+    pub computed: ClaimedValues,
+    pub receipts: Vec<Receipt>,
+    /// Public values the submission got wrong. Values older submissions did not commit are not
+    /// compared.
+    pub mismatches: Vec<&'static str>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Re-executes a posted batch without a node: `pre_state_snapshot` is an
+/// `InMemoryDB::snapshot_accounts` of the state before it, `batch_bytes` its `_transactionData`
+/// and `expected_public_values` its `_publicValuesBytes`, in any layout `decode_public_values`
+/// reads. Runs under the default config with fresh rate-limit counters and an empty `Inbox`; see
+/// `verify_batch_with`.
+pub fn verify_batch(
+    pre_state_snapshot: &[u8],
+    batch_bytes: &[u8],
+    expected_public_values: &[u8],
+) -> eyre::Result<VerificationReport> {
+    verify_batch_with(
+        CanvasConfig::default(),
+        RateLimitState::default(),
+        Inbox::default(),
+        pre_state_snapshot,
+        batch_bytes,
+        expected_public_values,
+    )
+}
+
+/// `verify_batch` for batches that ran under another config, after earlier batches moved the
+/// rate-limit counters, or that consumed L1 messages. An `Err` means the batch could not be
+/// executed at all.
+pub fn verify_batch_with(
+    config: CanvasConfig,
+    limits: RateLimitState,
+    inbox: Inbox,
+    pre_state_snapshot: &[u8],
+    batch_bytes: &[u8],
+    expected_public_values: &[u8],
+) -> eyre::Result<VerificationReport> {
+    let claimed = decode_public_values(expected_public_values)?;
+    let db = InMemoryDB::from_snapshot(pre_state_snapshot)?;
+    // Where the batch sits is taken from the claim. Only the contract can check it against the
+    // batches before it and the L1 block.
+    let batch = BatchContext {
+        index: claimed.batch_index.unwrap_or_default(),
+        previous_batch: claimed.previous_batch.unwrap_or_default(),
+        l1_block_number: claimed.l1_block_number.unwrap_or_default(),
+        l1_block_hash: claimed.l1_block_hash.unwrap_or_default(),
+        timestamp: claimed.batch_timestamp.unwrap_or_default(),
+        previous_batch_hash: claimed.previous_batch_hash.unwrap_or_default(),
+        previous_timestamp: claimed.previous_batch_timestamp.unwrap_or_default(),
+        sequencer: claimed.sequencer.unwrap_or_default(),
+        sequencer_signature: None,
+    };
+
+    let report = CanvasProcessor::with_config(db, config)
+        .with_limits(limits)
+        .process_posted_batch(batch_bytes, batch, inbox)?;
+
+    let computed = ClaimedValues::from(report.public_values);
+
+    Ok(VerificationReport {
+        mismatches: mismatches(&claimed, &computed),
+        claimed,
+        computed,
+        receipts: report.receipts,
+    })
+}
+
+// State roots are compared with `same_state_root`, so a pre-state that was empty under an
+// earlier commitment version matches its `V2` root.
+fn mismatches(claimed: &ClaimedValues, computed: &ClaimedValues) -> Vec<&'static str> {
+    let roots = [
+        (
+            "initialStateRoot",
+            claimed.initial_state_root,
+            computed.initial_state_root,
+        ),
+        (
+            "finalStateRoot",
+            claimed.final_state_root,
+            computed.final_state_root,
+        ),
+    ];
+    let values = [
+        (
+            "transactionCommit",
+            Some(claimed.transaction_commit),
+            Some(computed.transaction_commit),
+        ),
+        (
+            "receiptsRoot",
+            claimed.receipts_root,
+            computed.receipts_root,
+        ),
+        (
+            "accountsRoot",
+            claimed.accounts_root,
+            computed.accounts_root,
+        ),
+        (
+            "previousBatch",
+            claimed.previous_batch,
+            computed.previous_batch,
+        ),
+        ("limitsRoot", claimed.limits_root, computed.limits_root),
+        ("l1BlockHash", claimed.l1_block_hash, computed.l1_block_hash),
+        (
+            "depositsCommit",
+            claimed.deposits_commit,
+            computed.deposits_commit,
+        ),
+        (
+            "forcedCommit",
+            claimed.forced_commit,
+            computed.forced_commit,
+        ),
+        (
+            "previousBatchHash",
+            claimed.previous_batch_hash,
+            computed.previous_batch_hash,
+        ),
+        ("batchHash", claimed.batch_hash, computed.batch_hash),
+        (
+            "sequencer",
+            claimed.sequencer.map(|sequencer| sequencer.into_word()),
+            computed.sequencer.map(|sequencer| sequencer.into_word()),
+        ),
+    ];
+    let numbers = [
+        ("batchIndex", claimed.batch_index, computed.batch_index),
+        (
+            "l1BlockNumber",
+            claimed.l1_block_number,
+            computed.l1_block_number,
+        ),
+        (
+            "depositCount",
+            claimed.deposit_count,
+            computed.deposit_count,
+        ),
+        ("forcedCount", claimed.forced_count, computed.forced_count),
+        (
+            "batchTimestamp",
+            claimed.batch_timestamp,
+            computed.batch_timestamp,
+        ),
+        (
+            "previousBatchTimestamp",
+            claimed.previous_batch_timestamp,
+            computed.previous_batch_timestamp,
+        ),
+    ];
+
+    let roots = roots
+        .into_iter()
+        .filter(|(_, claimed, computed)| !same_state_root(&claimed.0, &computed.0))
+        .map(|(name, _, _)| name);
+    let values = values
+        .into_iter()
+        .filter(|(_, claimed, computed)| claimed.is_some() && claimed != computed)
+        .map(|(name, _, _)| name);
+    let numbers = numbers
+        .into_iter()
+        .filter(|(_, claimed, computed)| claimed.is_some() && claimed != computed)
+        .map(|(name, _, _)| name);
+
+    roots.chain(values).chain(numbers).collect()
+}
@@ -0,0 +1,429 @@
+use alloy_primitives::Address;
+use eyre::WrapErr;
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::iter;
+
+use crate::batch::{self, BatchContext};
+use crate::bridge::Inbox;
+use crate::commitment::{empty_root, leaf_hash, leaf_hash_cached, leaf_hashes, CommitmentVersion};
+use crate::config::CanvasConfig;
+use crate::data_root::DataRoots;
+use crate::error::CanvasError;
+use crate::limits::RateLimitState;
+use crate::namespace::{canvas_key, canvas_leaf, canvas_of, namespace_tree, DEFAULT_CANVAS};
+use crate::proof::AddressIndex;
+use crate::receipt::BatchReport;
+use crate::recovery::recover_each;
+use crate::smt::{PartialTree, SparseMerkleTree, SparseProof};
+use crate::sponsor::recover_sponsor;
+use crate::state_tree::{root_of, state_root_of, Leaf};
+use crate::SignedTransaction;
+use crate::{Account, AccountDB, CanvasProcessor, InMemoryDB};
+
+/// The part of the state a batch needs: the accounts it touches in full and only the leaf hash
+/// of every other account.
+///
+/// Up to `CommitmentVersion::V3` leaves are sorted by hash, so an edited leaf can move anywhere
+/// in the tree and a multiproof of the touched leaves is not enough to recompute the root. The
+/// untouched leaf hashes are, and are 32 bytes per account instead of its whole data.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Witness {
+    /// `None` for a touched address that is not in the state yet.
+    pub accounts: HashMap<Address, Option<Account>>,
+    /// In address order, as the tree orders them from `CommitmentVersion::V4` on.
+    pub untouched: Vec<[u8; 32]>,
+    /// Addresses of the untouched accounts, in order, for the `AddressIndex` and to place
+    /// `untouched` in an address ordered tree.
+    #[serde(default)]
+    pub untouched_addresses: Vec<Address>,
+    /// Under a sparse commitment version, a proof of each touched address in place of
+    /// `untouched`, which is left empty: the guest updates the root along the proven paths.
+    /// From `CommitmentVersion::V8` on each is against the root of the address's canvas.
+    #[serde(default)]
+    pub proofs: HashMap<Address, SparseProof>,
+    /// The canvas of each touched address outside `DEFAULT_CANVAS`.
+    #[serde(default)]
+    pub canvases: HashMap<Address, u64>,
+    /// From `CommitmentVersion::V8` on, a proof of the root of each touched canvas in the
+    /// namespace tree.
+    #[serde(default)]
+    pub canvas_proofs: BTreeMap<u64, SparseProof>,
+}
+
+impl Witness {
+    /// Extracts the witness of `transactions` and `inbox` from `db`. Senders are recovered with
+    /// `config.domain`; a transaction whose sender does not recover only touches its target.
+    /// Sponsors are touched like senders, and under a fee schedule every batch also touches the
+    /// fee recipient. A transaction with an `access_list` touches the accounts it declares, and
+    /// its signatures are not recovered.
+    pub fn build(
+        db: &InMemoryDB,
+        transactions: &[SignedTransaction],
+        inbox: Inbox,
+        config: &CanvasConfig,
+    ) -> Self {
+        let mut touched: HashSet<Address> =
+            inbox.deposits.iter().map(|deposit| deposit.to).collect();
+        let mut canvases = HashMap::new();
+        if !config.fees.is_free() {
+            touched.insert(config.fees.recipient);
+        }
+        let transactions = [inbox.forced_txs, transactions].concat();
+        for tx in &transactions {
+            for address in iter::once(tx.tx.target()).chain(tx.tx.sources()) {
+                touched.insert(address);
+                if tx.tx.canvas != DEFAULT_CANVAS && config.commitment.is_namespaced() {
+                    canvases.insert(address, tx.tx.canvas);
+                }
+            }
+            touched.extend(&tx.tx.access_list);
+        }
+        let undeclared: Vec<&SignedTransaction> = transactions
+            .iter()
+            .filter(|tx| tx.tx.access_list.is_empty())
+            .collect();
+        let senders = recover_each(&undeclared, &config.domain);
+        for (tx, sender) in undeclared.into_iter().zip(senders) {
+            if let Ok(sender) = sender {
+                touched.insert(sender);
+            }
+            if let Ok(Some(sponsor)) = recover_sponsor(tx, &config.domain) {
+                touched.insert(sponsor);
+            }
+        }
+
+        let state = db.accounts.borrow();
+        let accounts = touched
+            .iter()
+            .map(|address| (*address, state.get(address).cloned()))
+            .collect();
+
+        if config.commitment.is_sparse() {
+            let all: Vec<_> = state.iter().collect();
+            let scheme = config.commitment.hash_scheme();
+            let empty_tree = || SparseMerkleTree::with_scheme(scheme);
+            let mut trees = BTreeMap::<u64, SparseMerkleTree>::new();
+            for (hash, (address, account)) in
+                leaf_hashes(config.commitment, &all).into_iter().zip(&all)
+            {
+                trees
+                    .entry(canvas_of(config.commitment, account))
+                    .or_insert_with(empty_tree)
+                    .insert(address, hash);
+            }
+            let mut untouched_addresses: Vec<Address> = state
+                .keys()
+                .filter(|address| !touched.contains(*address))
+                .copied()
+                .collect();
+            untouched_addresses.sort_unstable();
+
+            let canvas = |address: &Address| canvases.get(address).copied().unwrap_or_default();
+            let proofs = touched
+                .iter()
+                .map(|address| {
+                    let tree = trees.entry(canvas(address)).or_insert_with(empty_tree);
+                    (*address, tree.proof(address))
+                })
+                .collect();
+            let canvas_proofs = match config.commitment.is_namespaced() {
+                true => {
+                    let roots: Vec<(u64, [u8; 32])> = trees
+                        .iter_mut()
+                        .map(|(canvas, tree)| (*canvas, tree.root()))
+                        .collect();
+                    let mut namespace = namespace_tree(scheme, roots);
+                    touched
+                        .iter()
+                        .map(|address| {
+                            let canvas = canvas(address);
+                            (canvas, namespace.proof(&canvas_key(canvas)))
+                        })
+                        .collect()
+                }
+                false => BTreeMap::new(),
+            };
+
+            return Self {
+                accounts,
+                untouched: Vec::new(),
+                untouched_addresses,
+                proofs,
+                canvases,
+                canvas_proofs,
+            };
+        }
+
+        let mut untouched_accounts: Vec<_> = state
+            .iter()
+            .filter(|(address, _)| !touched.contains(*address))
+            .collect();
+        untouched_accounts.sort_unstable_by_key(|(address, _)| **address);
+        let untouched = leaf_hashes(config.commitment, &untouched_accounts);
+        let untouched_addresses = untouched_accounts
+            .iter()
+            .map(|(address, _)| **address)
+            .collect();
+
+        Self {
+            accounts,
+            untouched,
+            untouched_addresses,
+            proofs: HashMap::new(),
+            canvases: HashMap::new(),
+            canvas_proofs: BTreeMap::new(),
+        }
+    }
+
+    /// Runs the posted `transaction_data` against the witness the way `process_posted_batch` runs
+    /// it against a full db.
+    pub fn process_batch(
+        self,
+        transaction_data: &[u8],
+        config: CanvasConfig,
+        batch: BatchContext,
+        inbox: Inbox,
+        limits: RateLimitState,
+        dictionary: Option<Vec<u8>>,
+    ) -> eyre::Result<BatchReport> {
+        let mut data_roots = DataRoots::default();
+        let sparse = match config.commitment.is_sparse() {
+            true => Some(RefCell::new(
+                self.partial_state(config.commitment, &mut data_roots)?,
+            )),
+            false => None,
+        };
+        let db = WitnessDB {
+            version: config.commitment,
+            canvases: self.canvases,
+            accounts: RefCell::new(self.accounts),
+            untouched: self.untouched,
+            untouched_addresses: self.untouched_addresses,
+            sparse,
+            data_roots: RefCell::new(data_roots),
+        };
+
+        let transactions = batch::decode_batch(transaction_data, dictionary.as_deref())
+            .wrap_err("Failed to decode batch")?;
+        CanvasProcessor::with_config(db, config)
+            .with_limits(limits)
+            .with_dictionary(dictionary)
+            .apply_batch_with(
+                &transactions,
+                batch::commit(transaction_data),
+                config.failure_policy,
+                batch,
+                inbox,
+                |processor| Ok(processor.db.state_root()),
+                |processor| Ok(processor.db.accounts_root()),
+            )
+    }
+
+    // The paths of every touched account, which have to agree on one root per canvas, and from
+    // `CommitmentVersion::V8` on the paths of those roots to one state root. Their data roots
+    // stay in `data_roots` for the root after the batch.
+    fn partial_state(
+        &self,
+        version: CommitmentVersion,
+        data_roots: &mut DataRoots,
+    ) -> eyre::Result<PartialState> {
+        let mut proofs = BTreeMap::<u64, Vec<_>>::new();
+        for (address, account) in &self.accounts {
+            let proof = self
+                .proofs
+                .get(address)
+                .ok_or_else(|| eyre::eyre!("Witness has no proof of {}", address))?;
+            let leaf = account
+                .as_ref()
+                .map(|account| leaf_hash_cached(version, address, account, data_roots))
+                .unwrap_or_default();
+            let canvas = self.canvases.get(address).copied().unwrap_or_default();
+            proofs
+                .entry(canvas)
+                .or_default()
+                .push((address, leaf, proof));
+        }
+        let canvases = proofs
+            .into_iter()
+            .map(|(canvas, proofs)| {
+                Ok((
+                    canvas,
+                    PartialTree::from_proofs_with(version.hash_scheme(), proofs)?,
+                ))
+            })
+            .collect::<eyre::Result<BTreeMap<_, _>>>()?;
+
+        let namespace = match version.is_namespaced() {
+            true => {
+                let keys: Vec<Address> =
+                    canvases.keys().map(|canvas| canvas_key(*canvas)).collect();
+                let mut roots = Vec::with_capacity(canvases.len());
+                for ((canvas, tree), key) in canvases.iter().zip(&keys) {
+                    let proof = self
+                        .canvas_proofs
+                        .get(canvas)
+                        .ok_or_else(|| eyre::eyre!("Witness has no proof of canvas {}", canvas))?;
+                    roots.push((
+                        key,
+                        canvas_leaf(version.hash_scheme(), *canvas, &tree.root()),
+                        proof,
+                    ));
+                }
+                Some(PartialTree::from_proofs_with(version.hash_scheme(), roots)?)
+            }
+            false => None,
+        };
+
+        Ok(PartialState {
+            canvases,
+            namespace,
+        })
+    }
+}
+
+// The trees a sparse witness reveals: the touched part of each touched canvas and, from
+// `CommitmentVersion::V8` on, of the namespace tree over the canvas roots.
+struct PartialState {
+    canvases: BTreeMap<u64, PartialTree>,
+    namespace: Option<PartialTree>,
+}
+
+impl PartialState {
+    fn set(&mut self, canvas: u64, address: &Address, leaf: [u8; 32]) -> eyre::Result<()> {
+        self.canvases
+            .get_mut(&canvas)
+            .ok_or_else(|| eyre::eyre!("No proof covers {}", address))?
+            .set(address, leaf)
+    }
+
+    // Zero for no accounts, like the root of an empty tree.
+    fn root(&mut self) -> eyre::Result<[u8; 32]> {
+        let Some(namespace) = &mut self.namespace else {
+            return Ok(self
+                .canvases
+                .get(&DEFAULT_CANVAS)
+                .map(PartialTree::root)
+                .unwrap_or_default());
+        };
+        let scheme = namespace.scheme();
+        for (canvas, tree) in &self.canvases {
+            namespace.set(
+                &canvas_key(*canvas),
+                canvas_leaf(scheme, *canvas, &tree.root()),
+            )?;
+        }
+        Ok(namespace.root())
+    }
+}
+
+/// Serves the accounts of a `Witness`. Reading an address the witness does not cover is an
+/// error, so an incomplete witness cannot pass for missing accounts.
+pub struct WitnessDB {
+    version: CommitmentVersion,
+    canvases: HashMap<Address, u64>,
+    accounts: RefCell<HashMap<Address, Option<Account>>>,
+    untouched: Vec<[u8; 32]>,
+    untouched_addresses: Vec<Address>,
+    sparse: Option<RefCell<PartialState>>,
+    data_roots: RefCell<DataRoots>,
+}
+
+impl WitnessDB {
+    pub fn state_root(&self) -> [u8; 32] {
+        let accounts = self.accounts.borrow();
+        if let Some(tree) = &self.sparse {
+            let mut tree = tree.borrow_mut();
+            let mut data_roots = self.data_roots.borrow_mut();
+            for (address, account) in accounts.iter() {
+                let leaf = account
+                    .as_ref()
+                    .map(|account| {
+                        leaf_hash_cached(self.version, address, account, &mut data_roots)
+                    })
+                    .unwrap_or_default();
+                let canvas = self.canvases.get(address).copied().unwrap_or_default();
+                tree.set(canvas, address, leaf)
+                    .expect("the witness proves every account it holds");
+            }
+            let root = tree
+                .root()
+                .expect("the witness proves every canvas it touches");
+            return match root == [0; 32] {
+                true => empty_root(self.version),
+                false => root,
+            };
+        }
+
+        let touched = accounts.iter().filter_map(|(address, account)| {
+            Some(Leaf {
+                hash: leaf_hash(self.version, address, account.as_ref()?),
+                account: *address,
+            })
+        });
+
+        // Hash ordered trees need no addresses, which witnesses from before them may lack.
+        let root = match self.version.sorts_by_address() {
+            true => {
+                let untouched = self.untouched.iter().zip(&self.untouched_addresses);
+                let leaves = untouched
+                    .map(|(hash, address)| Leaf {
+                        hash: *hash,
+                        account: *address,
+                    })
+                    .chain(touched)
+                    .collect();
+                state_root_of(self.version, leaves)
+            }
+            false => root_of(
+                self.untouched
+                    .iter()
+                    .copied()
+                    .chain(touched.map(|leaf| leaf.hash))
+                    .collect(),
+            ),
+        };
+
+        root.unwrap_or(empty_root(self.version))
+    }
+
+    pub fn accounts_root(&self) -> [u8; 32] {
+        let accounts = self.accounts.borrow();
+        let touched = accounts
+            .iter()
+            .filter(|(_, account)| account.is_some())
+            .map(|(address, _)| *address);
+
+        AddressIndex::new(self.untouched_addresses.iter().copied().chain(touched)).root()
+    }
+}
+
+impl AccountDB for WitnessDB {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
+        match self.accounts.borrow().get(address) {
+            Some(account) => Ok(account.clone().unwrap_or_default()),
+            None => Err(CanvasError::NotInWitness(*address)),
+        }
+    }
+
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        match self.accounts.borrow().get(address) {
+            Some(account) => Ok(account.clone()),
+            None => Err(CanvasError::NotInWitness(*address)),
+        }
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
+        self.accounts
+            .borrow_mut()
+            .insert(*address, Some(account.clone()));
+        Ok(())
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        self.accounts.borrow_mut().insert(*address, None);
+        Ok(())
+    }
+}
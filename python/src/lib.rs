@@ -0,0 +1,194 @@
+use std::{fmt, path::PathBuf};
+
+use alloy_primitives::Address;
+use program::{
+    codec::decode_transactions, edit::EditOp, recover_address_from_tx, signing::SigningDomain,
+    Account, ContributorStats, InMemoryDB,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+fn value_error(err: impl fmt::Display) -> PyErr {
+    PyValueError::new_err(format!("{:#}", err))
+}
+
+// Hands `value` to Python as the dicts, lists and strings its JSON form parses into.
+fn to_py(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(value_error)?;
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
+
+fn parse_address(address: &str) -> PyResult<Address> {
+    address.parse().map_err(value_error)
+}
+
+// One row of `State.accounts`.
+#[derive(Serialize)]
+struct AccountRecord<'a> {
+    address: Address,
+    #[serde(flatten)]
+    account: &'a Account,
+}
+
+// One row of `State.contributions`.
+#[derive(Serialize)]
+struct ContributionRecord {
+    account: Address,
+    contributor: Address,
+    #[serde(flatten)]
+    stats: ContributorStats,
+}
+
+// One row of `decode_batch`.
+#[derive(Serialize)]
+struct TransactionRecord {
+    // `None` when the signature does not recover under the domain.
+    sender: Option<Address>,
+    to: Address,
+    version: u8,
+    nonce: u64,
+    ops: Vec<EditOp>,
+    extra: String,
+    valid_until_batch: u64,
+    valid_until_timestamp: u64,
+    canvas: u64,
+    access_list: Vec<Address>,
+}
+
+/// The accounts of a state snapshot, as `InMemoryDB::snapshot_accounts` writes them.
+#[pyclass(unsendable)]
+struct State {
+    db: InMemoryDB,
+}
+
+impl State {
+    // Accounts in address order, so rows come out the same every time.
+    fn sorted(&self) -> Vec<(Address, Account)> {
+        let mut accounts: Vec<(Address, Account)> = self
+            .db
+            .accounts
+            .borrow()
+            .iter()
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        accounts.sort_by_key(|(address, _)| *address);
+        accounts
+    }
+}
+
+#[pymethods]
+impl State {
+    /// Parses snapshot bytes of any snapshot version.
+    #[staticmethod]
+    fn from_snapshot(snapshot: &[u8]) -> PyResult<Self> {
+        Ok(Self {
+            db: InMemoryDB::from_snapshot(snapshot).map_err(value_error)?,
+        })
+    }
+
+    /// Reads and parses a snapshot file.
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        let snapshot = std::fs::read(&path)
+            .map_err(|err| value_error(format!("Failed to read {}: {}", path.display(), err)))?;
+        Self::from_snapshot(&snapshot)
+    }
+
+    fn __len__(&self) -> usize {
+        self.db.accounts.borrow().len()
+    }
+
+    /// Every address with an account, in order.
+    fn addresses(&self) -> Vec<String> {
+        self.sorted()
+            .into_iter()
+            .map(|(address, _)| address.to_string())
+            .collect()
+    }
+
+    /// The account at `address` as a dict, or `None` if it has none.
+    fn account(&self, py: Python<'_>, address: &str) -> PyResult<PyObject> {
+        let address = parse_address(address)?;
+        match self.db.accounts.borrow().get(&address) {
+            Some(account) => to_py(py, account),
+            None => Ok(py.None()),
+        }
+    }
+
+    /// One dict per account with its address, for `pandas.DataFrame`. Balances are hex strings.
+    fn accounts(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let accounts = self.sorted();
+        let records: Vec<AccountRecord> = accounts
+            .iter()
+            .map(|(address, account)| AccountRecord {
+                address: *address,
+                account,
+            })
+            .collect();
+        to_py(py, &records)
+    }
+
+    /// One dict per contributor of each account: `account`, `contributor`, `edits`, `inserted`
+    /// and `deleted`, for `pandas.DataFrame`.
+    fn contributions(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let records: Vec<ContributionRecord> = self
+            .sorted()
+            .into_iter()
+            .flat_map(|(address, account)| {
+                account
+                    .contributions
+                    .into_iter()
+                    .map(move |(contributor, stats)| ContributionRecord {
+                        account: address,
+                        contributor,
+                        stats,
+                    })
+            })
+            .collect();
+        to_py(py, &records)
+    }
+}
+
+/// Decodes a batch's `_transactionData` into one dict per transaction, with the sender recovered
+/// under the signing domain of `chain_id` and `rollup`.
+#[pyfunction]
+#[pyo3(signature = (transaction_data, dictionary=None, chain_id=1, rollup=None))]
+fn decode_batch(
+    py: Python<'_>,
+    transaction_data: &[u8],
+    dictionary: Option<&[u8]>,
+    chain_id: u64,
+    rollup: Option<&str>,
+) -> PyResult<PyObject> {
+    let domain = SigningDomain {
+        chain_id,
+        verifying_contract: rollup.map(parse_address).transpose()?.unwrap_or_default(),
+    };
+    let records: Vec<TransactionRecord> = decode_transactions(transaction_data, dictionary)
+        .map_err(value_error)?
+        .into_iter()
+        .map(|signed| TransactionRecord {
+            sender: recover_address_from_tx(&signed, &domain).ok(),
+            ops: signed.tx.edit_ops(),
+            to: signed.tx.to,
+            version: signed.tx.version,
+            nonce: signed.tx.nonce,
+            extra: signed.tx.extra,
+            valid_until_batch: signed.tx.valid_until_batch,
+            valid_until_timestamp: signed.tx.valid_until_timestamp,
+            canvas: signed.tx.canvas,
+            access_list: signed.tx.access_list,
+        })
+        .collect();
+    to_py(py, &records)
+}
+
+#[pymodule]
+fn canvas(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<State>()?;
+    m.add_function(wrap_pyfunction!(decode_batch, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Executes batches of several shapes in the SP1 program and reports their cycles next to what
+//! `CycleModel` estimates for them. Needs the `prove` feature and the ELF from `cargo prove
+//! build`: `cargo bench --features prove --bench cycles [-- --save <FILE>] [-- --baseline
+//! <FILE>]`. With a baseline from an earlier `--save`, it fails if any batch takes more than 5%
+//! more cycles than it did.
+
+use std::{collections::BTreeMap, fs, process::ExitCode};
+
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use program::{
+    batch::encode_batch,
+    builder::TxBuilder,
+    chunked::{self, Encoding},
+    config::CanvasConfig,
+    cost::CycleModel,
+    limits::RateLimitState,
+    signing::signing_hash,
+    CanvasProcessor, InMemoryDB, Input, SignedTransaction,
+};
+use sp1_sdk::{ProverClient, SP1Stdin};
+
+const ELF: &str = "../program/elf/riscv32im-succinct-zkvm-elf";
+// How much slower than the baseline a batch may get, in percent.
+const TOLERANCE: f64 = 5.0;
+
+fn signed(signer: &PrivateKeySigner, builder: TxBuilder) -> SignedTransaction {
+    let tx = builder.build().unwrap();
+    let signature = signer
+        .sign_hash_sync(&signing_hash(&tx, &CanvasConfig::default().domain))
+        .unwrap();
+
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }
+}
+
+// `transactions` senders, each with `bytes` bytes of data in their own account, and a batch in
+// which each of them makes `ops` edits to it.
+fn input(transactions: usize, ops: usize, bytes: usize) -> (Input, Vec<SignedTransaction>) {
+    let signers: Vec<PrivateKeySigner> = (0..transactions)
+        .map(|_| PrivateKeySigner::random())
+        .collect();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    for signer in &signers {
+        processor
+            .apply_transaction(&signed(
+                signer,
+                TxBuilder::new(signer.address()).append("0f".repeat(bytes)),
+            ))
+            .unwrap();
+    }
+
+    let batch: Vec<SignedTransaction> = signers
+        .iter()
+        .map(|signer| {
+            let builder = (0..ops).fold(TxBuilder::new(signer.address()).nonce(1), |builder, i| {
+                builder.replace(i * 8 % (bytes * 2), 2, "a1")
+            });
+            signed(signer, builder)
+        })
+        .collect();
+    let config = CanvasConfig::default();
+
+    let input = Input {
+        transaction_data: encode_batch(&batch, config.codec, None).unwrap(),
+        db,
+        config,
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: RateLimitState::default(),
+        witness: None,
+        dictionary: None,
+    };
+    (input, batch)
+}
+
+fn cycles(elf: &[u8], input: &Input) -> u64 {
+    let mut stdin = SP1Stdin::new();
+    let mut encoded = Vec::new();
+    chunked::write_input(input, Encoding::Bincode, &mut encoded).unwrap();
+    for buffer in encoded.chunks(chunked::FRAME_BYTES) {
+        stdin.write_slice(buffer);
+    }
+    let (_, report) = ProverClient::new().execute(elf, stdin).run().unwrap();
+    report.total_instruction_count()
+}
+
+// The value following `flag` on the command line.
+fn arg(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn main() -> ExitCode {
+    let elf = fs::read(ELF).expect("build the program with `cargo prove build` first");
+    let model = CycleModel::default();
+    let shapes = [
+        ("1 tx, 1 op, 64B", 1, 1, 64),
+        ("1 tx, 128 ops, 4KiB", 1, 128, 4096),
+        ("16 txs, 1 op, 64B", 16, 1, 64),
+        ("16 txs, 16 ops, 1KiB", 16, 16, 1024),
+        ("64 txs, 4 ops, 16KiB", 64, 4, 16 * 1024),
+    ];
+
+    let mut report = BTreeMap::new();
+    println!(
+        "{:<24} {:>14} {:>14} {:>8}",
+        "batch", "cycles", "estimate", "ratio"
+    );
+    for (name, transactions, ops, bytes) in shapes {
+        let (input, batch) = input(transactions, ops, bytes);
+        let measured = cycles(&elf, &input);
+        let estimate = model.batch + batch.iter().map(|tx| model.transaction(tx)).sum::<u64>();
+        println!(
+            "{:<24} {:>14} {:>14} {:>8.2}",
+            name,
+            measured,
+            estimate,
+            measured as f64 / estimate as f64
+        );
+        report.insert(name.to_string(), measured);
+    }
+
+    if let Some(path) = arg("--save") {
+        fs::write(&path, serde_json::to_string_pretty(&report).unwrap()).unwrap();
+        println!("Saved to {}", path);
+    }
+
+    let Some(path) = arg("--baseline") else {
+        return ExitCode::SUCCESS;
+    };
+    let baseline: BTreeMap<String, u64> =
+        serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    let mut regressed = false;
+    for (name, measured) in &report {
+        let Some(before) = baseline.get(name) else {
+            continue;
+        };
+        let change = (*measured as f64 / *before as f64 - 1.0) * 100.0;
+        if change > TOLERANCE {
+            println!(
+                "{}: {} cycles, {:+.1}% on {}",
+                name, measured, change, before
+            );
+            regressed = true;
+        }
+    }
+    match regressed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,63 @@
+//! Times the state root of a large canvas with leaf hashing on one thread against the rayon
+//! hashing of the `parallel` feature the script builds the program with.
+//! `cargo bench --bench state_root [-- <accounts>]`, 100k accounts by default.
+
+use std::time::{Duration, Instant};
+
+use alloy::primitives::{Address, U256};
+use program::{
+    commitment::{leaf_hash, leaf_hashes, CommitmentVersion},
+    state_tree::root_of,
+    Account,
+};
+
+const VERSION: CommitmentVersion = CommitmentVersion::V3;
+
+fn canvas(size: usize) -> Vec<(Address, Account)> {
+    (0..size as u64)
+        .map(|i| {
+            let address = Address::left_padding_from(&i.to_be_bytes());
+            let account = Account {
+                nonce: i,
+                data: format!("{:064x}", i),
+                contributors: vec![address],
+                balance: U256::from(i),
+                ..Account::default()
+            };
+            (address, account)
+        })
+        .collect()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() {
+    let size = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let canvas = canvas(size);
+    let accounts: Vec<(&Address, &Account)> = canvas.iter().map(|(a, b)| (a, b)).collect();
+
+    let (serial, serial_time) = time(|| {
+        let leaves = accounts
+            .iter()
+            .map(|(address, account)| leaf_hash(VERSION, address, account))
+            .collect();
+        root_of(leaves)
+    });
+    let (parallel, parallel_time) = time(|| root_of(leaf_hashes(VERSION, &accounts)));
+    assert_eq!(serial, parallel);
+
+    println!("{} accounts", size);
+    println!("one thread: {:?}", serial_time);
+    println!(
+        "rayon:      {:?} ({:.1}x)",
+        parallel_time,
+        serial_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+}
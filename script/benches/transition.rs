@@ -0,0 +1,177 @@
+//! Criterion benchmarks of the code every batch runs through, natively: applying a transaction,
+//! the state root, encoding and decoding `_transactionData`, and recovering senders.
+//! `cargo bench --bench transition [-- <filter>]`; criterion compares each run with the last one
+//! and reports what got slower.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use alloy::{
+    primitives::{Address, U256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use program::{
+    builder::TxBuilder,
+    codec::{decode_transactions, encode_transactions, Codec},
+    commitment::CommitmentVersion,
+    config::CanvasConfig,
+    recover_address_from_tx,
+    recovery::recover_addresses_batch,
+    signing::signing_hash,
+    Account, CanvasProcessor, InMemoryDB, SignedTransaction,
+};
+
+fn signed(signer: &PrivateKeySigner, builder: TxBuilder) -> SignedTransaction {
+    let tx = builder.build().unwrap();
+    let signature = signer
+        .sign_hash_sync(&signing_hash(&tx, &CanvasConfig::default().domain))
+        .unwrap();
+
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }
+}
+
+// `size` accounts with 32 bytes of data each.
+fn accounts(size: usize) -> InMemoryDB {
+    let accounts: HashMap<Address, Account> = (0..size as u64)
+        .map(|i| {
+            let address = Address::left_padding_from(&i.to_be_bytes());
+            let account = Account {
+                nonce: i,
+                data: format!("{:064x}", i),
+                contributors: vec![address],
+                balance: U256::from(i),
+                ..Account::default()
+            };
+            (address, account)
+        })
+        .collect();
+
+    InMemoryDB {
+        accounts: RefCell::new(accounts),
+        ..Default::default()
+    }
+}
+
+// A transaction from each of `signers` appending `ops` times to their own account.
+fn batch(signers: &[PrivateKeySigner], ops: usize) -> Vec<SignedTransaction> {
+    signers
+        .iter()
+        .map(|signer| {
+            let builder = (0..ops).fold(TxBuilder::new(signer.address()), |builder, _| {
+                builder.append("a1")
+            });
+            signed(signer, builder)
+        })
+        .collect()
+}
+
+fn apply_transaction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_transaction");
+    let signer = PrivateKeySigner::random();
+    let own = signer.address();
+
+    for bytes in [64, 1024, 16 * 1024] {
+        let db = InMemoryDB::default();
+        CanvasProcessor::new(&db)
+            .apply_transaction(&signed(
+                &signer,
+                TxBuilder::new(own).append("0f".repeat(bytes)),
+            ))
+            .unwrap();
+
+        for ops in [1, 16, 128] {
+            let tx = signed(
+                &signer,
+                (0..ops).fold(TxBuilder::new(own).nonce(1), |builder, i| {
+                    builder.replace(i * 8 % (bytes * 2), 2, "a1")
+                }),
+            );
+            group.throughput(Throughput::Elements(ops as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}B", bytes), ops),
+                &tx,
+                |b, tx| {
+                    b.iter_batched(
+                        || CanvasProcessor::new(db.clone()),
+                        |mut processor| processor.apply_transaction(tx).unwrap(),
+                        BatchSize::SmallInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn generate_state_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_state_root");
+    group.sample_size(10);
+
+    for version in [CommitmentVersion::V3, CommitmentVersion::V10] {
+        let config = CanvasConfig {
+            commitment: version,
+            ..CanvasConfig::default()
+        };
+        for size in [1_000, 10_000, 100_000] {
+            let db = accounts(size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_function(BenchmarkId::new(format!("{:?}", version), size), |b| {
+                b.iter_batched(
+                    || CanvasProcessor::with_config(&db, config),
+                    |processor| processor.generate_state_root().unwrap(),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn batch_codec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_codec");
+    let signers: Vec<PrivateKeySigner> = (0..256).map(|_| PrivateKeySigner::random()).collect();
+    let transactions = batch(&signers, 8);
+
+    for codec in [Codec::None, Codec::Zlib, Codec::Zstd, Codec::Brotli] {
+        let encoded = encode_transactions(&transactions, codec, None).unwrap();
+        group.throughput(Throughput::Bytes(encoded.len() as u64));
+        group.bench_function(BenchmarkId::new("encode", format!("{:?}", codec)), |b| {
+            b.iter(|| encode_transactions(&transactions, codec, None).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("decode", format!("{:?}", codec)), |b| {
+            b.iter(|| decode_transactions(&encoded, None).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn signature_recovery(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature_recovery");
+    let domain = CanvasConfig::default().domain;
+    let signers: Vec<PrivateKeySigner> = (0..256).map(|_| PrivateKeySigner::random()).collect();
+    let transactions = batch(&signers, 1);
+
+    group.bench_function("one", |b| {
+        b.iter(|| recover_address_from_tx(&transactions[0], &domain).unwrap())
+    });
+    group.throughput(Throughput::Elements(transactions.len() as u64));
+    group.bench_function("batch", |b| {
+        b.iter(|| recover_addresses_batch(&transactions, &domain))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    apply_transaction,
+    generate_state_root,
+    batch_codec,
+    signature_recovery
+);
+criterion_main!(benches);
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use alloy::primitives::Address;
+use program::{
+    receipt::{Receipt, ReceiptStatus},
+    ContributorStats,
+};
+use serde::Serialize;
+
+use crate::{history::HistoryStore, serve::LeaderboardEntry};
+
+/// What one batch did. `inserted` and `deleted` count chars of account data, as receipts do.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchActivity {
+    pub index: u64,
+    pub block_number: u64,
+    pub transactions: usize,
+    pub succeeded: usize,
+    /// Senders with a successful transaction in the batch.
+    pub senders: usize,
+    pub inserted: u64,
+    pub deleted: u64,
+}
+
+/// What one sender wrote across every account, in successful transactions.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriterActivity {
+    pub sender: Address,
+    #[serde(flatten)]
+    pub stats: ContributorStats,
+}
+
+/// Totals over every recorded batch.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySummary {
+    pub batches: usize,
+    pub transactions: usize,
+    pub succeeded: usize,
+    pub unique_senders: usize,
+    pub inserted: u64,
+    pub deleted: u64,
+}
+
+// The successful receipts of every recorded batch.
+fn succeeded(history: &HistoryStore) -> impl Iterator<Item = &Receipt> {
+    history
+        .receipts()
+        .flat_map(|(_, _, receipts)| receipts)
+        .filter(|receipt| receipt.status == ReceiptStatus::Success)
+}
+
+fn add(stats: &mut ContributorStats, receipt: &Receipt) {
+    stats.edits += 1;
+    stats.inserted += receipt.inserted;
+    stats.deleted += receipt.deleted;
+}
+
+// Most edits first, then most units inserted, ties broken by address, as `canvas_getLeaderboard`
+// ranks them.
+fn ranked(
+    stats: BTreeMap<Address, ContributorStats>,
+    limit: usize,
+) -> Vec<(Address, ContributorStats)> {
+    let mut ranked: Vec<_> = stats.into_iter().collect();
+    ranked.sort_by(|(a, a_stats), (b, b_stats)| {
+        (b_stats.edits, b_stats.inserted)
+            .cmp(&(a_stats.edits, a_stats.inserted))
+            .then(a.cmp(b))
+    });
+    ranked.truncate(limit);
+    ranked
+}
+
+/// The `limit` senders with the most successful edits to `account` in the recorded batches.
+/// Unlike `canvas_getLeaderboard`, which reads the account's own counts, this counts receipts, so
+/// it covers only the batches the node replayed.
+pub fn top_contributors(
+    history: &HistoryStore,
+    account: &Address,
+    limit: usize,
+) -> Vec<LeaderboardEntry> {
+    let mut stats = BTreeMap::<Address, ContributorStats>::new();
+    for receipt in succeeded(history).filter(|receipt| receipt.to == *account) {
+        add(stats.entry(receipt.from).or_default(), receipt);
+    }
+
+    ranked(stats, limit)
+        .into_iter()
+        .map(|(contributor, stats)| LeaderboardEntry { contributor, stats })
+        .collect()
+}
+
+/// The `limit` senders with the most successful edits across every account.
+pub fn top_writers(history: &HistoryStore, limit: usize) -> Vec<WriterActivity> {
+    let mut stats = BTreeMap::<Address, ContributorStats>::new();
+    for receipt in succeeded(history) {
+        add(stats.entry(receipt.from).or_default(), receipt);
+    }
+
+    ranked(stats, limit)
+        .into_iter()
+        .map(|(sender, stats)| WriterActivity { sender, stats })
+        .collect()
+}
+
+/// Up to `limit` batches from batch `from` on, in batch order.
+pub fn batch_activity(history: &HistoryStore, from: u64, limit: usize) -> Vec<BatchActivity> {
+    history
+        .receipts()
+        .filter(|(index, _, _)| *index >= from)
+        .take(limit)
+        .map(|(index, block_number, receipts)| {
+            let succeeded: Vec<&Receipt> = receipts
+                .iter()
+                .filter(|receipt| receipt.status == ReceiptStatus::Success)
+                .collect();
+            BatchActivity {
+                index,
+                block_number,
+                transactions: receipts.len(),
+                succeeded: succeeded.len(),
+                senders: succeeded
+                    .iter()
+                    .map(|receipt| receipt.from)
+                    .collect::<BTreeSet<_>>()
+                    .len(),
+                inserted: succeeded.iter().map(|receipt| receipt.inserted).sum(),
+                deleted: succeeded.iter().map(|receipt| receipt.deleted).sum(),
+            }
+        })
+        .collect()
+}
+
+pub fn summary(history: &HistoryStore) -> ActivitySummary {
+    let mut summary = ActivitySummary::default();
+    for (_, _, receipts) in history.receipts() {
+        summary.batches += 1;
+        summary.transactions += receipts.len();
+    }
+
+    let mut senders = BTreeSet::new();
+    for receipt in succeeded(history) {
+        summary.succeeded += 1;
+        summary.inserted += receipt.inserted;
+        summary.deleted += receipt.deleted;
+        senders.insert(receipt.from);
+    }
+    summary.unique_senders = senders.len();
+    summary
+}
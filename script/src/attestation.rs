@@ -0,0 +1,198 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::SocketAddr,
+    sync::Mutex,
+};
+
+use alloy::{
+    primitives::{Address, Signature, B256},
+    providers::{Provider, ProviderBuilder},
+    signers::{local::PrivateKeySigner, SignerSync},
+    sol,
+    sol_types::SolStruct,
+};
+use jsonrpsee::{server::Server, types::ErrorObjectOwned, RpcModule};
+use program::signing::SigningDomain;
+use serde::{Deserialize, Serialize};
+
+use crate::dev::rpc_error;
+
+sol! {
+    /// EIP-712 statement signed by a follower: "I derived `stateRoot` after batch `batch`".
+    struct BatchAttestation {
+        uint64 batch;
+        bytes32 stateRoot;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    pub batch: u64,
+    pub state_root: B256,
+    pub signer: Address,
+    pub signature: Signature,
+}
+
+fn attestation_hash(batch: u64, state_root: B256, domain: &SigningDomain) -> B256 {
+    BatchAttestation {
+        batch,
+        stateRoot: state_root,
+    }
+    .eip712_signing_hash(&domain.eip712_domain())
+}
+
+impl Attestation {
+    pub fn sign(
+        signer: &PrivateKeySigner,
+        batch: u64,
+        state_root: B256,
+        domain: &SigningDomain,
+    ) -> eyre::Result<Self> {
+        let signature = signer.sign_hash_sync(&attestation_hash(batch, state_root, domain))?;
+
+        Ok(Self {
+            batch,
+            state_root,
+            signer: signer.address(),
+            signature,
+        })
+    }
+
+    pub fn verify(&self, domain: &SigningDomain) -> eyre::Result<()> {
+        let recovered = self
+            .signature
+            .recover_address_from_prehash(&attestation_hash(self.batch, self.state_root, domain))?;
+
+        if recovered != self.signer {
+            return Err(eyre::eyre!(
+                "Attestation claims signer {} but was signed by {}",
+                self.signer,
+                recovered
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A state root attested for a batch and who attested it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootVotes {
+    pub state_root: B256,
+    pub signers: Vec<Address>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAttestations {
+    pub batch: u64,
+    pub roots: Vec<RootVotes>,
+}
+
+/// Collects follower attestations and surfaces batches for which followers derived different
+/// roots. A signer attesting two roots for the same batch shows up under both.
+pub struct AttestationPool {
+    domain: SigningDomain,
+    batches: BTreeMap<u64, BTreeMap<B256, BTreeSet<Address>>>,
+}
+
+impl AttestationPool {
+    pub fn new(domain: SigningDomain) -> Self {
+        Self {
+            domain,
+            batches: BTreeMap::new(),
+        }
+    }
+
+    /// Records a verified attestation and returns whether its batch is now in dispute.
+    pub fn submit(&mut self, attestation: Attestation) -> eyre::Result<bool> {
+        attestation.verify(&self.domain)?;
+
+        let roots = self.batches.entry(attestation.batch).or_default();
+        roots
+            .entry(attestation.state_root)
+            .or_default()
+            .insert(attestation.signer);
+
+        Ok(roots.len() > 1)
+    }
+
+    pub fn batch(&self, batch: u64) -> Option<BatchAttestations> {
+        self.batches
+            .get(&batch)
+            .map(|roots| Self::summarize(batch, roots))
+    }
+
+    pub fn disagreements(&self) -> Vec<BatchAttestations> {
+        self.batches
+            .iter()
+            .filter(|(_, roots)| roots.len() > 1)
+            .map(|(batch, roots)| Self::summarize(*batch, roots))
+            .collect()
+    }
+
+    fn summarize(batch: u64, roots: &BTreeMap<B256, BTreeSet<Address>>) -> BatchAttestations {
+        BatchAttestations {
+            batch,
+            roots: roots
+                .iter()
+                .map(|(state_root, signers)| RootVotes {
+                    state_root: *state_root,
+                    signers: signers.iter().copied().collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn rpc_module(pool: AttestationPool) -> eyre::Result<RpcModule<Mutex<AttestationPool>>> {
+    let mut module = RpcModule::new(Mutex::new(pool));
+
+    module.register_method("canvas_submitAttestation", |params, pool, _| {
+        let attestation: Attestation = params.one()?;
+        let (batch, signer) = (attestation.batch, attestation.signer);
+
+        let disputed = pool
+            .lock()
+            .unwrap()
+            .submit(attestation)
+            .map_err(rpc_error)?;
+        if disputed {
+            tracing::warn!(batch, %signer, "Followers disagree on the state root");
+        }
+
+        Ok::<_, ErrorObjectOwned>(disputed)
+    })?;
+
+    module.register_method("canvas_getAttestations", |params, pool, _| {
+        let batch: u64 = params.one()?;
+        Ok::<_, ErrorObjectOwned>(pool.lock().unwrap().batch(batch))
+    })?;
+
+    module.register_method("canvas_getDisagreements", |_, pool, _| {
+        Ok::<_, ErrorObjectOwned>(pool.lock().unwrap().disagreements())
+    })?;
+
+    Ok(module)
+}
+
+pub async fn run(addr: SocketAddr, domain: SigningDomain) -> eyre::Result<()> {
+    let server = Server::builder().build(addr).await?;
+    let handle = server.start(rpc_module(AttestationPool::new(domain))?);
+
+    tracing::info!(%addr, "Attestation aggregator listening");
+
+    handle.stopped().await;
+    Ok(())
+}
+
+/// Sends an attestation to an aggregator and returns whether its batch is in dispute.
+pub async fn publish(url: &str, attestation: &Attestation) -> eyre::Result<bool> {
+    let provider = ProviderBuilder::new().on_http(url.parse()?);
+
+    Ok(provider
+        .raw_request("canvas_submitAttestation".into(), (attestation,))
+        .await?)
+}
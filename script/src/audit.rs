@@ -0,0 +1,102 @@
+use alloy::{
+    primitives::B256,
+    providers::{Provider, ProviderBuilder},
+    sol_types::SolCall,
+};
+use program::{
+    batch,
+    public_values::{decode_public_values, ClaimedValues},
+};
+
+use crate::sync::{submitBatchWithProofCall, submitSignedBatchWithProofCall};
+
+/// The values a `submitBatchWithProof` or `submitSignedBatchWithProof` transaction claimed, and whether its transaction commit
+/// matches the batch data it posted.
+pub struct Submission {
+    pub claimed: ClaimedValues,
+    pub commit_matches: bool,
+}
+
+pub async fn fetch_submission(rpc_url: &str, tx_hash: B256) -> eyre::Result<Submission> {
+    let provider = ProviderBuilder::new().on_http(rpc_url.parse()?);
+    let tx = provider
+        .get_transaction_by_hash(tx_hash)
+        .await?
+        .ok_or_else(|| eyre::eyre!("Transaction {} not found", tx_hash))?;
+
+    let (public_values, transaction_data) =
+        match submitBatchWithProofCall::abi_decode(&tx.input, true) {
+            Ok(call) => (call._publicValuesBytes, call._transactionData),
+            Err(_) => {
+                let call =
+                    submitSignedBatchWithProofCall::abi_decode(&tx.input, true).map_err(|_| {
+                        eyre::eyre!("Transaction {} is not a batch submission", tx_hash)
+                    })?;
+                (call._publicValuesBytes, call._transactionData)
+            }
+        };
+
+    let claimed = decode_public_values(&public_values)?;
+    Ok(Submission {
+        commit_matches: claimed.transaction_commit == batch::commit(&transaction_data),
+        claimed,
+    })
+}
+
+pub fn print_claimed(claimed: &ClaimedValues) {
+    let optional =
+        |value: Option<B256>| value.map_or("not committed".to_string(), |v| v.to_string());
+
+    println!("Layout version:     {}", claimed.version);
+    println!("Initial state root: {}", claimed.initial_state_root);
+    println!("Final state root:   {}", claimed.final_state_root);
+    println!("Transaction commit: {}", claimed.transaction_commit);
+    println!("Receipts root:      {}", optional(claimed.receipts_root));
+    println!("Accounts root:      {}", optional(claimed.accounts_root));
+    println!("Previous batch:     {}", optional(claimed.previous_batch));
+    println!("Limits root:        {}", optional(claimed.limits_root));
+    println!(
+        "Batch index:        {}",
+        claimed
+            .batch_index
+            .map_or("not committed".to_string(), |index| index.to_string())
+    );
+    match (claimed.l1_block_number, claimed.l1_block_hash) {
+        (Some(number), Some(hash)) => println!("L1 block:           {} ({})", number, hash),
+        _ => println!("L1 block:           not committed"),
+    }
+    match (claimed.deposit_count, claimed.deposits_commit) {
+        (Some(count), Some(commit)) => println!("Deposits:           {} ({})", count, commit),
+        _ => println!("Deposits:           not committed"),
+    }
+    match (claimed.forced_count, claimed.forced_commit) {
+        (Some(count), Some(commit)) => println!("Forced txs:         {} ({})", count, commit),
+        _ => println!("Forced txs:         not committed"),
+    }
+    println!(
+        "Batch timestamp:    {}",
+        claimed
+            .batch_timestamp
+            .map_or("not committed".to_string(), |timestamp| timestamp
+                .to_string())
+    );
+    println!(
+        "Previous hash:      {}",
+        optional(claimed.previous_batch_hash)
+    );
+    println!("Batch hash:         {}", optional(claimed.batch_hash));
+    println!(
+        "Previous timestamp: {}",
+        claimed
+            .previous_batch_timestamp
+            .map_or("not committed".to_string(), |timestamp| timestamp
+                .to_string())
+    );
+    println!(
+        "Sequencer:          {}",
+        claimed
+            .sequencer
+            .map_or("not committed".to_string(), |sequencer| sequencer
+                .to_string())
+    );
+}
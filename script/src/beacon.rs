@@ -0,0 +1,118 @@
+use alloy::{
+    consensus::BlobTransactionSidecar,
+    eips::eip4844::{env_settings::EnvKzgSettings, kzg_to_versioned_hash, Blob, Bytes48},
+    primitives::{Bytes, B256},
+    transports::http::reqwest::Client,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::OnceCell;
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Genesis {
+    #[serde(deserialize_with = "quoted")]
+    genesis_time: u64,
+}
+
+#[derive(Deserialize)]
+struct Spec {
+    #[serde(rename = "SECONDS_PER_SLOT", deserialize_with = "quoted")]
+    seconds_per_slot: u64,
+}
+
+#[derive(Deserialize)]
+struct Sidecar {
+    blob: Bytes,
+    kzg_commitment: Bytes,
+    kzg_proof: Bytes,
+}
+
+// The beacon API sends numbers as strings.
+fn quoted<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Fetches blob sidecars from a beacon node. Nodes prune blobs after about 18 days, so replaying
+/// older blob batches needs an archive such as a supernode or a blob explorer's beacon API.
+pub struct BlobFetcher {
+    client: Client,
+    url: String,
+    // Genesis time and seconds per slot, fetched on first use.
+    clock: OnceCell<(u64, u64)>,
+}
+
+impl BlobFetcher {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            clock: OnceCell::new(),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> eyre::Result<T> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json::<Response<T>>().await?.data)
+    }
+
+    async fn slot(&self, timestamp: u64) -> eyre::Result<u64> {
+        let (genesis_time, seconds_per_slot) = *self
+            .clock
+            .get_or_try_init(|| async {
+                let genesis: Genesis = self.get("/eth/v1/beacon/genesis").await?;
+                let spec: Spec = self.get("/eth/v1/config/spec").await?;
+                Ok::<_, eyre::Report>((genesis.genesis_time, spec.seconds_per_slot))
+            })
+            .await?;
+
+        timestamp
+            .checked_sub(genesis_time)
+            .map(|elapsed| elapsed / seconds_per_slot)
+            .ok_or_else(|| eyre::eyre!("Block at {} is before the beacon genesis", timestamp))
+    }
+
+    /// The blobs with `versioned_hashes`, in that order, of the block at `timestamp`. Each is
+    /// checked against its KZG commitment, so a beacon node cannot serve other data.
+    pub async fn blobs(
+        &self,
+        timestamp: u64,
+        versioned_hashes: &[B256],
+    ) -> eyre::Result<Vec<Blob>> {
+        let slot = self.slot(timestamp).await?;
+        let sidecars: Vec<Sidecar> = self
+            .get(&format!("/eth/v1/beacon/blob_sidecars/{}", slot))
+            .await?;
+
+        let mut sidecar = BlobTransactionSidecar::default();
+        for hash in versioned_hashes {
+            let found = sidecars
+                .iter()
+                .find(|s| kzg_to_versioned_hash(&s.kzg_commitment) == *hash)
+                .ok_or_else(|| eyre::eyre!("Slot {} has no blob {}", slot, hash))?;
+
+            sidecar.blobs.push(Blob::try_from(found.blob.as_ref())?);
+            sidecar
+                .commitments
+                .push(Bytes48::try_from(found.kzg_commitment.as_ref())?);
+            sidecar
+                .proofs
+                .push(Bytes48::try_from(found.kzg_proof.as_ref())?);
+        }
+        sidecar
+            .validate(versioned_hashes, EnvKzgSettings::Default.get())
+            .map_err(|err| eyre::eyre!("Invalid blob at slot {}: {}", slot, err))?;
+
+        Ok(sidecar.blobs)
+    }
+}
@@ -0,0 +1,180 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use alloy::primitives::{Address, B256};
+use program::{bridge::Deposit, SignedTransaction};
+use serde::{Deserialize, Serialize};
+
+use crate::sync::PostedBatch;
+
+const COVERAGE: &str = "synced.json";
+
+/// Everything the rollup queued and posted in one L1 block, each in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedBlock {
+    pub deposits: Vec<Deposit>,
+    pub forced_txs: Vec<(u64, SignedTransaction)>,
+    pub batches: Vec<PostedBatch>,
+}
+
+// The blocks the cache holds every rollup event of.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Coverage {
+    rollup: Address,
+    from_block: u64,
+    to_block: u64,
+    to_block_hash: B256,
+}
+
+/// What `sync` fetched from L1, for replaying without a provider: a `block_<number>.json` per
+/// block the rollup did anything in, and `synced.json` with the range of blocks covered. Blob
+/// batches are stored with their transaction data, so no beacon node is needed either.
+pub struct BatchCache {
+    dir: PathBuf,
+}
+
+impl BatchCache {
+    pub fn new(dir: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Replaces what the cache holds from `from_block` on with `blocks`, synced up to
+    /// `to_block`. A range that does not continue the covered one, or another rollup's, starts
+    /// the cache over. A crash part way leaves the cache empty for the next sync to fill.
+    pub fn record(
+        &self,
+        rollup: &Address,
+        from_block: u64,
+        to_block: u64,
+        to_block_hash: B256,
+        blocks: &BTreeMap<u64, CachedBlock>,
+    ) -> eyre::Result<()> {
+        let continued = self.coverage()?.filter(|coverage| {
+            coverage.rollup == *rollup
+                && coverage.from_block <= from_block
+                && from_block <= coverage.to_block + 1
+        });
+        let path = self.dir.join(COVERAGE);
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
+        // Blocks synced again may have been reorged since.
+        let first_stale = match &continued {
+            Some(_) => from_block,
+            None => 0,
+        };
+        for (number, path) in self.block_files()? {
+            if number >= first_stale {
+                fs::remove_file(path)?;
+            }
+        }
+        for (number, block) in blocks {
+            fs::write(self.block_path(*number), serde_json::to_vec(block)?)?;
+        }
+
+        let coverage = Coverage {
+            rollup: *rollup,
+            from_block: continued.map_or(from_block, |coverage| coverage.from_block),
+            to_block,
+            to_block_hash,
+        };
+        fs::write(path, serde_json::to_vec_pretty(&coverage)?)?;
+        Ok(())
+    }
+
+    /// The first and last block cached for `rollup`, and the hash of the last.
+    pub fn range(&self, rollup: &Address) -> eyre::Result<(u64, u64, B256)> {
+        match self.coverage()? {
+            Some(coverage) if coverage.rollup == *rollup => Ok((
+                coverage.from_block,
+                coverage.to_block,
+                coverage.to_block_hash,
+            )),
+            Some(coverage) => Err(eyre::eyre!(
+                "Cache in {} holds rollup {}, not {}",
+                self.dir.display(),
+                coverage.rollup,
+                rollup
+            )),
+            None => Err(eyre::eyre!(
+                "Nothing cached in {}, sync once without --offline",
+                self.dir.display()
+            )),
+        }
+    }
+
+    /// The cached blocks in `from_block..=to_block`, which all have to be covered.
+    pub fn blocks_in(
+        &self,
+        rollup: &Address,
+        from_block: u64,
+        to_block: u64,
+    ) -> eyre::Result<BTreeMap<u64, CachedBlock>> {
+        let (first, last, _) = self.range(rollup)?;
+        if from_block < first || to_block > last {
+            return Err(eyre::eyre!(
+                "Blocks {}..={} are not cached, only {}..={}",
+                from_block,
+                to_block,
+                first,
+                last
+            ));
+        }
+
+        let mut blocks = BTreeMap::new();
+        for (number, path) in self.block_files()? {
+            if (from_block..=to_block).contains(&number) {
+                blocks.insert(number, serde_json::from_slice(&fs::read(path)?)?);
+            }
+        }
+        Ok(blocks)
+    }
+
+    /// The hash of block `number`, known for the last covered block and those with a batch.
+    pub fn block_hash(&self, rollup: &Address, number: u64) -> eyre::Result<Option<B256>> {
+        let (_, last, hash) = self.range(rollup)?;
+        if number == last {
+            return Ok(Some(hash));
+        }
+
+        let path = self.block_path(number);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let block: CachedBlock = serde_json::from_slice(&fs::read(path)?)?;
+        Ok(block.batches.first().map(|batch| batch.block_hash))
+    }
+
+    fn coverage(&self) -> eyre::Result<Option<Coverage>> {
+        let path = self.dir.join(COVERAGE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+    }
+
+    fn block_path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("block_{}.json", number))
+    }
+
+    // Every cached block file with its block number.
+    fn block_files(&self) -> eyre::Result<Vec<(u64, PathBuf)>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let number = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("block_")?.strip_suffix(".json"))
+                .and_then(|number| number.parse::<u64>().ok());
+            if let Some(number) = number {
+                files.push((number, path));
+            }
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,47 @@
+use alloy::{
+    primitives::{Bytes, B256},
+    providers::{Provider, ProviderBuilder},
+};
+use program::limits::RateLimitState;
+use serde::{Deserialize, Serialize};
+
+/// The state right after a finalized batch, enough to follow the chain from there without
+/// replaying the batches before it. `tx_hash` and `transaction_commit` locate the batch on L1,
+/// and the state is only trusted once it matches the root its submission published.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub batch: u64,
+    /// The L1 transaction that posted the batch.
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub transaction_commit: B256,
+    /// `batchHash` the batch's proof committed, zero for checkpoints taken before the chain.
+    #[serde(default)]
+    pub batch_hash: B256,
+    /// `batchTimestamp` the batch's proof committed, zero for checkpoints taken before it was
+    /// kept.
+    #[serde(default)]
+    pub timestamp: u64,
+    /// `InMemoryDB::snapshot_accounts` of the state after the batch.
+    pub snapshot: Bytes,
+    pub limits: RateLimitState,
+    /// Queue indices of the first deposit and forced transaction no batch up to this one
+    /// consumed.
+    pub next_deposit: u64,
+    pub next_forced: u64,
+    /// Applied-batch keys of the batches in `block_number` up to and including this one, which a
+    /// follower starting here skips when it rescans the block. Empty for checkpoints taken before
+    /// they were kept, which only skip their own batch.
+    #[serde(default)]
+    pub applied_batches: Vec<B256>,
+}
+
+/// Fetches the checkpoint a `serve` node at `url` offers.
+pub async fn fetch(url: &str) -> eyre::Result<Checkpoint> {
+    let provider = ProviderBuilder::new().on_http(url.parse()?);
+
+    Ok(provider
+        .raw_request("canvas_getCheckpoint".into(), ())
+        .await?)
+}
@@ -0,0 +1,648 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use alloy::primitives::{Address, B256};
+use clap::{Args, Parser, Subcommand};
+use program::{
+    codec::Codec, commitment::CommitmentVersion, config::FailurePolicy, fees::FeeSchedule,
+    genesis::Genesis, limits::RateLimit, TX_VERSION_DOMAIN,
+};
+use serde::Deserialize;
+
+use crate::{render, sync::SyncConfig};
+
+/// Read when `--config` is not given and it exists in the working directory.
+const DEFAULT_CONFIG: &str = "canvas.toml";
+const DEFAULT_RPC_URL: &str = "https://eth.merkle.io";
+
+/// Follows, proves and serves the Canvas rollup.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// TOML file with defaults for the chain options.
+    #[arg(long, env = "CANVAS_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Replay the rollup from L1 and print the final state root.
+    Sync {
+        #[command(flatten)]
+        chain: ChainArgs,
+        /// Keep applying new batches as they land.
+        #[arg(long)]
+        follow: bool,
+        /// Seconds between polls with `--follow`.
+        #[arg(long, default_value_t = 12)]
+        poll_interval: u64,
+        /// Sign an attestation of each derived state root with this key.
+        #[arg(long, env = "CANVAS_ATTEST_KEY")]
+        attest_key: Option<String>,
+        #[arg(long, default_value = "http://127.0.0.1:8546")]
+        attest_url: String,
+    },
+    /// Sync, then prove a pending batch.
+    Prove(ProveArgs),
+    /// Submit proof artifacts with the keys in `SUBMIT_KEYS`.
+    Submit {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(long)]
+        public_values: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long)]
+        transaction_data: PathBuf,
+    },
+    /// Sync, then serve the state over JSON-RPC.
+    Serve {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(long, default_value = "127.0.0.1:8547")]
+        rpc_addr: SocketAddr,
+        /// Do not resolve contributor ENS profiles.
+        #[arg(long)]
+        no_profiles: bool,
+        /// Seconds a resolved profile is cached.
+        #[arg(long, default_value_t = 3600)]
+        profile_ttl: u64,
+        /// Batches of account history to keep for queries at earlier batches. Keeps every batch
+        /// by default; state roots are kept for every batch regardless.
+        #[arg(long)]
+        history_depth: Option<u64>,
+        /// Keep applying new batches and push them to WebSocket subscribers.
+        #[arg(long)]
+        follow: bool,
+        /// Seconds between polls with `--follow`.
+        #[arg(long, default_value_t = 12)]
+        poll_interval: u64,
+        /// Take transactions for the next batch and sign their soft confirmations with this key.
+        #[arg(long, env = "CANVAS_SEQUENCER_KEY")]
+        sequencer_key: Option<String>,
+        /// Also serve the state over gRPC on this address. Needs the `grpc` feature.
+        #[arg(long)]
+        grpc_addr: Option<SocketAddr>,
+        /// Gossip with other sequencer replicas from this libp2p address, e.g.
+        /// `/ip4/0.0.0.0/tcp/9000`. Needs `--sequencer-key` and the `p2p` feature.
+        #[arg(long)]
+        gossip_listen: Option<String>,
+        /// A replica to dial on start. May be repeated.
+        #[arg(long = "gossip-peer")]
+        gossip_peers: Vec<String>,
+        /// A sequencer whose gossiped confirmations are kept. May be repeated.
+        #[arg(long = "gossip-sequencer")]
+        gossip_sequencers: Vec<Address>,
+        /// Postgres database whose lease decides which replica sequences; the others serve
+        /// reads until it lapses. Needs `--sequencer-key` and the `postgres` feature.
+        #[arg(long, env = "CANVAS_LEASE_URL")]
+        lease_url: Option<String>,
+        /// Seconds the lease lasts without renewal.
+        #[arg(long, default_value_t = 15)]
+        lease_ttl: u64,
+    },
+    /// Sync, then print an account, or decode a batch and check it against the synced state.
+    Inspect {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(required_unless_present_any = ["l1_tx", "batch_file"])]
+        address: Option<Address>,
+        /// Inspect the batches posted by this L1 transaction instead.
+        #[arg(long, conflicts_with_all = ["address", "batch_file"])]
+        l1_tx: Option<B256>,
+        /// Inspect the `_transactionData` in this file instead.
+        #[arg(long, conflicts_with = "address")]
+        batch_file: Option<PathBuf>,
+        /// Gas price in wei to cost a batch's calldata at, instead of the L1's current one.
+        #[arg(long, conflicts_with = "address")]
+        gas_price: Option<u128>,
+        /// Blob base fee in wei to cost a batch's blobs at, instead of the L1's current one.
+        #[arg(long, conflicts_with = "address")]
+        blob_gas_price: Option<u128>,
+    },
+    /// Sync, then print activity totals, the senders who wrote the most and the latest batches.
+    Stats {
+        #[command(flatten)]
+        chain: ChainArgs,
+        /// Also rank the contributors to this account.
+        #[arg(long)]
+        account: Option<Address>,
+        /// Senders, contributors and batches to list.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Sync, then draw an account's data as a PNG, or an SVG if `--out` ends in `.svg`.
+    Render {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(long)]
+        address: Address,
+        #[arg(long, default_value = "canvas.png")]
+        out: PathBuf,
+        /// Pixels per row.
+        #[arg(long, default_value_t = render::DEFAULT_WIDTH)]
+        width: usize,
+        /// Output pixels per side of each data pixel.
+        #[arg(long, default_value_t = 8)]
+        scale: u32,
+    },
+    /// Run a sequencer, mock prover and L1 in process.
+    Dev {
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        rpc_addr: SocketAddr,
+        #[command(flatten)]
+        codec: CodecArgs,
+        /// Order sealed batches take queued transactions in: `fifo`, `fee` or `round-robin`.
+        /// Defaults to `fifo`.
+        #[arg(long)]
+        ordering: Option<String>,
+    },
+    /// Run the dev node with its state saved on disk, queued transactions sealed on an interval
+    /// and batches pushed over WebSocket.
+    Devnet {
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        rpc_addr: SocketAddr,
+        /// Directory the state is saved in and loaded from.
+        #[arg(long, default_value = "devnet")]
+        state_dir: PathBuf,
+        /// Milliseconds between sealing the queued transactions into a batch.
+        #[arg(long, default_value_t = 1000)]
+        seal_interval: u64,
+        #[command(flatten)]
+        codec: CodecArgs,
+        /// Order sealed batches take queued transactions in: `fifo`, `fee` or `round-robin`.
+        /// Defaults to `fifo`.
+        #[arg(long)]
+        ordering: Option<String>,
+    },
+    /// Prove the compressed proofs of consecutive batches as one.
+    AggregateProofs {
+        #[command(flatten)]
+        chain: ChainArgs,
+        /// Artifact directories of `prove --compressed`, in batch order.
+        #[arg(required = true)]
+        batches: Vec<PathBuf>,
+        #[arg(long, default_value = "../program/elf/riscv32im-succinct-zkvm-elf")]
+        elf: PathBuf,
+        #[arg(long, default_value = "../program/elf/aggregate-elf")]
+        aggregate_elf: PathBuf,
+        /// Defaults to `aggregate` in the data dir.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Also submit the proof with the keys in `SUBMIT_KEYS`.
+        #[arg(long)]
+        submit: bool,
+    },
+    /// Collect follower attestations.
+    Aggregate {
+        #[arg(long, default_value = "127.0.0.1:8546")]
+        rpc_addr: SocketAddr,
+    },
+    /// Print the state root of a genesis file, the `initialStateRoot` of batch 0.
+    Genesis {
+        file: PathBuf,
+        #[arg(long)]
+        commitment: Option<u8>,
+    },
+    /// Strip the receipts and account diffs from the batch events in `--hook-dir` older than
+    /// the last `--keep` batches, keeping their state roots.
+    Prune {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(long)]
+        keep: u64,
+    },
+    /// Print the accounts that differ between two state snapshots as JSON.
+    StateDiff { before: PathBuf, after: PathBuf },
+    /// Sync, or read a state snapshot, then write the accounts as JSON.
+    ExportState {
+        #[command(flatten)]
+        chain: ChainArgs,
+        /// Export this `InMemoryDB::snapshot_accounts` file instead of syncing.
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+        #[arg(long, default_value = "state.json")]
+        out: PathBuf,
+    },
+    /// Write the accounts of a JSON state export as a state snapshot.
+    ImportState {
+        file: PathBuf,
+        #[arg(long, default_value = "state.bin")]
+        out: PathBuf,
+    },
+    /// Write the canonical test vectors.
+    GenVectors {
+        #[arg(long, default_value = "vectors")]
+        out: PathBuf,
+        /// Seed of the keys and batches generated after the fixed ones.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Batches to generate from the seed.
+        #[arg(long, default_value_t = 4)]
+        random_batches: usize,
+    },
+    /// Print the public values of a submission or of raw bytes.
+    DecodePublicValues {
+        #[command(flatten)]
+        chain: ChainArgs,
+        #[arg(long, required_unless_present = "hex")]
+        tx: Option<B256>,
+        #[arg(long)]
+        hex: Option<String>,
+    },
+    /// Print a signed transaction in hex.
+    ///
+    /// Signs with a Ledger under `--ledger`, else with key `--index` of `MNEMONIC`, else with
+    /// `SIGNER_KEY`.
+    SignTx(SignTxArgs),
+}
+
+#[derive(Args)]
+pub struct ProveArgs {
+    #[command(flatten)]
+    pub chain: ChainArgs,
+    /// One hex encoded `SignedTransaction` per line.
+    #[arg(long)]
+    pub batch: PathBuf,
+    /// Prove against the touched accounts only.
+    #[arg(long)]
+    pub witness: bool,
+    /// The sequencer the batch commits to as its builder, zero if not given.
+    #[arg(long, default_value_t = Address::ZERO)]
+    pub sequencer: Address,
+    /// Private key of the sequencer to sign the batch with, which then names its address as
+    /// the sequencer. A signed batch is submitted with `submitSignedBatchWithProof`.
+    #[arg(long, env = "CANVAS_SEQUENCER_KEY", conflicts_with = "sequencer")]
+    pub sequencer_key: Option<String>,
+    #[arg(long, default_value = "../program/elf/riscv32im-succinct-zkvm-elf")]
+    pub elf: PathBuf,
+    /// Defaults to `proof` in the data dir.
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+    /// Also submit the proof with the keys in `SUBMIT_KEYS`.
+    #[arg(long)]
+    pub submit: bool,
+    /// Write a compressed proof for `aggregate-proofs` instead of one the contract verifies.
+    #[arg(long, conflicts_with = "submit")]
+    pub compressed: bool,
+    /// Encode the accounts in the program input with borsh, which the program decodes in fewer
+    /// cycles than bincode.
+    #[arg(long)]
+    pub borsh: bool,
+}
+
+#[derive(Args)]
+pub struct SignTxArgs {
+    #[arg(long)]
+    pub to: Address,
+    #[arg(long, default_value_t = 0)]
+    pub nonce: u64,
+    #[arg(long, default_value_t = TX_VERSION_DOMAIN)]
+    pub version: u8,
+    /// JSON array of edit ops.
+    #[arg(long, default_value = "[]")]
+    pub ops: String,
+    #[arg(long, default_value = "")]
+    pub extra: String,
+    /// Last batch the transaction may be included in. Needs `--version 5`.
+    #[arg(long, default_value_t = 0)]
+    pub valid_until_batch: u64,
+    /// Latest batch timestamp, in Unix seconds, the transaction may be included at. Needs
+    /// `--version 5`.
+    #[arg(long, default_value_t = 0)]
+    pub valid_until_timestamp: u64,
+    /// The canvas to edit `--to` in. Needs `--version 6` for any but the default canvas 0.
+    #[arg(long, default_value_t = 0)]
+    pub canvas: u64,
+    /// An account the transaction may touch; repeat for each. Needs `--version 7`.
+    #[arg(long = "access-list")]
+    pub access_list: Vec<Address>,
+    #[arg(long, default_value_t = 1)]
+    pub chain_id: u64,
+    /// The verifying contract of the signing domain.
+    #[arg(long)]
+    pub rollup: Option<Address>,
+    #[arg(long, default_value_t = 0)]
+    pub index: u32,
+    #[arg(long)]
+    pub ledger: bool,
+}
+
+/// Where the rollup lives and how to sync it. Each option falls back to the config file and then
+/// to its default.
+#[derive(Args)]
+pub struct ChainArgs {
+    #[arg(long, env = "CANVAS_RPC_URL")]
+    pub rpc_url: Option<String>,
+    /// Provider to sync from when `--rpc-url` errs or falls behind. Repeat for more, tried in
+    /// order.
+    #[arg(long = "fallback-rpc-url")]
+    pub fallback_rpc_urls: Vec<String>,
+    /// Most requests per second sent to each provider while syncing. Unlimited by default.
+    #[arg(long)]
+    pub rpc_rate_limit: Option<u32>,
+    /// Fetch every submission from two providers and stop if their calldata differs.
+    #[arg(long)]
+    pub cross_check_providers: bool,
+    #[arg(long, env = "CANVAS_ROLLUP")]
+    pub rollup: Option<Address>,
+    /// Usually the deployment block of the rollup.
+    #[arg(long, env = "CANVAS_FROM_BLOCK")]
+    pub from_block: Option<u64>,
+    /// Defaults to the latest block.
+    #[arg(long)]
+    pub to_block: Option<u64>,
+    #[arg(long)]
+    pub page_size: Option<u64>,
+    #[arg(long)]
+    pub retries: Option<u32>,
+    /// L1 transactions fetched at once while syncing.
+    #[arg(long)]
+    pub fetch_concurrency: Option<usize>,
+    /// Commitment version to replay under.
+    #[arg(long)]
+    pub commitment: Option<u8>,
+    /// Accept signatures with a high `s`, which batches posted before they were rejected may
+    /// contain.
+    #[arg(long)]
+    pub high_s_signatures: bool,
+    /// Batches a sender waits between edits of one account, as the deployment enforces them.
+    /// Needs commitment version 9.
+    #[arg(long)]
+    pub edit_cooldown: Option<u64>,
+    /// Batches a sender can revert their last edit of an account for, as the deployment keeps
+    /// them. Needs commitment version 11.
+    #[arg(long)]
+    pub undo_window: Option<u64>,
+    /// Stop syncing at a batch its sequencer did not sign, as a deployment that requires
+    /// signatures does.
+    #[arg(long)]
+    pub require_sequencer_signature: bool,
+    /// Replay batches proven under `FailurePolicy::Skip`, whose failing transactions get a failed
+    /// receipt instead of failing the batch.
+    #[arg(long)]
+    pub skip_failed_transactions: bool,
+    /// Where outputs such as proof artifacts go by default.
+    #[arg(long, env = "CANVAS_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Where synced blocks are cached. Defaults to `cache` in the data directory.
+    #[arg(long, env = "CANVAS_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Replay from the cache alone, without reaching the RPC or beacon node.
+    #[arg(long)]
+    pub offline: bool,
+    /// Write a JSON event per applied batch to this directory.
+    #[arg(long)]
+    pub hook_dir: Option<PathBuf>,
+    /// Mirror the accounts and every applied batch into this Postgres database. Needs the
+    /// `postgres` feature.
+    #[arg(long, env = "CANVAS_POSTGRES_URL")]
+    pub postgres_url: Option<String>,
+    /// TOML or JSON file with the accounts before batch 0. Defaults to an empty state.
+    #[arg(long, env = "CANVAS_GENESIS")]
+    pub genesis: Option<PathBuf>,
+    /// Serve Prometheus metrics of the node at `/metrics` on this address.
+    #[arg(long, env = "CANVAS_METRICS_ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Beacon API to fetch blobs from, for batches posted in blobs.
+    #[arg(long, env = "CANVAS_BEACON_URL")]
+    pub beacon_url: Option<String>,
+    /// `serve` node to fetch a checkpoint from, to sync from instead of genesis.
+    #[arg(long, env = "CANVAS_CHECKPOINT_URL")]
+    pub checkpoint_url: Option<String>,
+    #[command(flatten)]
+    pub codec: CodecArgs,
+}
+
+/// How new batches are compressed. Posted batches decode in any codec, given the dictionary if
+/// they were compressed with one.
+#[derive(Args)]
+pub struct CodecArgs {
+    /// `zlib`, `none`, `zstd` or `brotli`. Defaults to `zlib`.
+    #[arg(long)]
+    pub codec: Option<String>,
+    /// Shared dictionary for `zstd`.
+    #[arg(long, env = "CANVAS_ZSTD_DICTIONARY")]
+    pub zstd_dictionary: Option<PathBuf>,
+}
+
+/// The config file. Every key is optional and named like the flag it stands in for.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub rpc_url: Option<String>,
+    pub fallback_rpc_urls: Option<Vec<String>>,
+    pub rpc_rate_limit: Option<u32>,
+    pub cross_check_providers: Option<bool>,
+    pub rollup: Option<Address>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub page_size: Option<u64>,
+    pub retries: Option<u32>,
+    pub fetch_concurrency: Option<usize>,
+    pub commitment: Option<u8>,
+    pub high_s_signatures: Option<bool>,
+    pub edit_cooldown: Option<u64>,
+    pub undo_window: Option<u64>,
+    pub require_sequencer_signature: Option<bool>,
+    pub skip_failed_transactions: Option<bool>,
+    pub data_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub offline: Option<bool>,
+    pub hook_dir: Option<PathBuf>,
+    pub postgres_url: Option<String>,
+    pub ordering: Option<String>,
+    pub codec: Option<String>,
+    pub zstd_dictionary: Option<PathBuf>,
+    pub genesis: Option<PathBuf>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub beacon_url: Option<String>,
+    pub checkpoint_url: Option<String>,
+    /// What `serve --sequencer-key` charges, reloaded on SIGHUP; see `node::NodeConfig`.
+    pub fees: Option<FeeSchedule>,
+    /// The rate limits `serve --sequencer-key` admits under, reloaded on SIGHUP.
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Config {
+    /// Reads `path`, or `canvas.toml` if there is one when no path is given.
+    pub fn load(path: Option<&Path>) -> eyre::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG).exists() => Path::new(DEFAULT_CONFIG),
+            None => return Ok(Self::default()),
+        };
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?;
+        Ok(toml::from_str(&text)?)
+    }
+}
+
+/// Chain options with flags, environment and the config file applied.
+pub struct Chain {
+    pub rpc_url: String,
+    fallback_rpc_urls: Vec<String>,
+    rpc_rate_limit: Option<u32>,
+    cross_check_providers: bool,
+    rollup: Option<Address>,
+    from_block: u64,
+    to_block: Option<u64>,
+    page_size: u64,
+    retries: u32,
+    fetch_concurrency: usize,
+    pub commitment: Option<CommitmentVersion>,
+    pub high_s_signatures: bool,
+    pub edit_cooldown: u64,
+    pub undo_window: u64,
+    pub require_sequencer_signature: bool,
+    pub failure_policy: FailurePolicy,
+    pub data_dir: PathBuf,
+    cache_dir: PathBuf,
+    offline: bool,
+    pub hook_dir: Option<PathBuf>,
+    pub postgres_url: Option<String>,
+    pub codec: Codec,
+    pub dictionary: Option<Vec<u8>>,
+    pub genesis: Genesis,
+    pub metrics_addr: Option<SocketAddr>,
+    beacon_url: Option<String>,
+    pub checkpoint_url: Option<String>,
+}
+
+/// Reads a genesis file, as JSON if it ends in `.json` and as TOML otherwise.
+pub fn load_genesis(path: &Path) -> eyre::Result<Genesis> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => Ok(serde_json::from_str(&text)?),
+        _ => Ok(toml::from_str(&text)?),
+    }
+}
+
+impl CodecArgs {
+    pub fn resolve(self, config: &Config) -> eyre::Result<(Codec, Option<Vec<u8>>)> {
+        let codec = match self.codec.as_deref().or(config.codec.as_deref()) {
+            None | Some("zlib") => Codec::Zlib,
+            Some("none") => Codec::None,
+            Some("zstd") => Codec::Zstd,
+            Some("brotli") => Codec::Brotli,
+            Some(name) => return Err(eyre::eyre!("Unknown codec {}", name)),
+        };
+        let dictionary = match self.zstd_dictionary.or(config.zstd_dictionary.clone()) {
+            Some(path) => Some(
+                std::fs::read(&path)
+                    .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?,
+            ),
+            None => None,
+        };
+
+        Ok((codec, dictionary))
+    }
+}
+
+impl ChainArgs {
+    pub fn resolve(self, config: Config) -> eyre::Result<Chain> {
+        let (codec, dictionary) = self.codec.resolve(&config)?;
+        let commitment = self
+            .commitment
+            .or(config.commitment)
+            .map(|version| {
+                CommitmentVersion::from_u8(version)
+                    .ok_or_else(|| eyre::eyre!("Unknown commitment version {}", version))
+            })
+            .transpose()?;
+        let genesis = match self.genesis.or(config.genesis) {
+            Some(path) => load_genesis(&path)?,
+            None => Genesis::default(),
+        };
+        let data_dir = self
+            .data_dir
+            .or(config.data_dir)
+            .unwrap_or_else(|| PathBuf::from("data"));
+
+        Ok(Chain {
+            rpc_url: self
+                .rpc_url
+                .or(config.rpc_url)
+                .unwrap_or_else(|| DEFAULT_RPC_URL.to_string()),
+            fallback_rpc_urls: match self.fallback_rpc_urls.is_empty() {
+                true => config.fallback_rpc_urls.unwrap_or_default(),
+                false => self.fallback_rpc_urls,
+            },
+            rpc_rate_limit: self.rpc_rate_limit.or(config.rpc_rate_limit),
+            cross_check_providers: self.cross_check_providers
+                || config.cross_check_providers.unwrap_or_default(),
+            rollup: self.rollup.or(config.rollup),
+            from_block: self.from_block.or(config.from_block).unwrap_or(0),
+            to_block: self.to_block.or(config.to_block),
+            page_size: self.page_size.or(config.page_size).unwrap_or(10_000),
+            retries: self.retries.or(config.retries).unwrap_or(5),
+            fetch_concurrency: self
+                .fetch_concurrency
+                .or(config.fetch_concurrency)
+                .unwrap_or(8),
+            commitment,
+            high_s_signatures: self.high_s_signatures
+                || config.high_s_signatures.unwrap_or_default(),
+            edit_cooldown: self
+                .edit_cooldown
+                .or(config.edit_cooldown)
+                .unwrap_or_default(),
+            undo_window: self.undo_window.or(config.undo_window).unwrap_or_default(),
+            require_sequencer_signature: self.require_sequencer_signature
+                || config.require_sequencer_signature.unwrap_or_default(),
+            failure_policy: match self.skip_failed_transactions
+                || config.skip_failed_transactions.unwrap_or_default()
+            {
+                true => FailurePolicy::Skip,
+                false => FailurePolicy::Abort,
+            },
+            cache_dir: self
+                .cache_dir
+                .or(config.cache_dir)
+                .unwrap_or_else(|| data_dir.join("cache")),
+            offline: self.offline || config.offline.unwrap_or_default(),
+            data_dir,
+            hook_dir: self.hook_dir.or(config.hook_dir),
+            postgres_url: self.postgres_url.or(config.postgres_url),
+            codec,
+            dictionary,
+            genesis,
+            metrics_addr: self.metrics_addr.or(config.metrics_addr),
+            beacon_url: self.beacon_url.or(config.beacon_url),
+            checkpoint_url: self.checkpoint_url.or(config.checkpoint_url),
+        })
+    }
+}
+
+impl Chain {
+    pub fn rollup(&self) -> eyre::Result<Address> {
+        self.rollup.ok_or_else(|| {
+            eyre::eyre!("--rollup <ADDRESS>, CANVAS_ROLLUP or `rollup` in the config is required")
+        })
+    }
+
+    pub fn sync_config(&self) -> eyre::Result<SyncConfig> {
+        Ok(SyncConfig {
+            rpc_url: self.rpc_url.clone(),
+            fallback_rpc_urls: self.fallback_rpc_urls.clone(),
+            rate_limit: self.rpc_rate_limit,
+            cross_check: self.cross_check_providers,
+            rollup: self.rollup()?,
+            from_block: self.from_block,
+            to_block: self.to_block,
+            page_size: self.page_size,
+            retries: self.retries,
+            fetch_concurrency: self.fetch_concurrency,
+            beacon_url: self.beacon_url.clone(),
+            cache_dir: self.cache_dir.clone(),
+            offline: self.offline,
+        })
+    }
+}
@@ -0,0 +1,712 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    hex,
+    primitives::{Address, Bytes, B256},
+    sol_types::SolValue,
+};
+use alloy_rlp::Decodable;
+use jsonrpsee::{
+    server::Server,
+    types::{ErrorObject, ErrorObjectOwned},
+    RpcModule,
+};
+use program::{
+    batch::{self, BatchContext},
+    bridge::Inbox,
+    codec::Codec,
+    config::FailurePolicy,
+    limits::RateLimitState,
+    mempool::{Eviction, Mempool},
+    nonce::NonceCache,
+    ordering::OrderingPolicy,
+    overlay::OverlayDB,
+    receipt::Receipt,
+    recover_address_from_tx,
+    shuffle::execution_order,
+    simulate::SimulationResult,
+    sponsor::recover_sponsor,
+    witness::Witness,
+    Account, CanvasProcessor, InMemoryDB, Input, SignedTransaction,
+};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use tokio::sync::broadcast;
+
+use crate::{
+    hooks::{AccountDiff, BatchEvent},
+    metrics::{BatchGrowth, InputMetrics, StateMetrics},
+    serve::{subscription_module, EVENT_CAPACITY},
+};
+
+/// The file in a devnet's state directory holding everything it keeps across restarts.
+const DEVNET_STATE: &str = "devnet.json";
+
+/// A batch as it would have been posted with `submitBatchWithProof`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevBatch {
+    pub index: u64,
+    /// Zero for batches saved before it was kept.
+    #[serde(default)]
+    pub timestamp: u64,
+    pub initial_state_root: B256,
+    pub final_state_root: B256,
+    pub transaction_commit: B256,
+    pub receipts_root: B256,
+    pub accounts_root: B256,
+    pub limits_root: B256,
+    pub batch_hash: B256,
+    pub public_values: Bytes,
+    pub proof: Bytes,
+    pub transaction_data: Bytes,
+    pub receipts: Vec<Receipt>,
+}
+
+/// A transaction looked up by its `SignedTransaction::hash`. `batch` and `receipt` are `None`
+/// while it is queued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevTransaction {
+    pub hash: B256,
+    pub transaction: SignedTransaction,
+    pub batch: Option<u64>,
+    pub receipt: Option<Receipt>,
+}
+
+/// Outcome of executing a transaction against the current state without applying it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun {
+    pub success: bool,
+    pub error: Option<String>,
+    pub to: Address,
+    pub before: String,
+    pub after: String,
+    /// Unified diff of the target account's data.
+    pub diff: String,
+}
+
+impl DryRun {
+    fn new(to: Address, before: String, after: String) -> Self {
+        let diff = TextDiff::from_lines(&before, &after)
+            .unified_diff()
+            .header("before", "after")
+            .to_string();
+
+        Self {
+            success: true,
+            error: None,
+            to,
+            before,
+            after,
+            diff,
+        }
+    }
+}
+
+// What a devnet keeps across restarts: the state, the batches and the transactions in them. The
+// metrics start over.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevState {
+    snapshot: Bytes,
+    limits: RateLimitState,
+    batches: Vec<DevBatch>,
+    transactions: Vec<DevTransaction>,
+}
+
+/// Sequencer, mock prover and L1 in one process. Every submitted transaction is sequenced into
+/// its own batch, "proven" by running the program natively and finalized immediately. Queued
+/// transactions wait in the mempool until a batch is sealed.
+pub struct DevNode {
+    processor: CanvasProcessor<InMemoryDB>,
+    nonces: NonceCache,
+    mempool: Mempool,
+    batches: Vec<DevBatch>,
+    // Every sequenced transaction by hash, with its batch and receipt.
+    included: HashMap<B256, DevTransaction>,
+    metrics: StateMetrics,
+    growth: Vec<BatchGrowth>,
+    inputs: Vec<InputMetrics>,
+    // Where the state is saved after every batch, if anywhere.
+    state_dir: Option<PathBuf>,
+    events: Option<broadcast::Sender<Arc<BatchEvent>>>,
+}
+
+impl Default for DevNode {
+    fn default() -> Self {
+        let processor = CanvasProcessor::new(InMemoryDB::default());
+        let metrics = StateMetrics::collect(&processor.db, processor.config.max_size);
+
+        Self {
+            processor,
+            nonces: NonceCache::new(),
+            mempool: Mempool::default(),
+            batches: Vec::new(),
+            included: HashMap::new(),
+            metrics,
+            growth: Vec::new(),
+            inputs: Vec::new(),
+            state_dir: None,
+            events: None,
+        }
+    }
+}
+
+impl DevNode {
+    /// A node that saves its state into `dir` after every batch, picking up where the last one
+    /// saved there left off.
+    pub fn open(dir: &Path) -> eyre::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut node = Self {
+            state_dir: Some(dir.to_path_buf()),
+            ..Self::default()
+        };
+
+        let path = dir.join(DEVNET_STATE);
+        if !path.exists() {
+            return Ok(node);
+        }
+        let state: DevState = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?;
+        node.processor = CanvasProcessor::new(InMemoryDB::from_snapshot(&state.snapshot)?)
+            .with_limits(state.limits);
+        node.metrics = StateMetrics::collect(&node.processor.db, node.processor.config.max_size);
+        node.batches = state.batches;
+        node.included = state
+            .transactions
+            .into_iter()
+            .map(|tx| (tx.hash, tx))
+            .collect();
+        Ok(node)
+    }
+
+    /// Sends every batch, with the accounts it changed, to `events`.
+    pub fn with_events(mut self, events: broadcast::Sender<Arc<BatchEvent>>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Encodes batches with `codec`, and `dictionary` under `Codec::Zstd`.
+    pub fn with_codec(mut self, codec: Codec, dictionary: Option<Vec<u8>>) -> Self {
+        self.processor.config.codec = codec;
+        self.processor.dictionary = dictionary.clone();
+        self.mempool = self.mempool.with_codec(codec, dictionary);
+        self
+    }
+
+    /// Seals batches in the order `ordering` picks.
+    pub fn with_ordering(mut self, ordering: Box<dyn OrderingPolicy>) -> Self {
+        self.mempool = self.mempool.with_ordering(ordering);
+        self
+    }
+
+    pub fn submit(&mut self, tx: SignedTransaction) -> eyre::Result<DevBatch> {
+        let sender = recover_address_from_tx(&tx, &self.processor.config.domain)?;
+        self.nonces
+            .check(&self.processor.db, &sender, tx.tx.nonce)?;
+        self.nonces.admit(&sender);
+
+        let batch = match self.commit_batch(vec![tx]) {
+            Ok(batch) => batch,
+            Err(err) => {
+                self.nonces.dropped(&sender);
+                return Err(err);
+            }
+        };
+        self.nonces.applied(&sender, self.account(&sender).nonce);
+
+        Ok(batch)
+    }
+
+    /// Validates `tx` and queues it for the next `seal_batch`. Returns what admitting it evicted.
+    /// A full mempool keeps the transactions paying the highest fees.
+    pub fn queue(&mut self, tx: SignedTransaction) -> eyre::Result<Vec<Eviction>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.mempool.evict_expired(now);
+        let priority = self.processor.config.fees.fee(&tx).saturating_to();
+        self.mempool.submit(
+            &self.processor.db,
+            &self.processor.config,
+            tx,
+            now,
+            priority,
+        )
+    }
+
+    /// Sequences the ready queued transactions into one batch. `None` if none are ready. If the
+    /// batch fails, its transactions are queued again as they were.
+    pub fn seal_batch(&mut self) -> eyre::Result<Option<DevBatch>> {
+        let queued = self.mempool.pending().to_vec();
+        let transactions = self.mempool.drain_batch(&self.processor.db)?;
+        if transactions.is_empty() {
+            return Ok(None);
+        }
+
+        let hashes: HashSet<B256> = transactions.iter().map(SignedTransaction::hash).collect();
+        let batch = match self.commit_batch(transactions) {
+            Ok(batch) => batch,
+            Err(err) => {
+                for pending in queued {
+                    if hashes.contains(&pending.hash) {
+                        self.mempool.insert(pending);
+                    }
+                }
+                return Err(err);
+            }
+        };
+        self.nonces.clear();
+        Ok(Some(batch))
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.mempool.len()
+    }
+
+    // Runs `transactions` as the next batch; a batch that fails leaves the state as it was.
+    fn commit_batch(&mut self, transactions: Vec<SignedTransaction>) -> eyre::Result<DevBatch> {
+        let previous_timestamp = self.batches.last().map_or(0, |batch| batch.timestamp);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        // The dev L1 has no blocks to anchor to. The timestamp never goes behind the batch
+        // before, whatever the clock did since.
+        let context = BatchContext {
+            index: self.batches.len() as u64,
+            previous_batch: self
+                .batches
+                .last()
+                .map_or(B256::ZERO, |batch| batch.transaction_commit),
+            previous_batch_hash: self
+                .batches
+                .last()
+                .map_or(B256::ZERO, |batch| batch.batch_hash),
+            timestamp: now.max(previous_timestamp),
+            previous_timestamp,
+            ..BatchContext::default()
+        };
+
+        let transaction_data = batch::encode_batch(
+            &transactions,
+            self.processor.config.codec,
+            self.processor.dictionary.as_deref(),
+        )?;
+
+        // What the guest would read to prove the batch statelessly.
+        let input = Input {
+            witness: Some(Witness::build(
+                &self.processor.db,
+                &transactions,
+                Inbox::default(),
+                &self.processor.config,
+            )),
+            transaction_data: transaction_data.clone(),
+            db: InMemoryDB::default(),
+            config: self.processor.config,
+            batch: context,
+            deposits: Vec::new(),
+            forced_txs: Vec::new(),
+            dictionary: self.processor.dictionary.clone(),
+            limits: self.processor.limits.clone(),
+        };
+        let input_metrics = InputMetrics::collect(&input)?;
+        let before = match self.events {
+            Some(_) => self.touched(&transactions),
+            None => BTreeMap::new(),
+        };
+
+        let checkpoint = self.processor.checkpoint();
+        let report =
+            match self
+                .processor
+                .process_posted_batch(&transaction_data, context, Inbox::default())
+            {
+                Ok(report) => report,
+                Err(err) => {
+                    self.processor.revert_to(checkpoint)?;
+                    return Err(err);
+                }
+            };
+        self.processor.commit_checkpoint(checkpoint)?;
+
+        // Receipts are in execution order.
+        let order = execution_order(
+            &self.processor.config,
+            &transactions,
+            &context.previous_batch,
+            &self.processor.senders,
+        );
+        for (index, receipt) in order.into_iter().zip(&report.receipts) {
+            let tx = &transactions[index];
+            self.included.insert(
+                tx.hash(),
+                DevTransaction {
+                    hash: tx.hash(),
+                    transaction: tx.clone(),
+                    batch: Some(context.index),
+                    receipt: Some(receipt.clone()),
+                },
+            );
+        }
+
+        let public_values = report.public_values;
+        let batch = DevBatch {
+            index: self.batches.len() as u64,
+            timestamp: public_values.batchTimestamp,
+            initial_state_root: public_values.initialStateRoot,
+            final_state_root: public_values.finalStateRoot,
+            transaction_commit: public_values.transaction_commit,
+            receipts_root: public_values.receiptsRoot,
+            accounts_root: public_values.accountsRoot,
+            limits_root: public_values.limitsRoot,
+            batch_hash: public_values.batchHash,
+            public_values: public_values.abi_encode().into(),
+            proof: Bytes::new(),
+            transaction_data: transaction_data.into(),
+            receipts: report.receipts,
+        };
+        self.batches.push(batch.clone());
+
+        let metrics = StateMetrics::collect(&self.processor.db, self.processor.config.max_size);
+        self.growth
+            .push(BatchGrowth::between(batch.index, &self.metrics, &metrics));
+        self.metrics = metrics;
+        self.inputs.push(input_metrics);
+
+        if let Some(events) = &self.events {
+            let diffs = before
+                .into_iter()
+                .map(|(address, before)| AccountDiff {
+                    address,
+                    after: self.account(&address),
+                    before,
+                })
+                .collect();
+            // There is no L1 transaction, and the batch index stands in for the block.
+            let event = BatchEvent {
+                index: batch.index,
+                tx_hash: B256::ZERO,
+                block_number: batch.index,
+                transaction_commit: batch.transaction_commit,
+                state_root: batch.final_state_root,
+                receipts: batch.receipts.clone(),
+                diffs,
+            };
+            // Only fails when nobody is subscribed.
+            let _ = events.send(Arc::new(event));
+        }
+        // The batch stays applied if it cannot be saved; the error tells the caller.
+        self.save()?;
+
+        Ok(batch)
+    }
+
+    // Every account `transactions` may touch, as it is now.
+    fn touched(&self, transactions: &[SignedTransaction]) -> BTreeMap<Address, Account> {
+        let domain = self.processor.config.domain;
+        let fees = self.processor.config.fees;
+        let mut before = BTreeMap::new();
+        if !fees.is_free() {
+            before.insert(fees.recipient, self.account(&fees.recipient));
+        }
+
+        for tx in transactions {
+            let sender = recover_address_from_tx(tx, &domain).ok();
+            let sponsor = recover_sponsor(tx, &domain).ok().flatten();
+            for address in sender
+                .into_iter()
+                .chain(sponsor)
+                .chain([tx.tx.target()])
+                .chain(tx.tx.sources())
+            {
+                before
+                    .entry(address)
+                    .or_insert_with(|| self.account(&address));
+            }
+        }
+        before
+    }
+
+    // Writes the state to the state directory through a temporary file, so a crash leaves the
+    // last saved state whole.
+    fn save(&self) -> eyre::Result<()> {
+        let Some(dir) = &self.state_dir else {
+            return Ok(());
+        };
+
+        let state = DevState {
+            snapshot: self.processor.db.snapshot_accounts()?.into(),
+            limits: self.processor.limits.clone(),
+            batches: self.batches.clone(),
+            transactions: self.included.values().cloned().collect(),
+        };
+        let path = dir.join(DEVNET_STATE);
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, serde_json::to_vec(&state)?)?;
+        fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    pub fn dry_run(&self, tx: &SignedTransaction) -> DryRun {
+        let before = self.account(&tx.tx.target()).data;
+
+        match self.processor.execute_transaction(tx) {
+            Ok(effect) => DryRun::new(effect.to, before, effect.to_account.data),
+            Err(err) => DryRun {
+                success: false,
+                error: Some(format!("{:#}", err)),
+                to: tx.tx.target(),
+                before: before.clone(),
+                after: before,
+                diff: String::new(),
+            },
+        }
+    }
+
+    /// Receipts `transactions` would get as one batch on top of the current state, which is
+    /// left untouched.
+    pub fn dry_run_batch(&self, transactions: &[SignedTransaction]) -> eyre::Result<Vec<Receipt>> {
+        let overlay = OverlayDB::new(&self.processor.db);
+        Ok(CanvasProcessor::with_config(overlay, self.processor.config)
+            .with_limits(self.processor.limits.clone())
+            .apply_transactions(transactions, FailurePolicy::Skip)?)
+    }
+
+    /// `CanvasProcessor::simulate_batch` on top of the current state.
+    pub fn simulate_batch(
+        &self,
+        transactions: &[SignedTransaction],
+    ) -> eyre::Result<SimulationResult> {
+        self.processor.simulate_batch(transactions)
+    }
+
+    pub fn next_nonce(&mut self, address: &Address) -> eyre::Result<u64> {
+        self.nonces.next_nonce(&self.processor.db, address)
+    }
+
+    pub fn state_root(&self) -> eyre::Result<B256> {
+        Ok(self.processor.generate_state_root()?.into())
+    }
+
+    pub fn account(&self, address: &Address) -> Account {
+        self.processor
+            .db
+            .accounts
+            .borrow()
+            .get(address)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn batch(&self, index: u64) -> Option<DevBatch> {
+        self.batches.get(index as usize).cloned()
+    }
+
+    pub fn batch_count(&self) -> u64 {
+        self.batches.len() as u64
+    }
+
+    /// The sequenced or queued transaction with `SignedTransaction::hash` `hash`.
+    pub fn transaction(&self, hash: &B256) -> Option<DevTransaction> {
+        if let Some(included) = self.included.get(hash) {
+            return Some(included.clone());
+        }
+        self.mempool.get(hash).map(|pending| DevTransaction {
+            hash: *hash,
+            transaction: pending.tx.clone(),
+            batch: None,
+            receipt: None,
+        })
+    }
+
+    pub fn metrics(&self) -> StateMetrics {
+        self.metrics.clone()
+    }
+
+    /// Growth of the state in each batch, oldest first.
+    pub fn growth(&self) -> Vec<BatchGrowth> {
+        self.growth.clone()
+    }
+
+    /// Size of the stateless prover input of each batch, oldest first.
+    pub fn inputs(&self) -> Vec<InputMetrics> {
+        self.inputs.clone()
+    }
+}
+
+pub(crate) fn rpc_error(err: impl std::fmt::Display) -> ErrorObjectOwned {
+    ErrorObject::owned(-32000, format!("{:#}", err), None::<()>)
+}
+
+pub(crate) fn decode_raw_transaction(raw: &str) -> eyre::Result<SignedTransaction> {
+    let bytes = hex::decode(raw)?;
+    Ok(SignedTransaction::decode(&mut bytes.as_slice())?)
+}
+
+pub fn rpc_module(node: Arc<Mutex<DevNode>>) -> eyre::Result<RpcModule<Arc<Mutex<DevNode>>>> {
+    let mut module = RpcModule::new(node);
+
+    module.register_method("canvas_sendRawTransaction", |params, node, _| {
+        let raw: String = params.one()?;
+        let tx = decode_raw_transaction(&raw).map_err(rpc_error)?;
+        node.lock().unwrap().submit(tx).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_queueRawTransaction", |params, node, _| {
+        let raw: String = params.one()?;
+        let tx = decode_raw_transaction(&raw).map_err(rpc_error)?;
+        node.lock().unwrap().queue(tx).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_sealBatch", |_, node, _| {
+        node.lock().unwrap().seal_batch().map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getPendingCount", |_, node, _| {
+        node.lock().unwrap().pending_count()
+    })?;
+
+    module.register_method("canvas_dryRunTransaction", |params, node, _| {
+        let raw: String = params.one()?;
+        let tx = decode_raw_transaction(&raw).map_err(rpc_error)?;
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().dry_run(&tx))
+    })?;
+
+    module.register_method("canvas_dryRunBatch", |params, node, _| {
+        let raw: Vec<String> = params.one()?;
+        let transactions = raw
+            .iter()
+            .map(|raw| decode_raw_transaction(raw))
+            .collect::<eyre::Result<Vec<_>>>()
+            .map_err(rpc_error)?;
+        node.lock()
+            .unwrap()
+            .dry_run_batch(&transactions)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_simulateBatch", |params, node, _| {
+        let raw: Vec<String> = params.one()?;
+        let transactions = raw
+            .iter()
+            .map(|raw| decode_raw_transaction(raw))
+            .collect::<eyre::Result<Vec<_>>>()
+            .map_err(rpc_error)?;
+        node.lock()
+            .unwrap()
+            .simulate_batch(&transactions)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getStateRoot", |_, node, _| {
+        node.lock().unwrap().state_root().map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getAccount", |params, node, _| {
+        let address: Address = params.one()?;
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().account(&address))
+    })?;
+
+    module.register_method("canvas_getTransactionCount", |params, node, _| {
+        let address: Address = params.one()?;
+        node.lock().unwrap().next_nonce(&address).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getBatch", |params, node, _| {
+        let index: u64 = params.one()?;
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().batch(index))
+    })?;
+
+    module.register_method("canvas_getTransactionByHash", |params, node, _| {
+        let hash: B256 = params.one()?;
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().transaction(&hash))
+    })?;
+
+    module.register_method("canvas_getBatchCount", |_, node, _| {
+        node.lock().unwrap().batch_count()
+    })?;
+
+    module.register_method("canvas_getStateMetrics", |_, node, _| {
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().metrics())
+    })?;
+
+    module.register_method("canvas_getStateGrowth", |_, node, _| {
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().growth())
+    })?;
+
+    module.register_method("canvas_getInputMetrics", |_, node, _| {
+        Ok::<_, ErrorObjectOwned>(node.lock().unwrap().inputs())
+    })?;
+
+    Ok(module)
+}
+
+pub async fn run(
+    addr: SocketAddr,
+    codec: Codec,
+    dictionary: Option<Vec<u8>>,
+    ordering: Box<dyn OrderingPolicy>,
+) -> eyre::Result<()> {
+    let server = Server::builder().build(addr).await?;
+    let handle = server.start(rpc_module(Arc::new(Mutex::new(
+        DevNode::default()
+            .with_codec(codec, dictionary)
+            .with_ordering(ordering),
+    )))?);
+
+    tracing::info!(%addr, "Dev node listening");
+
+    handle.stopped().await;
+    Ok(())
+}
+
+/// The dev node with its state saved in `state_dir`, queued transactions sealed into a batch
+/// every `seal_interval`, and batches and account changes pushed over WebSocket on the same
+/// address as the RPC.
+pub async fn devnet(
+    addr: SocketAddr,
+    state_dir: PathBuf,
+    seal_interval: Duration,
+    codec: Codec,
+    dictionary: Option<Vec<u8>>,
+    ordering: Box<dyn OrderingPolicy>,
+) -> eyre::Result<()> {
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
+    let node = DevNode::open(&state_dir)?
+        .with_codec(codec, dictionary)
+        .with_ordering(ordering)
+        .with_events(events.clone());
+    let batches = node.batch_count();
+    let node = Arc::new(Mutex::new(node));
+
+    let mut module = rpc_module(node.clone())?;
+    module.merge(subscription_module(events)?)?;
+    let server = Server::builder().build(addr).await?;
+    let handle = server.start(module);
+
+    tracing::info!(%addr, state_dir = %state_dir.display(), batches, "Devnet listening");
+
+    tokio::select! {
+        _ = handle.stopped() => Ok(()),
+        _ = seal_queued(node, seal_interval) => Ok(()),
+    }
+}
+
+// Seals the ready queued transactions every `interval`. A batch that fails is logged, its
+// transactions are queued again, and the next interval retries them until they expire.
+async fn seal_queued(node: Arc<Mutex<DevNode>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match node.lock().unwrap().seal_batch() {
+            Ok(Some(batch)) => tracing::info!(index = batch.index, "Sealed batch"),
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Failed to seal batch: {:#}", err),
+        }
+    }
+}
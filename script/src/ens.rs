@@ -0,0 +1,140 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::{address, keccak256, Address, B256},
+    providers::{ProviderBuilder, RootProvider},
+    sol,
+    transports::http::{Client, Http},
+};
+use serde::Serialize;
+
+sol! {
+    #[sol(rpc)]
+    interface EnsRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface EnsResolver {
+        function name(bytes32 node) external view returns (string);
+        function addr(bytes32 node) external view returns (address);
+        function text(bytes32 node, string key) external view returns (string);
+    }
+}
+
+/// The ENS registry, at the same address on mainnet and the testnets.
+pub const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+/// Text records kept as profile metadata.
+const TEXT_KEYS: [&str; 5] = ["avatar", "description", "url", "com.twitter", "com.github"];
+
+/// A contributor's ENS name and profile records. `name` is only set when it resolves back to
+/// `address`, so a reverse record cannot claim someone else's name.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub address: Address,
+    pub name: Option<String>,
+    pub records: BTreeMap<String, String>,
+}
+
+/// Resolves addresses to `Profile`s through an L1 provider and caches them for `ttl`.
+pub struct ProfileRegistry {
+    provider: RootProvider<Http<Client>>,
+    ttl: Duration,
+    cache: Mutex<HashMap<Address, (Instant, Profile)>>,
+}
+
+impl ProfileRegistry {
+    pub fn new(rpc_url: &str, ttl: Duration) -> eyre::Result<Self> {
+        Ok(Self {
+            provider: ProviderBuilder::new().on_http(rpc_url.parse()?),
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn profile(&self, address: Address) -> eyre::Result<Profile> {
+        if let Some((fetched, profile)) = self.cache.lock().unwrap().get(&address) {
+            if fetched.elapsed() < self.ttl {
+                return Ok(profile.clone());
+            }
+        }
+
+        let profile = self.resolve(address).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(address, (Instant::now(), profile.clone()));
+        Ok(profile)
+    }
+
+    /// Resolves `addresses` into the cache ahead of the first request for them. Failures are
+    /// logged, and retried on request.
+    pub async fn warm(&self, addresses: impl IntoIterator<Item = Address>) {
+        for address in addresses {
+            if let Err(err) = self.profile(address).await {
+                tracing::warn!(%address, "Failed to resolve profile: {:#}", err);
+            }
+        }
+    }
+
+    async fn resolve(&self, address: Address) -> eyre::Result<Profile> {
+        let mut profile = Profile {
+            address,
+            name: None,
+            records: BTreeMap::new(),
+        };
+
+        let reverse_node = namehash(&format!("{}.addr.reverse", hex::encode(address)));
+        let Some(reverse) = self.resolver(reverse_node).await? else {
+            return Ok(profile);
+        };
+        let name = EnsResolver::new(reverse, &self.provider)
+            .name(reverse_node)
+            .call()
+            .await?
+            ._0;
+        if name.is_empty() {
+            return Ok(profile);
+        }
+
+        let node = namehash(&name);
+        let Some(resolver) = self.resolver(node).await? else {
+            return Ok(profile);
+        };
+        let resolver = EnsResolver::new(resolver, &self.provider);
+        if resolver.addr(node).call().await?._0 != address {
+            return Ok(profile);
+        }
+
+        for key in TEXT_KEYS {
+            let value = resolver.text(node, key.to_string()).call().await?._0;
+            if !value.is_empty() {
+                profile.records.insert(key.to_string(), value);
+            }
+        }
+        profile.name = Some(name);
+        Ok(profile)
+    }
+
+    async fn resolver(&self, node: B256) -> eyre::Result<Option<Address>> {
+        let registry = EnsRegistry::new(ENS_REGISTRY, &self.provider);
+        let resolver = registry.resolver(node).call().await?._0;
+
+        Ok((!resolver.is_zero()).then_some(resolver))
+    }
+}
+
+/// EIP-137 namehash. Names are expected to be normalized already.
+pub fn namehash(name: &str) -> B256 {
+    name.rsplit('.')
+        .filter(|label| !label.is_empty())
+        .fold(B256::ZERO, |node, label| {
+            keccak256([node.as_slice(), keccak256(label).as_slice()].concat())
+        })
+}
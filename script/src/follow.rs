@@ -0,0 +1,876 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashSet},
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use program::{
+    batch::{self, BatchContext},
+    bridge::{Deposit, Inbox},
+    config::CanvasConfig,
+    genesis::Genesis,
+    limits::RateLimitState,
+    public_values::decode_public_values,
+    receipt::ReceiptStatus,
+    sequencer::{verify_sequencer_signature, SequencerSignature},
+    sponsor::recover_sponsor,
+    Account, AccountDB, CanvasProcessor, InMemoryDB, SignedTransaction,
+};
+
+use crate::{
+    attestation,
+    checkpoint::Checkpoint,
+    hooks::{AccountDiff, BatchEvent, BatchHook},
+    metrics,
+    sync::{PostedBatch, Syncer},
+};
+
+/// A batch the follower has applied, and the state right after it for rolling back to.
+pub struct AppliedBatch {
+    pub tx_hash: B256,
+    /// Its position among the batches `tx_hash` posted.
+    pub position: u64,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_commit: B256,
+    /// `batchHash` its proof committed, zero when the proof predates the batch chain.
+    pub batch_hash: B256,
+    /// `batchTimestamp` its proof committed, zero when it has none.
+    pub timestamp: u64,
+    // Dropped once a later batch is finalized; only the last finalized one is ever restored.
+    snapshot: Option<Vec<u8>>,
+    // Rate-limit counters right after the batch, restored with the snapshot.
+    limits: RateLimitState,
+    // Queue indices of the first deposit and forced transaction after the ones the batch and
+    // those before it consumed.
+    next_deposit: u64,
+    next_forced: u64,
+}
+
+// Messages queued on the rollup contract so far, by queue index.
+struct Queue<T> {
+    items: BTreeMap<u64, T>,
+}
+
+impl<T: Clone> Queue<T> {
+    fn new() -> Self {
+        Self {
+            items: BTreeMap::new(),
+        }
+    }
+
+    fn insert(&mut self, index: u64, item: T) {
+        self.items.insert(index, item);
+    }
+
+    // The run of consecutive items from `next`.
+    fn pending(&self, next: u64) -> Vec<T> {
+        self.items
+            .range(next..)
+            .zip(next..)
+            .take_while(|((index, _), expected)| *index == expected)
+            .map(|((_, item), _)| item.clone())
+            .collect()
+    }
+
+    fn take(&self, next: u64, count: u64, kind: &str) -> eyre::Result<Vec<T>> {
+        (next..next + count)
+            .map(|index| {
+                self.items
+                    .get(&index)
+                    .cloned()
+                    .ok_or_else(|| eyre::eyre!("{} {} was not seen on L1", kind, index))
+            })
+            .collect()
+    }
+}
+
+/// Applies batches as they land on L1 and rolls back to the last finalized batch when a batch it
+/// applied is reorged out.
+pub struct Follower {
+    syncer: Syncer,
+    processor: CanvasProcessor<InMemoryDB>,
+    attester: Option<(PrivateKeySigner, String)>,
+    applied: Vec<AppliedBatch>,
+    hooks: Vec<Box<dyn BatchHook>>,
+    genesis: Genesis,
+    // Where replay started instead of genesis; `applied` holds the batches after it.
+    checkpoint: Option<Checkpoint>,
+    // `applied_key`s of the batches the state includes that a rescan could post again.
+    applied_keys: HashSet<B256>,
+    deposits: Queue<Deposit>,
+    forced_txs: Queue<SignedTransaction>,
+    // First block not scanned yet.
+    next_block: u64,
+    // Last finalized L1 block seen.
+    finalized: u64,
+}
+
+impl Follower {
+    pub fn new(syncer: Syncer, attester: Option<(PrivateKeySigner, String)>) -> Self {
+        let next_block = syncer.start_block();
+
+        Self {
+            syncer,
+            processor: CanvasProcessor::new(InMemoryDB::default()),
+            attester,
+            applied: Vec::new(),
+            hooks: Vec::new(),
+            genesis: Genesis::default(),
+            checkpoint: None,
+            applied_keys: HashSet::new(),
+            deposits: Queue::new(),
+            forced_txs: Queue::new(),
+            next_block,
+            finalized: 0,
+        }
+    }
+
+    /// Replays under `config` instead of the default one. Only takes effect before the first
+    /// batch is applied.
+    pub fn with_config(mut self, config: CanvasConfig) -> Self {
+        if self.applied.is_empty() {
+            self.processor =
+                CanvasProcessor::with_config(InMemoryDB::from_genesis(&self.genesis), config);
+        }
+        self
+    }
+
+    /// Replays from `genesis` instead of an empty state. Only takes effect before the first
+    /// batch is applied.
+    pub fn with_genesis(mut self, genesis: Genesis) -> Self {
+        if self.applied.is_empty() {
+            self.genesis = genesis;
+            self.processor.db = InMemoryDB::from_genesis(&self.genesis);
+        }
+        self
+    }
+
+    /// Starts from `checkpoint` instead of replaying from genesis. Its batch has to be in a
+    /// finalized block and its state has to match the state root the batch's submission
+    /// published. Only allowed before the first batch is applied.
+    pub async fn with_checkpoint(mut self, checkpoint: Checkpoint) -> eyre::Result<Self> {
+        if !self.applied.is_empty() || self.checkpoint.is_some() {
+            return Err(eyre::eyre!(
+                "Can only start from a checkpoint before syncing"
+            ));
+        }
+
+        let finalized = self.syncer.finalized_block().await?;
+        if checkpoint.block_number > finalized {
+            return Err(eyre::eyre!(
+                "Checkpoint batch {} is in block {}, past the finalized block {}",
+                checkpoint.batch,
+                checkpoint.block_number,
+                finalized
+            ));
+        }
+        let posted = self
+            .syncer
+            .batches_in(checkpoint.block_number, checkpoint.block_number)
+            .await?
+            .into_iter()
+            .find(|posted| is_checkpoint_batch(&checkpoint, posted))
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "Checkpoint batch {} was not posted by {} in block {}",
+                    checkpoint.batch,
+                    checkpoint.tx_hash,
+                    checkpoint.block_number
+                )
+            })?;
+        let public_values = posted.public_values.ok_or_else(|| {
+            eyre::eyre!(
+                "Checkpoint batch {} was posted in an aggregate, which publishes no state root \
+                 for it alone",
+                checkpoint.batch
+            )
+        })?;
+        let claimed = decode_public_values(&public_values)?;
+        if let Some(index) = claimed
+            .batch_index
+            .filter(|index| *index != checkpoint.batch)
+        {
+            return Err(eyre::eyre!(
+                "Checkpoint claims batch {} but its submission is batch {}",
+                checkpoint.batch,
+                index
+            ));
+        }
+        // Older checkpoints do not carry the timestamp; the submission has it either way.
+        let checkpoint = Checkpoint {
+            timestamp: claimed.batch_timestamp.unwrap_or_default(),
+            ..checkpoint
+        };
+
+        let db = InMemoryDB::from_snapshot(&checkpoint.snapshot)?;
+        let dictionary = self.processor.dictionary.take();
+        let processor = CanvasProcessor::with_config(db, self.processor.config)
+            .with_limits(checkpoint.limits.clone())
+            .with_dictionary(dictionary);
+        let state_root: B256 = processor.generate_state_root()?.into();
+        if state_root != claimed.final_state_root {
+            return Err(eyre::eyre!(
+                "Checkpoint state root {} does not match {} published for batch {}",
+                state_root,
+                claimed.final_state_root,
+                checkpoint.batch
+            ));
+        }
+
+        // Messages queued before the checkpoint's block may still be pending after it.
+        let start = self.syncer.start_block();
+        if checkpoint.block_number > start {
+            let end = checkpoint.block_number - 1;
+            for deposit in self.syncer.deposits_in(start, end).await? {
+                self.deposits.insert(deposit.index, deposit);
+            }
+            for (index, tx) in self.syncer.forced_txs_in(start, end).await? {
+                self.forced_txs.insert(index, tx);
+            }
+        }
+
+        tracing::info!(batch = checkpoint.batch, %state_root, "Starting from checkpoint");
+        self.processor = processor;
+        self.next_block = checkpoint.block_number;
+        self.finalized = finalized;
+        self.applied_keys = checkpoint_keys(&checkpoint).into_iter().collect();
+        self.checkpoint = Some(checkpoint);
+        Ok(self)
+    }
+
+    /// Decodes and encodes zstd batches with `dictionary`.
+    pub fn with_dictionary(mut self, dictionary: Option<Vec<u8>>) -> Self {
+        self.processor.dictionary = dictionary;
+        self
+    }
+
+    /// Runs `hook` after every batch applied from now on, after those added before it.
+    pub fn with_hook(mut self, hook: impl BatchHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Transaction commit of the last applied batch, the seed of the next one's ordering.
+    pub fn previous_batch(&self) -> B256 {
+        match (self.applied.last(), &self.checkpoint) {
+            (Some(applied), _) => applied.transaction_commit,
+            (None, Some(checkpoint)) => checkpoint.transaction_commit,
+            (None, None) => B256::ZERO,
+        }
+    }
+
+    /// Batch hash of the last applied batch, the one the next batch's proof has to chain from.
+    pub fn previous_batch_hash(&self) -> B256 {
+        match (self.applied.last(), &self.checkpoint) {
+            (Some(applied), _) => applied.batch_hash,
+            (None, Some(checkpoint)) => checkpoint.batch_hash,
+            (None, None) => B256::ZERO,
+        }
+    }
+
+    /// Timestamp of the last applied batch, which the next batch's cannot be before.
+    pub fn previous_timestamp(&self) -> u64 {
+        match (self.applied.last(), &self.checkpoint) {
+            (Some(applied), _) => applied.timestamp,
+            (None, Some(checkpoint)) => checkpoint.timestamp,
+            (None, None) => 0,
+        }
+    }
+
+    /// Deposits queued on L1 that no applied batch consumed yet, in queue order. The next batch
+    /// has to consume some prefix of them.
+    pub fn pending_deposits(&self) -> Vec<Deposit> {
+        self.deposits.pending(self.next_queued().0)
+    }
+
+    /// Forced transactions queued on L1 that no applied batch included yet, in queue order.
+    pub fn pending_forced_txs(&self) -> Vec<SignedTransaction> {
+        self.forced_txs.pending(self.next_queued().1)
+    }
+
+    // The next deposit and forced transaction index.
+    fn next_queued(&self) -> (u64, u64) {
+        match (self.applied.last(), &self.checkpoint) {
+            (Some(applied), _) => (applied.next_deposit, applied.next_forced),
+            (None, Some(checkpoint)) => (checkpoint.next_deposit, checkpoint.next_forced),
+            (None, None) => (0, 0),
+        }
+    }
+
+    /// The batch after the last applied one, anchored to the latest L1 block and timestamped
+    /// now, or at the last batch's time if the clock is behind it. The contract can only check
+    /// the anchor within 256 blocks, so prove and submit soon after.
+    pub async fn next_batch(&self) -> eyre::Result<BatchContext> {
+        let (number, hash) = self.syncer.latest_block().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let previous_timestamp = self.previous_timestamp();
+
+        Ok(BatchContext {
+            index: self.batches() as u64,
+            previous_batch: self.previous_batch(),
+            previous_batch_hash: self.previous_batch_hash(),
+            previous_timestamp,
+            ..BatchContext::default()
+        }
+        .anchored(number, hash)
+        .timestamped(now.max(previous_timestamp)))
+    }
+
+    pub fn into_processor(self) -> CanvasProcessor<InMemoryDB> {
+        self.processor
+    }
+
+    /// A copy of the current state, for serving while the follower moves on.
+    pub fn state(&self) -> eyre::Result<CanvasProcessor<InMemoryDB>> {
+        let db = InMemoryDB::from_snapshot(&self.processor.db.snapshot_accounts()?)?;
+        Ok(CanvasProcessor::with_config(db, self.processor.config)
+            .with_limits(self.processor.limits.clone())
+            .with_dictionary(self.processor.dictionary.clone()))
+    }
+
+    /// Number of batches applied so far, counting those before the checkpoint.
+    pub fn batches(&self) -> usize {
+        self.first_batch() + self.applied.len()
+    }
+
+    // Index of the first batch in `applied`.
+    fn first_batch(&self) -> usize {
+        self.checkpoint
+            .as_ref()
+            .map_or(0, |checkpoint| checkpoint.batch as usize + 1)
+    }
+
+    /// The latest applied batch in a finalized block whose state is still kept, for other nodes
+    /// to start from. `None` until a batch is finalized.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        let first = self.first_batch();
+        self.applied
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, applied)| applied.block_number <= self.finalized)
+            .find_map(|(index, applied)| {
+                Some(Checkpoint {
+                    batch: (first + index) as u64,
+                    tx_hash: applied.tx_hash,
+                    block_number: applied.block_number,
+                    transaction_commit: applied.transaction_commit,
+                    batch_hash: applied.batch_hash,
+                    timestamp: applied.timestamp,
+                    snapshot: applied.snapshot.clone()?.into(),
+                    limits: applied.limits.clone(),
+                    next_deposit: applied.next_deposit,
+                    next_forced: applied.next_forced,
+                    applied_batches: self.keys_in_block(index),
+                })
+            })
+            .or_else(|| self.checkpoint.clone())
+    }
+
+    // The applied-batch keys of the batches up to `applied[index]` posted in its block.
+    fn keys_in_block(&self, index: usize) -> Vec<B256> {
+        let block_number = self.applied[index].block_number;
+        let mut keys: Vec<B256> = self
+            .checkpoint
+            .iter()
+            .filter(|checkpoint| checkpoint.block_number == block_number)
+            .flat_map(checkpoint_keys)
+            .collect();
+        keys.extend(
+            self.applied[..=index]
+                .iter()
+                .filter(|applied| applied.block_number == block_number)
+                .map(|applied| applied_key(applied.tx_hash, applied.position)),
+        );
+        keys
+    }
+
+    /// Applies every batch posted up to the head.
+    pub async fn catch_up(&mut self) -> eyre::Result<()> {
+        let head = self.syncer.head().await?;
+        if self.next_block > head {
+            return Ok(());
+        }
+
+        // A batch can only consume deposits queued before it, so they are all seen first.
+        let synced = self.syncer.blocks_in(self.next_block, head).await?;
+        for deposit in synced.deposits {
+            self.deposits.insert(deposit.index, deposit);
+        }
+        for (index, tx) in synced.forced_txs {
+            self.forced_txs.insert(index, tx);
+        }
+        let mut posted = synced.batches;
+        // The checkpoint's block may hold it and batches before it, which its state includes.
+        if let Some(checkpoint) = &self.checkpoint {
+            if let Some(position) = posted
+                .iter()
+                .position(|posted| is_checkpoint_batch(checkpoint, posted))
+            {
+                posted.drain(..=position);
+            }
+        }
+        for posted in posted {
+            self.apply(posted).await?;
+        }
+        self.next_block = head + 1;
+
+        Ok(())
+    }
+
+    /// Keeps following the chain, polling every `interval`.
+    pub async fn follow(&mut self, interval: Duration) -> eyre::Result<()> {
+        loop {
+            self.poll().await?;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Rolls back a reorged batch, applies new ones and drops snapshots no longer needed.
+    pub async fn poll(&mut self) -> eyre::Result<()> {
+        self.check_reorg().await?;
+        self.catch_up().await?;
+        self.prune().await
+    }
+
+    /// Discards batch `batch` and every later one, restores the state after batch `batch - 1`
+    /// and re-applies from there.
+    pub async fn resync_from(&mut self, batch: usize) -> eyre::Result<()> {
+        let first = self.first_batch();
+        if batch > self.batches() {
+            return Err(eyre::eyre!(
+                "Cannot resync from batch {}, only {} applied",
+                batch,
+                self.batches()
+            ));
+        }
+        if batch < first {
+            return Err(eyre::eyre!(
+                "Cannot resync from batch {}, before the checkpoint",
+                batch
+            ));
+        }
+
+        let (db, limits) = match (batch - first).checked_sub(1).map(|i| &self.applied[i]) {
+            Some(kept) => {
+                let snapshot = kept.snapshot.as_ref().ok_or_else(|| {
+                    eyre::eyre!("No snapshot left for batch {}, it is finalized", batch - 1)
+                })?;
+                (InMemoryDB::from_snapshot(snapshot)?, kept.limits.clone())
+            }
+            None => match &self.checkpoint {
+                Some(checkpoint) => (
+                    InMemoryDB::from_snapshot(&checkpoint.snapshot)?,
+                    checkpoint.limits.clone(),
+                ),
+                None => (
+                    InMemoryDB::from_genesis(&self.genesis),
+                    RateLimitState::default(),
+                ),
+            },
+        };
+
+        self.applied.truncate(batch - first);
+        self.applied_keys = self.checkpoint.iter().flat_map(checkpoint_keys).collect();
+        self.applied_keys.extend(
+            self.applied
+                .iter()
+                .map(|kept| applied_key(kept.tx_hash, kept.position)),
+        );
+
+        // Rescan the last kept batch's block too, in case later batches landed in it; the
+        // applied-batch guard skips the kept one.
+        let start = match &self.checkpoint {
+            Some(checkpoint) => checkpoint.block_number,
+            None => self.syncer.start_block(),
+        };
+        self.next_block = self.applied.last().map_or(start, |kept| kept.block_number);
+        let dictionary = self.processor.dictionary.take();
+        self.processor = CanvasProcessor::with_config(db, self.processor.config)
+            .with_limits(limits)
+            .with_dictionary(dictionary);
+
+        tracing::info!(batch, "Resyncing");
+        self.catch_up().await
+    }
+
+    // Rolls back to the last finalized batch if any later batch's block has been replaced.
+    async fn check_reorg(&mut self) -> eyre::Result<()> {
+        let finalized = self.syncer.finalized_block().await?;
+        self.finalized = finalized;
+
+        for (index, applied) in self.applied.iter().enumerate() {
+            if applied.block_number <= finalized {
+                continue;
+            }
+            if self.syncer.block_hash(applied.block_number).await? == Some(applied.block_hash) {
+                continue;
+            }
+
+            tracing::warn!(
+                batch = self.first_batch() + index,
+                block = applied.block_number,
+                "Batch was reorged out"
+            );
+            let first_unfinalized = self
+                .applied
+                .iter()
+                .position(|a| a.block_number > finalized)
+                .unwrap_or(index);
+            return self
+                .resync_from(self.first_batch() + first_unfinalized)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps snapshots only for the last finalized batch and the ones after it.
+    pub async fn prune(&mut self) -> eyre::Result<()> {
+        let finalized = self.syncer.finalized_block().await?;
+        self.finalized = finalized;
+        let last_finalized = self
+            .applied
+            .iter()
+            .rposition(|a| a.block_number <= finalized);
+
+        if let Some(last_finalized) = last_finalized {
+            for applied in &mut self.applied[..last_finalized] {
+                applied.snapshot = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "batch",
+        skip_all,
+        fields(index = self.batches(), tx_hash = %posted.tx_hash, block = posted.block_number)
+    )]
+    async fn apply(&mut self, posted: PostedBatch) -> eyre::Result<()> {
+        let batch = self.batches();
+        tracing::info!("Applying batch");
+        let rollup_tx_data = posted.transaction_data;
+
+        // Replays after a crash or overlapping sync ranges must not apply a batch twice.
+        let commit = batch::commit(&rollup_tx_data);
+        let key = applied_key(posted.tx_hash, posted.position);
+        if self.applied_keys.contains(&key) {
+            tracing::info!(%commit, "Skipping already applied batch");
+            return Ok(());
+        }
+
+        // The claimed counts are checked by the contract against its queues, the timestamp
+        // against its clock and the batch hash against the one it kept from the batch before.
+        let (deposit_count, forced_count, timestamp, batch_hash, sequencer, claimed_root) =
+            match &posted.public_values {
+                Some(public_values) => {
+                    let claimed = decode_public_values(public_values)?;
+                    (
+                        claimed.deposit_count.unwrap_or_default(),
+                        claimed.forced_count.unwrap_or_default(),
+                        claimed.batch_timestamp.unwrap_or_default(),
+                        claimed.batch_hash.unwrap_or_default(),
+                        claimed.sequencer.unwrap_or_default(),
+                        Some(claimed.final_state_root),
+                    )
+                }
+                None => (0, 0, 0, B256::ZERO, Address::ZERO, None),
+            };
+        // Transactions expire against the batch as the program saw it. An aggregate commits no
+        // timestamp per batch, so its batches are not held to the one before.
+        let mut context = BatchContext {
+            index: batch as u64,
+            previous_batch: self.previous_batch(),
+            timestamp,
+            previous_batch_hash: self.previous_batch_hash(),
+            previous_timestamp: match posted.public_values {
+                Some(_) => self.previous_timestamp(),
+                None => 0,
+            },
+            sequencer,
+            ..BatchContext::default()
+        };
+        // The proof was accepted either way; a signature that does not hold only stops the sync
+        // where the deployment requires them.
+        let require = self.processor.config.require_sequencer_signature;
+        match &posted.sequencer_signature {
+            Some(signature) => {
+                let signed = SequencerSignature::from_bytes(signature)
+                    .map_err(eyre::Report::from)
+                    .and_then(|signature| {
+                        let signed = context.signed(signature);
+                        verify_sequencer_signature(&signed, commit, &self.processor.config.domain)?;
+                        Ok(signed)
+                    });
+                match signed {
+                    Ok(signed) => {
+                        tracing::info!(%sequencer, "Batch signed by its sequencer");
+                        context = signed;
+                    }
+                    Err(err) if require => return Err(err),
+                    Err(err) => tracing::warn!(%err, "Ignoring sequencer signature"),
+                }
+            }
+            None if require => {
+                return Err(eyre::eyre!(
+                    "Batch {} is not signed by its sequencer",
+                    batch
+                ))
+            }
+            None => {}
+        }
+        let (first_deposit, first_forced) = self.next_queued();
+        let deposits = self
+            .deposits
+            .take(first_deposit, deposit_count, "Deposit")?;
+        let forced_txs = self
+            .forced_txs
+            .take(first_forced, forced_count, "Forced transaction")?;
+
+        let decoded_txs =
+            batch::decode_batch(&rollup_tx_data, self.processor.dictionary.as_deref())?;
+        let domain = self.processor.config.domain;
+
+        // The first value seen of every account the batch touches.
+        let mut before = BTreeMap::new();
+        let fees = self.processor.config.fees;
+        if !fees.is_free() {
+            before.insert(
+                fees.recipient,
+                self.processor.db.get_account(&fees.recipient)?,
+            );
+        }
+        for deposit in &deposits {
+            if let Entry::Vacant(entry) = before.entry(deposit.to) {
+                entry.insert(self.processor.db.get_account(&deposit.to)?);
+            }
+        }
+        let metrics = metrics::pipeline();
+        metrics
+            .signature_recovery_seconds
+            .observe_closure_duration(|| {
+                self.processor.senders.prefetch(&forced_txs, &domain);
+                self.processor.senders.prefetch(&decoded_txs, &domain);
+            });
+        self.record_touched(&mut before, forced_txs.iter().chain(&decoded_txs))?;
+
+        // Under `FailurePolicy::Skip` the proof covers failed transactions with failed receipts;
+        // under `Abort` a batch that fails part-way leaves nothing behind.
+        let checkpoint = self.processor.checkpoint();
+        let report = match self.processor.apply_batch_with(
+            &decoded_txs,
+            commit,
+            self.processor.config.failure_policy,
+            context,
+            Inbox {
+                deposits: &deposits,
+                forced_txs: &forced_txs,
+            },
+            |processor| {
+                metrics
+                    .merkle_rebuild_seconds
+                    .observe_closure_duration(|| processor.generate_state_root())
+            },
+            // Only the state root is checked against L1.
+            |_| Ok([0; 32]),
+        ) {
+            Ok(report) => report,
+            Err(err) => {
+                self.processor.senders.clear();
+                self.processor.revert_to(checkpoint)?;
+                return Err(err);
+            }
+        };
+        let receipts = report.receipts;
+
+        // The contract accepted the root the proof committed; a host that derives another one
+        // has diverged from the program, and every batch it applies after would build on that.
+        // The batch is rolled back and left unmarked, so a retry applies it from the same state.
+        let state_root = report.public_values.finalStateRoot;
+        if let Some(claimed_root) = claimed_root {
+            if state_root != claimed_root {
+                let diffs = self.diffs(before);
+                self.processor.revert_to(checkpoint)?;
+                let divergence = Divergence {
+                    batch: batch as u64,
+                    tx_hash: posted.tx_hash,
+                    block_number: posted.block_number,
+                    claimed_root,
+                    state_root,
+                    diffs: diffs?,
+                };
+                tracing::error!(%claimed_root, %state_root, "State diverged from L1");
+                return Err(divergence.into());
+            }
+        }
+        self.processor.commit_checkpoint(checkpoint)?;
+        self.applied_keys.insert(key);
+
+        let rejected = receipts
+            .iter()
+            .filter(|receipt| receipt.status == ReceiptStatus::Failed)
+            .count() as u64;
+        metrics.batches_synced.inc();
+        metrics.transactions_rejected.inc_by(rejected);
+        metrics
+            .transactions_applied
+            .inc_by(receipts.len() as u64 - rejected);
+
+        self.applied.push(AppliedBatch {
+            tx_hash: posted.tx_hash,
+            position: posted.position,
+            block_number: posted.block_number,
+            block_hash: posted.block_hash,
+            transaction_commit: commit,
+            batch_hash,
+            timestamp,
+            snapshot: Some(self.processor.db.snapshot_accounts()?),
+            limits: self.processor.limits.clone(),
+            next_deposit: first_deposit + deposit_count,
+            next_forced: first_forced + forced_count,
+        });
+
+        if self.attester.is_none() && self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        if let Some((signer, url)) = &self.attester {
+            let attestation = attestation::Attestation::sign(
+                signer,
+                batch as u64,
+                state_root,
+                &self.processor.config.domain,
+            )?;
+
+            if attestation::publish(url, &attestation).await? {
+                tracing::warn!(%state_root, "Aggregator reports disagreement");
+            }
+        }
+
+        if !self.hooks.is_empty() {
+            let diffs = self.diffs(before)?;
+            let event = BatchEvent {
+                index: batch as u64,
+                tx_hash: posted.tx_hash,
+                block_number: posted.block_number,
+                transaction_commit: commit,
+                state_root,
+                receipts,
+                diffs,
+            };
+            for hook in &mut self.hooks {
+                hook.on_batch(&event)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Records in `before` the current value of every account `transactions` may touch that it
+    // has not seen yet.
+    fn record_touched<'a>(
+        &self,
+        before: &mut BTreeMap<Address, Account>,
+        transactions: impl IntoIterator<Item = &'a SignedTransaction>,
+    ) -> eyre::Result<()> {
+        let domain = self.processor.config.domain;
+        for tx in transactions {
+            let sender = self.processor.senders.recover(tx, &domain).ok();
+            let sponsor = recover_sponsor(tx, &domain).ok().flatten();
+            for address in sender
+                .into_iter()
+                .chain(sponsor)
+                .chain([tx.tx.target()])
+                .chain(tx.tx.sources())
+            {
+                if let Entry::Vacant(entry) = before.entry(address) {
+                    entry.insert(self.processor.db.get_account(&address)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Pairs the first value seen of every account a batch touched with its value now.
+    fn diffs(&self, before: BTreeMap<Address, Account>) -> eyre::Result<Vec<AccountDiff>> {
+        let mut diffs = Vec::with_capacity(before.len());
+        for (address, before) in before {
+            diffs.push(AccountDiff {
+                address,
+                after: self.processor.db.get_account(&address)?,
+                before,
+            });
+        }
+        Ok(diffs)
+    }
+}
+
+/// A batch after which the follower's state root is not the one its proof committed and the
+/// contract accepted, with what the batch did to each account it touched for finding the cause.
+#[derive(Debug)]
+pub struct Divergence {
+    pub batch: u64,
+    pub tx_hash: B256,
+    pub block_number: u64,
+    /// `finalStateRoot` of the batch's public values.
+    pub claimed_root: B256,
+    /// The root the follower derived.
+    pub state_root: B256,
+    pub diffs: Vec<AccountDiff>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "State root {} after batch {} (tx {}, block {}) does not match {} accepted on L1",
+            self.state_root, self.batch, self.tx_hash, self.block_number, self.claimed_root
+        )?;
+        let changed: Vec<_> = self
+            .diffs
+            .iter()
+            .filter(|diff| diff.before != diff.after)
+            .collect();
+        write!(f, "{} accounts changed:", changed.len())?;
+        for diff in changed {
+            let json = |account: &Account| serde_json::to_string(account).map_err(|_| fmt::Error);
+            write!(f, "\n  {}", diff.address)?;
+            write!(f, "\n    before: {}", json(&diff.before)?)?;
+            write!(f, "\n    after:  {}", json(&diff.after)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+// What the applied-batch guard knows a batch by: the L1 transaction that posted it and its
+// position among the batches that transaction posted. Its transaction data would not do, as two
+// batches can post the same, empty ones that only drain the L1 queues for instance. A checkpoint's
+// batch is always alone in its transaction, at position 0.
+fn applied_key(tx_hash: B256, position: u64) -> B256 {
+    keccak256((tx_hash, position).abi_encode_packed())
+}
+
+// The applied-batch keys a follower starting from `checkpoint` skips.
+fn checkpoint_keys(checkpoint: &Checkpoint) -> Vec<B256> {
+    if checkpoint.applied_batches.is_empty() {
+        vec![applied_key(checkpoint.tx_hash, 0)]
+    } else {
+        checkpoint.applied_batches.clone()
+    }
+}
+
+// Whether `posted` is the batch `checkpoint` was taken after.
+fn is_checkpoint_batch(checkpoint: &Checkpoint, posted: &PostedBatch) -> bool {
+    posted.tx_hash == checkpoint.tx_hash
+        && batch::commit(&posted.transaction_data) == checkpoint.transaction_commit
+}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+
+use alloy::primitives::Address;
+use alloy_rlp::{Decodable, Encodable};
+use futures::StreamExt;
+use libp2p::{
+    gossipsub::{self, IdentTopic, MessageAcceptance},
+    noise,
+    swarm::SwarmEvent,
+    tcp, yamux, Multiaddr, SwarmBuilder,
+};
+use program::{recover_address_from_tx, SignedTransaction};
+
+use crate::{
+    preconf::{Gossip, GossipConfig, SoftConfirmation},
+    serve::StateReader,
+};
+
+/// Topic of transactions a replica accepted, as RLP.
+const TRANSACTIONS_TOPIC: &str = "canvas/transactions/1";
+/// Topic of the active sequencer's soft confirmations, as JSON.
+const CONFIRMATIONS_TOPIC: &str = "canvas/confirmations/1";
+
+/// Gossips with the other sequencer replicas over libp2p gossipsub until an error stops it:
+/// publishes what `outbound` receives, admits the transactions the others accepted to the next
+/// batch, and keeps the confirmations of the sequencers in `config`. Messages are signed by the
+/// peer that published them, and only forwarded once they pass the same checks here: a
+/// transaction has to decode and its signature recover, a confirmation has to be signed by a
+/// known sequencer.
+pub async fn run(
+    config: GossipConfig,
+    reader: Arc<Mutex<StateReader>>,
+    mut outbound: tokio::sync::mpsc::UnboundedReceiver<Gossip>,
+) -> eyre::Result<()> {
+    let mut swarm = SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default,
+        )?
+        .with_behaviour(
+            |key| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                let gossip_config = gossipsub::ConfigBuilder::default()
+                    .validation_mode(gossipsub::ValidationMode::Strict)
+                    .validate_messages()
+                    .build()?;
+                Ok(gossipsub::Behaviour::new(
+                    gossipsub::MessageAuthenticity::Signed(key.clone()),
+                    gossip_config,
+                )?)
+            },
+        )?
+        .build();
+
+    let transactions = IdentTopic::new(TRANSACTIONS_TOPIC);
+    let confirmations = IdentTopic::new(CONFIRMATIONS_TOPIC);
+    swarm.behaviour_mut().subscribe(&transactions)?;
+    swarm.behaviour_mut().subscribe(&confirmations)?;
+    swarm.listen_on(config.listen.parse()?)?;
+    for peer in &config.peers {
+        swarm.dial(peer.parse::<Multiaddr>()?)?;
+    }
+
+    loop {
+        tokio::select! {
+            gossip = outbound.recv() => {
+                let Some(gossip) = gossip else {
+                    return Ok(());
+                };
+                let (topic, data) = match gossip {
+                    Gossip::Transaction(tx) => {
+                        let mut data = Vec::new();
+                        tx.encode(&mut data);
+                        (transactions.clone(), data)
+                    }
+                    Gossip::Confirmation(confirmation) => {
+                        (confirmations.clone(), serde_json::to_vec(&confirmation)?)
+                    }
+                };
+                // Fails while no replica is connected, which loses nothing they would not
+                // learn from the next batch.
+                if let Err(err) = swarm.behaviour_mut().publish(topic, data) {
+                    tracing::debug!(%err, "Failed to gossip");
+                }
+            }
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::NewListenAddr { address, .. } => {
+                    tracing::info!(%address, "Gossiping");
+                }
+                SwarmEvent::Behaviour(gossipsub::Event::Message {
+                    propagation_source,
+                    message_id,
+                    message,
+                }) => {
+                    let acceptance = if message.topic == transactions.hash() {
+                        accept_transaction(&reader, &message.data)
+                    } else if message.topic == confirmations.hash() {
+                        accept_confirmation(&reader, &config.sequencers, &message.data)
+                    } else {
+                        MessageAcceptance::Ignore
+                    };
+                    let _ = swarm.behaviour_mut().report_message_validation_result(
+                        &message_id,
+                        &propagation_source,
+                        acceptance,
+                    );
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+// Rejects a transaction that does not decode or whose signature does not recover. One that is
+// valid but not admitted, e.g. because this replica already has it, is not forwarded either.
+fn accept_transaction(reader: &Mutex<StateReader>, data: &[u8]) -> MessageAcceptance {
+    let Ok(tx) = SignedTransaction::decode(&mut &data[..]) else {
+        return MessageAcceptance::Reject;
+    };
+    let mut reader = reader.lock().unwrap();
+    if recover_address_from_tx(&tx, &reader.domain()).is_err() {
+        return MessageAcceptance::Reject;
+    }
+
+    match reader.admit_gossiped(tx) {
+        Ok(()) => MessageAcceptance::Accept,
+        Err(err) => {
+            tracing::debug!("Gossiped transaction not admitted: {:#}", err);
+            MessageAcceptance::Ignore
+        }
+    }
+}
+
+fn accept_confirmation(
+    reader: &Mutex<StateReader>,
+    sequencers: &[Address],
+    data: &[u8],
+) -> MessageAcceptance {
+    let Ok(confirmation) = serde_json::from_slice::<SoftConfirmation>(data) else {
+        return MessageAcceptance::Reject;
+    };
+
+    match reader
+        .lock()
+        .unwrap()
+        .record_confirmation(confirmation, sequencers)
+    {
+        Ok(()) => MessageAcceptance::Accept,
+        Err(err) => {
+            tracing::debug!("Gossiped confirmation rejected: {:#}", err);
+            MessageAcceptance::Reject
+        }
+    }
+}
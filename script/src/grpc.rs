@@ -0,0 +1,213 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::Address;
+use alloy_rlp::Decodable;
+use program::{receipt::ReceiptStatus, Account, SignedTransaction};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{
+    hooks::BatchEvent,
+    preconf::SoftConfirmation,
+    serve::{AccountProof, BatchSummary, StateReader},
+};
+
+mod proto {
+    tonic::include_proto!("canvas.v1");
+}
+
+use proto::canvas_server::{Canvas, CanvasServer};
+
+fn status(err: impl std::fmt::Display) -> Status {
+    Status::failed_precondition(format!("{:#}", err))
+}
+
+fn parse_address(bytes: &[u8]) -> Result<Address, Status> {
+    <[u8; 20]>::try_from(bytes)
+        .map(Address::from)
+        .map_err(|_| Status::invalid_argument("Address must be 20 bytes"))
+}
+
+impl From<Account> for proto::Account {
+    fn from(account: Account) -> Self {
+        Self {
+            nonce: account.nonce,
+            data: account.data,
+            contributors: account.contributors.iter().map(|a| a.to_vec()).collect(),
+            balance: account.balance.to_be_bytes::<32>().to_vec(),
+            owner: account.owner.to_vec(),
+            policy: account.policy.to_vec(),
+            writers: account.writers.iter().map(|a| a.to_vec()).collect(),
+            contributions: account
+                .contributions
+                .into_iter()
+                .map(|(contributor, stats)| proto::ContributorStats {
+                    contributor: contributor.to_vec(),
+                    edits: stats.edits,
+                    inserted: stats.inserted,
+                    deleted: stats.deleted,
+                })
+                .collect(),
+            name: account.metadata.name,
+            uri: account.metadata.uri,
+        }
+    }
+}
+
+impl From<AccountProof> for proto::AccountProof {
+    fn from(proof: AccountProof) -> Self {
+        Self {
+            address: proof.address.to_vec(),
+            leaf_hash: proof.leaf_hash.to_vec(),
+            proof: proof.proof.iter().map(|hash| hash.to_vec()).collect(),
+            state_root: proof.state_root.to_vec(),
+        }
+    }
+}
+
+impl From<BatchSummary> for proto::BatchSummary {
+    fn from(summary: BatchSummary) -> Self {
+        Self {
+            index: summary.index,
+            tx_hash: summary.tx_hash.to_vec(),
+            block_number: summary.block_number,
+            transaction_commit: summary.transaction_commit.to_vec(),
+            state_root: summary.state_root.to_vec(),
+            tx_count: summary.tx_count as u64,
+        }
+    }
+}
+
+impl From<SoftConfirmation> for proto::SoftConfirmation {
+    fn from(confirmation: SoftConfirmation) -> Self {
+        let receipt = confirmation.receipt;
+        let status = match receipt.status {
+            ReceiptStatus::Success => proto::ReceiptStatus::Success,
+            ReceiptStatus::Failed => proto::ReceiptStatus::Failed,
+        };
+        Self {
+            tx_hash: confirmation.tx_hash.to_vec(),
+            batch: confirmation.batch,
+            receipt: Some(proto::Receipt {
+                from: receipt.from.to_vec(),
+                to: receipt.to.to_vec(),
+                ops: receipt.ops,
+                inserted: receipt.inserted,
+                deleted: receipt.deleted,
+                status: status as i32,
+            }),
+            sequencer: confirmation.sequencer.to_vec(),
+            signature: confirmation.signature.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// The `Canvas` gRPC service over the same state as the JSON-RPC methods. Batches are streamed
+/// only with `events`, i.e. when following the chain.
+pub struct CanvasService {
+    reader: Arc<Mutex<StateReader>>,
+    events: Option<broadcast::Sender<Arc<BatchEvent>>>,
+}
+
+impl CanvasService {
+    pub fn new(
+        reader: Arc<Mutex<StateReader>>,
+        events: Option<broadcast::Sender<Arc<BatchEvent>>>,
+    ) -> Self {
+        Self { reader, events }
+    }
+}
+
+#[tonic::async_trait]
+impl Canvas for CanvasService {
+    type StreamBatchesStream = ReceiverStream<Result<proto::BatchSummary, Status>>;
+
+    async fn get_account(
+        &self,
+        request: Request<proto::GetAccountRequest>,
+    ) -> Result<Response<proto::Account>, Status> {
+        let address = parse_address(&request.get_ref().address)?;
+        let account = self.reader.lock().unwrap().account(&address);
+        Ok(Response::new(account.into()))
+    }
+
+    async fn get_proof(
+        &self,
+        request: Request<proto::GetProofRequest>,
+    ) -> Result<Response<proto::AccountProof>, Status> {
+        let address = parse_address(&request.get_ref().address)?;
+        let proof = self
+            .reader
+            .lock()
+            .unwrap()
+            .proof(&address)
+            .map_err(status)?;
+        Ok(Response::new(proof.into()))
+    }
+
+    // Like `canvas_subscribeBatches`, a client more than `EVENT_CAPACITY` batches behind gets an
+    // error and the stream ends.
+    async fn stream_batches(
+        &self,
+        _request: Request<proto::StreamBatchesRequest>,
+    ) -> Result<Response<Self::StreamBatchesStream>, Status> {
+        let mut receiver = self
+            .events
+            .as_ref()
+            .ok_or_else(|| Status::failed_precondition("Not following the chain"))?
+            .subscribe();
+        let (sender, stream) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            loop {
+                let summary = match receiver.recv().await {
+                    Ok(event) => Ok(BatchSummary::from(event.as_ref()).into()),
+                    Err(RecvError::Closed) => return,
+                    Err(RecvError::Lagged(missed)) => Err(Status::data_loss(format!(
+                        "Subscriber missed {} batches",
+                        missed
+                    ))),
+                };
+                let lagged = summary.is_err();
+                // Fails once the client is gone.
+                if sender.send(summary).await.is_err() || lagged {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+
+    async fn submit_transaction(
+        &self,
+        request: Request<proto::SubmitTransactionRequest>,
+    ) -> Result<Response<proto::SoftConfirmation>, Status> {
+        let tx = SignedTransaction::decode(&mut request.get_ref().raw.as_slice())
+            .map_err(|err| Status::invalid_argument(format!("{:#}", err)))?;
+        let confirmation = self
+            .reader
+            .lock()
+            .unwrap()
+            .submit_transaction(tx)
+            .map_err(status)?;
+        Ok(Response::new(confirmation.into()))
+    }
+}
+
+/// Serves `service` over gRPC on `addr` until the server fails.
+pub async fn run(addr: SocketAddr, service: CanvasService) -> eyre::Result<()> {
+    tracing::info!(%addr, "Serving canvas state over gRPC");
+    Server::builder()
+        .add_service(CanvasServer::new(service))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::{Address, B256};
+use program::{receipt::Receipt, Account};
+
+use crate::hooks::{BatchEvent, BatchHook};
+
+/// How many batches of account history a `HistoryStore` keeps. The state root of every batch is
+/// kept either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pruning {
+    #[default]
+    Archive,
+    /// Account history of only the last `n` batches. The account state can be read back to the
+    /// batch before the oldest kept one.
+    KeepLast(u64),
+}
+
+// What a batch changed, as reverse diffs: every account it touched as it was before the batch.
+// `before` is `None` once pruned; the receipts are never pruned.
+struct BatchRecord {
+    state_root: B256,
+    block_number: u64,
+    receipts: Vec<Receipt>,
+    before: Option<BTreeMap<Address, Account>>,
+}
+
+/// State roots and reverse diffs of the batches applied so far, keyed by batch index, for
+/// reading the state as it was after an earlier batch. An account after batch `n` is its value
+/// before the first later batch that touched it, or its current value if none did, so only
+/// touched accounts are stored.
+///
+/// Pruning never drops the history after the latest batch in a finalized block, which is what
+/// reading the latest state that can no longer be reorged takes.
+#[derive(Default)]
+pub struct HistoryStore {
+    pruning: Pruning,
+    batches: BTreeMap<u64, BatchRecord>,
+    finalized: Option<u64>,
+}
+
+impl HistoryStore {
+    pub fn new(pruning: Pruning) -> Self {
+        Self {
+            pruning,
+            batches: BTreeMap::new(),
+            finalized: None,
+        }
+    }
+
+    /// Records a batch. A batch at an index already recorded was re-applied after a reorg, so
+    /// it replaces that batch and drops every later one.
+    pub fn record(&mut self, event: &BatchEvent) {
+        self.batches.split_off(&event.index);
+        self.batches.insert(
+            event.index,
+            BatchRecord {
+                state_root: event.state_root,
+                block_number: event.block_number,
+                receipts: event.receipts.clone(),
+                before: Some(
+                    event
+                        .diffs
+                        .iter()
+                        .map(|diff| (diff.address, diff.before.clone()))
+                        .collect(),
+                ),
+            },
+        );
+
+        if let Pruning::KeepLast(n) = self.pruning {
+            self.prune(n);
+        }
+    }
+
+    /// Marks `batch` as the latest batch in a finalized block.
+    pub fn set_finalized(&mut self, batch: u64) {
+        self.finalized = Some(batch);
+        if let Pruning::KeepLast(n) = self.pruning {
+            self.prune(n);
+        }
+    }
+
+    /// Drops the account history of every batch but the last `keep`, and returns how many
+    /// batches it dropped it for. Batches after the finalized one keep theirs regardless.
+    pub fn prune(&mut self, keep: u64) -> usize {
+        // Without a finalized batch yet, any of them may be the one to read back to.
+        let (Some(latest), Some(finalized)) = (self.latest(), self.finalized) else {
+            return 0;
+        };
+        let Some(end) = latest.checked_sub(keep) else {
+            return 0;
+        };
+
+        self.batches
+            .range_mut(..=end.min(finalized))
+            .filter_map(|(_, record)| record.before.take())
+            .count()
+    }
+
+    /// Every recorded batch as its index, block number and receipts, in batch order.
+    pub fn receipts(&self) -> impl Iterator<Item = (u64, u64, &[Receipt])> {
+        self.batches
+            .iter()
+            .map(|(index, record)| (*index, record.block_number, record.receipts.as_slice()))
+    }
+
+    pub fn latest(&self) -> Option<u64> {
+        self.batches.last_key_value().map(|(index, _)| *index)
+    }
+
+    pub fn state_root_at(&self, batch: u64) -> eyre::Result<B256> {
+        self.check_applied(batch)?;
+        self.batches
+            .get(&batch)
+            .map(|record| record.state_root)
+            .ok_or_else(|| eyre::eyre!("Batch {} is not in the history", batch))
+    }
+
+    /// The account at `address` right after batch `batch`, given its value now.
+    pub fn account_at(
+        &self,
+        batch: u64,
+        address: &Address,
+        current: Account,
+    ) -> eyre::Result<Account> {
+        self.check_applied(batch)?;
+        // Every batch after `batch` has to be kept to know which touched the account first.
+        let later = self.batches.range(batch + 1..);
+        let recorded =
+            matches!(self.batches.first_key_value(), Some((oldest, _)) if *oldest <= batch + 1);
+        if !recorded || later.clone().any(|(_, record)| record.before.is_none()) {
+            return Err(eyre::eyre!("Batch {} is pruned from the history", batch));
+        }
+
+        let before = later.find_map(|(_, record)| record.before.as_ref()?.get(address));
+        Ok(before.cloned().unwrap_or(current))
+    }
+
+    fn check_applied(&self, batch: u64) -> eyre::Result<()> {
+        match self.latest() {
+            Some(latest) if batch <= latest => Ok(()),
+            _ => Err(eyre::eyre!("Batch {} is not applied yet", batch)),
+        }
+    }
+}
+
+/// Records every batch the follower applies into a store the RPC layer reads as well.
+impl BatchHook for Arc<Mutex<HistoryStore>> {
+    fn on_batch(&mut self, event: &BatchEvent) -> eyre::Result<()> {
+        self.lock().unwrap().record(event);
+        Ok(())
+    }
+}
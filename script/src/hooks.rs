@@ -0,0 +1,112 @@
+use std::{fs, path::PathBuf};
+
+use alloy::primitives::{Address, B256};
+use program::{receipt::Receipt, Account};
+use serde::{Deserialize, Serialize};
+
+/// An account touched by a batch, before and after it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub address: Address,
+    pub before: Account,
+    pub after: Account,
+}
+
+/// Everything a hook sees about a batch the follower just applied.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchEvent {
+    pub index: u64,
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub transaction_commit: B256,
+    pub state_root: B256,
+    pub receipts: Vec<Receipt>,
+    /// In address order.
+    pub diffs: Vec<AccountDiff>,
+}
+
+/// What a pruned event file keeps of its `BatchEvent`: where the batch was posted and its root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrunedEvent {
+    index: u64,
+    tx_hash: B256,
+    block_number: u64,
+    transaction_commit: B256,
+    state_root: B256,
+}
+
+/// Host-side logic run after each applied batch, e.g. mirroring state elsewhere or triggering
+/// renders. An error stops the follower, so hooks that may fail transiently should retry or log
+/// internally.
+pub trait BatchHook {
+    fn on_batch(&mut self, event: &BatchEvent) -> eyre::Result<()>;
+}
+
+/// Writes each batch event as `batch_<index>.json` into a directory.
+pub struct JsonDirHook {
+    dir: PathBuf,
+}
+
+impl JsonDirHook {
+    pub fn new(dir: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Strips the receipts and account diffs from the events of every batch but the last
+    /// `keep`, leaving their index, hashes and state root, and returns how many it stripped.
+    /// Events of batches in blocks after `finalized` stay whole, as a reorg may still replace
+    /// them, and so does the latest event.
+    pub fn prune(&self, keep: u64, finalized: u64) -> eyre::Result<usize> {
+        let mut events = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let index = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("batch_")?.strip_suffix(".json"))
+                .and_then(|index| index.parse::<u64>().ok());
+            if let Some(index) = index {
+                events.push((index, path));
+            }
+        }
+        let Some(latest) = events.iter().map(|(index, _)| *index).max() else {
+            return Ok(0);
+        };
+
+        let mut pruned = 0;
+        for (index, path) in events {
+            if index == latest || latest - index < keep {
+                continue;
+            }
+            let event: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            if event.get("diffs").is_none() {
+                continue;
+            }
+            let event: PrunedEvent = serde_json::from_value(event)?;
+            if event.block_number > finalized {
+                continue;
+            }
+
+            // Written aside first, so an interrupted prune never leaves a torn event.
+            let staged = path.with_extension("json.tmp");
+            fs::write(&staged, serde_json::to_string_pretty(&event)? + "\n")?;
+            fs::rename(&staged, &path)?;
+            pruned += 1;
+        }
+
+        Ok(pruned)
+    }
+}
+
+impl BatchHook for JsonDirHook {
+    fn on_batch(&mut self, event: &BatchEvent) -> eyre::Result<()> {
+        let path = self.dir.join(format!("batch_{}.json", event.index));
+        fs::write(path, serde_json::to_string_pretty(event)? + "\n")?;
+        Ok(())
+    }
+}
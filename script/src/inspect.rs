@@ -0,0 +1,149 @@
+use alloy::primitives::Address;
+use program::{
+    batch,
+    cost::{estimate_l1_cost, L1Cost, PostingMode},
+    receipt::ReceiptStatus,
+    CanvasProcessor, InMemoryDB,
+};
+
+use crate::{render::DEFAULT_WIDTH, serve::StateReader};
+
+// Renders each palette entry as a block in the matching ANSI 16-color background.
+fn render(data: &str) -> String {
+    let chars: Vec<char> = data.chars().collect();
+
+    chars
+        .chunks(DEFAULT_WIDTH)
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|c| match c.to_digit(16) {
+                    Some(d) if d < 8 => format!("\x1b[{}m  ", 40 + d),
+                    Some(d) => format!("\x1b[{}m  ", 100 + d - 8),
+                    None => format!("\x1b[0m{:2}", c),
+                })
+                .collect();
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn print_account(reader: &StateReader, address: &Address) -> eyre::Result<()> {
+    let account = reader.account(address);
+
+    println!("Address:      {}", address);
+    println!("Nonce:        {}", account.nonce);
+    println!("Data length:  {}", account.data.chars().count());
+    if account.owner != Address::ZERO {
+        println!("Owner:        {}", account.owner);
+        println!("Writers:      {}", account.writers.len());
+        for writer in &account.writers {
+            println!("  {}", writer);
+        }
+    }
+    println!("Contributors: {}", account.contributors.len());
+    for contributor in &account.contributors {
+        println!("  {}", contributor);
+    }
+
+    match reader.proof(address) {
+        Ok(proof) => {
+            println!("Leaf hash:    {}", proof.leaf_hash);
+            println!("State root:   {}", proof.state_root);
+            println!("Proof:");
+            for node in proof.proof {
+                println!("  {}", node);
+            }
+        }
+        Err(err) => println!("Proof:        unavailable ({})", err),
+    }
+
+    if !account.data.is_empty() {
+        println!("Data:\n{}", account.data);
+        println!("{}", render(&account.data));
+    }
+
+    Ok(())
+}
+
+// The gas of `cost`, and what it comes to in ETH when the price is known.
+fn format_cost(cost: &L1Cost, gas_price: Option<u128>) -> String {
+    match gas_price {
+        Some(gas_price) => format!(
+            "{} gas, {:.6} ETH at {:.3} gwei",
+            cost.gas,
+            cost.wei as f64 / 1e18,
+            gas_price as f64 / 1e9
+        ),
+        None => format!("{} gas", cost.gas),
+    }
+}
+
+/// Decodes `transaction_data` and prints each transaction in execution order with how it fares
+/// against the processor's state, then the state root the batch would leave and what posting it
+/// costs on L1 at `gas_price` as calldata and at `blob_gas_price` as blobs. Deposits and forced
+/// transactions are not included.
+pub fn print_batch(
+    processor: &CanvasProcessor<InMemoryDB>,
+    transaction_data: &[u8],
+    gas_price: Option<u128>,
+    blob_gas_price: Option<u128>,
+) -> eyre::Result<()> {
+    let transactions = batch::decode_batch(transaction_data, processor.dictionary.as_deref())?;
+    let simulation = processor.simulate_batch(&transactions)?;
+
+    println!("Commit:       {}", batch::commit(transaction_data));
+    println!("Transactions: {}", transactions.len());
+    for simulated in &simulation.transactions {
+        let tx = &transactions[simulated.index].tx;
+        let ops = tx.edit_ops();
+
+        println!("#{} {}", simulated.index, simulated.tx_hash);
+        // A failed receipt's sender is zero when the signature does not recover.
+        println!("  Sender:     {}", simulated.receipt.from);
+        println!("  Target:     {}", tx.target());
+        println!("  Version:    {}", tx.version);
+        println!("  Nonce:      {}", tx.nonce);
+        println!("  Ops:        {}", ops.len());
+        for op in &ops {
+            println!("    {:?}", op);
+        }
+        match (&simulated.error, simulated.receipt.status) {
+            (Some(err), _) => println!("  Result:     failed, {}", err),
+            (None, ReceiptStatus::Failed) => println!("  Result:     failed"),
+            (None, ReceiptStatus::Success) => println!("  Result:     ok"),
+        }
+    }
+
+    println!(
+        "Succeeded:    {} of {}",
+        simulation.succeeded(),
+        transactions.len()
+    );
+    println!("State root:   {}", simulation.state_root);
+
+    let calldata = estimate_l1_cost(
+        transaction_data,
+        PostingMode::Calldata,
+        gas_price.unwrap_or_default(),
+    );
+    let blob = estimate_l1_cost(
+        transaction_data,
+        PostingMode::Blob,
+        blob_gas_price.unwrap_or_default(),
+    );
+    println!(
+        "Calldata:     {} bytes, {} zero, {}",
+        calldata.bytes,
+        calldata.zero_bytes,
+        format_cost(&calldata, gas_price)
+    );
+    println!(
+        "Blobs:        {}, {}",
+        blob.blobs,
+        format_cost(&blob, blob_gas_price)
+    );
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::serve::StateReader;
+
+/// Where sequencer replicas keep the lease that makes one of them the leader. A lease is held
+/// until it lapses `ttl` after its holder last took or renewed it.
+pub trait LeaseStore: Send {
+    /// Takes the lease for `holder`, or renews it if `holder` already has it, unless another
+    /// holder's lease has not lapsed yet. Returns whether `holder` has it now.
+    fn try_acquire(&mut self, holder: &str, ttl: Duration) -> eyre::Result<bool>;
+}
+
+/// Keeps a replica's standing as leader in step with the lease: only the leader takes
+/// transactions for the next batch, the others serve reads and queue what the leader gossips,
+/// ready to take over once its lease lapses.
+pub struct Leadership {
+    store: Box<dyn LeaseStore>,
+    holder: String,
+    ttl: Duration,
+}
+
+impl Leadership {
+    pub fn new(store: Box<dyn LeaseStore>, holder: String, ttl: Duration) -> Self {
+        Self { store, holder, ttl }
+    }
+
+    /// Takes or renews the lease every third of its ttl and tells `reader` whether it leads.
+    /// A replica that cannot reach the store steps down, as its lease may lapse meanwhile.
+    pub async fn run(mut self, reader: Arc<Mutex<StateReader>>) -> eyre::Result<()> {
+        let mut leading = false;
+        reader.lock().unwrap().set_leading(leading);
+
+        loop {
+            let acquired = match self.store.try_acquire(&self.holder, self.ttl) {
+                Ok(acquired) => acquired,
+                Err(err) => {
+                    tracing::warn!("Failed to renew the sequencer lease: {:#}", err);
+                    false
+                }
+            };
+            if acquired != leading {
+                leading = acquired;
+                reader.lock().unwrap().set_leading(leading);
+                match leading {
+                    true => tracing::info!(holder = %self.holder, "Took the sequencer lease"),
+                    false => tracing::info!(holder = %self.holder, "Lost the sequencer lease"),
+                }
+            }
+
+            tokio::time::sleep(self.ttl / 3).await;
+        }
+    }
+}
@@ -1,63 +1,773 @@
-use std::io::Read;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use alloy::{
-    primitives::b256,
+    primitives::{Bytes, B256},
     providers::{Provider, ProviderBuilder},
-    sol,
-    sol_types::SolCall,
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use alloy_rlp::{Decodable, Encodable};
+use clap::Parser;
+use cli::{Chain, Cli, Command, Config, ProveArgs, SignTxArgs};
+use program::{
+    bridge::Inbox, builder::TxBuilder, commitment::CommitmentVersion, config::CanvasConfig,
+    diff::StateDiff, edit::EditOp, mempool::Mempool, sequencer::sequenced_batch_hash,
+    signing::SigningDomain, witness::Witness, InMemoryDB, Input, SignedTransaction, Transaction,
 };
-use alloy_rlp::Decodable;
-use flate2::read::ZlibDecoder;
-use program::{CanvasProcessor, InMemoryDB, SignedTransaction};
-
-sol!(
-    /// @notice Verifies the submission of a batch of txs with a zk proof.
-    /// @param _publicValuesBytes The zk proof of a state transition.
-    /// @param _proofBytes The encoded public values.
-    /// @param _transactionData The transaction data included in the batch.
-    function submitBatchWithProof(
-        bytes calldata _publicValuesBytes,
-        bytes calldata _proofBytes,
-        bytes calldata _transactionData
-    ) public;
-);
+use tracing_subscriber::EnvFilter;
+
+mod analytics;
+mod attestation;
+mod audit;
+mod beacon;
+mod cache;
+mod checkpoint;
+mod cli;
+mod dev;
+mod ens;
+mod follow;
+#[cfg(feature = "p2p")]
+mod gossip;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod hooks;
+mod inspect;
+mod leader;
+mod metrics;
+mod node;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod preconf;
+mod prover;
+mod providers;
+mod render;
+mod serve;
+mod submit;
+mod sync;
+mod vectors;
+mod wallet;
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
-    let rpc_url = "https://eth.merkle.io".parse()?;
-    let provider = ProviderBuilder::new().on_http(rpc_url);
-    let mut processor = CanvasProcessor {
-        db: &InMemoryDB::default(),
-    };
+    // Logs go to stderr, leaving stdout to what a command prints.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+
+    match cli.command {
+        Command::Sync {
+            chain,
+            follow,
+            poll_interval,
+            attest_key,
+            attest_url,
+        } => {
+            // Optionally attesting each derived root to an aggregator.
+            let attester = match attest_key {
+                Some(key) => Some((key.parse::<PrivateKeySigner>()?, attest_url)),
+                None => None,
+            };
+            let mut follower = sync(&chain.resolve(config)?, attester).await?;
+
+            if follow {
+                return follower.follow(Duration::from_secs(poll_interval)).await;
+            }
+
+            let processor = follower.into_processor();
+            let final_state_root = metrics::pipeline()
+                .merkle_rebuild_seconds
+                .observe_closure_duration(|| processor.generate_state_root())
+                .expect("Failed to generate final state root");
+            println!("Final state root: 0x{}", hex::encode(final_state_root));
+            Ok(())
+        }
+        Command::Prove(args) => prove(args, config).await,
+        Command::Submit {
+            chain,
+            public_values,
+            proof,
+            transaction_data,
+        } => {
+            let chain = chain.resolve(config)?;
+            let read = |path: &Path| -> eyre::Result<Bytes> { Ok(std::fs::read(path)?.into()) };
+
+            let mut submitter = submitter(&chain)?;
+            let tx_hash = submitter
+                .submit(
+                    read(&public_values)?,
+                    read(&proof)?,
+                    read(&transaction_data)?,
+                )
+                .await?;
+            let tx_hash = submitter.confirm(tx_hash).await?;
+            tracing::info!(%tx_hash, "Batch submission mined");
+            Ok(())
+        }
+        Command::Serve {
+            chain,
+            rpc_addr,
+            no_profiles,
+            profile_ttl,
+            history_depth,
+            follow,
+            poll_interval,
+            sequencer_key,
+            grpc_addr,
+            gossip_listen,
+            gossip_peers,
+            gossip_sequencers,
+            lease_url,
+            lease_ttl,
+        } => {
+            let node = node::NodeConfig::from(&config);
+            let chain = chain.resolve(config)?;
+            let pruning =
+                history_depth.map_or(history::Pruning::Archive, history::Pruning::KeepLast);
+            let history = Arc::new(Mutex::new(history::HistoryStore::new(pruning)));
+            let follower = sync_with_history(&chain, None, Some(history.clone())).await?;
 
-    let txs = [
-        b256!("efe792bb5130db405b2d7feb683a6bb4d1ec002e88843cd478dcfd5105d1d964"),
-        b256!("5624feb01173396f3c26169ac4bc4122525a7a90f38c3851bebb9becf73d1ab8"),
-        b256!("25a64b120544a2e45974823e152fa7dd407c6ec3746a77a5ed054079cbd45027"),
-    ];
+            let profiles = if no_profiles {
+                None
+            } else {
+                Some(ens::ProfileRegistry::new(
+                    &chain.rpc_url,
+                    Duration::from_secs(profile_ttl),
+                )?)
+            };
+            let sequencer = match sequencer_key {
+                Some(key) => Some(preconf::Sequencer::new(
+                    key.parse::<PrivateKeySigner>()?,
+                    Mempool::default(),
+                    follower.batches() as u64,
+                )),
+                None => None,
+            };
+            let sequencer = match (sequencer, gossip_listen) {
+                (Some(sequencer), Some(listen)) => {
+                    Some(sequencer.with_gossip(preconf::GossipConfig {
+                        listen,
+                        peers: gossip_peers,
+                        sequencers: gossip_sequencers,
+                    }))
+                }
+                (None, Some(_)) => {
+                    return Err(eyre::eyre!("--gossip-listen needs --sequencer-key"))
+                }
+                (sequencer, None) => sequencer,
+            };
+            let sequencer = match (sequencer, lease_url) {
+                (Some(sequencer), Some(url)) => {
+                    let leadership = leader::Leadership::new(
+                        lease_store(&url).await?,
+                        sequencer.address().to_string(),
+                        Duration::from_secs(lease_ttl),
+                    );
+                    Some(sequencer.with_leadership(leadership))
+                }
+                (None, Some(_)) => return Err(eyre::eyre!("--lease-url needs --sequencer-key")),
+                (sequencer, None) => sequencer,
+            };
+            let follow = follow.then(|| Duration::from_secs(poll_interval));
+            serve::run(
+                rpc_addr,
+                follower,
+                follow,
+                profiles,
+                Some(history),
+                sequencer,
+                grpc_addr,
+                node::Lifecycle {
+                    config: node,
+                    watcher: node::ConfigWatcher::new(cli.config),
+                    shutdown: node::ShutdownCoordinator::new(chain.data_dir.clone()),
+                },
+            )
+            .await
+        }
+        Command::Inspect {
+            chain,
+            address,
+            l1_tx,
+            batch_file,
+            gas_price,
+            blob_gas_price,
+        } => {
+            let chain = chain.resolve(config)?;
+            // Fetched before syncing, so a bad hash fails fast.
+            let batches = match (l1_tx, batch_file) {
+                (Some(tx_hash), _) => {
+                    let syncer = sync::Syncer::new(chain.sync_config()?)?;
+                    let posted = syncer.posted_batches(tx_hash).await?;
+                    if posted.is_empty() {
+                        return Err(eyre::eyre!("Transaction {} posted no batch", tx_hash));
+                    }
+                    posted
+                        .into_iter()
+                        .map(|batch| batch.transaction_data.to_vec())
+                        .collect()
+                }
+                (None, Some(path)) => vec![std::fs::read(path)?],
+                (None, None) => Vec::new(),
+            };
 
-    for tx in txs {
-        let res = provider.get_transaction_by_hash(tx).await?.unwrap();
-        let decoded: submitBatchWithProofCall =
-            submitBatchWithProofCall::abi_decode(&res.input, true)?;
-        let rollup_tx_data = decoded._transactionData;
+            let processor = sync(&chain, None).await?.into_processor();
+            if let Some(address) = address {
+                return inspect::print_account(&serve::StateReader::new(processor), &address);
+            }
+            let (gas_price, blob_gas_price) = l1_prices(&chain, gas_price, blob_gas_price).await;
+            for (index, transaction_data) in batches.iter().enumerate() {
+                if batches.len() > 1 {
+                    println!("Batch {} of {}", index + 1, batches.len());
+                }
+                inspect::print_batch(&processor, transaction_data, gas_price, blob_gas_price)?;
+            }
+            Ok(())
+        }
+        Command::Stats {
+            chain,
+            account,
+            limit,
+        } => {
+            let history = Arc::new(Mutex::new(history::HistoryStore::default()));
+            sync_with_history(&chain.resolve(config)?, None, Some(history.clone())).await?;
+            let history = history.lock().unwrap();
 
-        let mut d = ZlibDecoder::new(rollup_tx_data.as_ref());
-        let mut bytes = Vec::<u8>::new();
-        d.read_to_end(&mut bytes).unwrap();
+            let summary = analytics::summary(&history);
+            println!("Batches:        {}", summary.batches);
+            println!("Transactions:   {}", summary.transactions);
+            println!("Succeeded:      {}", summary.succeeded);
+            println!("Unique senders: {}", summary.unique_senders);
+            println!("Inserted:       {}", summary.inserted);
+            println!("Deleted:        {}", summary.deleted);
 
-        let decoded_txs = Vec::<SignedTransaction>::decode(&mut bytes.as_slice())?;
+            println!("Top writers:");
+            for writer in analytics::top_writers(&history, limit) {
+                println!(
+                    "  {} {} edits, {} inserted, {} deleted",
+                    writer.sender, writer.stats.edits, writer.stats.inserted, writer.stats.deleted
+                );
+            }
+            if let Some(account) = account {
+                println!("Top contributors to {}:", account);
+                for entry in analytics::top_contributors(&history, &account, limit) {
+                    println!(
+                        "  {} {} edits, {} inserted, {} deleted",
+                        entry.contributor,
+                        entry.stats.edits,
+                        entry.stats.inserted,
+                        entry.stats.deleted
+                    );
+                }
+            }
 
-        for rollup_tx in decoded_txs {
-            processor.apply_transaction(&rollup_tx)?;
+            println!("Latest batches:");
+            let from = (summary.batches as u64).saturating_sub(limit as u64);
+            for batch in analytics::batch_activity(&history, from, limit) {
+                println!(
+                    "  {} (block {}) {} of {} succeeded, {} senders, {} inserted, {} deleted",
+                    batch.index,
+                    batch.block_number,
+                    batch.succeeded,
+                    batch.transactions,
+                    batch.senders,
+                    batch.inserted,
+                    batch.deleted
+                );
+            }
+            Ok(())
+        }
+        Command::Render {
+            chain,
+            address,
+            out,
+            width,
+            scale,
+        } => {
+            let processor = sync(&chain.resolve(config)?, None).await?.into_processor();
+            let account = serve::StateReader::new(processor).account(&address);
+            render::Canvas::new(&account.data, width, scale)?.write(&out)?;
+            tracing::info!(%address, out = %out.display(), "Rendered account");
+            Ok(())
+        }
+        Command::Dev {
+            rpc_addr,
+            codec,
+            ordering,
+        } => {
+            let (codec, dictionary) = codec.resolve(&config)?;
+            let ordering = ordering_policy(ordering.or(config.ordering))?;
+            dev::run(rpc_addr, codec, dictionary, ordering).await
+        }
+        Command::Devnet {
+            rpc_addr,
+            state_dir,
+            seal_interval,
+            codec,
+            ordering,
+        } => {
+            let (codec, dictionary) = codec.resolve(&config)?;
+            let ordering = ordering_policy(ordering.or(config.ordering))?;
+            dev::devnet(
+                rpc_addr,
+                state_dir,
+                Duration::from_millis(seal_interval),
+                codec,
+                dictionary,
+                ordering,
+            )
+            .await
+        }
+        Command::AggregateProofs {
+            chain,
+            batches,
+            elf,
+            aggregate_elf,
+            out,
+            submit,
+        } => {
+            let chain = chain.resolve(config)?;
+            let batches = batches
+                .iter()
+                .map(|dir| prover::Artifacts::read(dir))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            let artifacts = prover::aggregate(&batches, &elf, &aggregate_elf)?;
+
+            let out = out.unwrap_or_else(|| chain.data_dir.join("aggregate"));
+            artifacts.write(&out)?;
+            tracing::info!(dir = %out.display(), "Wrote aggregate proof artifacts");
+
+            if submit {
+                let mut submitter = submitter(&chain)?;
+                let tx_hash = submitter
+                    .submit_aggregated(
+                        artifacts.public_values,
+                        artifacts.proof,
+                        artifacts.transaction_data,
+                    )
+                    .await?;
+                let tx_hash = submitter.confirm(tx_hash).await?;
+                tracing::info!(%tx_hash, "Aggregate submission mined");
+            }
+            Ok(())
+        }
+        Command::Aggregate { rpc_addr } => attestation::run(rpc_addr, Default::default()).await,
+        Command::Genesis { file, commitment } => {
+            let version = match commitment.or(config.commitment) {
+                Some(version) => CommitmentVersion::from_u8(version)
+                    .ok_or_else(|| eyre::eyre!("Unknown commitment version {}", version))?,
+                None => CommitmentVersion::default(),
+            };
+            let genesis = cli::load_genesis(&file)?;
+            println!("Accounts: {}", genesis.accounts.len());
+            println!(
+                "Genesis state root: 0x{}",
+                hex::encode(genesis.state_root(version))
+            );
+            Ok(())
+        }
+        Command::Prune { chain, keep } => {
+            let chain = chain.resolve(config)?;
+            let dir = chain.hook_dir.as_ref().ok_or_else(|| {
+                eyre::eyre!("--hook-dir <DIR> or `hook-dir` in the config is required")
+            })?;
+            let finalized = sync::Syncer::new(chain.sync_config()?)?
+                .finalized_block()
+                .await?;
+            let pruned = hooks::JsonDirHook::new(dir)?.prune(keep, finalized)?;
+            tracing::info!(pruned, dir = %dir.display(), "Pruned batch events");
+            Ok(())
+        }
+        Command::ExportState {
+            chain,
+            snapshot,
+            out,
+        } => {
+            let db = match snapshot {
+                Some(path) => InMemoryDB::from_snapshot(&std::fs::read(path)?)?,
+                None => {
+                    sync(&chain.resolve(config)?, None)
+                        .await?
+                        .into_processor()
+                        .db
+                }
+            };
+            std::fs::write(&out, db.export_json()?)?;
+            tracing::info!(
+                accounts = db.accounts.borrow().len(),
+                out = %out.display(),
+                "Exported state"
+            );
+            Ok(())
         }
+        Command::ImportState { file, out } => {
+            let db = InMemoryDB::import_json(&std::fs::read_to_string(&file)?)?;
+            std::fs::write(&out, db.snapshot_accounts()?)?;
+            tracing::info!(
+                accounts = db.accounts.borrow().len(),
+                out = %out.display(),
+                "Imported state"
+            );
+            Ok(())
+        }
+        Command::StateDiff { before, after } => {
+            let load = |path: &Path| -> eyre::Result<InMemoryDB> {
+                InMemoryDB::from_snapshot(&std::fs::read(path)?)
+            };
+            let diff = StateDiff::between(&load(&before)?, &load(&after)?);
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+            Ok(())
+        }
+        Command::GenVectors {
+            out,
+            seed,
+            random_batches,
+        } => vectors::generate(
+            &out,
+            vectors::Options {
+                seed,
+                random_batches,
+            },
+        ),
+        Command::DecodePublicValues { chain, tx, hex } => {
+            decode_public_values(&chain.resolve(config)?, tx, hex).await
+        }
+        Command::SignTx(args) => sign_tx(args).await,
     }
+}
 
-    let final_state_root = processor
-        .generate_state_root()
-        .expect("Failed to generate final state root");
+// The ordering policy `name`, `fifo` if unset.
+fn ordering_policy(
+    name: Option<String>,
+) -> eyre::Result<Box<dyn program::ordering::OrderingPolicy>> {
+    let name = name.unwrap_or("fifo".to_string());
+    program::ordering::policy(&name).ok_or_else(|| eyre::eyre!("Unknown ordering {}", name))
+}
 
-    println!("Final state root: 0x{}", hex::encode(final_state_root));
+// Replays the rollup up to the head.
+async fn sync(
+    chain: &Chain,
+    attester: Option<(PrivateKeySigner, String)>,
+) -> eyre::Result<follow::Follower> {
+    sync_with_history(chain, attester, None).await
+}
+
+// `sync`, recording every batch into `history`.
+async fn sync_with_history(
+    chain: &Chain,
+    attester: Option<(PrivateKeySigner, String)>,
+    history: Option<Arc<Mutex<history::HistoryStore>>>,
+) -> eyre::Result<follow::Follower> {
+    if let Some(addr) = chain.metrics_addr {
+        metrics::serve_prometheus(addr).await?;
+    }
+
+    let mut follower = follow::Follower::new(sync::Syncer::new(chain.sync_config()?)?, attester)
+        .with_config(CanvasConfig {
+            commitment: chain.commitment.unwrap_or_default(),
+            high_s_signatures: chain.high_s_signatures,
+            codec: chain.codec,
+            edit_cooldown: chain.edit_cooldown,
+            undo_window: chain.undo_window,
+            require_sequencer_signature: chain.require_sequencer_signature,
+            failure_policy: chain.failure_policy,
+            ..CanvasConfig::default()
+        })
+        .with_genesis(chain.genesis.clone())
+        .with_dictionary(chain.dictionary.clone());
+    if let Some(url) = &chain.checkpoint_url {
+        follower = follower
+            .with_checkpoint(checkpoint::fetch(url).await?)
+            .await?;
+    }
+    if let Some(dir) = &chain.hook_dir {
+        follower = follower.with_hook(hooks::JsonDirHook::new(dir)?);
+    }
+    if let Some(url) = &chain.postgres_url {
+        follower = with_postgres(follower, url).await?;
+    }
+    if let Some(history) = history {
+        follower = follower.with_hook(history);
+    }
+    follower.catch_up().await?;
+
+    Ok(follower)
+}
+
+#[cfg(feature = "postgres")]
+async fn with_postgres(follower: follow::Follower, url: &str) -> eyre::Result<follow::Follower> {
+    Ok(follower.with_hook(postgres::PostgresAccountDB::connect(url).await?))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn with_postgres(_follower: follow::Follower, _url: &str) -> eyre::Result<follow::Follower> {
+    Err(eyre::eyre!(
+        "--postgres-url needs a build with the `postgres` feature"
+    ))
+}
+
+#[cfg(feature = "postgres")]
+async fn lease_store(url: &str) -> eyre::Result<Box<dyn leader::LeaseStore>> {
+    Ok(Box::new(postgres::PostgresLease::connect(url).await?))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn lease_store(_url: &str) -> eyre::Result<Box<dyn leader::LeaseStore>> {
+    Err(eyre::eyre!(
+        "--lease-url needs a build with the `postgres` feature"
+    ))
+}
+
+// Proves the pending batch in `--batch` on top of the synced state.
+async fn prove(args: ProveArgs, config: Config) -> eyre::Result<()> {
+    let chain = args.chain.resolve(config)?;
+    let transactions = std::fs::read_to_string(&args.batch)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let bytes = hex::decode(line.trim().trim_start_matches("0x"))?;
+            Ok(SignedTransaction::decode(&mut bytes.as_slice())?)
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let sequencer_key = args
+        .sequencer_key
+        .map(|key| key.parse::<PrivateKeySigner>())
+        .transpose()?;
+    let follower = sync(&chain, None).await?;
+    let batch = follower.next_batch().await?.sequenced_by(
+        sequencer_key
+            .as_ref()
+            .map_or(args.sequencer, |key| key.address()),
+    );
+    let deposits = follower.pending_deposits();
+    let forced_txs = follower.pending_forced_txs();
+    let processor = follower.into_processor();
+    // Encoded once, so the proof commits to exactly the bytes that get posted.
+    let transaction_data = program::batch::encode_batch(
+        &transactions,
+        processor.config.codec,
+        processor.dictionary.as_deref(),
+    )?;
+    let batch = match &sequencer_key {
+        Some(key) => {
+            let hash = sequenced_batch_hash(
+                &batch,
+                program::batch::commit(&transaction_data),
+                &processor.config.domain,
+            );
+            batch.signed(key.sign_hash_sync(&hash)?.into())
+        }
+        None => batch,
+    };
+
+    // With `--witness` the guest only reads the accounts the batch touches.
+    let input = if args.witness {
+        Input {
+            witness: Some(Witness::build(
+                &processor.db,
+                &transactions,
+                Inbox {
+                    deposits: &deposits,
+                    forced_txs: &forced_txs,
+                },
+                &processor.config,
+            )),
+            transaction_data,
+            db: InMemoryDB::default(),
+            config: processor.config,
+            batch,
+            deposits,
+            forced_txs,
+            limits: processor.limits,
+            dictionary: processor.dictionary,
+        }
+    } else {
+        Input {
+            transaction_data,
+            db: processor.db,
+            config: processor.config,
+            batch,
+            deposits,
+            forced_txs,
+            limits: processor.limits,
+            witness: None,
+            dictionary: processor.dictionary,
+        }
+    };
+    let input_metrics = metrics::InputMetrics::collect(&input)?;
+    tracing::info!(
+        input_bytes = input_metrics.input_bytes,
+        witness_bytes = input_metrics.witness_bytes,
+        accounts = input_metrics.accounts,
+        missing_accounts = input_metrics.missing_accounts,
+        proof_hashes = input_metrics.proof_hashes,
+        "Built prover input"
+    );
+
+    let encoding = match args.borsh {
+        true => program::chunked::Encoding::Borsh,
+        false => program::chunked::Encoding::Bincode,
+    };
+    let artifacts = prover::prove(&input, &args.elf, args.compressed, encoding)?;
+
+    let out = args.out.unwrap_or_else(|| chain.data_dir.join("proof"));
+    artifacts.write(&out)?;
+    std::fs::write(
+        out.join("input_metrics.json"),
+        serde_json::to_string_pretty(&input_metrics)? + "\n",
+    )?;
+    tracing::info!(dir = %out.display(), "Wrote proof artifacts");
+
+    if args.submit {
+        let mut submitter = submitter(&chain)?;
+        let tx_hash = match &input.batch.sequencer_signature {
+            Some(signature) => {
+                submitter
+                    .submit_signed(
+                        artifacts.public_values,
+                        artifacts.proof,
+                        artifacts.transaction_data,
+                        signature,
+                    )
+                    .await?
+            }
+            None => {
+                submitter
+                    .submit(
+                        artifacts.public_values,
+                        artifacts.proof,
+                        artifacts.transaction_data,
+                    )
+                    .await?
+            }
+        };
+        let tx_hash = submitter.confirm(tx_hash).await?;
+        tracing::info!(%tx_hash, "Batch submission mined");
+    }
+
+    Ok(())
+}
+
+// The gas price and blob base fee given, or else the L1's current ones. Either is `None` when the
+// L1 cannot be asked, as when replaying offline.
+async fn l1_prices(
+    chain: &Chain,
+    gas_price: Option<u128>,
+    blob_gas_price: Option<u128>,
+) -> (Option<u128>, Option<u128>) {
+    let provider = match chain.rpc_url.parse() {
+        Ok(url) => ProviderBuilder::new().on_http(url),
+        Err(_) => return (gas_price, blob_gas_price),
+    };
+    let gas_price = match gas_price {
+        Some(price) => Some(price),
+        None => provider.get_gas_price().await.ok(),
+    };
+    let blob_gas_price = match blob_gas_price {
+        Some(price) => Some(price),
+        None => provider.get_blob_base_fee().await.ok(),
+    };
+    (gas_price, blob_gas_price)
+}
+
+// A submitter for the keys in `SUBMIT_KEYS`, keeping its submissions in flight in the data dir.
+fn submitter(chain: &Chain) -> eyre::Result<submit::Submitter> {
+    std::fs::create_dir_all(&chain.data_dir)?;
+    submit::Submitter::new(chain.rpc_url.clone(), chain.rollup()?, submit_keys()?)
+        .with_state_file(chain.data_dir.join("submissions.json"))
+}
+
+// The comma separated submission keys in `SUBMIT_KEYS`.
+fn submit_keys() -> eyre::Result<Vec<PrivateKeySigner>> {
+    Ok(std::env::var("SUBMIT_KEYS")?
+        .split(',')
+        .map(|key| key.trim().parse::<PrivateKeySigner>())
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+// Prints the public values of a submission (`--tx`) or of raw bytes (`--hex`).
+async fn decode_public_values(
+    chain: &Chain,
+    tx: Option<B256>,
+    hex_bytes: Option<String>,
+) -> eyre::Result<()> {
+    if let Some(hex_bytes) = hex_bytes {
+        let bytes = hex::decode(hex_bytes.trim_start_matches("0x"))?;
+        audit::print_claimed(&program::public_values::decode_public_values(&bytes)?);
+        return Ok(());
+    }
+
+    let tx_hash = tx.ok_or_else(|| eyre::eyre!("--tx <HASH> or --hex <BYTES> is required"))?;
+    let submission = audit::fetch_submission(&chain.rpc_url, tx_hash).await?;
+    audit::print_claimed(&submission.claimed);
+    if !submission.commit_matches {
+        println!("Transaction commit does not match the posted transaction data");
+    }
 
     Ok(())
 }
+
+// Prints a signed transaction in hex, as `prove --batch` and `canvas_sendRawTransaction` take it.
+async fn sign_tx(args: SignTxArgs) -> eyre::Result<()> {
+    let ops: Vec<EditOp> = serde_json::from_str(&args.ops)?;
+    let tx = TxBuilder::new(args.to)
+        .version(args.version)
+        .nonce(args.nonce)
+        .extra(args.extra)
+        .valid_until_batch(args.valid_until_batch)
+        .valid_until_timestamp(args.valid_until_timestamp)
+        .canvas(args.canvas)
+        .access_list(args.access_list)
+        .ops(ops)
+        .build()?;
+    let domain = SigningDomain {
+        chain_id: args.chain_id,
+        verifying_contract: args.rollup.unwrap_or_default(),
+    };
+
+    let signed = if args.ledger {
+        ledger_sign(tx, args.index, &domain).await?
+    } else {
+        let signer = match std::env::var("MNEMONIC") {
+            Ok(phrase) => wallet::mnemonic_signer(&phrase, args.index)?,
+            Err(_) => std::env::var("SIGNER_KEY")?.parse::<PrivateKeySigner>()?,
+        };
+        wallet::sign_transaction(tx, &signer, &domain).await?
+    };
+
+    let mut raw = Vec::new();
+    signed.encode(&mut raw);
+    println!("0x{}", hex::encode(raw));
+    Ok(())
+}
+
+#[cfg(feature = "ledger")]
+async fn ledger_sign(
+    tx: Transaction,
+    index: u32,
+    domain: &SigningDomain,
+) -> eyre::Result<SignedTransaction> {
+    let signer = wallet::ledger_signer(index as usize).await?;
+    wallet::sign_transaction(tx, &signer, domain).await
+}
+
+#[cfg(not(feature = "ledger"))]
+async fn ledger_sign(
+    _tx: Transaction,
+    _index: u32,
+    _domain: &SigningDomain,
+) -> eyre::Result<SignedTransaction> {
+    Err(eyre::eyre!(
+        "--ledger needs a build with the `ledger` feature"
+    ))
+}
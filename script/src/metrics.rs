@@ -0,0 +1,261 @@
+use std::{net::SocketAddr, sync::LazyLock};
+
+use alloy_rlp::Encodable;
+use program::{InMemoryDB, Input};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, Registry, TextEncoder,
+};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+// Upper bounds, in bytes, of the size histogram buckets. A last bucket goes up to the configured
+// maximum account size.
+const BUCKETS: [usize; 6] = [0, 16, 64, 256, 1024, 4096];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeBucket {
+    /// Largest data size, in bytes, counted in this bucket.
+    pub max_size: usize,
+    pub accounts: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateMetrics {
+    pub accounts: u64,
+    pub data_bytes: u64,
+    pub largest_account: u64,
+    pub sizes: Vec<SizeBucket>,
+}
+
+impl StateMetrics {
+    pub fn collect(db: &InMemoryDB, max_size: usize) -> Self {
+        let mut sizes: Vec<SizeBucket> = BUCKETS
+            .iter()
+            .chain(std::iter::once(&max_size))
+            .map(|&max_size| SizeBucket {
+                max_size,
+                accounts: 0,
+            })
+            .collect();
+
+        let mut metrics = Self::default();
+        for account in db.accounts.borrow().values() {
+            let size = account.data.len();
+            metrics.accounts += 1;
+            metrics.data_bytes += size as u64;
+            metrics.largest_account = metrics.largest_account.max(size as u64);
+
+            let bucket = sizes
+                .iter()
+                .position(|b| size <= b.max_size)
+                .unwrap_or(sizes.len() - 1);
+            sizes[bucket].accounts += 1;
+        }
+        metrics.sizes = sizes;
+
+        metrics
+    }
+}
+
+/// How much a batch grew the state.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchGrowth {
+    pub batch: u64,
+    pub accounts: u64,
+    pub data_bytes: u64,
+    pub new_accounts: i64,
+    pub data_bytes_delta: i64,
+}
+
+impl BatchGrowth {
+    pub fn between(batch: u64, before: &StateMetrics, after: &StateMetrics) -> Self {
+        Self {
+            batch,
+            accounts: after.accounts,
+            data_bytes: after.data_bytes,
+            new_accounts: after.accounts as i64 - before.accounts as i64,
+            data_bytes_delta: after.data_bytes as i64 - before.data_bytes as i64,
+        }
+    }
+}
+
+/// What the zkVM reads for a batch, which is what drives proving cost besides the transactions
+/// themselves. Sizes are of the bincode `SP1Stdin` writes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputMetrics {
+    pub transactions: u64,
+    /// RLP size of the signed transactions.
+    pub transaction_bytes: u64,
+    pub stateless: bool,
+    /// Accounts given in full, from the witness or the whole `db`.
+    pub accounts: u64,
+    /// Touched addresses the witness shows to be absent.
+    pub missing_accounts: u64,
+    /// Leaf hashes standing in for untouched accounts, or the siblings of the sparse proofs.
+    pub proof_hashes: u64,
+    pub witness_bytes: u64,
+    pub input_bytes: u64,
+}
+
+impl InputMetrics {
+    pub fn collect(input: &Input) -> eyre::Result<Self> {
+        let transactions = input.transactions()?;
+        let mut metrics = Self {
+            transactions: transactions.len() as u64,
+            transaction_bytes: transactions.iter().map(|tx| tx.length() as u64).sum(),
+            stateless: input.witness.is_some(),
+            accounts: input.db.accounts.borrow().len() as u64,
+            missing_accounts: 0,
+            proof_hashes: 0,
+            witness_bytes: 0,
+            input_bytes: bincode::serialized_size(input)?,
+        };
+
+        if let Some(witness) = &input.witness {
+            let present = witness.accounts.values().filter(|a| a.is_some()).count() as u64;
+            metrics.accounts = present;
+            metrics.missing_accounts = witness.accounts.len() as u64 - present;
+            let siblings: usize = witness.proofs.values().map(|p| p.siblings.len()).sum();
+            metrics.proof_hashes = (witness.untouched.len() + siblings) as u64;
+            metrics.witness_bytes = bincode::serialized_size(witness)?;
+        }
+
+        Ok(metrics)
+    }
+}
+
+/// Counters and histograms of the sync and prove pipeline, for operators of a long-running node.
+/// Served in the Prometheus text format by `serve_prometheus`.
+pub struct Pipeline {
+    registry: Registry,
+    pub batches_synced: IntCounter,
+    /// Transactions with a successful receipt.
+    pub transactions_applied: IntCounter,
+    /// Transactions skipped with a failed receipt.
+    pub transactions_rejected: IntCounter,
+    pub signature_recovery_seconds: Histogram,
+    pub merkle_rebuild_seconds: Histogram,
+    /// Only with the `prove` feature, which runs the program in the zkVM.
+    #[cfg(feature = "prove")]
+    pub prover_cycles: Histogram,
+    pub submission_gas: Histogram,
+    /// Pending transactions queued again from the mempool log on start.
+    pub mempool_restored: IntCounter,
+    /// Logged transactions dropped on start, as they no longer apply or do not decode.
+    pub mempool_dropped: IntCounter,
+}
+
+static PIPELINE: LazyLock<Pipeline> = LazyLock::new(Pipeline::new);
+
+/// The metrics of this process.
+pub fn pipeline() -> &'static Pipeline {
+    &PIPELINE
+}
+
+impl Pipeline {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("canvas".to_string()), None).unwrap();
+        let counter = |name: &str, help: &str| {
+            let counter = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let histogram = |name: &str, help: &str, buckets: Vec<f64>| {
+            let histogram =
+                Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets)).unwrap();
+            registry.register(Box::new(histogram.clone())).unwrap();
+            histogram
+        };
+        let seconds = exponential_buckets(0.0005, 4.0, 10).unwrap();
+
+        Self {
+            batches_synced: counter("batches_synced_total", "Batches applied from L1"),
+            transactions_applied: counter(
+                "transactions_applied_total",
+                "Transactions applied with a successful receipt",
+            ),
+            transactions_rejected: counter(
+                "transactions_rejected_total",
+                "Transactions skipped with a failed receipt",
+            ),
+            signature_recovery_seconds: histogram(
+                "signature_recovery_seconds",
+                "Time to recover the senders of a batch",
+                seconds.clone(),
+            ),
+            merkle_rebuild_seconds: histogram(
+                "merkle_rebuild_seconds",
+                "Time to rebuild the state root",
+                seconds,
+            ),
+            #[cfg(feature = "prove")]
+            prover_cycles: histogram(
+                "prover_cycles",
+                "zkVM cycles of a proven batch",
+                exponential_buckets(1e6, 4.0, 10).unwrap(),
+            ),
+            submission_gas: histogram(
+                "submission_gas",
+                "Gas used by a mined batch submission",
+                exponential_buckets(50_000.0, 2.0, 10).unwrap(),
+            ),
+            mempool_restored: counter(
+                "mempool_restored_total",
+                "Pending transactions restored from the mempool log",
+            ),
+            mempool_dropped: counter(
+                "mempool_dropped_total",
+                "Logged transactions dropped when restoring the mempool",
+            ),
+            registry,
+        }
+    }
+
+    /// Everything in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut text = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut text)
+            .expect("metrics encode as text");
+        String::from_utf8(text).expect("metrics text is UTF-8")
+    }
+}
+
+/// Serves `pipeline` at `GET /metrics` on `addr` in the background.
+pub async fn serve_prometheus(addr: SocketAddr) -> eyre::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(addr = %listener.local_addr()?, "Serving metrics at /metrics");
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = [0; 1024];
+                let Ok(read) = stream.read(&mut request).await else {
+                    return;
+                };
+
+                let response = if request[..read].starts_with(b"GET /metrics ") {
+                    let body = pipeline().render();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use alloy_rlp::Decodable;
+use program::{
+    config::CanvasConfig,
+    fees::FeeSchedule,
+    limits::RateLimit,
+    mempool::{LoggedTransaction, MempoolLog, PendingTransaction},
+    SignedTransaction,
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{cli::Config, serve::StateReader};
+
+/// What a serving node's sequencer admits transactions under, from the config file, and what
+/// a SIGHUP reloads. The replay keeps the chain's own config; only the transactions executed on
+/// the pending state are charged and limited by this one. The palette is the program's
+/// `MAX_VALUE`, the same for every node, so it is not among them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeConfig {
+    pub fees: FeeSchedule,
+    pub rate_limit: RateLimit,
+}
+
+impl From<&Config> for NodeConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            fees: config.fees.unwrap_or_default(),
+            rate_limit: config.rate_limit.unwrap_or_default(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// `config` with this node's fees and rate limits.
+    pub fn apply(&self, config: CanvasConfig) -> CanvasConfig {
+        CanvasConfig {
+            fees: self.fees,
+            rate_limit: self.rate_limit,
+            ..config
+        }
+    }
+}
+
+/// How a serving node reloads its config and stops: `config` to start with, reloaded by
+/// `watcher`, and `shutdown` to persist its state on the way out.
+pub struct Lifecycle {
+    pub config: NodeConfig,
+    pub watcher: ConfigWatcher,
+    pub shutdown: ShutdownCoordinator,
+}
+
+/// Reads the `NodeConfig` out of the config file again on every SIGHUP and hands it to the
+/// served state. The server keeps running through a reload, so subscribers stay connected.
+pub struct ConfigWatcher {
+    // The file `Config::load` read on start, `None` for its default.
+    path: Option<PathBuf>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// A file that fails to load leaves the node on the config it had.
+    pub async fn run(self, reader: Arc<Mutex<StateReader>>) -> eyre::Result<()> {
+        let mut hangups = signal(SignalKind::hangup())?;
+
+        while hangups.recv().await.is_some() {
+            match Config::load(self.path.as_deref()) {
+                Ok(config) => {
+                    let node = NodeConfig::from(&config);
+                    reader.lock().unwrap().set_node_config(node);
+                    tracing::info!(?node, "Reloaded the node config");
+                }
+                Err(err) => tracing::warn!("Failed to reload the node config: {:#}", err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stops a serving node on SIGINT or SIGTERM, leaving a snapshot of the served state in `dir`,
+/// readable with `export-state --snapshot`. The sequencer's pending transactions are in its
+/// `MempoolLogFile` in the same directory all along, which the next `serve` queues again.
+pub struct ShutdownCoordinator {
+    dir: PathBuf,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.dir.join("snapshot.bin")
+    }
+
+    pub fn mempool_path(&self) -> PathBuf {
+        self.dir.join("mempool.log")
+    }
+
+    /// Resolves on the first SIGINT or SIGTERM.
+    pub async fn signalled(&self) -> eyre::Result<()> {
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+        Ok(())
+    }
+
+    /// Writes the snapshot of `reader`.
+    pub fn persist(&self, reader: &StateReader) -> eyre::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.snapshot_path(), reader.snapshot()?)?;
+        tracing::info!(
+            snapshot = %self.snapshot_path().display(),
+            pending = reader.pending_transactions().len(),
+            "Persisted the node's state"
+        );
+        Ok(())
+    }
+}
+
+/// The sequencer's pending transactions on disk, one per line as the unix time it was received,
+/// its priority and its hex RLP encoding. Each is appended and synced once the mempool admits
+/// it, before it is confirmed, so a crash loses none; the log is rewritten with what is still
+/// pending after evictions and whenever batches move the sequencer on.
+#[derive(Debug)]
+pub struct MempoolLogFile {
+    path: PathBuf,
+    file: File,
+}
+
+/// What restoring a `MempoolLogFile` on start made of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MempoolRecovery {
+    /// Queued again.
+    pub restored: usize,
+    /// No longer applying on the served state, e.g. included in a batch since, or no longer
+    /// admitted by the mempool.
+    pub dropped: usize,
+    /// Lines that do not decode, such as one a crash cut off.
+    pub unreadable: usize,
+}
+
+impl MempoolLogFile {
+    /// The transactions logged at `path`, in the order they were admitted, and how many lines
+    /// did not decode. Nothing for a log that does not exist.
+    pub fn read(path: &Path) -> eyre::Result<(Vec<LoggedTransaction>, usize)> {
+        if !path.exists() {
+            return Ok((Vec::new(), 0));
+        }
+        let (mut logged, mut unreadable) = (Vec::new(), 0);
+        for line in fs::read_to_string(path)?.lines() {
+            match decode_line(line) {
+                Some(tx) => logged.push(tx),
+                None => unreadable += 1,
+            }
+        }
+        Ok((logged, unreadable))
+    }
+
+    /// Opens the log at `path` to append to, creating it if there is none.
+    pub fn open(path: PathBuf) -> eyre::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl MempoolLog for MempoolLogFile {
+    fn append(&mut self, tx: &PendingTransaction) -> eyre::Result<()> {
+        writeln!(self.file, "{}", encode_line(tx))?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    // Replaces the old log at once, so a crash leaves one or the other.
+    fn compact(&mut self, pending: &[PendingTransaction]) -> eyre::Result<()> {
+        let temporary = self.path.with_extension("log.tmp");
+        let mut lines = String::new();
+        for tx in pending {
+            lines.push_str(&encode_line(tx));
+            lines.push('\n');
+        }
+        fs::write(&temporary, lines)?;
+        fs::rename(&temporary, &self.path)?;
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn encode_line(tx: &PendingTransaction) -> String {
+    format!(
+        "{} {} {}",
+        tx.received,
+        tx.priority,
+        hex::encode(alloy_rlp::encode(&tx.tx))
+    )
+}
+
+fn decode_line(line: &str) -> Option<LoggedTransaction> {
+    let mut fields = line.split(' ');
+    let received = fields.next()?.parse().ok()?;
+    let priority = fields.next()?.parse().ok()?;
+    let raw = hex::decode(fields.next()?).ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(LoggedTransaction {
+        tx: SignedTransaction::decode(&mut raw.as_slice()).ok()?,
+        received,
+        priority,
+    })
+}
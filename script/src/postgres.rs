@@ -0,0 +1,292 @@
+use std::{cell::RefCell, collections::HashMap, future::Future, time::Duration};
+
+use alloy::primitives::{Address, B256};
+use program::{error::CanvasError, receipt::Receipt, Account, AccountDB};
+use sqlx::{postgres::PgPoolOptions, types::Json, PgPool, Postgres, Transaction};
+
+use crate::{
+    hooks::{BatchEvent, BatchHook},
+    leader::LeaseStore,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS accounts (
+    address BYTEA PRIMARY KEY,
+    account JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS leases (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+CREATE TABLE IF NOT EXISTS batches (
+    batch_index BIGINT PRIMARY KEY,
+    tx_hash BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    transaction_commit BYTEA NOT NULL,
+    state_root BYTEA NOT NULL,
+    receipts JSONB NOT NULL,
+    before JSONB NOT NULL
+);
+";
+
+/// A batch as the `batches` table keeps it, next to the accounts it left.
+#[derive(Debug, Clone)]
+pub struct StoredBatch {
+    pub index: u64,
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub transaction_commit: B256,
+    pub state_root: B256,
+    pub receipts: Vec<Receipt>,
+}
+
+/// Accounts in Postgres, as JSON keyed by address. Writes are buffered until `commit_batch`
+/// applies them and records the batch in one SQL transaction, so readers never see half a batch.
+/// Reads see the buffered writes and fetch the rest in one query per call.
+///
+/// Each batch row keeps the accounts it replaced, so a batch committed again at an index already
+/// stored, after a reorg, first restores the state before the batches it replaces.
+pub struct PostgresAccountDB {
+    pool: PgPool,
+    pending: RefCell<HashMap<Address, Option<Account>>>,
+}
+
+// `AccountDB` is synchronous; its calls wait for the query on the current Tokio runtime, which
+// has to be multi-threaded.
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+fn db_error(err: sqlx::Error) -> CanvasError {
+    CanvasError::Db(err.to_string())
+}
+
+impl PostgresAccountDB {
+    /// Connects to the database at `url` and creates the tables if they do not exist.
+    pub async fn connect(url: &str) -> eyre::Result<Self> {
+        let pool = PgPoolOptions::new().connect(url).await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+
+        Ok(Self {
+            pool,
+            pending: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Writes the buffered accounts and `batch` in one SQL transaction. Batches stored at
+    /// `batch.index` or later are rolled back and dropped first.
+    pub async fn commit_batch(&self, batch: &StoredBatch) -> eyre::Result<()> {
+        let writes: Vec<(Address, Option<Account>)> = self
+            .pending
+            .borrow()
+            .iter()
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        let mut tx = self.pool.begin().await?;
+
+        let replaced: Vec<(Json<Vec<(Address, Option<Account>)>>,)> = sqlx::query_as(
+            "SELECT before FROM batches WHERE batch_index >= $1 ORDER BY batch_index DESC",
+        )
+        .bind(batch.index as i64)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (Json(before),) in replaced {
+            write(&mut tx, &before).await?;
+        }
+        sqlx::query("DELETE FROM batches WHERE batch_index >= $1")
+            .bind(batch.index as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        let addresses: Vec<Vec<u8>> = writes.iter().map(|(address, _)| address.to_vec()).collect();
+        let stored: Vec<(Vec<u8>, Json<Account>)> =
+            sqlx::query_as("SELECT address, account FROM accounts WHERE address = ANY($1)")
+                .bind(&addresses)
+                .fetch_all(&mut *tx)
+                .await?;
+        let stored: HashMap<Address, Account> = stored
+            .into_iter()
+            .map(|(address, Json(account))| (Address::from_slice(&address), account))
+            .collect();
+        let before: Vec<(Address, Option<Account>)> = writes
+            .iter()
+            .map(|(address, _)| (*address, stored.get(address).cloned()))
+            .collect();
+        write(&mut tx, &writes).await?;
+
+        sqlx::query(
+            "INSERT INTO batches (batch_index, tx_hash, block_number, transaction_commit, \
+             state_root, receipts, before) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(batch.index as i64)
+        .bind(batch.tx_hash.as_slice())
+        .bind(batch.block_number as i64)
+        .bind(batch.transaction_commit.as_slice())
+        .bind(batch.state_root.as_slice())
+        .bind(Json(&batch.receipts))
+        .bind(Json(&before))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.pending.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// Drops the buffered writes.
+    pub fn discard(&self) {
+        self.pending.borrow_mut().clear();
+    }
+}
+
+// Sets or, for `None`, deletes each account.
+async fn write(
+    tx: &mut Transaction<'_, Postgres>,
+    accounts: &[(Address, Option<Account>)],
+) -> Result<(), sqlx::Error> {
+    for (address, account) in accounts {
+        match account {
+            Some(account) => {
+                sqlx::query(
+                    "INSERT INTO accounts (address, account) VALUES ($1, $2) \
+                 ON CONFLICT (address) DO UPDATE SET account = EXCLUDED.account",
+                )
+                .bind(address.as_slice())
+                .bind(Json(account))
+                .execute(&mut **tx)
+                .await?
+            }
+            None => {
+                sqlx::query("DELETE FROM accounts WHERE address = $1")
+                    .bind(address.as_slice())
+                    .execute(&mut **tx)
+                    .await?
+            }
+        };
+    }
+    Ok(())
+}
+
+impl AccountDB for PostgresAccountDB {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
+        Ok(self.find_account(address)?.unwrap_or_default())
+    }
+
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        Ok(self.find_accounts(&[*address])?.remove(0))
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
+        self.pending
+            .borrow_mut()
+            .insert(*address, Some(account.clone()));
+        Ok(())
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        self.pending.borrow_mut().insert(*address, None);
+        Ok(())
+    }
+
+    fn find_accounts(&self, addresses: &[Address]) -> Result<Vec<Option<Account>>, CanvasError> {
+        let unbuffered: Vec<Vec<u8>> = {
+            let pending = self.pending.borrow();
+            addresses
+                .iter()
+                .filter(|address| !pending.contains_key(*address))
+                .map(|address| address.to_vec())
+                .collect()
+        };
+        let stored: Vec<(Vec<u8>, Json<Account>)> = match unbuffered.is_empty() {
+            true => Vec::new(),
+            false => block_on(
+                sqlx::query_as("SELECT address, account FROM accounts WHERE address = ANY($1)")
+                    .bind(&unbuffered)
+                    .fetch_all(&self.pool),
+            )
+            .map_err(db_error)?,
+        };
+        let stored: HashMap<Address, Account> = stored
+            .into_iter()
+            .map(|(address, Json(account))| (Address::from_slice(&address), account))
+            .collect();
+
+        let pending = self.pending.borrow();
+        Ok(addresses
+            .iter()
+            .map(|address| match pending.get(address) {
+                Some(account) => account.clone(),
+                None => stored.get(address).cloned(),
+            })
+            .collect())
+    }
+}
+
+/// Mirrors every batch the follower applies: the accounts it touched, as they are after it,
+/// and the batch with its receipts. A touched account that reads back as the default one was
+/// removed.
+impl BatchHook for PostgresAccountDB {
+    fn on_batch(&mut self, event: &BatchEvent) -> eyre::Result<()> {
+        for diff in &event.diffs {
+            match diff.after == Account::default() {
+                true => self.remove_account(&diff.address)?,
+                false => self.set_account(&diff.address, &diff.after)?,
+            }
+        }
+
+        let batch = StoredBatch {
+            index: event.index,
+            tx_hash: event.tx_hash,
+            block_number: event.block_number,
+            transaction_commit: event.transaction_commit,
+            state_root: event.state_root,
+            receipts: event.receipts.clone(),
+        };
+        if let Err(err) = block_on(self.commit_batch(&batch)) {
+            self.discard();
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+// The lease every replica of one deployment competes for.
+const SEQUENCER_LEASE: &str = "sequencer";
+
+/// The sequencer lease as a row of the `leases` table. Expiry goes by the database's clock, so
+/// the replicas' clocks do not have to agree.
+pub struct PostgresLease {
+    pool: PgPool,
+}
+
+impl PostgresLease {
+    /// Connects to the database at `url` and creates the tables if they do not exist.
+    pub async fn connect(url: &str) -> eyre::Result<Self> {
+        let pool = PgPoolOptions::new().connect(url).await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+impl LeaseStore for PostgresLease {
+    fn try_acquire(&mut self, holder: &str, ttl: Duration) -> eyre::Result<bool> {
+        // Only inserts or updates, and so returns a row, while the lease is free, lapsed or
+        // already `holder`'s.
+        let acquired: Option<(String,)> = block_on(
+            sqlx::query_as(
+                "INSERT INTO leases (name, holder, expires_at) \
+                 VALUES ($1, $2, now() + make_interval(secs => $3)) \
+                 ON CONFLICT (name) DO UPDATE SET holder = EXCLUDED.holder, \
+                 expires_at = EXCLUDED.expires_at \
+                 WHERE leases.holder = EXCLUDED.holder OR leases.expires_at < now() \
+                 RETURNING holder",
+            )
+            .bind(SEQUENCER_LEASE)
+            .bind(holder)
+            .bind(ttl.as_secs_f64())
+            .fetch_optional(&self.pool),
+        )?;
+        Ok(acquired.is_some())
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, Signature, B256},
+    signers::{local::PrivateKeySigner, SignerSync},
+    sol,
+    sol_types::SolStruct,
+};
+use program::{
+    batch::BatchContext, limits::RateLimitState, mempool::Mempool, overlay::OverlayDB,
+    receipt::Receipt, signing::SigningDomain, Account, AccountDB, CanvasProcessor, InMemoryDB,
+    SignedTransaction,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    leader::Leadership,
+    metrics,
+    node::{MempoolLogFile, MempoolRecovery},
+};
+
+sol! {
+    /// EIP-712 promise signed by a sequencer: "`txHash` will be in batch `batch`".
+    struct Preconfirmation {
+        bytes32 txHash;
+        uint64 batch;
+    }
+}
+
+fn preconfirmation_hash(tx_hash: B256, batch: u64, domain: &SigningDomain) -> B256 {
+    Preconfirmation {
+        txHash: tx_hash,
+        batch,
+    }
+    .eip712_signing_hash(&domain.eip712_domain())
+}
+
+/// What `canvas_submitTransaction` answers with: the receipt the transaction got on the pending
+/// state and the sequencer's signed promise to include it in `batch`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoftConfirmation {
+    pub tx_hash: B256,
+    pub batch: u64,
+    pub receipt: Receipt,
+    pub sequencer: Address,
+    pub signature: Signature,
+}
+
+impl SoftConfirmation {
+    /// Checks that `signature` is `sequencer`'s over the promise under `domain`.
+    pub fn verify(&self, domain: &SigningDomain) -> eyre::Result<()> {
+        let hash = preconfirmation_hash(self.tx_hash, self.batch, domain);
+        let signer = self.signature.recover_address_from_prehash(&hash)?;
+        if signer != self.sequencer {
+            return Err(eyre::eyre!(
+                "Confirmation of {} is signed by {}, not {}",
+                self.tx_hash,
+                signer,
+                self.sequencer
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where a sequencer replica gossips with the others: the libp2p address it listens on, the
+/// replicas it dials on start, and the sequencers whose confirmations it accepts.
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    pub listen: String,
+    pub peers: Vec<String>,
+    pub sequencers: Vec<Address>,
+}
+
+/// What a replica tells the others about a transaction it accepted.
+#[derive(Debug, Clone)]
+pub enum Gossip {
+    Transaction(SignedTransaction),
+    Confirmation(SoftConfirmation),
+}
+
+/// The transactions the sequencer accepted for the next batch and the state they leave on top
+/// of the served one. Each is executed on that pending state before it is confirmed, so a
+/// confirmed transaction applies as long as the batch takes the mempool in order.
+pub struct Sequencer {
+    signer: PrivateKeySigner,
+    mempool: Mempool,
+    writes: Vec<(Address, Option<Account>)>,
+    // The rate-limit counters after the pending transactions; `None` before any, when they are
+    // the served state's.
+    limits: Option<RateLimitState>,
+    next_batch: u64,
+    gossip: Option<GossipConfig>,
+    leadership: Option<Leadership>,
+}
+
+impl Sequencer {
+    /// Promises batch `next_batch` until `rebase` moves it on.
+    pub fn new(signer: PrivateKeySigner, mempool: Mempool, next_batch: u64) -> Self {
+        Self {
+            signer,
+            mempool,
+            writes: Vec::new(),
+            limits: None,
+            next_batch,
+            gossip: None,
+            leadership: None,
+        }
+    }
+
+    /// Shares the transactions it accepts, and its confirmations, with the replicas in `config`
+    /// once served.
+    pub fn with_gossip(mut self, config: GossipConfig) -> Self {
+        self.gossip = Some(config);
+        self
+    }
+
+    /// The gossip config, for whoever runs the gossip.
+    pub fn take_gossip(&mut self) -> Option<GossipConfig> {
+        self.gossip.take()
+    }
+
+    /// Takes transactions only while `leadership` holds the lease, once served.
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = Some(leadership);
+        self
+    }
+
+    /// The leadership, for whoever keeps the lease.
+    pub fn take_leadership(&mut self) -> Option<Leadership> {
+        self.leadership.take()
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// The batch it promises transactions to.
+    pub fn next_batch(&self) -> u64 {
+        self.next_batch
+    }
+
+    /// Executes `tx` on the pending state over `processor` and, if it succeeds and the mempool
+    /// admits it, keeps its writes and signs its confirmation.
+    pub fn submit(
+        &mut self,
+        processor: &CanvasProcessor<InMemoryDB>,
+        tx: SignedTransaction,
+    ) -> eyre::Result<SoftConfirmation> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut pending = self.pending(processor, now)?;
+        let receipt = pending.apply_transaction(&tx)?;
+        let tx_hash = tx.hash();
+        let evicted = self
+            .mempool
+            .submit(&processor.db, &processor.config, tx.clone(), now, 0)?;
+        if evicted.is_empty() {
+            self.keep(pending);
+        } else {
+            // Making room dropped earlier transactions, so the pending state has to be rebuilt
+            // without them.
+            self.replay(processor, now);
+            if self.mempool.get(&tx_hash).is_none() {
+                return Err(eyre::eyre!("Mempool is full"));
+            }
+        }
+
+        let domain = processor.config.domain;
+        let signature =
+            self.signer
+                .sign_hash_sync(&preconfirmation_hash(tx_hash, self.next_batch, &domain))?;
+
+        Ok(SoftConfirmation {
+            tx_hash,
+            batch: self.next_batch,
+            receipt,
+            sequencer: self.signer.address(),
+            signature,
+        })
+    }
+
+    /// Moves on to `processor`, the state after `batches` batches, re-applying the pending
+    /// transactions on top of it. The ones that no longer apply, such as those the new batches
+    /// included, are dropped.
+    pub fn rebase(&mut self, processor: &CanvasProcessor<InMemoryDB>, batches: u64) {
+        self.next_batch = batches;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        self.mempool.evict_expired(now);
+        self.replay(processor, now);
+    }
+
+    /// Queues the transactions logged at `path` again, those that still apply on top of
+    /// `processor` in their order, and logs there every transaction it accepts from now on.
+    pub fn restore(
+        &mut self,
+        processor: &CanvasProcessor<InMemoryDB>,
+        path: PathBuf,
+    ) -> eyre::Result<MempoolRecovery> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let (logged, unreadable) = MempoolLogFile::read(&path)?;
+        let count = logged.len();
+        self.mempool.restore(
+            &processor.db,
+            &processor.config,
+            logged,
+            Box::new(MempoolLogFile::open(path)?),
+        )?;
+        // The mempool only checked them against the served state; the ones that no longer
+        // execute on top of those before them are dropped as well.
+        self.replay(processor, now);
+        let restored = self.mempool.len();
+        let recovery = MempoolRecovery {
+            restored,
+            dropped: count.saturating_sub(restored),
+            unreadable,
+        };
+
+        let metrics = metrics::pipeline();
+        metrics.mempool_restored.inc_by(recovery.restored as u64);
+        metrics
+            .mempool_dropped
+            .inc_by((recovery.dropped + recovery.unreadable) as u64);
+        Ok(recovery)
+    }
+
+    /// The pending transactions in the order they are to be sequenced.
+    pub fn pending_transactions(&self) -> Vec<SignedTransaction> {
+        self.mempool
+            .pending()
+            .iter()
+            .map(|pending| pending.tx.clone())
+            .collect()
+    }
+
+    // Re-applies the pool on top of `processor` alone, keeping the transactions that still apply.
+    fn replay(&mut self, processor: &CanvasProcessor<InMemoryDB>, now: u64) {
+        self.writes.clear();
+        self.limits = None;
+        let Ok(mut pending) = self.pending(processor, now) else {
+            return;
+        };
+        for queued in self.mempool.drain(usize::MAX) {
+            if pending.apply_transaction(&queued.tx).is_ok() {
+                self.mempool.insert(queued);
+            }
+        }
+        self.keep(pending);
+        if let Err(err) = self.mempool.compact_log() {
+            tracing::warn!("Failed to compact the mempool log: {:#}", err);
+        }
+    }
+
+    // Makes the state `pending` left the new pending state.
+    fn keep(&mut self, pending: CanvasProcessor<OverlayDB<&InMemoryDB>>) {
+        self.limits = Some(pending.limits);
+        self.writes = pending.db.into_writes().into_iter().collect();
+    }
+
+    // A processor over the pending state, applying transactions as part of the promised batch.
+    fn pending<'a>(
+        &self,
+        processor: &'a CanvasProcessor<InMemoryDB>,
+        now: u64,
+    ) -> eyre::Result<CanvasProcessor<OverlayDB<&'a InMemoryDB>>> {
+        let overlay = OverlayDB::new(&processor.db);
+        for (address, account) in &self.writes {
+            match account {
+                Some(account) => overlay.set_account(address, account)?,
+                None => overlay.remove_account(address)?,
+            }
+        }
+
+        let mut pending = CanvasProcessor::with_config(overlay, processor.config)
+            .with_limits(self.limits.as_ref().unwrap_or(&processor.limits).clone());
+        pending.batch = BatchContext {
+            index: self.next_batch,
+            ..BatchContext::default()
+        }
+        .timestamped(now);
+        Ok(pending)
+    }
+}
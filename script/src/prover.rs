@@ -0,0 +1,214 @@
+use std::{fs, path::Path};
+
+use alloy::primitives::Bytes;
+use program::{
+    aggregate::AggregateInput,
+    chunked::{self, Encoding},
+    Input,
+};
+
+/// What `submitBatchWithProof` takes for a batch.
+pub struct Artifacts {
+    pub public_values: Bytes,
+    pub proof: Bytes,
+    pub transaction_data: Bytes,
+}
+
+impl Artifacts {
+    pub fn write(&self, dir: &Path) -> eyre::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("public_values.bin"), &self.public_values)?;
+        fs::write(dir.join("proof.bin"), &self.proof)?;
+        fs::write(dir.join("transaction_data.bin"), &self.transaction_data)?;
+        Ok(())
+    }
+
+    /// Reads what `write` wrote.
+    pub fn read(dir: &Path) -> eyre::Result<Self> {
+        let read = |name: &str| -> eyre::Result<Bytes> {
+            let path = dir.join(name);
+            Ok(fs::read(&path)
+                .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?
+                .into())
+        };
+
+        Ok(Self {
+            public_values: read("public_values.bin")?,
+            proof: read("proof.bin")?,
+            transaction_data: read("transaction_data.bin")?,
+        })
+    }
+}
+
+/// What `submitAggregatedBatches` takes for a run of batches.
+pub struct AggregateArtifacts {
+    pub public_values: Bytes,
+    pub proof: Bytes,
+    /// Of each batch, in order.
+    pub transaction_data: Vec<Bytes>,
+}
+
+impl AggregateArtifacts {
+    pub fn write(&self, dir: &Path) -> eyre::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("public_values.bin"), &self.public_values)?;
+        fs::write(dir.join("proof.bin"), &self.proof)?;
+        for (i, transaction_data) in self.transaction_data.iter().enumerate() {
+            fs::write(
+                dir.join(format!("transaction_data_{}.bin", i)),
+                transaction_data,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Proves `input`, its accounts written with `encoding`, with the SP1 program at `elf`. A
+/// `compressed` proof is not verifiable on L1 but can be aggregated; its `proof` is the bincode
+/// encoded `SP1ProofWithPublicValues`.
+#[cfg(feature = "prove")]
+#[tracing::instrument(skip_all, fields(batch = input.batch.index, transaction_bytes = input.transaction_data.len()))]
+pub fn prove(
+    input: &Input,
+    elf: &Path,
+    compressed: bool,
+    encoding: Encoding,
+) -> eyre::Result<Artifacts> {
+    use sp1_sdk::{ProverClient, SP1Stdin};
+
+    let elf = fs::read(elf)?;
+    let client = ProverClient::new();
+    let (pk, vk) = client.setup(&elf);
+
+    let mut stdin = SP1Stdin::new();
+    let mut encoded = Vec::new();
+    chunked::write_input(input, encoding, &mut encoded)?;
+    for buffer in encoded.chunks(chunked::FRAME_BYTES) {
+        stdin.write_slice(buffer);
+    }
+
+    let (_, report) = client.execute(&elf, stdin.clone()).run()?;
+    crate::metrics::pipeline()
+        .prover_cycles
+        .observe(report.total_instruction_count() as f64);
+
+    let proof = if compressed {
+        client.prove(&pk, stdin).compressed().run()?
+    } else {
+        client.prove(&pk, stdin).plonk().run()?
+    };
+    tracing::info!(compressed, "Proved batch");
+    client.verify(&proof, &vk)?;
+
+    Ok(Artifacts {
+        public_values: proof.public_values.to_vec().into(),
+        proof: match compressed {
+            true => bincode::serialize(&proof)?.into(),
+            false => proof.bytes().into(),
+        },
+        transaction_data: input.transaction_data.clone().into(),
+    })
+}
+
+/// Without the `prove` feature, runs the program natively and returns an empty proof, like the dev
+/// node's mock prover.
+#[cfg(not(feature = "prove"))]
+#[tracing::instrument(skip_all, fields(batch = input.batch.index, transaction_bytes = input.transaction_data.len()))]
+pub fn prove(
+    input: &Input,
+    _elf: &Path,
+    _compressed: bool,
+    _encoding: Encoding,
+) -> eyre::Result<Artifacts> {
+    use alloy::sol_types::SolValue;
+
+    tracing::warn!("Built without the `prove` feature, writing a mock proof");
+
+    let report = input.clone().process()?;
+
+    Ok(Artifacts {
+        public_values: report.public_values.abi_encode().into(),
+        proof: Bytes::new(),
+        transaction_data: input.transaction_data.clone().into(),
+    })
+}
+
+/// Proves the compressed proofs of consecutive `batches`, made from the program at `elf`, as one
+/// PLONK proof with the aggregation program at `aggregate_elf`.
+#[cfg(feature = "prove")]
+#[tracing::instrument(skip_all, fields(batches = batches.len()))]
+pub fn aggregate(
+    batches: &[Artifacts],
+    elf: &Path,
+    aggregate_elf: &Path,
+) -> eyre::Result<AggregateArtifacts> {
+    use sp1_sdk::{HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues, SP1Stdin};
+
+    let client = ProverClient::new();
+    let (_, batch_vk) = client.setup(&fs::read(elf)?);
+    let aggregate_elf = fs::read(aggregate_elf)?;
+    let (pk, vk) = client.setup(&aggregate_elf);
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&AggregateInput {
+        vkey: batch_vk.hash_u32(),
+        public_values: batches
+            .iter()
+            .map(|batch| batch.public_values.to_vec())
+            .collect(),
+    });
+    for batch in batches {
+        let proof: SP1ProofWithPublicValues = bincode::deserialize(&batch.proof)?;
+        let SP1Proof::Compressed(proof) = proof.proof else {
+            return Err(eyre::eyre!(
+                "Batch proofs have to be compressed, see `prove --compressed`"
+            ));
+        };
+        stdin.write_proof(*proof, batch_vk.vk.clone());
+    }
+
+    let proof = client.prove(&pk, stdin).plonk().run()?;
+    tracing::info!("Proved aggregate");
+    client.verify(&proof, &vk)?;
+
+    Ok(AggregateArtifacts {
+        public_values: proof.public_values.to_vec().into(),
+        proof: proof.bytes().into(),
+        transaction_data: batches
+            .iter()
+            .map(|batch| batch.transaction_data.clone())
+            .collect(),
+    })
+}
+
+/// Without the `prove` feature, checks and aggregates the public values natively and returns an
+/// empty proof.
+#[cfg(not(feature = "prove"))]
+#[tracing::instrument(skip_all, fields(batches = batches.len()))]
+pub fn aggregate(
+    batches: &[Artifacts],
+    _elf: &Path,
+    _aggregate_elf: &Path,
+) -> eyre::Result<AggregateArtifacts> {
+    use alloy::sol_types::SolValue;
+
+    tracing::warn!("Built without the `prove` feature, writing a mock proof");
+
+    let values = AggregateInput {
+        vkey: [0; 8],
+        public_values: batches
+            .iter()
+            .map(|batch| batch.public_values.to_vec())
+            .collect(),
+    }
+    .process()?;
+
+    Ok(AggregateArtifacts {
+        public_values: values.abi_encode().into(),
+        proof: Bytes::new(),
+        transaction_data: batches
+            .iter()
+            .map(|batch| batch.transaction_data.clone())
+            .collect(),
+    })
+}
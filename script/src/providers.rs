@@ -0,0 +1,198 @@
+use std::{future::Future, sync::Mutex, time::Duration};
+
+use alloy::{
+    providers::{Provider, ProviderBuilder, RootProvider},
+    transports::http::{Client, Http},
+};
+use futures::future::join_all;
+use tokio::time::Instant;
+
+pub type HttpProvider = RootProvider<Http<Client>>;
+
+// How long a provider that failed a request or a health check is passed over for.
+const COOLDOWN: Duration = Duration::from_secs(30);
+// Blocks a provider's head may trail the highest one before it is passed over.
+const MAX_LAG: u64 = 8;
+
+struct Endpoint {
+    url: String,
+    provider: HttpProvider,
+    // When its next request may be sent, one interval after the last.
+    next_request: tokio::sync::Mutex<Instant>,
+    // Until when it is passed over, after it failed.
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn is_up(&self) -> bool {
+        self.down_until
+            .lock()
+            .unwrap()
+            .map_or(true, |until| until <= Instant::now())
+    }
+
+    fn set_up(&self, up: bool) {
+        let mut down_until = self.down_until.lock().unwrap();
+        if up && down_until.is_some() {
+            tracing::info!(url = %self.url, "Provider is back");
+        }
+        *down_until = (!up).then(|| Instant::now() + COOLDOWN);
+    }
+}
+
+/// The L1 providers a `Syncer` reads from, in order of preference. A request goes to the first
+/// one that is up and fails over to the next on an error; a provider that errs, or whose head
+/// trails the others', is passed over for a while. Each provider gets at most
+/// `requests_per_second`, and a request every provider failed is retried with exponential
+/// backoff up to `retries` times.
+pub struct ProviderPool {
+    endpoints: Vec<Endpoint>,
+    interval: Duration,
+    retries: u32,
+}
+
+impl ProviderPool {
+    pub fn new(
+        urls: &[String],
+        requests_per_second: Option<u32>,
+        retries: u32,
+    ) -> eyre::Result<Self> {
+        if urls.is_empty() {
+            return Err(eyre::eyre!("No provider to sync from"));
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                Ok(Endpoint {
+                    url: url.clone(),
+                    provider: ProviderBuilder::new().on_http(url.parse()?),
+                    next_request: tokio::sync::Mutex::new(Instant::now()),
+                    down_until: Mutex::new(None),
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(Self {
+            endpoints,
+            interval: requests_per_second
+                .filter(|rate| *rate > 0)
+                .map_or(Duration::ZERO, |rate| Duration::from_secs(1) / rate),
+            retries,
+        })
+    }
+
+    /// Sends `request` to the providers in turn until one answers.
+    pub async fn request<T, E, F, Fut>(&self, request: F) -> eyre::Result<T>
+    where
+        F: FnMut(HttpProvider) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Ok(self.request_skipping(None, request).await?.1)
+    }
+
+    /// Sends `request` to two providers and returns both answers, for the caller to compare.
+    /// The second is `None` with a single provider; with more, it fails if none of the others
+    /// answers, as the first answer cannot be checked then.
+    pub async fn cross_checked<T, E, F, Fut>(&self, mut request: F) -> eyre::Result<(T, Option<T>)>
+    where
+        F: FnMut(HttpProvider) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let (first, answer) = self.request_skipping(None, &mut request).await?;
+        if self.endpoints.len() < 2 {
+            return Ok((answer, None));
+        }
+        let (_, second) = self.request_skipping(Some(first), request).await?;
+        Ok((answer, Some(second)))
+    }
+
+    /// Asks every provider for its head and passes over those that fail or trail the highest
+    /// head by more than `MAX_LAG` blocks. Returns the number of providers up.
+    pub async fn check_health(&self) -> usize {
+        let heads = join_all(self.endpoints.iter().map(|endpoint| async move {
+            self.throttle(endpoint).await;
+            endpoint.provider.get_block_number().await
+        }))
+        .await;
+        let highest = heads.iter().flatten().copied().max().unwrap_or_default();
+
+        for (endpoint, head) in self.endpoints.iter().zip(&heads) {
+            match head {
+                Ok(head) if head + MAX_LAG >= highest => endpoint.set_up(true),
+                Ok(head) => {
+                    tracing::warn!(url = %endpoint.url, head, highest, "Provider is behind");
+                    endpoint.set_up(false);
+                }
+                Err(err) => {
+                    tracing::warn!(url = %endpoint.url, error = %err, "Provider is unreachable");
+                    endpoint.set_up(false);
+                }
+            }
+        }
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_up())
+            .count()
+    }
+
+    // Sends `request` to each provider but `skip`, those up first, until one answers, and
+    // returns the index of the one that did with its answer.
+    async fn request_skipping<T, E, F, Fut>(
+        &self,
+        skip: Option<usize>,
+        mut request: F,
+    ) -> eyre::Result<(usize, T)>
+    where
+        F: FnMut(HttpProvider) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let mut order: Vec<usize> = (0..self.endpoints.len())
+            .filter(|&i| Some(i) != skip)
+            .collect();
+        let mut delay = Duration::from_millis(500);
+        let mut attempt = 0;
+
+        loop {
+            // Those passed over are still tried when no other answers.
+            order.sort_by_key(|&i| !self.endpoints[i].is_up());
+            let mut last = None;
+            for &i in &order {
+                let endpoint = &self.endpoints[i];
+                self.throttle(endpoint).await;
+                match request(endpoint.provider.clone()).await {
+                    Ok(value) => {
+                        endpoint.set_up(true);
+                        return Ok((i, value));
+                    }
+                    Err(err) => {
+                        tracing::warn!(url = %endpoint.url, error = %err, "Provider error");
+                        endpoint.set_up(false);
+                        last = Some(err);
+                    }
+                }
+            }
+
+            let err = last.ok_or_else(|| eyre::eyre!("No other provider to ask"))?;
+            if attempt >= self.retries {
+                return Err(err.into());
+            }
+            tracing::warn!(?delay, error = %err, "Every provider failed, retrying");
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    // Waits until `endpoint` may take another request.
+    async fn throttle(&self, endpoint: &Endpoint) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let mut next = endpoint.next_request.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = Instant::now() + self.interval;
+    }
+}
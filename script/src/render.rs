@@ -0,0 +1,121 @@
+use std::{fmt::Write, path::Path};
+
+use image::{Rgba, RgbaImage};
+
+/// Pixels per row when no width is given; `MAX_SIZE` fills 98 rows.
+pub const DEFAULT_WIDTH: usize = 100;
+
+/// RGB of each hex digit of account data, the standard 16 VGA colors in ANSI order, so a PNG
+/// matches `inspect`'s terminal rendering.
+pub const PALETTE: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xaa, 0x00, 0x00],
+    [0x00, 0xaa, 0x00],
+    [0xaa, 0x55, 0x00],
+    [0x00, 0x00, 0xaa],
+    [0xaa, 0x00, 0xaa],
+    [0x00, 0xaa, 0xaa],
+    [0xaa, 0xaa, 0xaa],
+    [0x55, 0x55, 0x55],
+    [0xff, 0x55, 0x55],
+    [0x55, 0xff, 0x55],
+    [0xff, 0xff, 0x55],
+    [0x55, 0x55, 0xff],
+    [0xff, 0x55, 0xff],
+    [0x55, 0xff, 0xff],
+    [0xff, 0xff, 0xff],
+];
+
+/// Account data laid out `width` pixels per row, each pixel `scale` units square. Chars that are
+/// not hex digits, and the rest of the last row, are left transparent.
+pub struct Canvas {
+    pixels: Vec<Option<u8>>,
+    width: usize,
+    scale: u32,
+}
+
+impl Canvas {
+    pub fn new(data: &str, width: usize, scale: u32) -> eyre::Result<Self> {
+        if width == 0 || scale == 0 {
+            return Err(eyre::eyre!("Width and scale must be at least 1"));
+        }
+
+        Ok(Self {
+            pixels: data
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8))
+                .collect(),
+            width,
+            scale,
+        })
+    }
+
+    fn rows(&self) -> usize {
+        self.pixels.len().div_ceil(self.width).max(1)
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = (u32, u32, [u8; 3])> + '_ {
+        self.pixels.iter().enumerate().filter_map(|(i, pixel)| {
+            pixel.map(|d| {
+                let (x, y) = (i % self.width, i / self.width);
+                (x as u32, y as u32, PALETTE[d as usize])
+            })
+        })
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(
+            self.width as u32 * self.scale,
+            self.rows() as u32 * self.scale,
+        );
+        for (x, y, [r, g, b]) in self.pixels() {
+            for dy in 0..self.scale {
+                for dx in 0..self.scale {
+                    image.put_pixel(
+                        x * self.scale + dx,
+                        y * self.scale + dy,
+                        Rgba([r, g, b, 0xff]),
+                    );
+                }
+            }
+        }
+        image
+    }
+
+    pub fn to_svg(&self) -> String {
+        let (width, height) = (
+            self.width as u32 * self.scale,
+            self.rows() as u32 * self.scale,
+        );
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\" shape-rendering=\"crispEdges\">\n"
+        );
+        for (x, y, [r, g, b]) in self.pixels() {
+            writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                x * self.scale,
+                y * self.scale,
+                r,
+                g,
+                b,
+                s = self.scale
+            )
+            .unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Writes an SVG if `path` ends in `.svg` and a PNG otherwise.
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("svg") => std::fs::write(path, self.to_svg())?,
+            _ => self
+                .to_image()
+                .save_with_format(path, image::ImageFormat::Png)?,
+        }
+        Ok(())
+    }
+}
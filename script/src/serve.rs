@@ -0,0 +1,915 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{Address, Bytes, B256},
+    sol_types::SolValue,
+};
+use jsonrpsee::{
+    server::Server, types::ErrorObjectOwned, RpcModule, SubscriptionMessage, SubscriptionSink,
+};
+use program::{
+    commitment::leaf_hash,
+    light::AccountWithProof,
+    namespace::CanvasProof,
+    proof::{ExclusionProof, MultiProof},
+    signing::SigningDomain,
+    smt::SparseProof,
+    Account, CanvasProcessor, ContributorStats, InMemoryDB, SignedTransaction,
+};
+use serde::Serialize;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+
+use crate::{
+    analytics::{self, ActivitySummary, BatchActivity, WriterActivity},
+    checkpoint::Checkpoint,
+    dev::{decode_raw_transaction, rpc_error},
+    ens::ProfileRegistry,
+    follow::Follower,
+    history::HistoryStore,
+    hooks::{AccountDiff, BatchEvent, BatchHook},
+    node::{Lifecycle, MempoolRecovery, NodeConfig},
+    preconf::{Gossip, GossipConfig, Sequencer, SoftConfirmation},
+};
+
+/// Batch events kept for subscribers that fall behind before they are dropped.
+pub(crate) const EVENT_CAPACITY: usize = 64;
+
+/// One contributor's place on an account's leaderboard.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub contributor: Address,
+    #[serde(flatten)]
+    pub stats: ContributorStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: Address,
+    pub leaf_hash: B256,
+    pub proof: Vec<B256>,
+    pub state_root: B256,
+}
+
+/// Read-only view of a replayed state, and of earlier states with a `HistoryStore`. With a
+/// `Sequencer` it also takes transactions for the next batch, confirmed against the state plus
+/// the ones pending before them, and with gossip shares them with the other replicas.
+pub struct StateReader {
+    processor: CanvasProcessor<InMemoryDB>,
+    history: Option<Arc<Mutex<HistoryStore>>>,
+    sequencer: Option<Sequencer>,
+    checkpoint: Option<Checkpoint>,
+    gossip: Option<mpsc::UnboundedSender<Gossip>>,
+    // False while another replica holds the sequencer lease.
+    leading: bool,
+    // Confirmations gossiped by the active sequencer, until their batch is followed.
+    confirmations: HashMap<B256, SoftConfirmation>,
+    // Applied over the config of every state served, once set.
+    node: Option<NodeConfig>,
+}
+
+impl StateReader {
+    pub fn new(processor: CanvasProcessor<InMemoryDB>) -> Self {
+        Self {
+            processor,
+            history: None,
+            sequencer: None,
+            checkpoint: None,
+            gossip: None,
+            leading: true,
+            confirmations: HashMap::new(),
+            node: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: Arc<Mutex<HistoryStore>>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn with_sequencer(mut self, sequencer: Sequencer) -> Self {
+        self.sequencer = Some(sequencer);
+        self
+    }
+
+    /// Sends every transaction submitted here, with its confirmation, to `gossip`.
+    pub fn with_gossip(mut self, gossip: mpsc::UnboundedSender<Gossip>) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Serves `processor`, the state after `batches` batches, from now on, e.g. a newer state of
+    /// the chain. Pending transactions are re-applied on top of it.
+    pub fn set_processor(&mut self, processor: CanvasProcessor<InMemoryDB>, batches: u64) {
+        self.processor = processor;
+        if let Some(node) = &self.node {
+            self.processor.config = node.apply(self.processor.config);
+        }
+        if let Some(sequencer) = &mut self.sequencer {
+            sequencer.rebase(&self.processor, batches);
+        }
+        self.confirmations
+            .retain(|_, confirmation| confirmation.batch >= batches);
+    }
+
+    /// Executes the transactions submitted from now on under `node`. The pending ones are
+    /// re-applied under it, dropping those it no longer admits.
+    pub fn set_node_config(&mut self, node: NodeConfig) {
+        self.node = Some(node);
+        self.processor.config = node.apply(self.processor.config);
+        if let Some(sequencer) = &mut self.sequencer {
+            let batches = sequencer.next_batch();
+            sequencer.rebase(&self.processor, batches);
+        }
+    }
+
+    /// The served accounts as `InMemoryDB::snapshot_accounts` encodes them.
+    pub fn snapshot(&self) -> eyre::Result<Vec<u8>> {
+        self.processor.db.snapshot_accounts()
+    }
+
+    /// Offers `checkpoint`, the state after the latest finalized batch, to nodes syncing from a
+    /// checkpoint from now on.
+    pub fn set_checkpoint(&mut self, checkpoint: Option<Checkpoint>) {
+        // Its batch is the latest finalized one, whose state the history has to keep serving.
+        if let (Some(history), Some(checkpoint)) = (&self.history, &checkpoint) {
+            history.lock().unwrap().set_finalized(checkpoint.batch);
+        }
+        self.checkpoint = checkpoint;
+    }
+
+    pub fn checkpoint(&self) -> eyre::Result<Checkpoint> {
+        self.checkpoint
+            .clone()
+            .ok_or_else(|| eyre::eyre!("No finalized batch to checkpoint yet"))
+    }
+
+    /// Takes transactions while `leading`; otherwise only queues gossiped ones, for when it leads.
+    pub fn set_leading(&mut self, leading: bool) {
+        self.leading = leading;
+    }
+
+    /// Whether this replica takes transactions: it sequences and no other replica holds the
+    /// lease.
+    pub fn is_leading(&self) -> bool {
+        self.sequencer.is_some() && self.leading
+    }
+
+    /// Admits `tx` to the next batch and returns the sequencer's confirmation of it.
+    pub fn submit_transaction(&mut self, tx: SignedTransaction) -> eyre::Result<SoftConfirmation> {
+        if !self.leading {
+            return Err(eyre::eyre!(
+                "Not the leader, submit to the replica holding the sequencer lease"
+            ));
+        }
+        let confirmation = self
+            .sequencer
+            .as_mut()
+            .ok_or_else(|| eyre::eyre!("Not sequencing"))?
+            .submit(&self.processor, tx.clone())?;
+
+        if let Some(gossip) = &self.gossip {
+            // Only fails once the gossip has stopped, which stops the server too.
+            let _ = gossip.send(Gossip::Transaction(tx));
+            let _ = gossip.send(Gossip::Confirmation(confirmation.clone()));
+        }
+        Ok(confirmation)
+    }
+
+    /// Admits `tx`, gossiped by another replica, to the next batch without gossiping it again.
+    /// This replica's own confirmation of it is not handed out.
+    pub fn admit_gossiped(&mut self, tx: SignedTransaction) -> eyre::Result<()> {
+        self.sequencer
+            .as_mut()
+            .ok_or_else(|| eyre::eyre!("Not sequencing"))?
+            .submit(&self.processor, tx)?;
+        Ok(())
+    }
+
+    /// Keeps `confirmation`, gossiped by one of `sequencers`, until its batch is followed.
+    pub fn record_confirmation(
+        &mut self,
+        confirmation: SoftConfirmation,
+        sequencers: &[Address],
+    ) -> eyre::Result<()> {
+        if !sequencers.contains(&confirmation.sequencer) {
+            return Err(eyre::eyre!(
+                "{} is not a known sequencer",
+                confirmation.sequencer
+            ));
+        }
+        confirmation.verify(&self.processor.config.domain)?;
+        self.confirmations
+            .insert(confirmation.tx_hash, confirmation);
+        Ok(())
+    }
+
+    /// The gossiped confirmation of the transaction with hash `tx_hash`, if any.
+    pub fn soft_confirmation(&self, tx_hash: &B256) -> Option<SoftConfirmation> {
+        self.confirmations.get(tx_hash).cloned()
+    }
+
+    /// Queues the sequencer's transactions logged at `path` again on the served state; see
+    /// `Sequencer::restore`.
+    pub fn restore_mempool(&mut self, path: PathBuf) -> eyre::Result<MempoolRecovery> {
+        self.sequencer
+            .as_mut()
+            .ok_or_else(|| eyre::eyre!("Not sequencing"))?
+            .restore(&self.processor, path)
+    }
+
+    pub fn pending_transactions(&self) -> Vec<SignedTransaction> {
+        self.sequencer
+            .as_ref()
+            .map(Sequencer::pending_transactions)
+            .unwrap_or_default()
+    }
+
+    pub fn account(&self, address: &Address) -> Account {
+        self.processor
+            .db
+            .accounts
+            .borrow()
+            .get(address)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn state_root(&self) -> eyre::Result<B256> {
+        Ok(self.processor.generate_state_root()?.into())
+    }
+
+    pub fn domain(&self) -> SigningDomain {
+        self.processor.config.domain
+    }
+
+    pub fn proof(&self, address: &Address) -> eyre::Result<AccountProof> {
+        let account = self.processor.db.accounts.borrow().get(address).cloned();
+        let account = account.ok_or_else(|| eyre::eyre!("Address not found"))?;
+
+        Ok(AccountProof {
+            address: *address,
+            leaf_hash: leaf_hash(self.processor.config.commitment, address, &account).into(),
+            proof: self
+                .processor
+                .generate_proof(address)?
+                .into_iter()
+                .map(B256::from)
+                .collect(),
+            state_root: self.state_root()?,
+        })
+    }
+
+    /// The ABI-encoded `AccountClaim` of `address` against the current state root.
+    pub fn claim(&self, address: &Address) -> eyre::Result<Bytes> {
+        Ok(self.processor.generate_claim(address)?.abi_encode().into())
+    }
+
+    /// The account of `address` with what proves it to a `LightClient`.
+    pub fn account_with_proof(&self, address: &Address) -> eyre::Result<AccountWithProof> {
+        self.processor.account_with_proof(address)
+    }
+
+    pub fn multiproof(&self, addresses: &[Address]) -> eyre::Result<MultiProof> {
+        self.processor.generate_multiproof(addresses)
+    }
+
+    /// Proof that `address` has no account, against the `accountsRoot` of the current state.
+    pub fn exclusion_proof(&self, address: &Address) -> eyre::Result<ExclusionProof> {
+        self.processor.generate_exclusion_proof(address)
+    }
+
+    /// Inclusion or exclusion proof of `address` against the state root, under a sparse
+    /// commitment version.
+    pub fn sparse_proof(&self, address: &Address) -> eyre::Result<SparseProof> {
+        self.processor.generate_sparse_proof(address)
+    }
+
+    /// The root of `canvas` and its proof against the state root, under a commitment version
+    /// with canvases.
+    pub fn canvas_proof(&self, canvas: u64) -> eyre::Result<CanvasProof> {
+        self.processor.generate_canvas_proof(canvas)
+    }
+
+    /// The account at `address` right after batch `batch`.
+    pub fn account_at(&self, address: &Address, batch: u64) -> eyre::Result<Account> {
+        self.history()?
+            .lock()
+            .unwrap()
+            .account_at(batch, address, self.account(address))
+    }
+
+    pub fn state_root_at(&self, batch: u64) -> eyre::Result<B256> {
+        self.history()?.lock().unwrap().state_root_at(batch)
+    }
+
+    /// `analytics::top_contributors` over the batches in the history.
+    pub fn top_contributors(
+        &self,
+        address: &Address,
+        limit: usize,
+    ) -> eyre::Result<Vec<LeaderboardEntry>> {
+        Ok(analytics::top_contributors(
+            &self.history()?.lock().unwrap(),
+            address,
+            limit,
+        ))
+    }
+
+    pub fn top_writers(&self, limit: usize) -> eyre::Result<Vec<WriterActivity>> {
+        Ok(analytics::top_writers(
+            &self.history()?.lock().unwrap(),
+            limit,
+        ))
+    }
+
+    pub fn batch_activity(&self, from: u64, limit: usize) -> eyre::Result<Vec<BatchActivity>> {
+        Ok(analytics::batch_activity(
+            &self.history()?.lock().unwrap(),
+            from,
+            limit,
+        ))
+    }
+
+    pub fn activity_summary(&self) -> eyre::Result<ActivitySummary> {
+        Ok(analytics::summary(&self.history()?.lock().unwrap()))
+    }
+
+    fn history(&self) -> eyre::Result<&Mutex<HistoryStore>> {
+        self.history
+            .as_deref()
+            .ok_or_else(|| eyre::eyre!("No history is kept"))
+    }
+
+    /// Every address that contributed to any account.
+    pub fn contributors(&self) -> BTreeSet<Address> {
+        let accounts = self.processor.db.accounts.borrow();
+        accounts
+            .values()
+            .flat_map(|account| account.contributors.iter().copied())
+            .collect()
+    }
+
+    /// The `limit` contributors to `address` with the most edits, then the most units inserted,
+    /// ties broken by address.
+    pub fn leaderboard(&self, address: &Address, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .account(address)
+            .contributions
+            .into_iter()
+            .map(|(contributor, stats)| LeaderboardEntry { contributor, stats })
+            .collect();
+        entries.sort_by(|a, b| {
+            (b.stats.edits, b.stats.inserted)
+                .cmp(&(a.stats.edits, a.stats.inserted))
+                .then(a.contributor.cmp(&b.contributor))
+        });
+        entries.truncate(limit);
+        entries
+    }
+}
+
+pub fn rpc_module(
+    reader: Arc<Mutex<StateReader>>,
+) -> eyre::Result<RpcModule<Arc<Mutex<StateReader>>>> {
+    let mut module = RpcModule::new(reader);
+
+    module.register_method("canvas_getAccount", |params, reader, _| {
+        let address: Address = params.one()?;
+        Ok::<_, ErrorObjectOwned>(reader.lock().unwrap().account(&address))
+    })?;
+
+    module.register_method("canvas_getStateRoot", |_, reader, _| {
+        reader.lock().unwrap().state_root().map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getProof", |params, reader, _| {
+        let address: Address = params.one()?;
+        reader.lock().unwrap().proof(&address).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getClaim", |params, reader, _| {
+        let address: Address = params.one()?;
+        reader.lock().unwrap().claim(&address).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getAccountWithProof", |params, reader, _| {
+        let address: Address = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .account_with_proof(&address)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getMultiproof", |params, reader, _| {
+        let addresses: Vec<Address> = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .multiproof(&addresses)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getExclusionProof", |params, reader, _| {
+        let address: Address = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .exclusion_proof(&address)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getSparseProof", |params, reader, _| {
+        let address: Address = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .sparse_proof(&address)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getCanvasProof", |params, reader, _| {
+        let canvas: u64 = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .canvas_proof(canvas)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getAccountAt", |params, reader, _| {
+        let (address, batch): (Address, u64) = params.parse()?;
+        reader
+            .lock()
+            .unwrap()
+            .account_at(&address, batch)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getStateRootAt", |params, reader, _| {
+        let batch: u64 = params.one()?;
+        reader
+            .lock()
+            .unwrap()
+            .state_root_at(batch)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getContributors", |params, reader, _| {
+        let address: Address = params.one()?;
+        Ok::<_, ErrorObjectOwned>(reader.lock().unwrap().account(&address).contributors)
+    })?;
+
+    module.register_method("canvas_getLeaderboard", |params, reader, _| {
+        let (address, limit): (Address, usize) = params.parse()?;
+        Ok::<_, ErrorObjectOwned>(reader.lock().unwrap().leaderboard(&address, limit))
+    })?;
+
+    module.register_method("canvas_getTopContributors", |params, reader, _| {
+        let (address, limit): (Address, usize) = params.parse()?;
+        reader
+            .lock()
+            .unwrap()
+            .top_contributors(&address, limit)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getTopWriters", |params, reader, _| {
+        let limit: usize = params.one()?;
+        reader.lock().unwrap().top_writers(limit).map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getBatchActivity", |params, reader, _| {
+        let (from, limit): (u64, usize) = params.parse()?;
+        reader
+            .lock()
+            .unwrap()
+            .batch_activity(from, limit)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getActivitySummary", |_, reader, _| {
+        reader.lock().unwrap().activity_summary().map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getCheckpoint", |_, reader, _| {
+        reader.lock().unwrap().checkpoint().map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_submitTransaction", |params, reader, _| {
+        let raw: String = params.one()?;
+        let tx = decode_raw_transaction(&raw).map_err(rpc_error)?;
+        reader
+            .lock()
+            .unwrap()
+            .submit_transaction(tx)
+            .map_err(rpc_error)
+    })?;
+
+    module.register_method("canvas_getPendingTransactions", |_, reader, _| {
+        Ok::<_, ErrorObjectOwned>(reader.lock().unwrap().pending_transactions())
+    })?;
+
+    module.register_method("canvas_isLeader", |_, reader, _| {
+        reader.lock().unwrap().is_leading()
+    })?;
+
+    module.register_method("canvas_getSoftConfirmation", |params, reader, _| {
+        let tx_hash: B256 = params.one()?;
+        Ok::<_, ErrorObjectOwned>(reader.lock().unwrap().soft_confirmation(&tx_hash))
+    })?;
+
+    Ok(module)
+}
+
+/// What the profile methods read: the state for contributor lists, the registry for profiles.
+pub struct ProfileContext {
+    pub reader: Arc<Mutex<StateReader>>,
+    pub profiles: Arc<ProfileRegistry>,
+}
+
+/// ENS names and profiles of contributors.
+pub fn profile_module(context: ProfileContext) -> eyre::Result<RpcModule<ProfileContext>> {
+    let mut module = RpcModule::new(context);
+
+    module.register_async_method("canvas_getProfile", |params, ctx, _| async move {
+        let address: Address = params.one()?;
+        ctx.profiles.profile(address).await.map_err(rpc_error)
+    })?;
+
+    module.register_async_method(
+        "canvas_getContributorProfiles",
+        |params, ctx, _| async move {
+            let address: Address = params.one()?;
+            let contributors = ctx.reader.lock().unwrap().account(&address).contributors;
+
+            let mut profiles = Vec::with_capacity(contributors.len());
+            for contributor in contributors {
+                profiles.push(ctx.profiles.profile(contributor).await.map_err(rpc_error)?);
+            }
+            Ok::<_, ErrorObjectOwned>(profiles)
+        },
+    )?;
+
+    Ok(module)
+}
+
+/// What `canvas_subscribeBatches` pushes for each applied batch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub index: u64,
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub transaction_commit: B256,
+    pub state_root: B256,
+    pub tx_count: usize,
+}
+
+impl From<&BatchEvent> for BatchSummary {
+    fn from(event: &BatchEvent) -> Self {
+        Self {
+            index: event.index,
+            tx_hash: event.tx_hash,
+            block_number: event.block_number,
+            transaction_commit: event.transaction_commit,
+            state_root: event.state_root,
+            tx_count: event.receipts.len(),
+        }
+    }
+}
+
+/// What `canvas_subscribeAccount` pushes when a batch changes the account.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUpdate {
+    pub batch: u64,
+    #[serde(flatten)]
+    pub diff: AccountDiff,
+}
+
+/// Batches the follower applied during a poll, held back until the served state includes them.
+#[derive(Clone, Default)]
+pub struct EventQueue(Arc<Mutex<Vec<BatchEvent>>>);
+
+impl EventQueue {
+    fn take(&self) -> Vec<BatchEvent> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl BatchHook for EventQueue {
+    fn on_batch(&mut self, event: &BatchEvent) -> eyre::Result<()> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+/// Pushes over WebSocket the batches applied, and the changes to an account, once served.
+pub fn subscription_module(
+    events: broadcast::Sender<Arc<BatchEvent>>,
+) -> eyre::Result<RpcModule<broadcast::Sender<Arc<BatchEvent>>>> {
+    let mut module = RpcModule::new(events);
+
+    module.register_subscription(
+        "canvas_subscribeBatches",
+        "canvas_batch",
+        "canvas_unsubscribeBatches",
+        |_, pending, events, _| async move {
+            let receiver = events.subscribe();
+            let sink = pending.accept().await?;
+            forward(sink, receiver, |event| {
+                vec![SubscriptionMessage::from_json(&BatchSummary::from(event))]
+            })
+            .await
+        },
+    )?;
+
+    module.register_subscription(
+        "canvas_subscribeAccount",
+        "canvas_account",
+        "canvas_unsubscribeAccount",
+        |params, pending, events, _| async move {
+            let address: Address = match params.one() {
+                Ok(address) => address,
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
+            let receiver = events.subscribe();
+            let sink = pending.accept().await?;
+            forward(sink, receiver, |event| {
+                event
+                    .diffs
+                    .iter()
+                    .filter(|diff| diff.address == address)
+                    .map(|diff| {
+                        SubscriptionMessage::from_json(&AccountUpdate {
+                            batch: event.index,
+                            diff: diff.clone(),
+                        })
+                    })
+                    .collect()
+            })
+            .await
+        },
+    )?;
+
+    Ok(module)
+}
+
+// Sends what `messages` makes of each event until the client unsubscribes. A subscriber that
+// falls more than `EVENT_CAPACITY` batches behind is closed, as it missed updates.
+async fn forward(
+    sink: SubscriptionSink,
+    mut receiver: broadcast::Receiver<Arc<BatchEvent>>,
+    messages: impl Fn(&BatchEvent) -> Vec<Result<SubscriptionMessage, serde_json::Error>>,
+) -> jsonrpsee::core::SubscriptionResult {
+    loop {
+        let event = tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            event = receiver.recv() => event,
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(RecvError::Closed) => return Ok(()),
+            Err(RecvError::Lagged(missed)) => {
+                return Err(format!("Subscriber missed {} batches", missed).into())
+            }
+        };
+
+        for message in messages(&event) {
+            sink.send(message?).await?;
+        }
+    }
+}
+
+// Keeps following the chain, swapping each new state into `reader` before its batches are
+// published, so a subscriber reading on an update sees the state it describes.
+async fn follow_chain(
+    follower: Follower,
+    interval: Duration,
+    reader: Arc<Mutex<StateReader>>,
+    events: broadcast::Sender<Arc<BatchEvent>>,
+) -> eyre::Result<()> {
+    let queue = EventQueue::default();
+    let mut follower = follower.with_hook(queue.clone());
+    let mut tip = (follower.batches(), follower.previous_batch());
+
+    loop {
+        follower.poll().await?;
+
+        // A reorg can change the state without applying anything new.
+        if tip != (follower.batches(), follower.previous_batch()) {
+            tip = (follower.batches(), follower.previous_batch());
+            reader
+                .lock()
+                .unwrap()
+                .set_processor(follower.state()?, follower.batches() as u64);
+        }
+        // Finality moves on without new batches too.
+        reader.lock().unwrap().set_checkpoint(follower.checkpoint());
+        for event in queue.take() {
+            // Only fails when nobody is subscribed.
+            let _ = events.send(Arc::new(event));
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Serves the state, and contributor profiles with a registry. The profiles of everyone who
+/// contributed so far are resolved in the background on startup. With `follow` the follower
+/// keeps polling at that interval, the served state tracks the chain and new batches are pushed
+/// to subscribers. With a `sequencer` transactions are taken for the next batch, and shared with
+/// the other replicas if it has a gossip config, and only while holding the lease if it has a
+/// leadership. With `grpc` the same state is also served over gRPC on that address.
+///
+/// `lifecycle` reloads the node config on SIGHUP, and on SIGINT or SIGTERM stops the server and
+/// persists the served state. The sequencer logs its pending transactions as it accepts them,
+/// and those that still apply are queued again on start, after a crash as well.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    addr: SocketAddr,
+    mut follower: Follower,
+    follow: Option<Duration>,
+    profiles: Option<ProfileRegistry>,
+    history: Option<Arc<Mutex<HistoryStore>>>,
+    mut sequencer: Option<Sequencer>,
+    grpc: Option<SocketAddr>,
+    lifecycle: Lifecycle,
+) -> eyre::Result<()> {
+    follower.prune().await?;
+    let checkpoint = follower.checkpoint();
+    let (processor, following) = match follow {
+        Some(interval) => (follower.state()?, Some((follower, interval))),
+        None => (follower.into_processor(), None),
+    };
+    let gossip = sequencer.as_mut().and_then(Sequencer::take_gossip);
+    let leadership = sequencer.as_mut().and_then(Sequencer::take_leadership);
+    let (outbound, outbound_rx) = mpsc::unbounded_channel();
+    let mut reader = StateReader::new(processor);
+    if gossip.is_some() {
+        reader = reader.with_gossip(outbound);
+    }
+    // Standing by until the lease is taken.
+    reader.set_leading(leadership.is_none());
+    if let Some(history) = history {
+        reader = reader.with_history(history);
+    }
+    if let Some(sequencer) = sequencer {
+        reader = reader.with_sequencer(sequencer);
+        let recovery = reader.restore_mempool(lifecycle.shutdown.mempool_path())?;
+        if recovery != MempoolRecovery::default() {
+            tracing::info!(
+                restored = recovery.restored,
+                dropped = recovery.dropped,
+                unreadable = recovery.unreadable,
+                "Queued the transactions pending before the restart"
+            );
+        }
+    }
+    reader.set_node_config(lifecycle.config);
+    reader.set_checkpoint(checkpoint);
+    let reader = Arc::new(Mutex::new(reader));
+    let mut module = rpc_module(reader.clone())?;
+
+    if let Some(profiles) = profiles {
+        let profiles = Arc::new(profiles);
+        let contributors = reader.lock().unwrap().contributors();
+        let warming = profiles.clone();
+        tokio::spawn(async move { warming.warm(contributors).await });
+
+        module.merge(profile_module(ProfileContext {
+            reader: reader.clone(),
+            profiles,
+        })?)?;
+    }
+
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
+    if following.is_some() {
+        module.merge(subscription_module(events.clone())?)?;
+    }
+
+    // A checkpoint carries the whole state.
+    let server = Server::builder()
+        .max_response_body_size(u32::MAX)
+        .build(addr)
+        .await?;
+    let handle = server.start(module);
+
+    tracing::info!(%addr, "Serving canvas state");
+
+    let grpc = {
+        let reader = reader.clone();
+        let events = following.is_some().then(|| events.clone());
+        async move {
+            match grpc {
+                Some(addr) => serve_grpc(addr, reader, events).await,
+                None => std::future::pending().await,
+            }
+        }
+    };
+
+    let gossip = {
+        let reader = reader.clone();
+        async move {
+            match gossip {
+                Some(config) => serve_gossip(config, reader, outbound_rx).await,
+                None => std::future::pending().await,
+            }
+        }
+    };
+    let lease = {
+        let reader = reader.clone();
+        async move {
+            match leadership {
+                Some(leadership) => leadership.run(reader).await,
+                None => std::future::pending().await,
+            }
+        }
+    };
+
+    let follow = {
+        let reader = reader.clone();
+        async move {
+            match following {
+                Some((follower, interval)) => {
+                    follow_chain(follower, interval, reader, events).await
+                }
+                None => std::future::pending().await,
+            }
+        }
+    };
+    let shutdown = lifecycle.shutdown;
+
+    tokio::select! {
+        _ = handle.clone().stopped() => return Ok(()),
+        result = follow => return result,
+        result = grpc => return result,
+        result = gossip => return result,
+        result = lease => return result,
+        result = lifecycle.watcher.run(reader.clone()) => return result,
+        result = shutdown.signalled() => result?,
+    }
+
+    // Nothing is applied once the follower has stopped, so the snapshot is of the last state
+    // served.
+    tracing::info!("Shutting down");
+    let _ = handle.stop();
+    handle.stopped().await;
+    let reader = reader.lock().unwrap();
+    shutdown.persist(&reader)
+}
+
+#[cfg(feature = "p2p")]
+async fn serve_gossip(
+    config: GossipConfig,
+    reader: Arc<Mutex<StateReader>>,
+    outbound: mpsc::UnboundedReceiver<Gossip>,
+) -> eyre::Result<()> {
+    crate::gossip::run(config, reader, outbound).await
+}
+
+#[cfg(not(feature = "p2p"))]
+async fn serve_gossip(
+    _config: GossipConfig,
+    _reader: Arc<Mutex<StateReader>>,
+    _outbound: mpsc::UnboundedReceiver<Gossip>,
+) -> eyre::Result<()> {
+    Err(eyre::eyre!(
+        "--gossip-listen needs a build with the `p2p` feature"
+    ))
+}
+
+#[cfg(feature = "grpc")]
+async fn serve_grpc(
+    addr: SocketAddr,
+    reader: Arc<Mutex<StateReader>>,
+    events: Option<broadcast::Sender<Arc<BatchEvent>>>,
+) -> eyre::Result<()> {
+    crate::grpc::run(addr, crate::grpc::CanvasService::new(reader, events)).await
+}
+
+#[cfg(not(feature = "grpc"))]
+async fn serve_grpc(
+    _addr: SocketAddr,
+    _reader: Arc<Mutex<StateReader>>,
+    _events: Option<broadcast::Sender<Arc<BatchEvent>>>,
+) -> eyre::Result<()> {
+    Err(eyre::eyre!(
+        "--grpc-addr needs a build with the `grpc` feature"
+    ))
+}
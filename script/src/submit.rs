@@ -0,0 +1,476 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    eips::BlockId,
+    hex,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, Bytes, B256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    signers::local::PrivateKeySigner,
+    sol_types::{decode_revert_reason, SolCall},
+    transports::{RpcError, TransportErrorKind},
+};
+use program::sequencer::SequencerSignature;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    metrics,
+    sync::{submitAggregatedBatchesCall, submitBatchWithProofCall, submitSignedBatchWithProofCall},
+};
+
+// Gas limit headroom over the estimate, in percent.
+const GAS_HEADROOM: u128 = 20;
+
+// Fee increase of a replacement, in percent. Nodes only replace a pending transaction whose fees
+// are at least 10% higher.
+const FEE_BUMP: u128 = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InFlight {
+    // Every transaction sent at this nonce, the latest last. Any of them may be the one mined.
+    tx_hashes: Vec<B256>,
+    nonce: u64,
+    input: Bytes,
+    gas_limit: u128,
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+    // Unix seconds the first and the latest were sent.
+    sent_at: u64,
+    bumped_at: u64,
+}
+
+struct SubmissionKey {
+    signer: PrivateKeySigner,
+    // Unknown until the key is first used.
+    next_nonce: Option<u64>,
+    in_flight: Option<InFlight>,
+}
+
+// How a submission ended.
+enum Outcome {
+    Mined(TransactionReceipt),
+    // Another transaction took the nonce.
+    Replaced,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// The reason a call reverted with: the string or panic it decodes to, else its raw revert data,
+// else the error itself.
+fn revert_reason(err: &RpcError<TransportErrorKind>) -> String {
+    match err
+        .as_error_resp()
+        .and_then(|payload| payload.as_revert_data())
+    {
+        Some(data) => {
+            decode_revert_reason(&data).unwrap_or_else(|| format!("0x{}", hex::encode(&data)))
+        }
+        None => err.to_string(),
+    }
+}
+
+/// Submits batches to the rollup contract from a pool of keys. Each key has at most one
+/// submission in flight and tracks its own nonce, and keys are used in turn, so a transaction
+/// stuck on one key only holds up that key.
+///
+/// Gas is estimated before sending, which also turns a call that would revert into an error
+/// with the contract's reason instead of a wasted transaction. A submission left unmined for
+/// `bump_after` is sent again at the same nonce with higher fees, up to `max_fee_per_gas`. With
+/// a state file, submissions in flight are saved there and picked up again after a restart.
+pub struct Submitter {
+    rpc_url: String,
+    rollup: Address,
+    keys: Vec<SubmissionKey>,
+    cursor: usize,
+    state_file: Option<PathBuf>,
+    /// How long a submission may stay unconfirmed before its key is reported as stuck.
+    pub stuck_after: Duration,
+    /// How long a submission may stay unmined before it is replaced with higher fees.
+    pub bump_after: Duration,
+    /// The highest fee per gas a replacement may offer, in wei.
+    pub max_fee_per_gas: u128,
+    /// How often `confirm` checks for the receipt.
+    pub poll_interval: Duration,
+}
+
+impl Submitter {
+    pub fn new(rpc_url: String, rollup: Address, signers: Vec<PrivateKeySigner>) -> Self {
+        Self {
+            rpc_url,
+            rollup,
+            keys: signers
+                .into_iter()
+                .map(|signer| SubmissionKey {
+                    signer,
+                    next_nonce: None,
+                    in_flight: None,
+                })
+                .collect(),
+            cursor: 0,
+            state_file: None,
+            stuck_after: Duration::from_secs(180),
+            bump_after: Duration::from_secs(60),
+            max_fee_per_gas: 500_000_000_000,
+            poll_interval: Duration::from_secs(4),
+        }
+    }
+
+    /// Keeps the submissions in flight in `path`, resuming those already saved there for any
+    /// of the keys.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            let saved: BTreeMap<Address, InFlight> = serde_json::from_slice(&fs::read(&path)?)?;
+            for key in &mut self.keys {
+                if let Some(in_flight) = saved.get(&key.signer.address()) {
+                    tracing::info!(
+                        from = %key.signer.address(),
+                        nonce = in_flight.nonce,
+                        "Resuming submission in flight"
+                    );
+                    key.next_nonce = Some(in_flight.nonce + 1);
+                    key.in_flight = Some(in_flight.clone());
+                }
+            }
+        }
+        self.state_file = Some(path);
+        Ok(self)
+    }
+
+    /// Sends `submitBatchWithProof` from the next idle key and returns the transaction hash.
+    pub async fn submit(
+        &mut self,
+        public_values: Bytes,
+        proof: Bytes,
+        transaction_data: Bytes,
+    ) -> eyre::Result<B256> {
+        let call = submitBatchWithProofCall {
+            _publicValuesBytes: public_values,
+            _proofBytes: proof,
+            _transactionData: transaction_data,
+        };
+        self.send(call.abi_encode().into()).await
+    }
+
+    /// Sends `submitSignedBatchWithProof`, with the sequencer's signature over the batch.
+    pub async fn submit_signed(
+        &mut self,
+        public_values: Bytes,
+        proof: Bytes,
+        transaction_data: Bytes,
+        signature: &SequencerSignature,
+    ) -> eyre::Result<B256> {
+        let call = submitSignedBatchWithProofCall {
+            _publicValuesBytes: public_values,
+            _proofBytes: proof,
+            _transactionData: transaction_data,
+            _sequencerSignature: signature.to_bytes().to_vec().into(),
+        };
+        self.send(call.abi_encode().into()).await
+    }
+
+    /// Sends `submitAggregatedBatches` for an aggregate proof, with the transaction data of each
+    /// of its batches in order.
+    pub async fn submit_aggregated(
+        &mut self,
+        public_values: Bytes,
+        proof: Bytes,
+        transaction_data: Vec<Bytes>,
+    ) -> eyre::Result<B256> {
+        let call = submitAggregatedBatchesCall {
+            _publicValuesBytes: public_values,
+            _proofBytes: proof,
+            _transactionData: transaction_data,
+        };
+        self.send(call.abi_encode().into()).await
+    }
+
+    /// Waits for the submission first sent as `tx_hash` to be mined, replacing it whenever it
+    /// stalls, and returns the hash of the transaction that was. A reverted submission is an
+    /// error with the contract's reason.
+    pub async fn confirm(&mut self, tx_hash: B256) -> eyre::Result<B256> {
+        loop {
+            let index = self
+                .keys
+                .iter()
+                .position(|key| {
+                    key.in_flight
+                        .as_ref()
+                        .is_some_and(|in_flight| in_flight.tx_hashes.contains(&tx_hash))
+                })
+                .ok_or_else(|| eyre::eyre!("Submission {} is not in flight", tx_hash))?;
+
+            match self.settle(index).await? {
+                Some(Outcome::Mined(receipt)) if receipt.status() => {
+                    return Ok(receipt.transaction_hash)
+                }
+                Some(Outcome::Mined(receipt)) => {
+                    let reason = self.revert_reason_of(index, &receipt).await?;
+                    self.finish(index)?;
+                    return Err(eyre::eyre!(
+                        "Submission {} reverted: {}",
+                        receipt.transaction_hash,
+                        reason
+                    ));
+                }
+                Some(Outcome::Replaced) => {
+                    return Err(eyre::eyre!(
+                        "Submission {} was replaced by another transaction at its nonce",
+                        tx_hash
+                    ))
+                }
+                None => tokio::time::sleep(self.poll_interval).await,
+            }
+        }
+    }
+
+    // Sends a call to the rollup from the next idle key.
+    async fn send(&mut self, input: Bytes) -> eyre::Result<B256> {
+        self.refresh().await?;
+
+        let key_count = self.keys.len();
+        let index = (0..key_count)
+            .map(|offset| (self.cursor + offset) % key_count)
+            .find(|&i| self.keys[i].in_flight.is_none())
+            .ok_or_else(|| eyre::eyre!("Every submission key has a transaction in flight"))?;
+        self.cursor = (index + 1) % key_count;
+
+        let key = &self.keys[index];
+        let from = key.signer.address();
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(key.signer.clone()))
+            .on_http(self.rpc_url.parse()?);
+
+        let nonce = match key.next_nonce {
+            Some(nonce) => nonce,
+            None => provider.get_transaction_count(from).latest().await?,
+        };
+
+        let call = TransactionRequest::default()
+            .with_from(from)
+            .with_to(self.rollup)
+            .with_input(input.clone());
+        let gas = provider
+            .estimate_gas(&call)
+            .await
+            .map_err(|err| eyre::eyre!("Submission would revert: {}", revert_reason(&err)))?;
+        let fees = provider.estimate_eip1559_fees(None).await?;
+
+        let in_flight = InFlight {
+            tx_hashes: Vec::new(),
+            nonce,
+            input,
+            gas_limit: gas + gas * GAS_HEADROOM / 100,
+            max_fee_per_gas: fees.max_fee_per_gas.min(self.max_fee_per_gas),
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas.min(self.max_fee_per_gas),
+            sent_at: now(),
+            bumped_at: now(),
+        };
+        let tx_hash = *provider
+            .send_transaction(self.transaction(&in_flight))
+            .await?
+            .tx_hash();
+
+        let key = &mut self.keys[index];
+        key.next_nonce = Some(nonce + 1);
+        key.in_flight = Some(InFlight {
+            tx_hashes: vec![tx_hash],
+            ..in_flight
+        });
+        self.save()?;
+
+        tracing::info!(%from, nonce, %tx_hash, gas, "Submitted batch");
+
+        Ok(tx_hash)
+    }
+
+    // The transaction sending `in_flight` with its current fees.
+    fn transaction(&self, in_flight: &InFlight) -> TransactionRequest {
+        TransactionRequest::default()
+            .with_to(self.rollup)
+            .with_input(in_flight.input.clone())
+            .with_nonce(in_flight.nonce)
+            .with_gas_limit(in_flight.gas_limit)
+            .with_max_fee_per_gas(in_flight.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(in_flight.max_priority_fee_per_gas)
+    }
+
+    // Checks the submission in flight on key `index`. Returns how it ended, having recorded its
+    // gas and moved the key's nonce on, or replaces it with higher fees if it stalled.
+    async fn settle(&mut self, index: usize) -> eyre::Result<Option<Outcome>> {
+        let key = &self.keys[index];
+        let Some(in_flight) = key.in_flight.clone() else {
+            return Ok(None);
+        };
+        let from = key.signer.address();
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(key.signer.clone()))
+            .on_http(self.rpc_url.parse()?);
+
+        // The nonce is read before the receipts, so a submission mined in between shows up as a
+        // receipt rather than as the nonce moving without one.
+        let nonce = provider.get_transaction_count(from).latest().await?;
+        for tx_hash in in_flight.tx_hashes.iter().rev() {
+            if let Some(receipt) = provider.get_transaction_receipt(*tx_hash).await? {
+                metrics::pipeline()
+                    .submission_gas
+                    .observe(receipt.gas_used as f64);
+                self.keys[index].next_nonce = Some(in_flight.nonce + 1);
+                if receipt.status() {
+                    self.finish(index)?;
+                }
+                return Ok(Some(Outcome::Mined(receipt)));
+            }
+        }
+
+        // Mined without any receipt of ours: the nonce went to some other transaction.
+        if nonce > in_flight.nonce {
+            tracing::warn!(%from, nonce = in_flight.nonce, "Submission replaced at its nonce");
+            self.keys[index].next_nonce = None;
+            self.finish(index)?;
+            return Ok(Some(Outcome::Replaced));
+        }
+
+        if now().saturating_sub(in_flight.bumped_at) < self.bump_after.as_secs()
+            || in_flight.max_fee_per_gas >= self.max_fee_per_gas
+        {
+            return Ok(None);
+        }
+
+        let fees = provider.estimate_eip1559_fees(None).await?;
+        let bump = |old: u128, current: u128| {
+            (old + old * FEE_BUMP / 100 + 1)
+                .max(current)
+                .min(self.max_fee_per_gas)
+        };
+        let mut bumped = InFlight {
+            max_fee_per_gas: bump(in_flight.max_fee_per_gas, fees.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(
+                in_flight.max_priority_fee_per_gas,
+                fees.max_priority_fee_per_gas,
+            ),
+            bumped_at: now(),
+            ..in_flight
+        };
+
+        // The first may be mined while the replacement is on its way, which nodes reject with
+        // a low nonce; the next check finds its receipt.
+        match provider.send_transaction(self.transaction(&bumped)).await {
+            Ok(pending) => {
+                let tx_hash = *pending.tx_hash();
+                tracing::info!(
+                    %from,
+                    nonce = bumped.nonce,
+                    %tx_hash,
+                    max_fee_per_gas = bumped.max_fee_per_gas,
+                    "Replaced stalled submission"
+                );
+                bumped.tx_hashes.push(tx_hash);
+            }
+            Err(err) => tracing::warn!(%from, %err, "Could not replace stalled submission"),
+        }
+        self.keys[index].in_flight = Some(bumped);
+        self.save()?;
+        Ok(None)
+    }
+
+    // Why the submission in flight on key `index` reverted in `receipt`, from the call replayed
+    // at the block before.
+    async fn revert_reason_of(
+        &self,
+        index: usize,
+        receipt: &TransactionReceipt,
+    ) -> eyre::Result<String> {
+        let key = &self.keys[index];
+        let Some(in_flight) = &key.in_flight else {
+            return Ok("unknown".to_string());
+        };
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.parse()?);
+        let call = TransactionRequest::default()
+            .with_from(key.signer.address())
+            .with_to(self.rollup)
+            .with_input(in_flight.input.clone())
+            .with_gas_limit(in_flight.gas_limit);
+        let block = receipt.block_number.unwrap_or_default().saturating_sub(1);
+
+        Ok(
+            match provider.call(&call).block(BlockId::number(block)).await {
+                Ok(_) => "no reason given".to_string(),
+                Err(err) => revert_reason(&err),
+            },
+        )
+    }
+
+    // Frees key `index`.
+    fn finish(&mut self, index: usize) -> eyre::Result<()> {
+        self.keys[index].in_flight = None;
+        self.save()
+    }
+
+    // Writes the submissions in flight to the state file, if there is one.
+    fn save(&self) -> eyre::Result<()> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+
+        let in_flight: BTreeMap<Address, &InFlight> = self
+            .keys
+            .iter()
+            .filter_map(|key| Some((key.signer.address(), key.in_flight.as_ref()?)))
+            .collect();
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, serde_json::to_vec_pretty(&in_flight)?)?;
+        fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Keys whose in-flight submission has been unconfirmed for longer than `stuck_after`.
+    pub fn stuck(&self) -> Vec<Address> {
+        self.keys
+            .iter()
+            .filter(|key| {
+                key.in_flight
+                    .as_ref()
+                    .is_some_and(|tx| now().saturating_sub(tx.sent_at) > self.stuck_after.as_secs())
+            })
+            .map(|key| key.signer.address())
+            .collect()
+    }
+
+    // Frees keys whose submission has been mined, reporting those that reverted, and replaces
+    // stalled ones.
+    async fn refresh(&mut self) -> eyre::Result<()> {
+        for index in 0..self.keys.len() {
+            if let Some(Outcome::Mined(receipt)) = self.settle(index).await? {
+                if !receipt.status() {
+                    let reason = self.revert_reason_of(index, &receipt).await?;
+                    tracing::warn!(
+                        tx_hash = %receipt.transaction_hash,
+                        %reason,
+                        "Submission reverted"
+                    );
+                    self.finish(index)?;
+                }
+            }
+        }
+
+        for address in self.stuck() {
+            tracing::warn!(%address, "Submission looks stuck, skipping that key");
+        }
+
+        Ok(())
+    }
+}
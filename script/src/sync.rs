@@ -0,0 +1,476 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, Bytes, B256},
+    providers::Provider,
+    rpc::types::{Filter, Log, Transaction},
+    sol,
+    sol_types::{SolCall, SolEvent},
+};
+use alloy_rlp::Decodable;
+use futures::{stream, StreamExt, TryStreamExt};
+use program::{blob::decode_blobs, bridge::Deposit, SignedTransaction};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    beacon::BlobFetcher,
+    cache::{BatchCache, CachedBlock},
+    providers::{HttpProvider, ProviderPool},
+};
+
+sol!(
+    /// @notice Verifies the submission of a batch of txs with a zk proof.
+    /// @param _publicValuesBytes The zk proof of a state transition.
+    /// @param _proofBytes The encoded public values.
+    /// @param _transactionData The transaction data included in the batch.
+    function submitBatchWithProof(
+        bytes calldata _publicValuesBytes,
+        bytes calldata _proofBytes,
+        bytes calldata _transactionData
+    ) public;
+
+    /// @notice Verifies the submission of a batch of txs with a zk proof, signed by the
+    /// sequencer that built it.
+    /// @param _publicValuesBytes The zk proof of a state transition.
+    /// @param _proofBytes The encoded public values.
+    /// @param _transactionData The transaction data included in the batch.
+    /// @param _sequencerSignature The sequencer's 65-byte signature over its `SequencedBatch`.
+    function submitSignedBatchWithProof(
+        bytes calldata _publicValuesBytes,
+        bytes calldata _proofBytes,
+        bytes calldata _transactionData,
+        bytes calldata _sequencerSignature
+    ) public;
+
+    /// @notice Verifies the submission of a batch of txs with a zk proof, with the transaction
+    /// data in the transaction's blobs.
+    /// @param _publicValuesBytes The zk proof of a state transition.
+    /// @param _proofBytes The encoded public values.
+    function submitBatchWithBlobs(
+        bytes calldata _publicValuesBytes,
+        bytes calldata _proofBytes
+    ) public;
+
+    /// @notice Verifies the submission of consecutive batches with one aggregate zk proof.
+    /// @param _publicValuesBytes The encoded aggregate public values.
+    /// @param _proofBytes The zk proof of the state transitions.
+    /// @param _transactionData The transaction data of each batch, in order.
+    function submitAggregatedBatches(
+        bytes calldata _publicValuesBytes,
+        bytes calldata _proofBytes,
+        bytes[] calldata _transactionData
+    ) public;
+);
+
+sol!(
+    /// @notice Emitted when `amount` is locked on L1 for `to` on the canvas.
+    event DepositQueued(
+        uint64 indexed index,
+        address indexed from,
+        address indexed to,
+        uint256 amount
+    );
+
+    /// @notice Emitted when a signed transaction is submitted to the contract directly, for the
+    /// next batches to include.
+    event ForcedTransactionQueued(uint64 indexed index, bytes transaction);
+);
+
+pub struct SyncConfig {
+    pub rpc_url: String,
+    /// Providers to fail over to, in order, when `rpc_url` errs or falls behind.
+    pub fallback_rpc_urls: Vec<String>,
+    /// Most requests per second to each provider, unlimited if unset.
+    pub rate_limit: Option<u32>,
+    /// Fetch every submission from two providers and stop if they disagree on it.
+    pub cross_check: bool,
+    pub rollup: Address,
+    pub from_block: u64,
+    /// Defaults to the latest block.
+    pub to_block: Option<u64>,
+    /// Blocks per `eth_getLogs` request.
+    pub page_size: u64,
+    pub retries: u32,
+    /// Submissions fetched at once.
+    pub fetch_concurrency: usize,
+    /// Beacon API for the blobs of batches posted with `submitBatchWithBlobs`.
+    pub beacon_url: Option<String>,
+    /// Where every range synced is cached; see `BatchCache`.
+    pub cache_dir: PathBuf,
+    /// Read blocks from the cache alone, treating its last block as the latest and finalized.
+    pub offline: bool,
+}
+
+/// A batch posted to the rollup contract, in chain order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostedBatch {
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub block_hash: B256,
+    /// `None` for a batch of an aggregate submission, which commits to its batches together
+    /// and never consumes L1 queue messages.
+    pub public_values: Option<Bytes>,
+    pub transaction_data: Bytes,
+    /// Its position among the batches its transaction posted, non-zero only in an aggregate.
+    #[serde(default)]
+    pub position: u64,
+    /// The sequencer's signature, for a batch posted with `submitSignedBatchWithProof`.
+    #[serde(default)]
+    pub sequencer_signature: Option<Bytes>,
+}
+
+/// What the rollup queued and posted in a range of blocks, each in order.
+#[derive(Default)]
+pub struct SyncedBlocks {
+    pub deposits: Vec<Deposit>,
+    pub forced_txs: Vec<(u64, SignedTransaction)>,
+    pub batches: Vec<PostedBatch>,
+}
+
+impl FromIterator<CachedBlock> for SyncedBlocks {
+    fn from_iter<I: IntoIterator<Item = CachedBlock>>(blocks: I) -> Self {
+        let mut synced = SyncedBlocks::default();
+        for block in blocks {
+            synced.deposits.extend(block.deposits);
+            synced.forced_txs.extend(block.forced_txs);
+            synced.batches.extend(block.batches);
+        }
+        synced
+    }
+}
+
+/// Finds batches by scanning the rollup contract's logs and decoding the `submitBatchWithProof`
+/// calls that emitted them. Any event will do, so the scan does not depend on the contract's
+/// event signatures.
+pub struct Syncer {
+    providers: ProviderPool,
+    blobs: Option<BlobFetcher>,
+    cache: BatchCache,
+    config: SyncConfig,
+}
+
+impl Syncer {
+    pub fn new(config: SyncConfig) -> eyre::Result<Self> {
+        let urls: Vec<String> = std::iter::once(config.rpc_url.clone())
+            .chain(config.fallback_rpc_urls.iter().cloned())
+            .collect();
+        let providers = ProviderPool::new(&urls, config.rate_limit, config.retries)?;
+        let blobs = config.beacon_url.as_deref().map(BlobFetcher::new);
+        let cache = BatchCache::new(&config.cache_dir)?;
+        Ok(Self {
+            providers,
+            blobs,
+            cache,
+            config,
+        })
+    }
+
+    pub fn start_block(&self) -> u64 {
+        self.config.from_block
+    }
+
+    /// The configured `to_block`, or the latest block. Checks the health of the providers
+    /// first, so each sync round starts on those that are up and at the head.
+    pub async fn head(&self) -> eyre::Result<u64> {
+        if self.config.offline {
+            let (_, last, _) = self.cache.range(&self.config.rollup)?;
+            return Ok(self.config.to_block.map_or(last, |block| block.min(last)));
+        }
+        if self.providers.check_health().await == 0 {
+            tracing::warn!("No provider is healthy");
+        }
+        match self.config.to_block {
+            Some(block) => Ok(block),
+            None => {
+                self.providers
+                    .request(|provider| async move { provider.get_block_number().await })
+                    .await
+            }
+        }
+    }
+
+    pub async fn finalized_block(&self) -> eyre::Result<u64> {
+        if self.config.offline {
+            let (_, last, _) = self.cache.range(&self.config.rollup)?;
+            return Ok(last);
+        }
+        self.block(BlockNumberOrTag::Finalized)
+            .await?
+            .map(|(number, _)| number)
+            .ok_or_else(|| eyre::eyre!("Provider has no finalized block"))
+    }
+
+    pub async fn latest_block(&self) -> eyre::Result<(u64, B256)> {
+        if self.config.offline {
+            let (_, last, hash) = self.cache.range(&self.config.rollup)?;
+            return Ok((last, hash));
+        }
+        self.block(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| eyre::eyre!("Provider has no latest block"))
+    }
+
+    pub async fn block_hash(&self, number: u64) -> eyre::Result<Option<B256>> {
+        if self.config.offline {
+            return self.cache.block_hash(&self.config.rollup, number);
+        }
+        Ok(self
+            .block(BlockNumberOrTag::Number(number))
+            .await?
+            .map(|(_, hash)| hash))
+    }
+
+    async fn block(&self, tag: BlockNumberOrTag) -> eyre::Result<Option<(u64, B256)>> {
+        let block = self
+            .providers
+            .request(|provider| async move { provider.get_block_by_number(tag, false).await })
+            .await?;
+
+        Ok(block.map(|block| (block.header.number, block.header.hash)))
+    }
+
+    /// Deposits, forced transactions and batches of blocks `from_block..=to_block`, which are
+    /// cached for `offline` syncs, or read from the cache by one.
+    pub async fn blocks_in(&self, from_block: u64, to_block: u64) -> eyre::Result<SyncedBlocks> {
+        if self.config.offline {
+            let blocks = self
+                .cache
+                .blocks_in(&self.config.rollup, from_block, to_block)?;
+            return Ok(blocks.into_values().collect());
+        }
+
+        let mut blocks: BTreeMap<u64, CachedBlock> = BTreeMap::new();
+        for (number, deposit) in self.deposit_logs_in(from_block, to_block).await? {
+            blocks.entry(number).or_default().deposits.push(deposit);
+        }
+        for (number, forced_tx) in self.forced_tx_logs_in(from_block, to_block).await? {
+            blocks.entry(number).or_default().forced_txs.push(forced_tx);
+        }
+        for batch in self.batches_in(from_block, to_block).await? {
+            blocks
+                .entry(batch.block_number)
+                .or_default()
+                .batches
+                .push(batch);
+        }
+
+        let hash = self.block_hash(to_block).await?.unwrap_or_default();
+        self.cache
+            .record(&self.config.rollup, from_block, to_block, hash, &blocks)?;
+        Ok(blocks.into_values().collect())
+    }
+
+    // Batches posted in blocks `from_block..=to_block`. Up to `fetch_concurrency` submissions
+    // are fetched and decoded at once, and their batches still come out in chain order.
+    async fn batches_in(&self, from_block: u64, to_block: u64) -> eyre::Result<Vec<PostedBatch>> {
+        let mut tx_hashes = Vec::new();
+        for log in self.logs_in(Filter::new(), from_block, to_block).await? {
+            // Logs are ordered, so several from one transaction are adjacent.
+            let Some(tx_hash) = log.transaction_hash else {
+                continue;
+            };
+            if tx_hashes.last() != Some(&tx_hash) {
+                tx_hashes.push(tx_hash);
+            }
+        }
+
+        let posted: Vec<Vec<PostedBatch>> = stream::iter(tx_hashes)
+            .map(|tx_hash| self.posted_batches(tx_hash))
+            .buffered(self.config.fetch_concurrency.max(1))
+            .try_collect()
+            .await?;
+        Ok(posted.into_iter().flatten().collect())
+    }
+
+    /// Deposits queued in blocks `from_block..=to_block`, in queue order.
+    pub async fn deposits_in(&self, from_block: u64, to_block: u64) -> eyre::Result<Vec<Deposit>> {
+        if self.config.offline {
+            return Ok(self.blocks_in(from_block, to_block).await?.deposits);
+        }
+        let deposits = self.deposit_logs_in(from_block, to_block).await?;
+        Ok(deposits.into_iter().map(|(_, deposit)| deposit).collect())
+    }
+
+    /// Forced transactions queued in blocks `from_block..=to_block` with their queue index, in
+    /// queue order.
+    pub async fn forced_txs_in(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> eyre::Result<Vec<(u64, SignedTransaction)>> {
+        if self.config.offline {
+            return Ok(self.blocks_in(from_block, to_block).await?.forced_txs);
+        }
+        let forced_txs = self.forced_tx_logs_in(from_block, to_block).await?;
+        Ok(forced_txs
+            .into_iter()
+            .map(|(_, forced_tx)| forced_tx)
+            .collect())
+    }
+
+    // Deposits queued in blocks `from_block..=to_block` with the block each was queued in.
+    async fn deposit_logs_in(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> eyre::Result<Vec<(u64, Deposit)>> {
+        let filter = Filter::new().event_signature(DepositQueued::SIGNATURE_HASH);
+
+        self.logs_in(filter, from_block, to_block)
+            .await?
+            .iter()
+            .map(|log| {
+                let event = log.log_decode::<DepositQueued>()?.inner.data;
+                let deposit = Deposit {
+                    index: event.index,
+                    from: event.from,
+                    to: event.to,
+                    amount: event.amount,
+                };
+                Ok((log.block_number.unwrap_or_default(), deposit))
+            })
+            .collect()
+    }
+
+    // Forced transactions queued in blocks `from_block..=to_block` with the block each was
+    // queued in.
+    async fn forced_tx_logs_in(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> eyre::Result<Vec<(u64, (u64, SignedTransaction))>> {
+        let filter = Filter::new().event_signature(ForcedTransactionQueued::SIGNATURE_HASH);
+
+        self.logs_in(filter, from_block, to_block)
+            .await?
+            .iter()
+            .map(|log| {
+                let event = log.log_decode::<ForcedTransactionQueued>()?.inner.data;
+                let tx = SignedTransaction::decode(&mut event.transaction.as_ref())?;
+                Ok((log.block_number.unwrap_or_default(), (event.index, tx)))
+            })
+            .collect()
+    }
+
+    // The rollup contract's logs matching `filter`, a page of blocks at a time.
+    async fn logs_in(
+        &self,
+        filter: Filter,
+        from_block: u64,
+        to_block: u64,
+    ) -> eyre::Result<Vec<Log>> {
+        let mut logs = Vec::new();
+        let mut start = from_block;
+
+        while start <= to_block {
+            let end = to_block.min(start + self.config.page_size.max(1) - 1);
+            let filter = filter
+                .clone()
+                .address(self.config.rollup)
+                .from_block(start)
+                .to_block(end);
+
+            let filter = &filter;
+            logs.extend(
+                self.providers
+                    .request(|provider| async move { provider.get_logs(filter).await })
+                    .await?,
+            );
+            start = end + 1;
+        }
+
+        Ok(logs)
+    }
+
+    /// The batches a transaction posted, none if it is not a submission. With `cross_check`
+    /// the transaction is fetched from two providers, which have to agree on its calldata and
+    /// block.
+    pub async fn posted_batches(&self, tx_hash: B256) -> eyre::Result<Vec<PostedBatch>> {
+        let fetch = move |provider: HttpProvider| async move {
+            provider.get_transaction_by_hash(tx_hash).await
+        };
+        let tx = if self.config.cross_check {
+            let (tx, other) = self.providers.cross_checked(fetch).await?;
+            let posted =
+                |tx: &Option<Transaction>| tx.as_ref().map(|tx| (tx.input.clone(), tx.block_hash));
+            if other.is_some_and(|other| posted(&other) != posted(&tx)) {
+                return Err(eyre::eyre!("Providers disagree on transaction {}", tx_hash));
+            }
+            tx
+        } else {
+            self.providers.request(fetch).await?
+        }
+        .ok_or_else(|| eyre::eyre!("Transaction {} not found", tx_hash))?;
+
+        if tx.to != Some(self.config.rollup) {
+            return Ok(Vec::new());
+        }
+        let block_number = tx.block_number.unwrap_or_default();
+        let posted = |public_values, transaction_data| PostedBatch {
+            tx_hash,
+            block_number,
+            block_hash: tx.block_hash.unwrap_or_default(),
+            public_values,
+            transaction_data,
+            position: 0,
+            sequencer_signature: None,
+        };
+
+        if let Ok(call) = submitBatchWithProofCall::abi_decode(&tx.input, true) {
+            return Ok(vec![posted(
+                Some(call._publicValuesBytes),
+                call._transactionData,
+            )]);
+        }
+        if let Ok(call) = submitSignedBatchWithProofCall::abi_decode(&tx.input, true) {
+            return Ok(vec![PostedBatch {
+                sequencer_signature: Some(call._sequencerSignature),
+                ..posted(Some(call._publicValuesBytes), call._transactionData)
+            }]);
+        }
+        if let Ok(call) = submitBatchWithBlobsCall::abi_decode(&tx.input, true) {
+            let hashes = tx.blob_versioned_hashes.clone().unwrap_or_default();
+            let transaction_data = self.blob_data(block_number, &hashes).await?;
+            return Ok(vec![posted(
+                Some(call._publicValuesBytes),
+                transaction_data.into(),
+            )]);
+        }
+        if let Ok(call) = submitAggregatedBatchesCall::abi_decode(&tx.input, true) {
+            return Ok(call
+                ._transactionData
+                .into_iter()
+                .zip(0..)
+                .map(|(transaction_data, position)| PostedBatch {
+                    position,
+                    ..posted(None, transaction_data)
+                })
+                .collect());
+        }
+
+        Ok(Vec::new())
+    }
+
+    // The transaction data in the blobs with `hashes`, posted in block `block_number`.
+    async fn blob_data(&self, block_number: u64, hashes: &[B256]) -> eyre::Result<Vec<u8>> {
+        let fetcher = self.blobs.as_ref().ok_or_else(|| {
+            eyre::eyre!(
+                "Batch in block {} is posted in blobs, which need --beacon-url",
+                block_number
+            )
+        })?;
+        let block = self
+            .providers
+            .request(|provider| async move {
+                provider
+                    .get_block_by_number(BlockNumberOrTag::Number(block_number), false)
+                    .await
+            })
+            .await?
+            .ok_or_else(|| eyre::eyre!("Block {} not found", block_number))?;
+
+        decode_blobs(&fetcher.blobs(block.header.timestamp, hashes).await?)
+    }
+}
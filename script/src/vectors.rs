@@ -0,0 +1,556 @@
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use alloy::{
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use alloy_rlp::Encodable;
+use program::{
+    batch::BatchContext,
+    bridge::Inbox,
+    commitment::{leaf_hash, leaf_preimage, CommitmentVersion},
+    compress_transactions,
+    edit::EditOp,
+    public_values::PUBLIC_VALUES_VERSION,
+    receipt::Receipt,
+    signing::signing_hash,
+    smt::SparseMerkleTree,
+    snapshot::SNAPSHOT_VERSION,
+    Account, AccountDB, CanvasProcessor, Data, InMemoryDB, SignedTransaction, Transaction,
+    TX_VERSION_DOMAIN, TX_VERSION_LATEST, TX_VERSION_LEGACY, TX_VERSION_OPS,
+};
+use serde::Serialize;
+
+use crate::wallet::sign_transaction_sync;
+
+// Well-known development keys, so anyone can re-sign the vectors.
+const KEYS: [&str; 3] = [
+    "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+    "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a",
+];
+
+// Keys drawn from the seed, next to the well-known ones.
+const SEEDED_KEYS: usize = 3;
+
+/// What `gen-vectors` writes on top of the fixed batches.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Seed of the generated keys and batches.
+    pub seed: u64,
+    /// Batches generated from `seed` after the fixed ones.
+    pub random_batches: usize,
+}
+
+// What the vectors were generated with and against, for an implementation to check it targets
+// the same versions.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    seed: u64,
+    fixed_batches: usize,
+    random_batches: usize,
+    tx_version: u8,
+    commitment_version: u8,
+    public_values_version: u64,
+    snapshot_version: u16,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionVector {
+    batch: usize,
+    index: usize,
+    private_key: String,
+    from: Address,
+    tx: Transaction,
+    signing_hash: B256,
+    r: B256,
+    s: B256,
+    odd_y_parity: bool,
+    raw: Bytes,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchVector {
+    index: usize,
+    transactions: Vec<Bytes>,
+    transaction_data: Bytes,
+    transaction_commit: B256,
+    initial_state_root: B256,
+    final_state_root: B256,
+    receipts_root: B256,
+    accounts_root: B256,
+    limits_root: B256,
+    previous_batch_hash: B256,
+    batch_hash: B256,
+    receipts: Vec<Receipt>,
+    public_values_version: u64,
+    public_values: Bytes,
+    snapshot: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProofVector {
+    batch: usize,
+    address: Address,
+    account: Account,
+    leaf_preimage: Bytes,
+    leaf_hash: B256,
+    proof: Vec<B256>,
+    root: B256,
+}
+
+// Proofs in the sparse tree of `CommitmentVersion::V5`, of every account and of one address
+// without an account, whose `leafHash` is zero.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SparseProofVector {
+    batch: usize,
+    address: Address,
+    leaf_hash: B256,
+    bitmap: U256,
+    siblings: Vec<B256>,
+    root: B256,
+}
+
+fn legacy(to: Address, nonce: u64, data: Vec<Data>) -> Transaction {
+    Transaction {
+        to,
+        version: TX_VERSION_LEGACY,
+        data,
+        ops: Vec::new(),
+        nonce,
+        extra: String::new(),
+        valid_until_batch: 0,
+        valid_until_timestamp: 0,
+        canvas: 0,
+        access_list: Vec::new(),
+    }
+}
+
+fn ops(to: Address, nonce: u64, ops: Vec<EditOp>) -> Transaction {
+    versioned(TX_VERSION_OPS, to, nonce, ops)
+}
+
+fn versioned(version: u8, to: Address, nonce: u64, ops: Vec<EditOp>) -> Transaction {
+    Transaction {
+        to,
+        version,
+        data: Vec::new(),
+        ops,
+        nonce,
+        extra: String::new(),
+        valid_until_batch: 0,
+        valid_until_timestamp: 0,
+        canvas: 0,
+        access_list: Vec::new(),
+    }
+}
+
+fn insert(index: usize, value: &str) -> Data {
+    Data {
+        index,
+        count: 0,
+        value: value.to_string(),
+    }
+}
+
+// Two batches covering legacy, typed and domain-signed edits on shared and self-owned canvases.
+fn batches(signers: &[PrivateKeySigner]) -> Vec<Vec<(usize, Transaction)>> {
+    let canvas = Address::with_last_byte(0xca);
+    let own = signers[2].address();
+
+    vec![
+        vec![
+            (0, legacy(canvas, 0, vec![insert(0, "0123")])),
+            (1, legacy(canvas, 0, vec![insert(4, "4567")])),
+            (2, legacy(own, 0, vec![insert(0, "ff")])),
+        ],
+        vec![
+            (
+                0,
+                ops(
+                    canvas,
+                    1,
+                    vec![
+                        EditOp::Replace {
+                            index: 0,
+                            count: 2,
+                            value: "ab".to_string(),
+                        },
+                        EditOp::Append {
+                            value: "c".to_string(),
+                        },
+                    ],
+                ),
+            ),
+            (
+                1,
+                legacy(
+                    canvas,
+                    1,
+                    vec![Data {
+                        index: 2,
+                        count: 3,
+                        value: String::new(),
+                    }],
+                ),
+            ),
+            (
+                1,
+                versioned(
+                    TX_VERSION_DOMAIN,
+                    canvas,
+                    2,
+                    vec![EditOp::Append {
+                        value: "d".to_string(),
+                    }],
+                ),
+            ),
+            (
+                2,
+                ops(
+                    own,
+                    1,
+                    vec![
+                        EditOp::Delete { index: 0, count: 1 },
+                        EditOp::Insert {
+                            index: 1,
+                            value: "e".to_string(),
+                        },
+                    ],
+                ),
+            ),
+        ],
+    ]
+}
+
+// Draws the i-th word from keccak256(seed || i), so a seed gives the same vectors on every
+// machine and in every implementation that wants to re-derive them.
+struct Seeded {
+    seed: u64,
+    drawn: u64,
+}
+
+impl Seeded {
+    fn word(&mut self) -> B256 {
+        let word = keccak256([self.seed.to_be_bytes(), self.drawn.to_be_bytes()].concat());
+        self.drawn += 1;
+        word
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (U256::from_be_bytes(self.word().0) % U256::from(n)).to::<usize>()
+    }
+
+    // One to four palette entries.
+    fn value(&mut self) -> String {
+        (0..1 + self.below(4))
+            .map(|_| char::from_digit(self.below(16) as u32, 16).unwrap())
+            .collect()
+    }
+}
+
+// An edit of data `length` chars long that applies, leaving `length` as it will be after it.
+fn random_op(seeded: &mut Seeded, length: &mut usize) -> EditOp {
+    let kind = match *length {
+        0 => seeded.below(2),
+        _ => seeded.below(4),
+    };
+    let index = seeded.below(*length + 1);
+    // Up to three chars, none past the end.
+    let count = (*length - index).min(1 + seeded.below(3));
+    let op = match kind {
+        0 => EditOp::Append {
+            value: seeded.value(),
+        },
+        1 => EditOp::Insert {
+            index,
+            value: seeded.value(),
+        },
+        2 => EditOp::Delete { index, count },
+        _ => EditOp::Replace {
+            index,
+            count,
+            value: seeded.value(),
+        },
+    };
+    let removed = match op {
+        EditOp::Delete { count, .. } | EditOp::Replace { count, .. } => count,
+        _ => 0,
+    };
+    *length = *length - removed + op.value().map_or(0, |value| value.chars().count());
+    op
+}
+
+// A batch of two to five transactions of one to three edits each, from any of `signers` to the
+// shared canvas, their own account or another signer's, in every typed version, on the state
+// `db` holds after the batches before it.
+fn random_batch(
+    seeded: &mut Seeded,
+    signers: &[PrivateKeySigner],
+    db: &InMemoryDB,
+) -> eyre::Result<Vec<(usize, Transaction)>> {
+    let canvas = Address::with_last_byte(0xca);
+    let versions = [TX_VERSION_OPS, TX_VERSION_DOMAIN, TX_VERSION_LATEST];
+    let mut nonces: HashMap<Address, u64> = HashMap::new();
+    let mut lengths: HashMap<Address, usize> = HashMap::new();
+
+    let mut batch = Vec::new();
+    for _ in 0..2 + seeded.below(4) {
+        let signer_index = seeded.below(signers.len());
+        let from = signers[signer_index].address();
+        let to = match seeded.below(3) {
+            0 => canvas,
+            1 => from,
+            _ => signers[seeded.below(signers.len())].address(),
+        };
+
+        if !nonces.contains_key(&from) {
+            nonces.insert(from, db.get_account(&from)?.nonce);
+        }
+        if !lengths.contains_key(&to) {
+            lengths.insert(to, db.get_account(&to)?.data.chars().count());
+        }
+        let length = lengths.get_mut(&to).unwrap();
+        let ops = (0..1 + seeded.below(3))
+            .map(|_| random_op(seeded, length))
+            .collect();
+        let nonce = nonces.get_mut(&from).unwrap();
+        let version = versions[seeded.below(versions.len())];
+        batch.push((signer_index, versioned(version, to, *nonce, ops)));
+        *nonce += 1;
+    }
+    Ok(batch)
+}
+
+fn write_json(dir: &Path, name: &str, value: &impl Serialize) -> eyre::Result<()> {
+    fs::write(dir.join(name), serde_json::to_string_pretty(value)? + "\n")?;
+    Ok(())
+}
+
+pub fn generate(dir: &Path, options: Options) -> eyre::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut seeded = Seeded {
+        seed: options.seed,
+        drawn: 0,
+    };
+    let mut keys: Vec<String> = KEYS.iter().map(|key| key.to_string()).collect();
+    keys.extend((0..SEEDED_KEYS).map(|_| seeded.word().to_string()));
+    let signers = keys
+        .iter()
+        .map(|key| key.parse::<PrivateKeySigner>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let mut tx_vectors = Vec::new();
+    let mut batch_vectors = Vec::new();
+    let mut proof_vectors = Vec::new();
+    let mut sparse_vectors = Vec::new();
+    let mut context = BatchContext::default();
+
+    let fixed = batches(&signers);
+    for batch_index in 0..fixed.len() + options.random_batches {
+        let batch = match fixed.get(batch_index) {
+            Some(batch) => batch.clone(),
+            None => random_batch(&mut seeded, &signers, &db)?,
+        };
+        let mut transactions = Vec::new();
+
+        for (index, (signer_index, tx)) in batch.into_iter().enumerate() {
+            let signer = &signers[signer_index];
+            let signed = sign_transaction_sync(tx, signer, &processor.config.domain)?;
+
+            tx_vectors.push(TransactionVector {
+                batch: batch_index,
+                index,
+                private_key: keys[signer_index].clone(),
+                from: signer.address(),
+                tx: signed.tx.clone(),
+                signing_hash: signing_hash(&signed.tx, &processor.config.domain),
+                r: signed.r.into(),
+                s: signed.s.into(),
+                odd_y_parity: signed.odd_y_parity,
+                raw: encode_signed(&signed),
+            });
+            transactions.push(signed);
+        }
+
+        let report = processor.process_batch_after(&transactions, context, Inbox::default())?;
+        let public_values = report.public_values;
+        context = BatchContext {
+            index: public_values.batchIndex + 1,
+            previous_batch: public_values.transaction_commit,
+            previous_batch_hash: public_values.batchHash,
+            ..BatchContext::default()
+        };
+
+        let snapshot = format!("snapshot_{}.bin", batch_index);
+        fs::write(dir.join(&snapshot), db.snapshot_accounts()?)?;
+
+        batch_vectors.push(BatchVector {
+            index: batch_index,
+            transactions: transactions.iter().map(encode_signed).collect(),
+            transaction_data: compress_transactions(&transactions)?.into(),
+            transaction_commit: public_values.transaction_commit,
+            initial_state_root: public_values.initialStateRoot,
+            final_state_root: public_values.finalStateRoot,
+            receipts_root: public_values.receiptsRoot,
+            accounts_root: public_values.accountsRoot,
+            limits_root: public_values.limitsRoot,
+            previous_batch_hash: public_values.previousBatchHash,
+            batch_hash: public_values.batchHash,
+            receipts: report.receipts,
+            public_values_version: public_values.publicValuesVersion,
+            public_values: public_values.abi_encode().into(),
+            snapshot,
+        });
+
+        let mut accounts: Vec<(Address, Account)> = db
+            .accounts
+            .borrow()
+            .iter()
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        accounts.sort_by_key(|(address, _)| *address);
+
+        let mut tree = SparseMerkleTree::new();
+        for (address, account) in &accounts {
+            tree.insert(address, leaf_hash(CommitmentVersion::V5, address, account));
+        }
+        let root = B256::from(tree.root());
+        let absent = Address::with_last_byte(0xee);
+        for address in accounts.iter().map(|(address, _)| *address).chain([absent]) {
+            let proof = tree.proof(&address);
+            let leaf = accounts
+                .iter()
+                .find(|(a, _)| *a == address)
+                .map(|(a, account)| leaf_hash(CommitmentVersion::V5, a, account))
+                .unwrap_or_default();
+            sparse_vectors.push(SparseProofVector {
+                batch: batch_index,
+                address,
+                leaf_hash: leaf.into(),
+                bitmap: proof.bitmap,
+                siblings: proof.siblings.into_iter().map(B256::from).collect(),
+                root,
+            });
+        }
+
+        for (address, account) in accounts {
+            let preimage = leaf_preimage(processor.config.commitment, &address, &account);
+            proof_vectors.push(ProofVector {
+                batch: batch_index,
+                address,
+                leaf_hash: keccak256(&preimage),
+                leaf_preimage: preimage.into(),
+                proof: processor
+                    .generate_proof(&address)?
+                    .into_iter()
+                    .map(B256::from)
+                    .collect(),
+                root: public_values.finalStateRoot,
+                account,
+            });
+        }
+    }
+
+    write_json(
+        dir,
+        "manifest.json",
+        &Manifest {
+            seed: options.seed,
+            fixed_batches: fixed.len(),
+            random_batches: options.random_batches,
+            tx_version: TX_VERSION_LATEST,
+            commitment_version: processor.config.commitment as u8,
+            public_values_version: PUBLIC_VALUES_VERSION,
+            snapshot_version: SNAPSHOT_VERSION,
+        },
+    )?;
+    write_json(dir, "transactions.json", &tx_vectors)?;
+    write_json(dir, "batches.json", &batch_vectors)?;
+    write_json(dir, "proofs.json", &proof_vectors)?;
+    write_json(dir, "sparse_proofs.json", &sparse_vectors)?;
+    fs::write(
+        dir.join("PublicValuesVectors.sol"),
+        solidity_vectors(&batch_vectors),
+    )?;
+
+    println!(
+        "Wrote {} transactions, {} batches, {} proofs and {} sparse proofs to {}",
+        tx_vectors.len(),
+        batch_vectors.len(),
+        proof_vectors.len(),
+        sparse_vectors.len(),
+        dir.display()
+    );
+
+    Ok(())
+}
+
+// A Solidity library with the public values of every batch next to the values a verifier reads
+// out of them, for the contract's tests to decode and compare.
+fn solidity_vectors(batches: &[BatchVector]) -> String {
+    let mut out = String::new();
+    out.push_str("// SPDX-License-Identifier: MIT\n");
+    out.push_str("// Written by `gen-vectors`, do not edit.\n");
+    out.push_str("pragma solidity ^0.8.20;\n\n");
+    out.push_str("library PublicValuesVectors {\n");
+    let _ = writeln!(
+        out,
+        "    uint64 internal constant VERSION = {};\n",
+        PUBLIC_VALUES_VERSION
+    );
+    out.push_str("    struct Vector {\n");
+    out.push_str("        bytes publicValues;\n");
+    out.push_str("        bytes32 initialStateRoot;\n");
+    out.push_str("        bytes32 finalStateRoot;\n");
+    out.push_str("        bytes32 transactionCommit;\n");
+    out.push_str("        uint64 batchIndex;\n");
+    out.push_str("        bytes32 batchHash;\n");
+    out.push_str("    }\n\n");
+    out.push_str("    function vectors() internal pure returns (Vector[] memory out) {\n");
+    let _ = writeln!(out, "        out = new Vector[]({});", batches.len());
+    for batch in batches {
+        let _ = writeln!(out, "        out[{}] = Vector({{", batch.index);
+        let _ = writeln!(
+            out,
+            "            publicValues: hex\"{}\",",
+            hex::encode(&batch.public_values)
+        );
+        let _ = writeln!(
+            out,
+            "            initialStateRoot: {},",
+            batch.initial_state_root
+        );
+        let _ = writeln!(
+            out,
+            "            finalStateRoot: {},",
+            batch.final_state_root
+        );
+        let _ = writeln!(
+            out,
+            "            transactionCommit: {},",
+            batch.transaction_commit
+        );
+        let _ = writeln!(out, "            batchIndex: {},", batch.index);
+        let _ = writeln!(out, "            batchHash: {}", batch.batch_hash);
+        out.push_str("        });\n");
+    }
+    out.push_str("    }\n}\n");
+    out
+}
+
+fn encode_signed(tx: &SignedTransaction) -> Bytes {
+    let mut raw = Vec::<u8>::new();
+    tx.encode(&mut raw);
+    raw.into()
+}
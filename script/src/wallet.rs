@@ -0,0 +1,83 @@
+#[cfg(feature = "ledger")]
+use alloy::signers::ledger::{HDPath, LedgerSigner};
+use alloy::signers::{
+    local::{coins_bip39::English, MnemonicBuilder, PrivateKeySigner},
+    Signature, Signer, SignerSync,
+};
+use program::{
+    signing::{is_legacy_signed, payload_hash, signing_hash, typed_transaction, SigningDomain},
+    sponsor::{sponsorship, SponsorSignature},
+    SignedTransaction, Transaction,
+};
+
+/// Signs `tx` the way `recover_address_from_tx` checks it. Legacy versions are personal-signed
+/// over their payload hash and later ones signed as `CanvasTransaction` typed data, so a
+/// hardware wallet shows what it signs rather than a bare hash.
+pub async fn sign_transaction<S: Signer + Send + Sync>(
+    tx: Transaction,
+    signer: &S,
+    domain: &SigningDomain,
+) -> eyre::Result<SignedTransaction> {
+    let signature = if is_legacy_signed(&tx) {
+        signer.sign_message(payload_hash(&tx).as_slice()).await?
+    } else {
+        signer
+            .sign_typed_data(&typed_transaction(&tx), &domain.eip712_domain())
+            .await?
+    };
+
+    Ok(signed(tx, signature))
+}
+
+/// `sign_transaction` for signers holding their key, which sign the prehash directly.
+pub fn sign_transaction_sync(
+    tx: Transaction,
+    signer: &impl SignerSync,
+    domain: &SigningDomain,
+) -> eyre::Result<SignedTransaction> {
+    let signature = signer.sign_hash_sync(&signing_hash(&tx, domain))?;
+
+    Ok(signed(tx, signature))
+}
+
+/// Adds `signer` as the sponsor of `signed`, signing its `Sponsorship` as typed data.
+pub async fn sponsor_transaction<S: Signer + Send + Sync>(
+    mut signed: SignedTransaction,
+    signer: &S,
+    domain: &SigningDomain,
+) -> eyre::Result<SignedTransaction> {
+    let signature = signer
+        .sign_typed_data(&sponsorship(&signed.tx, domain), &domain.eip712_domain())
+        .await?;
+
+    signed.sponsor = Some(SponsorSignature {
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+    });
+    Ok(signed)
+}
+
+fn signed(tx: Transaction, signature: Signature) -> SignedTransaction {
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }
+}
+
+/// The key at `m/44'/60'/0'/0/{index}` of an English BIP-39 phrase.
+pub fn mnemonic_signer(phrase: &str, index: u32) -> eyre::Result<PrivateKeySigner> {
+    Ok(MnemonicBuilder::<English>::default()
+        .phrase(phrase)
+        .index(index)?
+        .build()?)
+}
+
+/// The Ledger Live account `index` of the first connected Ledger.
+#[cfg(feature = "ledger")]
+pub async fn ledger_signer(index: usize) -> eyre::Result<LedgerSigner> {
+    Ok(LedgerSigner::new(HDPath::LedgerLive(index), None).await?)
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::{config, signed};
+use program::{
+    access::AccessError, builder::TxBuilder, commitment::CommitmentVersion, error::CanvasError,
+    Account, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn ownership_needs_a_commitment_that_binds_it() {
+    let (owner, writer) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let a = Address::with_last_byte(0xa);
+    let db = InMemoryDB::default();
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V0));
+    assert!(matches!(
+        processor.apply_transaction(&signed(&owner, TxBuilder::new(a).claim())),
+        Err(CanvasError::Access(AccessError::OwnerNotCommitted))
+    ));
+    assert!(matches!(
+        processor.apply_transaction(&signed(&owner, TxBuilder::new(a).transfer(a))),
+        Err(CanvasError::Access(AccessError::OwnerNotCommitted))
+    ));
+
+    // Owners from V1, writers only from V3.
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V2));
+    processor
+        .apply_transaction(&signed(&owner, TxBuilder::new(a).claim()))
+        .unwrap();
+    assert!(matches!(
+        processor.apply_transaction(&signed(
+            &owner,
+            TxBuilder::new(a).nonce(1).grant(writer.address()),
+        )),
+        Err(CanvasError::Access(AccessError::WritersNotCommitted))
+    ));
+    assert!(db.get_account(&a).unwrap().writers.is_empty());
+}
+
+#[test]
+fn uncommitted_owners_do_not_lock_an_account() {
+    let (owner, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let a = Address::with_last_byte(0xa);
+    // An owner the V0 leaf does not bind, as a prover could supply in a witness.
+    let db = InMemoryDB::default();
+    db.set_account(
+        &a,
+        &Account {
+            owner: owner.address(),
+            writers: vec![owner.address()],
+            ..Account::default()
+        },
+    )
+    .unwrap();
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V0));
+    processor
+        .apply_transaction(&signed(&other, TxBuilder::new(a).append("ab")))
+        .unwrap();
+    assert_eq!(db.get_account(&a).unwrap().data, "ab");
+}
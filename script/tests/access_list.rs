@@ -0,0 +1,101 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rlp::{encode, Decodable};
+use common::signed;
+use program::{
+    batch::{encode_batch, BatchContext},
+    bridge::Inbox,
+    builder::TxBuilder,
+    config::{CanvasConfig, FailurePolicy},
+    error::CanvasError,
+    limits::RateLimitState,
+    witness::Witness,
+    CanvasProcessor, InMemoryDB, SignedTransaction, TX_VERSION_ACCESS_LIST, TX_VERSION_CANVAS,
+};
+
+fn declaring(to: Address, nonce: u64, accounts: Vec<Address>) -> TxBuilder {
+    TxBuilder::new(to)
+        .version(TX_VERSION_ACCESS_LIST)
+        .nonce(nonce)
+        .access_list(accounts)
+        .append("ab")
+}
+
+#[test]
+fn touching_an_undeclared_account_fails_the_transaction() {
+    let signer = PrivateKeySigner::random();
+    let sender = signer.address();
+    let target = Address::with_last_byte(0xb);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let declared = signed(&signer, declaring(target, 0, vec![sender, target]));
+    let decoded = SignedTransaction::decode(&mut encode(&declared).as_slice()).unwrap();
+    assert_eq!(decoded.tx.access_list, vec![sender, target]);
+    assert_eq!(decoded.hash(), declared.hash());
+    processor.apply_transaction(&declared).unwrap();
+
+    let undeclared = signed(&signer, declaring(target, 1, vec![sender]));
+    assert!(matches!(
+        processor.apply_transaction(&undeclared),
+        Err(CanvasError::Undeclared(address)) if address == target
+    ));
+    let accounts = db.accounts.borrow();
+    assert_eq!(accounts[&sender].nonce, 1);
+    assert_eq!(accounts[&target].data, "ab");
+
+    assert!(declaring(target, 0, vec![sender])
+        .version(TX_VERSION_CANVAS)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn witness_covers_the_declared_accounts() {
+    let signer = PrivateKeySigner::random();
+    let sender = signer.address();
+    let target = Address::with_last_byte(0xb);
+    let source = Address::with_last_byte(0xc);
+    let config = CanvasConfig {
+        failure_policy: FailurePolicy::Skip,
+        ..CanvasConfig::default()
+    };
+    let db = InMemoryDB::default();
+    CanvasProcessor::with_config(&db, config)
+        .process_batch(&[signed(&signer, TxBuilder::new(source).append("cd"))])
+        .unwrap();
+
+    let transactions = vec![
+        signed(
+            &signer,
+            declaring(target, 1, vec![sender, target, source]).copy(source, 0, 1, 0),
+        ),
+        // Fails before reading the account it leaves out.
+        signed(&signer, declaring(source, 2, vec![sender])),
+    ];
+    let witness = Witness::build(&db, &transactions, Inbox::default(), &config);
+    let mut covered: Vec<Address> = witness.accounts.keys().copied().collect();
+    covered.sort();
+    let mut declared = vec![sender, target, source];
+    declared.sort();
+    assert_eq!(covered, declared);
+
+    let expected = CanvasProcessor::with_config(&db, config)
+        .process_batch(&transactions)
+        .unwrap();
+    let report = witness
+        .process_batch(
+            &encode_batch(&transactions, config.codec, None).unwrap(),
+            config,
+            BatchContext::default(),
+            Inbox::default(),
+            RateLimitState::default(),
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        report.public_values.finalStateRoot,
+        expected.public_values.finalStateRoot
+    );
+}
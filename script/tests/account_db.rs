@@ -0,0 +1,95 @@
+mod common;
+
+use std::cell::Cell;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::signed;
+use program::{
+    builder::TxBuilder, error::CanvasError, Account, AccountDB, CanvasProcessor, InMemoryDB,
+    WriteBatch,
+};
+
+// Counts the calls a remote backend would make a round trip for.
+#[derive(Default)]
+struct CountingDB {
+    db: InMemoryDB,
+    single: Cell<usize>,
+    bulk: Cell<usize>,
+}
+
+impl AccountDB for CountingDB {
+    fn get_account(&self, address: &Address) -> Result<Account, CanvasError> {
+        self.single.set(self.single.get() + 1);
+        self.db.get_account(address)
+    }
+
+    fn find_account(&self, address: &Address) -> Result<Option<Account>, CanvasError> {
+        self.single.set(self.single.get() + 1);
+        self.db.find_account(address)
+    }
+
+    fn set_account(&self, address: &Address, account: &Account) -> Result<(), CanvasError> {
+        self.single.set(self.single.get() + 1);
+        self.db.set_account(address, account)
+    }
+
+    fn remove_account(&self, address: &Address) -> Result<(), CanvasError> {
+        self.single.set(self.single.get() + 1);
+        self.db.remove_account(address)
+    }
+
+    fn find_accounts(&self, addresses: &[Address]) -> Result<Vec<Option<Account>>, CanvasError> {
+        self.bulk.set(self.bulk.get() + 1);
+        self.db.find_accounts(addresses)
+    }
+
+    fn apply_writes(&self, batch: WriteBatch) -> Result<(), CanvasError> {
+        self.bulk.set(self.bulk.get() + 1);
+        self.db.apply_writes(batch)
+    }
+}
+
+#[test]
+fn transactions_read_and_write_in_bulk() {
+    let signer = PrivateKeySigner::random();
+    let (a, b) = (Address::with_last_byte(0xa), Address::with_last_byte(0xb));
+    let db = CountingDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    processor
+        .apply_transaction(&signed(&signer, TxBuilder::new(a).nonce(0).append("hello")))
+        .unwrap();
+    processor
+        .apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(b).nonce(1).move_range(a, 0, 5, 0),
+        ))
+        .unwrap();
+
+    assert_eq!(db.single.get(), 0);
+    // A read of what the transaction touches, one of what its writes replace, and the writes.
+    assert_eq!(db.bulk.get(), 6);
+    assert_eq!(db.db.get_account(&a).unwrap().data, "");
+    assert_eq!(db.db.get_account(&b).unwrap().data, "hello");
+}
+
+#[test]
+fn touched_accounts_reset_with_the_root() {
+    let signer = PrivateKeySigner::random();
+    let a = Address::with_last_byte(0xa);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    assert!(processor.touched_accounts().is_empty());
+
+    processor
+        .apply_transaction(&signed(&signer, TxBuilder::new(a).append("hello")))
+        .unwrap();
+    let touched = processor.touched_accounts();
+    assert_eq!(touched.len(), 2);
+    assert!(touched.contains(&a) && touched.contains(&signer.address()));
+    // Reading them does not clear them.
+    assert_eq!(processor.touched_accounts(), touched);
+
+    processor.generate_state_root().unwrap();
+    assert!(processor.touched_accounts().is_empty());
+}
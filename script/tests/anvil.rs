@@ -0,0 +1,247 @@
+//! End to end through L1: batches are posted to a mock rollup on a local anvil node as
+//! `submitBatchWithProof` calls, and the `sync` binary has to derive from them the state root the
+//! host computed. Skipped when `anvil` is not on the path.
+
+mod common;
+
+use alloy::{
+    hex,
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use common::{
+    anvil::{state_root, Anvil},
+    signed,
+};
+use program::{
+    batch::{encode_batch, BatchContext},
+    bridge::Inbox,
+    builder::TxBuilder,
+    codec::Codec,
+    config::{CanvasConfig, FailurePolicy},
+    CanvasProcessor, InMemoryDB, PublicValuesStruct, SignedTransaction,
+};
+
+// Runs `batches` through `processor` in order, returning the public values and transaction data
+// of each as the prover would post them.
+fn prove(
+    processor: &mut CanvasProcessor<&InMemoryDB>,
+    batches: &[Vec<SignedTransaction>],
+) -> Vec<(PublicValuesStruct, Vec<u8>)> {
+    let mut context = BatchContext::default();
+    batches
+        .iter()
+        .map(|transactions| {
+            let public_values = processor
+                .process_batch_after(transactions, context, Inbox::default())
+                .unwrap()
+                .public_values;
+            context = BatchContext {
+                index: public_values.batchIndex + 1,
+                previous_batch: public_values.transaction_commit,
+                previous_batch_hash: public_values.batchHash,
+                ..BatchContext::default()
+            };
+            let transaction_data = encode_batch(transactions, Codec::Zlib, None).unwrap();
+            (public_values, transaction_data)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn sync_derives_the_host_state_root() {
+    let Some(anvil) = Anvil::start().await else {
+        return;
+    };
+
+    // Two chained batches, as the prover would post them.
+    let signer = PrivateKeySigner::random();
+    let batches = [
+        vec![
+            signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(1)).append("ab"),
+            ),
+            signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(2))
+                    .nonce(1)
+                    .append("cd"),
+            ),
+        ],
+        vec![signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(2)
+                .append("ef"),
+        )],
+    ];
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    for (public_values, transaction_data) in prove(&mut processor, &batches) {
+        anvil.post(&public_values, transaction_data).await;
+    }
+    let expected = format!(
+        "0x{}",
+        hex::encode(processor.generate_state_root().unwrap())
+    );
+    let primary = anvil.sync("primary", &["--rpc-url", &anvil.rpc_url]);
+    assert_eq!(state_root(&primary), expected);
+
+    // A provider that is down is failed over, and two providers serving the same chain agree.
+    let down = format!("http://127.0.0.1:{}", common::anvil::free_port());
+    let providers = [
+        "--rpc-url",
+        down.as_str(),
+        "--fallback-rpc-url",
+        anvil.rpc_url.as_str(),
+        "--fallback-rpc-url",
+        anvil.rpc_url.as_str(),
+        "--cross-check-providers",
+    ];
+    assert_eq!(state_root(&anvil.sync("fallback", &providers)), expected);
+}
+
+#[tokio::test]
+async fn batches_with_the_same_data_are_each_applied() {
+    let Some(anvil) = Anvil::start().await else {
+        return;
+    };
+
+    // Empty batches post the same transaction data, yet each is a batch of its own.
+    let signer = PrivateKeySigner::random();
+    let batches = [
+        vec![],
+        vec![],
+        vec![signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1)).append("ab"),
+        )],
+    ];
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    for (public_values, transaction_data) in prove(&mut processor, &batches) {
+        anvil.post(&public_values, transaction_data).await;
+    }
+    let expected = format!(
+        "0x{}",
+        hex::encode(processor.generate_state_root().unwrap())
+    );
+
+    let events = anvil.dir("same_data").join("events");
+    let output = anvil.sync(
+        "same_data",
+        &[
+            "--rpc-url",
+            &anvil.rpc_url,
+            "--hook-dir",
+            events.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(state_root(&output), expected);
+    for index in 0..batches.len() {
+        assert!(events.join(format!("batch_{}.json", index)).exists());
+    }
+}
+
+#[tokio::test]
+async fn failed_transactions_are_skipped_as_proven() {
+    let Some(anvil) = Anvil::start().await else {
+        return;
+    };
+
+    // The second transaction reuses a nonce, so under `FailurePolicy::Skip` it only gets a
+    // failed receipt.
+    let signer = PrivateKeySigner::random();
+    let batches = [vec![
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1)).append("ab"),
+        ),
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1)).append("cd"),
+        ),
+    ]];
+    let db = InMemoryDB::default();
+    let config = CanvasConfig {
+        failure_policy: FailurePolicy::Skip,
+        ..CanvasConfig::default()
+    };
+    let mut processor = CanvasProcessor::with_config(&db, config);
+    for (public_values, transaction_data) in prove(&mut processor, &batches) {
+        anvil.post(&public_values, transaction_data).await;
+    }
+    let expected = format!(
+        "0x{}",
+        hex::encode(processor.generate_state_root().unwrap())
+    );
+
+    let events = anvil.dir("skip").join("events");
+    let args = [
+        "--rpc-url",
+        &anvil.rpc_url,
+        "--hook-dir",
+        events.to_str().unwrap(),
+    ];
+    assert!(!anvil.sync("abort", &args).status.success());
+    let output = anvil.sync(
+        "skip",
+        &[&args[..], &["--skip-failed-transactions"][..]].concat(),
+    );
+    assert_eq!(state_root(&output), expected);
+    let event: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(events.join("batch_0.json")).unwrap()).unwrap();
+    assert_eq!(event["receipts"][0]["status"], "Success");
+    assert_eq!(event["receipts"][1]["status"], "Failed");
+}
+
+#[tokio::test]
+async fn sync_stops_where_the_state_diverges() {
+    let Some(anvil) = Anvil::start().await else {
+        return;
+    };
+
+    let signer = PrivateKeySigner::random();
+    let batches = [
+        vec![signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1)).append("ab"),
+        )],
+        vec![signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(2))
+                .nonce(1)
+                .append("cd"),
+        )],
+    ];
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    let mut posted = prove(&mut processor, &batches);
+    // A root the host does not derive, as if it had diverged from the program.
+    posted[1].0.finalStateRoot = B256::with_last_byte(1);
+    for (public_values, transaction_data) in posted {
+        anvil.post(&public_values, transaction_data).await;
+    }
+
+    let events = anvil.dir("diverged").join("events");
+    let output = anvil.sync(
+        "diverged",
+        &[
+            "--rpc-url",
+            &anvil.rpc_url,
+            "--hook-dir",
+            events.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    let report = String::from_utf8_lossy(&output.stderr);
+    assert!(report.contains("after batch 1"), "{}", report);
+    assert!(report.contains("2 accounts changed"), "{}", report);
+    for account in [Address::with_last_byte(2), signer.address()] {
+        assert!(report.contains(&account.to_string()), "{}", report);
+    }
+    // The diverging batch is not recorded as applied.
+    assert!(events.join("batch_0.json").exists());
+    assert!(!events.join("batch_1.json").exists());
+}
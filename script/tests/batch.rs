@@ -0,0 +1,226 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner, sol_types::SolValue};
+use alloy_rlp::encode;
+use common::signed;
+use program::{
+    batch::{batch_hash, commit, decode_batch, encode_batch, BatchContext},
+    builder::TxBuilder,
+    codec::Codec,
+    config::CanvasConfig,
+    public_values::decode_public_values,
+    CanvasProcessor, InMemoryDB, Input,
+};
+
+#[test]
+fn every_codec_round_trips() {
+    let signer = PrivateKeySigner::random();
+    let transactions = vec![
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(0)
+                .append("ab"),
+        ),
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(1)
+                .append("cd"),
+        ),
+    ];
+
+    for codec in [Codec::Zlib, Codec::None, Codec::Zstd, Codec::Brotli] {
+        let data = encode_batch(&transactions, codec, None).unwrap();
+        let decoded = decode_batch(&data, None).unwrap();
+        assert_eq!(encode(&decoded), encode(&transactions), "{:?}", codec);
+    }
+}
+
+#[test]
+fn commit_matches_the_program() {
+    let signer = PrivateKeySigner::random();
+    let transactions = vec![signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(0xb))
+            .nonce(0)
+            .append("ab"),
+    )];
+
+    for codec in [Codec::Zlib, Codec::Brotli] {
+        let db = InMemoryDB::default();
+        let config = CanvasConfig {
+            codec,
+            ..CanvasConfig::default()
+        };
+        let mut processor = CanvasProcessor::with_config(&db, config);
+        let report = processor.process_batch(&transactions).unwrap();
+
+        let data = encode_batch(&transactions, codec, None).unwrap();
+        assert_eq!(commit(&data), report.public_values.transaction_commit);
+    }
+}
+
+#[test]
+fn program_commits_to_the_posted_bytes() {
+    let signer = PrivateKeySigner::random();
+    let transactions = vec![
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(0)
+                .append("ab"),
+        ),
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(1)
+                .append("cd"),
+        ),
+    ];
+    // Posted uncompressed although the config asks for zlib, as another encoder might.
+    let posted = encode_batch(&transactions, Codec::None, None).unwrap();
+
+    let report = Input {
+        transaction_data: posted.clone(),
+        db: InMemoryDB::default(),
+        config: CanvasConfig::default(),
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: Default::default(),
+        witness: None,
+        dictionary: None,
+    }
+    .process()
+    .unwrap();
+
+    assert_eq!(report.public_values.transaction_commit, commit(&posted));
+    assert_eq!(report.receipts.len(), 2);
+}
+
+fn input(transaction_data: Vec<u8>, db: InMemoryDB, batch: BatchContext) -> Input {
+    Input {
+        transaction_data,
+        db,
+        config: CanvasConfig::default(),
+        batch,
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: Default::default(),
+        witness: None,
+        dictionary: None,
+    }
+}
+
+#[test]
+fn proofs_chain_through_the_batch_hash() {
+    let signer = PrivateKeySigner::random();
+    let first = encode_batch(
+        &[signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(0)
+                .append("ab"),
+        )],
+        Codec::Zlib,
+        None,
+    )
+    .unwrap();
+    let second = encode_batch(
+        &[signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(0)
+                .append("cd"),
+        )],
+        Codec::Zlib,
+        None,
+    )
+    .unwrap();
+
+    let report = input(first, InMemoryDB::default(), BatchContext::default())
+        .process()
+        .unwrap();
+    let values = report.public_values;
+    assert_eq!(values.previousBatchHash, Default::default());
+    assert_eq!(
+        values.batchHash,
+        batch_hash(
+            values.previousBatchHash,
+            0,
+            values.transactionCommit,
+            values.finalStateRoot
+        )
+    );
+
+    let chained = BatchContext {
+        index: 1,
+        previous_batch: values.transactionCommit,
+        previous_batch_hash: values.batchHash,
+        ..BatchContext::default()
+    };
+    let next = input(second.clone(), InMemoryDB::default(), chained)
+        .process()
+        .unwrap()
+        .public_values;
+    assert_eq!(next.previousBatchHash, values.batchHash);
+    assert_ne!(next.batchHash, values.batchHash);
+
+    let claimed = decode_public_values(&next.abi_encode()).unwrap();
+    assert_eq!(claimed.previous_batch_hash, Some(values.batchHash));
+    assert_eq!(claimed.batch_hash, Some(next.batchHash));
+
+    // A first batch cannot claim to follow one.
+    let out_of_order = BatchContext {
+        previous_batch_hash: values.batchHash,
+        ..BatchContext::default()
+    };
+    assert!(input(second, InMemoryDB::default(), out_of_order)
+        .process()
+        .is_err());
+}
+
+#[test]
+fn batch_time_never_runs_backwards() {
+    let signer = PrivateKeySigner::random();
+    let posted = encode_batch(
+        &[signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(0xb))
+                .nonce(0)
+                .append("ab"),
+        )],
+        Codec::Zlib,
+        None,
+    )
+    .unwrap();
+    let sequencer = Address::with_last_byte(0x5e);
+    let batch = BatchContext {
+        index: 1,
+        previous_timestamp: 1_700_000_000,
+        ..BatchContext::default()
+    }
+    .sequenced_by(sequencer);
+
+    let values = input(
+        posted.clone(),
+        InMemoryDB::default(),
+        batch.timestamped(1_700_000_000),
+    )
+    .process()
+    .unwrap()
+    .public_values;
+    let claimed = decode_public_values(&values.abi_encode()).unwrap();
+    assert_eq!(claimed.batch_timestamp, Some(1_700_000_000));
+    assert_eq!(claimed.previous_batch_timestamp, Some(1_700_000_000));
+    assert_eq!(claimed.sequencer, Some(sequencer));
+
+    assert!(input(
+        posted,
+        InMemoryDB::default(),
+        batch.timestamped(1_699_999_999)
+    )
+    .process()
+    .is_err());
+}
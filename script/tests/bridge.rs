@@ -0,0 +1,53 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, U256},
+    signers::local::PrivateKeySigner,
+};
+use common::{config, signed};
+use program::{
+    bridge::{withdrawal_address, BridgeError},
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    error::CanvasError,
+    Account, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn withdrawals_need_committed_balances() {
+    let signer = PrivateKeySigner::random();
+    let recipient = Address::with_last_byte(0x1);
+    let record = withdrawal_address(&signer.address(), 0);
+    let withdraw = signed(
+        &signer,
+        TxBuilder::new(record).withdraw(recipient, U256::from(60)),
+    );
+    let db = InMemoryDB::default();
+    db.set_account(
+        &signer.address(),
+        &Account {
+            balance: U256::from(100),
+            ..Account::default()
+        },
+    )
+    .unwrap();
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V0));
+    assert!(matches!(
+        processor.apply_transaction(&withdraw),
+        Err(CanvasError::Bridge(BridgeError::NotCommitted))
+    ));
+    assert_eq!(
+        db.get_account(&signer.address()).unwrap().balance,
+        U256::from(100)
+    );
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V1));
+    processor.apply_transaction(&withdraw).unwrap();
+    assert_eq!(
+        db.get_account(&signer.address()).unwrap().balance,
+        U256::from(40)
+    );
+    let record = db.get_account(&record).unwrap();
+    assert_eq!((record.balance, record.owner), (U256::from(60), recipient));
+}
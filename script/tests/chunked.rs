@@ -0,0 +1,87 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner, sol_types::SolValue};
+use common::signed;
+use program::{
+    batch::encode_batch,
+    builder::TxBuilder,
+    chunked::{self, Encoding},
+    codec::Codec,
+    config::CanvasConfig,
+    Account, InMemoryDB, Input, SignedTransaction, MAX_SIZE,
+};
+
+// Full canvases, enough of them to span several frames, and a batch editing one.
+fn input() -> Input {
+    let db = InMemoryDB::default();
+    for i in 0..64u8 {
+        db.accounts.borrow_mut().insert(
+            Address::with_last_byte(i),
+            Account {
+                data: "f".repeat(MAX_SIZE),
+                ..Account::default()
+            },
+        );
+    }
+
+    let signer = PrivateKeySigner::random();
+    let transactions: Vec<SignedTransaction> = (0..3)
+        .map(|nonce| {
+            signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(7))
+                    .nonce(nonce)
+                    .replace(0, 2, "a1"),
+            )
+        })
+        .collect();
+
+    Input {
+        transaction_data: encode_batch(&transactions, Codec::default(), None).unwrap(),
+        db,
+        config: CanvasConfig::default(),
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: Default::default(),
+        witness: None,
+        dictionary: None,
+    }
+}
+
+#[test]
+fn round_trips_across_frames() {
+    let input = input();
+    let mut encoded = Vec::new();
+    chunked::write_input(&input, Encoding::Bincode, &mut encoded).unwrap();
+    assert!(encoded.len() > 2 * chunked::FRAME_BYTES);
+
+    let read = chunked::read_input(encoded.as_slice()).unwrap();
+    assert_eq!(read.db.accounts.borrow().len(), 64);
+    assert_eq!(read.transactions().unwrap().len(), 3);
+    assert_eq!(
+        read.process().unwrap().public_values.abi_encode(),
+        input.process().unwrap().public_values.abi_encode()
+    );
+}
+
+#[test]
+fn rejects_truncated_input() {
+    let mut encoded = Vec::new();
+    chunked::write_input(&input(), Encoding::Bincode, &mut encoded).unwrap();
+    encoded.truncate(encoded.len() - 1);
+
+    assert!(chunked::read_input(encoded.as_slice()).is_err());
+}
+
+#[test]
+fn borsh_round_trips_across_frames() {
+    let input = input();
+    let mut encoded = Vec::new();
+    chunked::write_input(&input, Encoding::Borsh, &mut encoded).unwrap();
+    assert!(encoded.len() > 2 * chunked::FRAME_BYTES);
+
+    let read = chunked::read_input(encoded.as_slice()).unwrap();
+    assert_eq!(*read.db.accounts.borrow(), *input.db.accounts.borrow());
+    assert_eq!(read.transactions().unwrap().len(), 3);
+}
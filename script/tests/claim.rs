@@ -0,0 +1,70 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use common::{config, signed};
+use program::{
+    builder::TxBuilder,
+    claim::{verify_claim, AccountClaim},
+    commitment::CommitmentVersion,
+    CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn claims_verify_against_the_final_state_root() {
+    for commitment in [
+        CommitmentVersion::V0,
+        CommitmentVersion::V3,
+        CommitmentVersion::V5,
+        CommitmentVersion::V8,
+        CommitmentVersion::V10,
+    ] {
+        let signer = PrivateKeySigner::random();
+        let db = InMemoryDB::default();
+        let config = config(commitment);
+        let mut processor = CanvasProcessor::with_config(&db, config);
+        let report = processor
+            .process_batch(&[
+                signed(
+                    &signer,
+                    TxBuilder::new(Address::with_last_byte(1))
+                        .nonce(0)
+                        .append("ab"),
+                ),
+                signed(
+                    &signer,
+                    TxBuilder::new(Address::with_last_byte(2))
+                        .nonce(1)
+                        .append("cd"),
+                ),
+            ])
+            .unwrap();
+        let root = report.public_values.finalStateRoot;
+
+        let claim = processor
+            .generate_claim(&Address::with_last_byte(2))
+            .unwrap();
+        let decoded = AccountClaim::abi_decode(&claim.abi_encode(), true).unwrap();
+        assert_eq!(decoded.batchIndex, 0);
+        assert!(verify_claim(&decoded, &root));
+        assert!(!verify_claim(&decoded, &B256::repeat_byte(1)));
+
+        let mut other = decoded.clone();
+        other.account = Address::with_last_byte(1);
+        assert!(!verify_claim(&other, &root));
+
+        let mut forged = decoded;
+        let last = forged.leafPreimage.len() - 1;
+        let mut preimage = forged.leafPreimage.to_vec();
+        preimage[last] ^= 1;
+        forged.leafPreimage = preimage.into();
+        assert!(!verify_claim(&forged, &root));
+
+        assert!(processor
+            .generate_claim(&Address::with_last_byte(3))
+            .is_err());
+    }
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::{config, signed};
+use program::{
+    builder::TxBuilder, commitment::CommitmentVersion, error::CanvasError, Account, AccountDB,
+    CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn clearing_a_released_account_removes_its_leaf() {
+    let signer = PrivateKeySigner::random();
+    let a = Address::with_last_byte(0xa);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V3));
+
+    let transactions = vec![
+        signed(&signer, TxBuilder::new(a).nonce(0).claim().append("text")),
+        signed(
+            &signer,
+            TxBuilder::new(a).nonce(1).clear().transfer(Address::ZERO),
+        ),
+    ];
+    let report = processor.process_batch(&transactions).unwrap();
+    assert_eq!(report.receipts[1].deleted, 4);
+    assert_eq!(db.find_account(&a).unwrap(), None);
+
+    // The same state as if `a` had never been written.
+    let expected = InMemoryDB::default();
+    expected
+        .set_account(
+            &signer.address(),
+            &Account {
+                nonce: 2,
+                ..Account::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        processor.generate_state_root().unwrap(),
+        CanvasProcessor::with_config(&expected, config(CommitmentVersion::V3))
+            .generate_state_root()
+            .unwrap()
+    );
+}
+
+#[test]
+fn clearing_takes_the_owner_and_keeps_what_is_left() {
+    let (owner, writer) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let a = Address::with_last_byte(0xa);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V3));
+    processor
+        .apply_transaction(&signed(
+            &owner,
+            TxBuilder::new(a)
+                .nonce(0)
+                .claim()
+                .grant(writer.address())
+                .append("text"),
+        ))
+        .unwrap();
+
+    match processor.apply_transaction(&signed(&writer, TxBuilder::new(a).nonce(0).clear())) {
+        Err(CanvasError::Access(_)) => {}
+        result => panic!("expected an access error, got {:?}", result),
+    }
+
+    processor
+        .apply_transaction(&signed(&owner, TxBuilder::new(a).nonce(1).clear()))
+        .unwrap();
+    let account = db.find_account(&a).unwrap().unwrap();
+    assert_eq!(account.data, "");
+    assert_eq!(account.owner, owner.address());
+    assert!(account.writers.is_empty());
+    assert_eq!(account.contributors, vec![owner.address()]);
+}
@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    net::TcpListener,
+    path::PathBuf,
+    process::{Child, Command, Output, Stdio},
+    time::Duration,
+};
+
+use alloy::{
+    hex,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, Bytes},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::{SolCall, SolValue},
+    transports::http::{Client, Http},
+};
+use program::PublicValuesStruct;
+
+sol!(
+    function submitBatchWithProof(
+        bytes calldata _publicValuesBytes,
+        bytes calldata _proofBytes,
+        bytes calldata _transactionData
+    ) public;
+);
+
+// Anvil's first dev account.
+const DEPLOYER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+// A contract that accepts any call and emits an empty `LOG0`, which is all `sync` needs to find
+// the calls: runtime `PUSH1 0 PUSH1 0 LOG0 STOP` behind the code that returns it.
+const MOCK_ROLLUP: &str = "6006600c60003960066000f360006000a000";
+
+/// A local anvil node with a mock rollup deployed, killed when dropped however the test ends.
+pub struct Anvil {
+    node: Child,
+    pub rpc_url: String,
+    pub rollup: Address,
+}
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.node.kill();
+        let _ = self.node.wait();
+    }
+}
+
+impl Anvil {
+    /// Starts a node and deploys the rollup, `None` when `anvil` is not on the path.
+    pub async fn start() -> Option<Self> {
+        if Command::new("anvil").arg("--version").output().is_err() {
+            eprintln!("anvil not found, skipping");
+            return None;
+        }
+        let port = free_port();
+        let node = Command::new("anvil")
+            .args(["--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut anvil = Anvil {
+            node,
+            rpc_url: format!("http://127.0.0.1:{}", port),
+            rollup: Address::ZERO,
+        };
+
+        let provider = anvil.provider();
+        let mut started = false;
+        for _ in 0..50 {
+            if provider.get_block_number().await.is_ok() {
+                started = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(started, "anvil did not start on port {}", port);
+
+        let deploy =
+            TransactionRequest::default().with_deploy_code(hex::decode(MOCK_ROLLUP).unwrap());
+        anvil.rollup = provider
+            .send_transaction(deploy)
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap()
+            .contract_address
+            .unwrap();
+        Some(anvil)
+    }
+
+    fn provider(&self) -> impl Provider<Http<Client>> {
+        let deployer: PrivateKeySigner = DEPLOYER_KEY.parse().unwrap();
+        ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(deployer))
+            .on_http(self.rpc_url.parse().unwrap())
+    }
+
+    /// Posts a batch to the rollup as `submitBatchWithProof`, with an empty proof.
+    pub async fn post(&self, public_values: &PublicValuesStruct, transaction_data: Vec<u8>) {
+        let call = submitBatchWithProofCall {
+            _publicValuesBytes: public_values.abi_encode().into(),
+            _proofBytes: Bytes::new(),
+            _transactionData: transaction_data.into(),
+        };
+        let submit = TransactionRequest::default()
+            .with_to(self.rollup)
+            .with_input(call.abi_encode());
+        let receipt = self
+            .provider()
+            .send_transaction(submit)
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        assert!(receipt.status());
+    }
+
+    /// Runs `sync` of the rollup from this node with `args`, in `dir(name)` emptied first.
+    pub fn sync(&self, name: &str, args: &[&str]) -> Output {
+        let dir = self.dir(name);
+        let _ = fs::remove_dir_all(&dir);
+        Command::new(env!("CARGO_BIN_EXE_script"))
+            .arg("sync")
+            .args(args)
+            .args(["--rollup", &self.rollup.to_string()])
+            .args(["--from-block", "0"])
+            .arg("--data-dir")
+            .arg(dir.join("data"))
+            .output()
+            .unwrap()
+    }
+
+    /// Where `sync(name, ..)` keeps its data, and where a test can have it write more.
+    pub fn dir(&self, name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("anvil_{}_{}", name, self.rollup))
+    }
+}
+
+/// The state root a successful `sync` printed.
+pub fn state_root(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "sync failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Final state root: "))
+        .expect("sync printed no state root")
+        .to_string()
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
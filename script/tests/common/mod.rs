@@ -0,0 +1,38 @@
+// Fixtures shared by the integration tests; each test crate uses only some of them.
+#![allow(dead_code)]
+
+pub mod anvil;
+
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use program::{
+    builder::TxBuilder, commitment::CommitmentVersion, config::CanvasConfig, signing::signing_hash,
+    SignedTransaction, Transaction,
+};
+
+/// The default config under `commitment`.
+pub fn config(commitment: CommitmentVersion) -> CanvasConfig {
+    CanvasConfig {
+        commitment,
+        ..CanvasConfig::default()
+    }
+}
+
+/// `tx` signed by `signer` under the default signing domain, without a sponsor.
+pub fn sign(signer: &PrivateKeySigner, tx: Transaction) -> SignedTransaction {
+    let signature = signer
+        .sign_hash_sync(&signing_hash(&tx, &CanvasConfig::default().domain))
+        .unwrap();
+
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }
+}
+
+/// The transaction `builder` builds, signed by `signer`.
+pub fn signed(signer: &PrivateKeySigner, builder: TxBuilder) -> SignedTransaction {
+    sign(signer, builder.build().unwrap())
+}
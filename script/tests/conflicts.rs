@@ -0,0 +1,90 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    config::FailurePolicy,
+    conflicts::{waves, Access},
+    receipt::Receipt,
+    CanvasProcessor, InMemoryDB, SignedTransaction,
+};
+
+fn signer(index: u8) -> PrivateKeySigner {
+    PrivateKeySigner::from_bytes(&B256::with_last_byte(index + 1)).unwrap()
+}
+
+fn access(sender: u8, reads: &[u8]) -> Option<Access> {
+    Some(Access {
+        sender: Address::with_last_byte(sender),
+        sponsor: None,
+        reads: reads.iter().map(|&a| Address::with_last_byte(a)).collect(),
+    })
+}
+
+#[test]
+fn waves_split_where_a_transaction_touches_an_earlier_ones_account() {
+    let accesses = [
+        access(1, &[1, 10]),
+        access(2, &[2, 20]),
+        // Reads what the first writes.
+        access(3, &[3, 10]),
+        access(4, &[4, 40]),
+        None,
+        access(5, &[5, 50]),
+    ];
+    assert_eq!(waves(&accesses), vec![0..2, 2..4, 4..5, 5..6]);
+    assert!(waves(&[]).is_empty());
+}
+
+// Disjoint appends, a sender writing twice, copies and moves between the signers' accounts and
+// a transaction that fails, which may only change what the batch does if it runs serially.
+fn batch() -> Vec<SignedTransaction> {
+    let own = |i: u8| signer(i).address();
+    vec![
+        signed(&signer(0), TxBuilder::new(own(0)).append("a1")),
+        signed(&signer(1), TxBuilder::new(own(1)).append("b2")),
+        signed(&signer(2), TxBuilder::new(own(2)).append("c3")),
+        signed(&signer(0), TxBuilder::new(own(0)).nonce(1).append("d4")),
+        signed(&signer(3), TxBuilder::new(own(3)).copy(own(1), 0, 2, 0)),
+        signed(&signer(4), TxBuilder::new(own(4)).nonce(7).append("e5")),
+        signed(&signer(1), TxBuilder::new(own(1)).nonce(1).append("f6")),
+        signed(
+            &signer(2),
+            TxBuilder::new(own(2)).nonce(1).move_range(own(2), 0, 1, 1),
+        ),
+        signed(&signer(5), TxBuilder::new(own(0)).append("07")),
+    ]
+}
+
+#[test]
+fn parallel_execution_matches_applying_one_by_one() {
+    let transactions = batch();
+
+    let serial_db = InMemoryDB::default();
+    let mut serial = CanvasProcessor::new(&serial_db);
+    let expected: Vec<Receipt> = transactions
+        .iter()
+        .map(|tx| {
+            serial
+                .apply_transaction(tx)
+                .unwrap_or_else(|_| Receipt::failed(tx, &serial.config.domain))
+        })
+        .collect();
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    let receipts = processor
+        .apply_transactions(&transactions, FailurePolicy::Skip)
+        .unwrap();
+
+    assert_eq!(receipts, expected);
+    assert_eq!(
+        processor.generate_state_root().unwrap(),
+        serial.generate_state_root().unwrap()
+    );
+    assert_eq!(*db.accounts.borrow(), *serial_db.accounts.borrow());
+}
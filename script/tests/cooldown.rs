@@ -0,0 +1,75 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::signed;
+use program::{
+    batch::BatchContext,
+    builder::TxBuilder,
+    commitment::{leaf_hash, CommitmentVersion},
+    config::CanvasConfig,
+    error::CanvasError,
+    snapshot, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+const CANVAS: Address = Address::with_last_byte(0xca);
+
+fn config(commitment: CommitmentVersion) -> CanvasConfig {
+    CanvasConfig {
+        commitment,
+        edit_cooldown: 3,
+        ..CanvasConfig::default()
+    }
+}
+
+#[test]
+fn sender_waits_out_the_cooldown() {
+    let (signer, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V9));
+    let edit = |signer: &PrivateKeySigner, nonce| {
+        signed(signer, TxBuilder::new(CANVAS).nonce(nonce).append("a"))
+    };
+
+    processor.batch = BatchContext {
+        index: 4,
+        ..BatchContext::default()
+    };
+    processor.apply_transaction(&edit(&signer, 0)).unwrap();
+    // Others are not held back by it.
+    processor.apply_transaction(&edit(&other, 0)).unwrap();
+
+    processor.batch.index = 6;
+    assert!(matches!(
+        processor.apply_transaction(&edit(&signer, 1)),
+        Err(CanvasError::Cooldown { until: 7, .. })
+    ));
+
+    processor.batch.index = 7;
+    processor.apply_transaction(&edit(&signer, 1)).unwrap();
+    let account = db.get_account(&CANVAS).unwrap();
+    assert_eq!(account.data, "aaa");
+    assert_eq!(account.last_edits.get(&signer.address()), Some(&7));
+    assert_eq!(account.last_edits.get(&other.address()), Some(&4));
+
+    // Committed, and kept by snapshots.
+    let mut forged = account.clone();
+    forged.last_edits.clear();
+    assert_ne!(
+        leaf_hash(CommitmentVersion::V9, &CANVAS, &account),
+        leaf_hash(CommitmentVersion::V9, &CANVAS, &forged)
+    );
+    let restored = snapshot::decode(&db.snapshot_accounts().unwrap()).unwrap();
+    assert_eq!(restored[&CANVAS], account);
+}
+
+#[test]
+fn cooldown_needs_a_version_that_commits_it() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V8));
+
+    assert!(matches!(
+        processor.apply_transaction(&signed(&signer, TxBuilder::new(CANVAS).append("a"))),
+        Err(CanvasError::CooldownNotCommitted)
+    ));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::{config, signed};
+use program::{
+    builder::TxBuilder, commitment::CommitmentVersion, error::CanvasError, AccountDB,
+    CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn copies_and_moves_between_accounts() {
+    let signer = PrivateKeySigner::random();
+    let (a, b) = (Address::with_last_byte(0xa), Address::with_last_byte(0xb));
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let transactions = vec![
+        signed(&signer, TxBuilder::new(a).nonce(0).append("hello world")),
+        signed(
+            &signer,
+            TxBuilder::new(b)
+                .nonce(1)
+                .append("[]")
+                .copy(a, 0, 5, 1)
+                .move_range(a, 5, 6, 0),
+        ),
+        signed(&signer, TxBuilder::new(a).nonce(2).move_range(a, 0, 1, 4)),
+    ];
+    processor.process_batch(&transactions).unwrap();
+
+    assert_eq!(db.get_account(&a).unwrap().data, "elloh");
+    assert_eq!(db.get_account(&b).unwrap().data, " world[hello]");
+    let stats = db.get_account(&a).unwrap().contributions[&signer.address()];
+    assert_eq!((stats.edits, stats.deleted), (3, 7));
+}
+
+#[test]
+fn moving_out_takes_writing_the_source() {
+    let (owner, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let (a, b) = (Address::with_last_byte(0xa), Address::with_last_byte(0xb));
+    let db = InMemoryDB::default();
+    // Claims need a commitment that binds owners.
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V3));
+    processor
+        .apply_transaction(&signed(
+            &owner,
+            TxBuilder::new(a).nonce(0).claim().append("secret"),
+        ))
+        .unwrap();
+
+    processor
+        .apply_transaction(&signed(&other, TxBuilder::new(b).nonce(0).copy(a, 0, 6, 0)))
+        .unwrap();
+    assert_eq!(db.get_account(&b).unwrap().data, "secret");
+
+    match processor.apply_transaction(&signed(
+        &other,
+        TxBuilder::new(b).nonce(1).move_range(a, 0, 6, 0),
+    )) {
+        Err(CanvasError::Access(_)) => {}
+        result => panic!("expected an access error, got {:?}", result),
+    }
+    assert_eq!(db.get_account(&a).unwrap().data, "secret");
+}
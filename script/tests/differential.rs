@@ -0,0 +1,304 @@
+//! Differential tests of the state transition function. Random batches of valid and invalid
+//! transactions run through the host `CanvasProcessor`, through a `Witness` of the same state,
+//! and with the `prove` feature through the SP1 program in the executor; all of them must commit
+//! the same values, give the same receipts and fail alike. A failing case is shrunk and its
+//! `Input` written as JSON to the target's tmp dir; copied into `tests/counterexamples` it is
+//! replayed on every run.
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use common::signed;
+use program::{
+    batch::encode_batch,
+    bridge::Inbox,
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    config::{CanvasConfig, FailurePolicy},
+    edit::EditOp,
+    witness::Witness,
+    CanvasProcessor, InMemoryDB, Input, SignedTransaction,
+};
+use proptest::{
+    prelude::*,
+    test_runner::{Config, TestError, TestRunner},
+};
+
+const SIGNERS: usize = 4;
+// The signers' own accounts and two shared ones.
+const TARGETS: usize = SIGNERS + 2;
+
+fn signer(index: usize) -> PrivateKeySigner {
+    PrivateKeySigner::from_bytes(&B256::with_last_byte(index as u8 + 1)).unwrap()
+}
+
+fn target(index: usize) -> Address {
+    match index < SIGNERS {
+        true => signer(index).address(),
+        false => Address::with_last_byte(0xc0 + index as u8),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TxCase {
+    signer: usize,
+    to: usize,
+    // Added to the sender's next nonce, so anything but zero fails.
+    nonce_skew: u64,
+    ops: Vec<EditOp>,
+}
+
+// Mostly palette digits, sometimes chars the validator rejects.
+fn value() -> impl Strategy<Value = String> {
+    prop_oneof![9 => "[0-9a-f]{0,6}", 1 => "[0-9a-z]{1,3}"]
+}
+
+// Indices and counts run a little past the data the accounts start with, so some are out of
+// range.
+fn op() -> impl Strategy<Value = EditOp> {
+    prop_oneof![
+        (0usize..12, value()).prop_map(|(index, value)| EditOp::Insert { index, value }),
+        (0usize..12, 0usize..4).prop_map(|(index, count)| EditOp::Delete { index, count }),
+        (0usize..12, 0usize..4, value()).prop_map(|(index, count, value)| EditOp::Replace {
+            index,
+            count,
+            value
+        }),
+        value().prop_map(|value| EditOp::Append { value }),
+        Just(EditOp::Claim),
+        (0..SIGNERS).prop_map(|i| EditOp::Grant {
+            writer: signer(i).address()
+        }),
+        (0..TARGETS, 0usize..12, 0usize..4, 0usize..12).prop_map(|(source, index, count, at)| {
+            EditOp::Copy {
+                source: target(source),
+                index,
+                count,
+                at,
+            }
+        }),
+        (0..TARGETS, 0usize..12, 0usize..4, 0usize..12).prop_map(|(source, index, count, at)| {
+            EditOp::Move {
+                source: target(source),
+                index,
+                count,
+                at,
+            }
+        }),
+    ]
+}
+
+fn tx_case() -> impl Strategy<Value = TxCase> {
+    (
+        0..SIGNERS,
+        0..TARGETS,
+        prop_oneof![9 => Just(0u64), 1 => 1u64..3],
+        prop::collection::vec(op(), 1..4),
+    )
+        .prop_map(|(signer, to, nonce_skew, ops)| TxCase {
+            signer,
+            to,
+            nonce_skew,
+            ops,
+        })
+}
+
+// Every signer's account starts with a few chars, then `cases` form one batch.
+fn input(commitment: CommitmentVersion, abort: bool, cases: &[TxCase]) -> Input {
+    let config = CanvasConfig {
+        commitment,
+        failure_policy: match abort {
+            true => FailurePolicy::Abort,
+            false => FailurePolicy::Skip,
+        },
+        ..CanvasConfig::default()
+    };
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config);
+    for i in 0..SIGNERS {
+        processor
+            .apply_transaction(&signed(
+                &signer(i),
+                TxBuilder::new(target(i)).append("0123"),
+            ))
+            .unwrap();
+    }
+
+    let mut nonces = [1; SIGNERS];
+    let transactions: Vec<SignedTransaction> = cases
+        .iter()
+        .map(|case| {
+            let nonce = nonces[case.signer] + case.nonce_skew;
+            if case.nonce_skew == 0 {
+                nonces[case.signer] += 1;
+            }
+            let builder = case
+                .ops
+                .iter()
+                .cloned()
+                .fold(TxBuilder::new(target(case.to)).nonce(nonce), TxBuilder::op);
+            signed(&signer(case.signer), builder)
+        })
+        .collect();
+
+    Input {
+        transaction_data: encode_batch(&transactions, config.codec, None).unwrap(),
+        db,
+        config,
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: Default::default(),
+        witness: None,
+        dictionary: None,
+    }
+}
+
+fn check(input: &Input) -> Result<(), TestCaseError> {
+    let host = input.clone().process();
+    let witness = Input {
+        witness: Some(Witness::build(
+            &input.db,
+            &input.transactions().unwrap(),
+            Inbox::default(),
+            &input.config,
+        )),
+        db: InMemoryDB::default(),
+        ..input.clone()
+    }
+    .process();
+
+    match (&host, &witness) {
+        (Ok(host), Ok(witness)) => {
+            prop_assert_eq!(
+                host.public_values.abi_encode(),
+                witness.public_values.abi_encode()
+            );
+            prop_assert_eq!(&host.receipts, &witness.receipts);
+        }
+        (Err(host), Err(witness)) => prop_assert_eq!(
+            host.root_cause().to_string(),
+            witness.root_cause().to_string()
+        ),
+        (host, witness) => prop_assert!(
+            false,
+            "Host {:?} but witness {:?}",
+            host.as_ref().err(),
+            witness.as_ref().err()
+        ),
+    }
+
+    #[cfg(feature = "prove")]
+    guest::check(input, host.as_ref().ok())?;
+    Ok(())
+}
+
+#[cfg(feature = "prove")]
+mod guest {
+    use program::{
+        chunked::{self, Encoding},
+        receipt::BatchReport,
+    };
+    use sp1_sdk::{ProverClient, SP1Stdin};
+
+    use super::*;
+
+    const ELF: &str = "../program/elf/riscv32im-succinct-zkvm-elf";
+
+    // The program must commit what the host computed, or fail where the host failed.
+    pub fn check(input: &Input, host: Option<&BatchReport>) -> Result<(), TestCaseError> {
+        let elf = std::fs::read(ELF).expect("build the program with `cargo prove build` first");
+        let mut stdin = SP1Stdin::new();
+        let mut encoded = Vec::new();
+        chunked::write_input(input, Encoding::Bincode, &mut encoded).unwrap();
+        for buffer in encoded.chunks(chunked::FRAME_BYTES) {
+            stdin.write_slice(buffer);
+        }
+
+        match (ProverClient::new().execute(&elf, stdin).run(), host) {
+            (Ok((public_values, _)), Some(host)) => prop_assert_eq!(
+                public_values.as_slice(),
+                host.public_values.abi_encode().as_slice()
+            ),
+            (Err(_), None) => {}
+            (guest, host) => prop_assert!(
+                false,
+                "Guest {} but host {}",
+                if guest.is_ok() { "succeeded" } else { "failed" },
+                if host.is_some() {
+                    "succeeded"
+                } else {
+                    "failed"
+                }
+            ),
+        }
+        Ok(())
+    }
+}
+
+// Writes `input` where a failing run can be picked up from, named after its hash.
+fn save(input: &Input) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("counterexamples");
+    fs::create_dir_all(&dir).unwrap();
+    let json = serde_json::to_vec_pretty(input).unwrap();
+    let path = dir.join(format!("{}.json", keccak256(&json)));
+    fs::write(&path, json).unwrap();
+    path
+}
+
+#[test]
+fn host_witness_and_guest_agree() {
+    let cases = match cfg!(feature = "prove") {
+        true => 16,
+        false => 128,
+    };
+    let strategy = (
+        prop::sample::select(vec![
+            CommitmentVersion::V5,
+            CommitmentVersion::V7,
+            CommitmentVersion::V8,
+        ]),
+        any::<bool>(),
+        prop::collection::vec(tx_case(), 1..8),
+    );
+    let mut runner = TestRunner::new(Config {
+        cases,
+        failure_persistence: None,
+        ..Config::default()
+    });
+
+    match runner.run(&strategy, |(commitment, abort, cases)| {
+        check(&input(commitment, abort, &cases))
+    }) {
+        Ok(()) => {}
+        Err(TestError::Fail(reason, (commitment, abort, cases))) => {
+            let path = save(&input(commitment, abort, &cases));
+            panic!("{} with the input in {}", reason, path.display());
+        }
+        Err(err) => panic!("{}", err),
+    }
+}
+
+#[test]
+fn saved_counterexamples_agree() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/counterexamples");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        let input: Input = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        if let Err(err) = check(&input) {
+            panic!("{}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rlp::{Decodable, Encodable};
+use common::signed;
+use program::{
+    batch::BatchContext, builder::TxBuilder, config::CanvasConfig, error::CanvasError,
+    expiry::ExpiryError, signing::signing_hash, CanvasProcessor, InMemoryDB, Transaction,
+    TX_VERSION_DOMAIN, TX_VERSION_EXPIRY,
+};
+
+fn expiring(valid_until_batch: u64, valid_until_timestamp: u64) -> TxBuilder {
+    TxBuilder::new(Address::with_last_byte(1))
+        .version(TX_VERSION_EXPIRY)
+        .valid_until_batch(valid_until_batch)
+        .valid_until_timestamp(valid_until_timestamp)
+        .append("a")
+}
+
+#[test]
+fn expiry_round_trips_and_is_signed() {
+    let tx = expiring(3, 1_700_000_000).build().unwrap();
+    let mut encoded = Vec::new();
+    tx.encode(&mut encoded);
+
+    let decoded = Transaction::decode(&mut encoded.as_slice()).unwrap();
+    assert_eq!(
+        (decoded.valid_until_batch, decoded.valid_until_timestamp),
+        (3, 1_700_000_000)
+    );
+
+    let extended = expiring(4, 1_700_000_000).build().unwrap();
+    let domain = CanvasConfig::default().domain;
+    assert_ne!(signing_hash(&tx, &domain), signing_hash(&extended, &domain));
+}
+
+#[test]
+fn expired_transactions_fail() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    processor.batch = BatchContext {
+        index: 4,
+        ..BatchContext::default()
+    }
+    .timestamped(1_700_000_000);
+    match processor.apply_transaction(&signed(&signer, expiring(3, 0))) {
+        Err(CanvasError::Expiry(err)) => assert_eq!(
+            err,
+            ExpiryError::Batch {
+                valid_until: 3,
+                batch: 4
+            }
+        ),
+        result => panic!("expected an ExpiryError, got {:?}", result),
+    }
+    match processor.apply_transaction(&signed(&signer, expiring(0, 1_699_999_999))) {
+        Err(CanvasError::Expiry(ExpiryError::Timestamp { .. })) => {}
+        result => panic!("expected an ExpiryError, got {:?}", result),
+    }
+
+    processor
+        .apply_transaction(&signed(&signer, expiring(4, 1_700_000_000)))
+        .unwrap();
+}
+
+#[test]
+fn only_expiry_versions_expire() {
+    assert!(TxBuilder::new(Address::ZERO)
+        .version(TX_VERSION_DOMAIN)
+        .valid_until_batch(1)
+        .build()
+        .is_err());
+}
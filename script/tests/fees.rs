@@ -0,0 +1,108 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, U256},
+    signers::local::PrivateKeySigner,
+};
+use alloy_rlp::Encodable;
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    config::CanvasConfig,
+    error::CanvasError,
+    fees::{FeeError, FeeSchedule},
+    Account, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+// Balances are committed from version 1 on.
+fn config() -> CanvasConfig {
+    CanvasConfig {
+        commitment: CommitmentVersion::V1,
+        fees: FeeSchedule {
+            per_op: U256::from(100),
+            per_byte: U256::from(1),
+            recipient: Address::with_last_byte(0xfe),
+        },
+        ..CanvasConfig::default()
+    }
+}
+
+fn funded(signer: &PrivateKeySigner, balance: u64) -> InMemoryDB {
+    let db = InMemoryDB::default();
+    db.accounts.borrow_mut().insert(
+        signer.address(),
+        Account {
+            balance: U256::from(balance),
+            ..Account::default()
+        },
+    );
+    db
+}
+
+#[test]
+fn fee_moves_to_the_recipient() {
+    let signer = PrivateKeySigner::random();
+    let db = funded(&signer, 10_000);
+    let mut processor = CanvasProcessor::with_config(&db, config());
+
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1))
+            .append("a")
+            .append("b"),
+    );
+    let fee = U256::from(200 + tx.length());
+    processor.apply_transaction(&tx).unwrap();
+
+    let recipient = config().fees.recipient;
+    assert_eq!(
+        db.get_account(&signer.address()).unwrap().balance,
+        U256::from(10_000) - fee
+    );
+    assert_eq!(db.get_account(&recipient).unwrap().balance, fee);
+}
+
+#[test]
+fn insufficient_balance_fails_without_writes() {
+    let signer = PrivateKeySigner::random();
+    let db = funded(&signer, 100);
+    let mut processor = CanvasProcessor::with_config(&db, config());
+
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+    match processor.apply_transaction(&tx) {
+        Err(CanvasError::Fee(FeeError::InsufficientBalance { balance, .. })) => {
+            assert_eq!(balance, U256::from(100))
+        }
+        result => panic!("expected a FeeError, got {:?}", result),
+    }
+    assert_eq!(db.get_account(&signer.address()).unwrap().nonce, 0);
+    assert_eq!(db.accounts.borrow().len(), 1);
+}
+
+#[test]
+fn fees_need_committed_balances() {
+    let signer = PrivateKeySigner::random();
+    let db = funded(&signer, 10_000);
+    let config = CanvasConfig {
+        commitment: CommitmentVersion::V0,
+        ..config()
+    };
+    let mut processor = CanvasProcessor::with_config(&db, config);
+
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+    assert!(matches!(
+        processor.apply_transaction(&tx),
+        Err(CanvasError::Fee(FeeError::NotCommitted))
+    ));
+    assert_eq!(
+        db.get_account(&signer.address()).unwrap().balance,
+        U256::from(10_000)
+    );
+}
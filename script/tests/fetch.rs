@@ -0,0 +1,35 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+// Submissions are fetched the way `Syncer::batches_in` does: up to `concurrency` at once, each
+// yielding the batches it posted, flattened in chain order.
+#[tokio::test]
+async fn buffered_fetches_come_out_in_chain_order() {
+    let finished = Arc::new(Mutex::new(Vec::new()));
+    let fetch = |submission: u64| {
+        let finished = finished.clone();
+        async move {
+            // Later submissions answer first.
+            tokio::time::sleep(Duration::from_millis(10 * (8 - submission))).await;
+            finished.lock().unwrap().push(submission);
+            Ok::<_, eyre::Report>(vec![(submission, 0), (submission, 1)])
+        }
+    };
+
+    let posted: Vec<Vec<(u64, u64)>> = stream::iter(0..8)
+        .map(fetch)
+        .buffered(4)
+        .try_collect()
+        .await
+        .unwrap();
+    let batches: Vec<_> = posted.into_iter().flatten().collect();
+
+    let finished = finished.lock().unwrap().clone();
+    assert_ne!(finished, (0..8).collect::<Vec<_>>());
+    let expected: Vec<_> = (0..8).flat_map(|s| [(s, 0), (s, 1)]).collect();
+    assert_eq!(batches, expected);
+}
@@ -0,0 +1,115 @@
+use alloy::primitives::{b256, U256};
+use program::{
+    commitment::{empty_root, CommitmentVersion},
+    config::CanvasConfig,
+    genesis::Genesis,
+    AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+const GENESIS: &str = r#"
+[accounts."0x00000000000000000000000000000000000000ca"]
+data = "0f0f"
+contributors = ["0x00000000000000000000000000000000000000c1"]
+
+[accounts."0x00000000000000000000000000000000000000c1"]
+nonce = 2
+balance = "0x2710"
+owner = "0x00000000000000000000000000000000000000c1"
+"#;
+
+const VERSIONS: [CommitmentVersion; 13] = [
+    CommitmentVersion::V0,
+    CommitmentVersion::V1,
+    CommitmentVersion::V2,
+    CommitmentVersion::V3,
+    CommitmentVersion::V4,
+    CommitmentVersion::V5,
+    CommitmentVersion::V6,
+    CommitmentVersion::V7,
+    CommitmentVersion::V8,
+    CommitmentVersion::V9,
+    CommitmentVersion::V10,
+    CommitmentVersion::V11,
+    CommitmentVersion::V12,
+];
+
+fn genesis() -> Genesis {
+    toml::from_str(GENESIS).unwrap()
+}
+
+#[test]
+fn loads_with_defaults() {
+    let db = InMemoryDB::from_genesis(&genesis());
+    let canvas = db
+        .get_account(
+            &"0x00000000000000000000000000000000000000ca"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+    let sender = db
+        .get_account(
+            &"0x00000000000000000000000000000000000000c1"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(canvas.data, "0f0f");
+    assert_eq!(canvas.nonce, 0);
+    assert_eq!(sender.nonce, 2);
+    assert_eq!(sender.balance, U256::from(10_000));
+}
+
+#[test]
+fn state_root_matches_processor() {
+    let genesis = genesis();
+    for version in VERSIONS {
+        let config = CanvasConfig {
+            commitment: version,
+            ..CanvasConfig::default()
+        };
+        let processor = CanvasProcessor::with_config(InMemoryDB::from_genesis(&genesis), config);
+
+        assert_eq!(
+            genesis.state_root(version),
+            processor.generate_state_root().unwrap()
+        );
+    }
+}
+
+#[test]
+fn json_export_round_trips_as_genesis() {
+    let db = InMemoryDB::from_genesis(&genesis());
+    let json = db.export_json().unwrap();
+
+    let imported = InMemoryDB::import_json(&json).unwrap();
+    assert_eq!(
+        imported.snapshot_accounts().unwrap(),
+        db.snapshot_accounts().unwrap()
+    );
+
+    let genesis: Genesis = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        genesis.state_root(CommitmentVersion::default()),
+        self::genesis().state_root(CommitmentVersion::default())
+    );
+    assert!(json.contains(r#""data": "0f0f""#));
+}
+
+#[test]
+fn empty_genesis_has_empty_root() {
+    for version in VERSIONS {
+        assert_eq!(Genesis::default().state_root(version), empty_root(version));
+    }
+}
+
+// Every operator has to derive this root from the file above; a change to it is a change of the
+// genesis commitment.
+#[test]
+fn state_root_is_pinned() {
+    assert_eq!(
+        genesis().state_root(CommitmentVersion::V3),
+        b256!("c1769044ae0f9866e0a952399e0463fb3b6f5cb6165637d379e49bd588e05215").0
+    );
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use common::{config, signed};
+use program::{
+    builder::TxBuilder, commitment::CommitmentVersion, light::LightClient, CanvasProcessor,
+    InMemoryDB,
+};
+
+#[test]
+fn light_client_checks_accounts_against_posted_root() {
+    for commitment in [
+        CommitmentVersion::V3,
+        CommitmentVersion::V5,
+        CommitmentVersion::V8,
+        CommitmentVersion::V10,
+    ] {
+        let db = InMemoryDB::default();
+        let config = config(commitment);
+        let mut processor = CanvasProcessor::with_config(&db, config);
+        let report = processor
+            .process_batch(&[signed(
+                &PrivateKeySigner::random(),
+                TxBuilder::new(Address::with_last_byte(0xa)).append("ab"),
+            )])
+            .unwrap();
+        let client =
+            LightClient::from_public_values(&report.public_values.abi_encode(), commitment)
+                .unwrap();
+
+        let target = Address::with_last_byte(0xa);
+        let response = processor.account_with_proof(&target).unwrap();
+        assert_eq!(client.verify(&response).unwrap().unwrap().data, "ab");
+
+        let mut forged = response.clone();
+        forged.account.as_mut().unwrap().data = "ac".to_string();
+        assert!(client.verify(&forged).is_err());
+
+        let stale = LightClient::new(B256::repeat_byte(1), commitment);
+        assert!(stale.verify(&response).is_err());
+
+        // Only sparse trees prove an address has no account.
+        let absent = processor.account_with_proof(&Address::with_last_byte(0xb));
+        match commitment.is_sparse() {
+            true => assert_eq!(client.verify(&absent.unwrap()).unwrap(), None),
+            false => assert!(absent.is_err()),
+        }
+    }
+}
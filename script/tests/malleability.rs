@@ -0,0 +1,75 @@
+mod common;
+
+use alloy::{
+    primitives::{uint, Address, U256},
+    signers::local::PrivateKeySigner,
+};
+use common::signed;
+use program::{
+    builder::TxBuilder, config::CanvasConfig, error::CanvasError, recover_address_any_s,
+    recover_address_from_tx, signing::is_low_s, CanvasProcessor, InMemoryDB, SignedTransaction,
+};
+
+const SECP256K1_ORDER: U256 =
+    uint!(0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141_U256);
+
+// The other signature of the same key over the same hash.
+fn twin(tx: &SignedTransaction) -> SignedTransaction {
+    SignedTransaction {
+        s: SECP256K1_ORDER - tx.s,
+        odd_y_parity: !tx.odd_y_parity,
+        ..tx.clone()
+    }
+}
+
+#[test]
+fn high_s_twin_is_rejected() {
+    let signer = PrivateKeySigner::random();
+    let domain = CanvasConfig::default().domain;
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+    let high = twin(&tx);
+    assert!(is_low_s(&tx.s));
+    assert!(!is_low_s(&high.s));
+
+    assert_eq!(
+        recover_address_from_tx(&tx, &domain).unwrap(),
+        signer.address()
+    );
+    assert!(matches!(
+        recover_address_from_tx(&high, &domain),
+        Err(CanvasError::MalleableSignature)
+    ));
+    assert_eq!(
+        recover_address_any_s(&high, &domain).unwrap(),
+        signer.address()
+    );
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    assert!(matches!(
+        processor.apply_transaction(&high),
+        Err(CanvasError::MalleableSignature)
+    ));
+}
+
+#[test]
+fn high_s_replays_when_allowed() {
+    let signer = PrivateKeySigner::random();
+    let high = twin(&signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    ));
+
+    let db = InMemoryDB::default();
+    let config = CanvasConfig {
+        high_s_signatures: true,
+        ..CanvasConfig::default()
+    };
+    let receipt = CanvasProcessor::with_config(&db, config)
+        .apply_transaction(&high)
+        .unwrap();
+    assert_eq!(receipt.from, signer.address());
+}
@@ -0,0 +1,388 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    config::CanvasConfig,
+    mempool::{
+        LoggedTransaction, Mempool, MempoolConfig, MempoolError, MempoolLog, PendingTransaction,
+    },
+    ordering::{FeePriority, RoundRobin},
+    recover_address_from_tx, Account, AccountDB, InMemoryDB, SignedTransaction,
+};
+
+// A log in memory the test keeps a handle to, which fails every write while `failing` is set.
+#[derive(Debug, Clone, Default)]
+struct MemoryLog {
+    logged: Arc<Mutex<Vec<B256>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MemoryLog {
+    fn logged(&self) -> Vec<B256> {
+        self.logged.lock().unwrap().clone()
+    }
+
+    fn fail(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    fn write(&self) -> eyre::Result<()> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(eyre::eyre!("disk full"));
+        }
+        Ok(())
+    }
+}
+
+impl MempoolLog for MemoryLog {
+    fn append(&mut self, tx: &PendingTransaction) -> eyre::Result<()> {
+        self.write()?;
+        self.logged.lock().unwrap().push(tx.hash);
+        Ok(())
+    }
+
+    fn compact(&mut self, pending: &[PendingTransaction]) -> eyre::Result<()> {
+        self.write()?;
+        *self.logged.lock().unwrap() = pending.iter().map(|pending| pending.hash).collect();
+        Ok(())
+    }
+}
+
+fn logged(tx: &SignedTransaction, received: u64, priority: u64) -> LoggedTransaction {
+    LoggedTransaction {
+        tx: tx.clone(),
+        received,
+        priority,
+    }
+}
+
+#[test]
+fn deduplicates_by_hash() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let mut mempool = Mempool::default();
+
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+    let other = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1))
+            .nonce(1)
+            .append("b"),
+    );
+    assert_ne!(tx.hash(), other.hash());
+
+    mempool.submit(&db, &config, tx.clone(), 0, 0).unwrap();
+    let err = mempool
+        .submit(&db, &config, tx.clone(), 0, 0)
+        .unwrap_err()
+        .downcast::<MempoolError>()
+        .unwrap();
+    assert_eq!(err, MempoolError::Duplicate { hash: tx.hash() });
+
+    mempool.submit(&db, &config, other.clone(), 0, 0).unwrap();
+    assert_eq!(mempool.get(&other.hash()).unwrap().sender, signer.address());
+    assert!(mempool.get(&B256::ZERO).is_none());
+}
+
+#[test]
+fn senders_are_held_to_their_quota() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_sender_txs: 2,
+        ..MempoolConfig::default()
+    });
+    let tx = |nonce| {
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(nonce)
+                .append("a"),
+        )
+    };
+
+    mempool.submit(&db, &config, tx(0), 0, 0).unwrap();
+    mempool.submit(&db, &config, tx(1), 10, 0).unwrap();
+    let err = mempool
+        .submit(&db, &config, tx(2), 20, 0)
+        .unwrap_err()
+        .downcast::<MempoolError>()
+        .unwrap();
+    assert_eq!(
+        err,
+        MempoolError::SenderTxQuota {
+            address: signer.address(),
+            max: 2,
+            window: 60,
+        }
+    );
+
+    // The first admission has left the window.
+    mempool.submit(&db, &config, tx(2), 60, 0).unwrap();
+    assert_eq!(mempool.len(), 3);
+}
+
+#[test]
+fn full_pool_rejects_lower_priority() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_size: 1,
+        ..MempoolConfig::default()
+    });
+    let tx = |nonce| {
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(nonce)
+                .append("a"),
+        )
+    };
+
+    mempool.submit(&db, &config, tx(0), 0, 5).unwrap();
+    let err = mempool
+        .submit(&db, &config, tx(1), 0, 4)
+        .unwrap_err()
+        .downcast::<MempoolError>()
+        .unwrap();
+    assert_eq!(
+        err,
+        MempoolError::PoolFull {
+            priority: 4,
+            lowest: 5
+        }
+    );
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn ordering_policy_picks_between_senders() {
+    let (busy, quiet) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let tx = |signer: &PrivateKeySigner, nonce| {
+        signed(
+            signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(nonce)
+                .append("a"),
+        )
+    };
+    let fill = |mempool: &mut Mempool| {
+        for nonce in 0..3 {
+            mempool
+                .submit(&db, &config, tx(&busy, nonce), 0, 1)
+                .unwrap();
+        }
+        mempool.submit(&db, &config, tx(&quiet, 0), 0, 2).unwrap();
+    };
+    let senders = |batch: Vec<SignedTransaction>| -> Vec<Address> {
+        batch
+            .iter()
+            .map(|tx| recover_address_from_tx(tx, &config.domain).unwrap())
+            .collect()
+    };
+
+    let mut fifo = Mempool::default();
+    fill(&mut fifo);
+    assert_eq!(
+        senders(fifo.drain_batch(&db).unwrap()),
+        [
+            busy.address(),
+            busy.address(),
+            busy.address(),
+            quiet.address()
+        ]
+    );
+
+    let mut fee = Mempool::default().with_ordering(Box::new(FeePriority));
+    fill(&mut fee);
+    assert_eq!(senders(fee.drain_batch(&db).unwrap())[0], quiet.address());
+
+    let mut fair = Mempool::default().with_ordering(Box::new(RoundRobin));
+    fill(&mut fair);
+    assert_eq!(
+        senders(fair.drain_batch(&db).unwrap()),
+        [
+            busy.address(),
+            quiet.address(),
+            busy.address(),
+            busy.address()
+        ]
+    );
+}
+
+#[test]
+fn rejected_transactions_are_not_logged() {
+    let (signer, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_size: 1,
+        ..MempoolConfig::default()
+    });
+    let log = MemoryLog::default();
+    mempool
+        .restore(&db, &config, Vec::new(), Box::new(log.clone()))
+        .unwrap();
+
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+    mempool.submit(&db, &config, tx.clone(), 0, 5).unwrap();
+    assert!(mempool.submit(&db, &config, tx.clone(), 0, 5).is_err());
+    let same_nonce = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("b"),
+    );
+    assert!(mempool.submit(&db, &config, same_nonce, 0, 5).is_err());
+    let cheaper = signed(
+        &other,
+        TxBuilder::new(Address::with_last_byte(2)).append("c"),
+    );
+    assert!(mempool.submit(&db, &config, cheaper, 0, 4).is_err());
+    assert_eq!(log.logged(), vec![tx.hash()]);
+
+    // Making room for a higher priority rewrites the log without the evicted one.
+    let dearer = signed(
+        &other,
+        TxBuilder::new(Address::with_last_byte(2)).append("d"),
+    );
+    let evicted = mempool.submit(&db, &config, dearer.clone(), 0, 6).unwrap();
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(log.logged(), vec![dearer.hash()]);
+}
+
+#[test]
+fn unlogged_transactions_are_not_admitted() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_sender_txs: 1,
+        ..MempoolConfig::default()
+    });
+    let log = MemoryLog::default();
+    mempool
+        .restore(&db, &config, Vec::new(), Box::new(log.clone()))
+        .unwrap();
+    let tx = signed(
+        &signer,
+        TxBuilder::new(Address::with_last_byte(1)).append("a"),
+    );
+
+    log.fail(true);
+    assert!(mempool.submit(&db, &config, tx.clone(), 0, 0).is_err());
+    assert!(mempool.is_empty());
+
+    // Nor did it count against the sender's quota.
+    log.fail(false);
+    mempool.submit(&db, &config, tx.clone(), 0, 0).unwrap();
+    assert_eq!(log.logged(), vec![tx.hash()]);
+}
+
+#[test]
+fn restore_drops_included_transactions() {
+    let signer = PrivateKeySigner::random();
+    let config = CanvasConfig::default();
+    let tx = |nonce| {
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(nonce)
+                .append("a"),
+        )
+    };
+
+    // A batch included the first one since it was logged.
+    let db = InMemoryDB::default();
+    db.set_account(
+        &signer.address(),
+        &Account {
+            nonce: 1,
+            ..Account::default()
+        },
+    )
+    .unwrap();
+    let mut mempool = Mempool::default();
+    let log = MemoryLog::default();
+    let restored = mempool
+        .restore(
+            &db,
+            &config,
+            vec![logged(&tx(0), 0, 0), logged(&tx(1), 0, 0)],
+            Box::new(log.clone()),
+        )
+        .unwrap();
+    assert_eq!(restored, 1);
+    assert!(mempool.get(&tx(0).hash()).is_none());
+    assert!(mempool.get(&tx(1).hash()).is_some());
+    assert_eq!(log.logged(), vec![tx(1).hash()]);
+
+    // And from now on the pool logs what it admits.
+    mempool.submit(&db, &config, tx(2), 0, 0).unwrap();
+    assert_eq!(log.logged(), vec![tx(1).hash(), tx(2).hash()]);
+}
+
+#[test]
+fn restore_keeps_what_was_logged() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = CanvasConfig::default();
+    let tx = |nonce| {
+        signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1))
+                .nonce(nonce)
+                .append("a"),
+        )
+    };
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_sender_txs: 2,
+        sender_window: 60,
+        ..MempoolConfig::default()
+    });
+
+    // Both were admitted before the restart, within the quota then.
+    let restored = mempool
+        .restore(
+            &db,
+            &config,
+            vec![logged(&tx(0), 100, 7), logged(&tx(1), 110, 3)],
+            Box::new(MemoryLog::default()),
+        )
+        .unwrap();
+    assert_eq!(restored, 2);
+    let first = mempool.get(&tx(0).hash()).unwrap();
+    assert_eq!((first.received, first.priority), (100, 7));
+    let second = mempool.get(&tx(1).hash()).unwrap();
+    assert_eq!((second.received, second.priority), (110, 3));
+
+    // They still count within the window they were admitted in, and no longer after it.
+    assert!(matches!(
+        mempool
+            .submit(&db, &config, tx(2), 150, 0)
+            .unwrap_err()
+            .downcast::<MempoolError>()
+            .unwrap(),
+        MempoolError::SenderTxQuota { .. }
+    ));
+    mempool.submit(&db, &config, tx(2), 160, 0).unwrap();
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use alloy::signers::local::PrivateKeySigner;
+use common::{config, signed};
+use program::{
+    access::AccessError,
+    builder::TxBuilder,
+    commitment::{leaf_hash, CommitmentVersion},
+    error::CanvasError,
+    metadata::{Metadata, MetadataError, MAX_NAME},
+    snapshot, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn owner_sets_committed_metadata() {
+    let signer = PrivateKeySigner::random();
+    let own = signer.address();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V12));
+
+    // Outside the hex palette, which only applies to the data.
+    processor
+        .apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(own)
+                .append("ab")
+                .set_metadata("Gallery", "ipfs://avatar"),
+        ))
+        .unwrap();
+    let account = db.get_account(&own).unwrap();
+    assert_eq!(account.data, "ab");
+    assert_eq!(
+        account.metadata,
+        Metadata {
+            name: "Gallery".into(),
+            uri: "ipfs://avatar".into(),
+        }
+    );
+
+    let mut forged = account.clone();
+    forged.metadata.name = "Other".into();
+    assert_ne!(
+        leaf_hash(CommitmentVersion::V12, &own, &account),
+        leaf_hash(CommitmentVersion::V12, &own, &forged)
+    );
+    let restored = snapshot::decode(&db.snapshot_accounts().unwrap()).unwrap();
+    assert_eq!(restored[&own], account);
+    // As `canvas_getAccount` returns it.
+    let json = serde_json::to_value(&account).unwrap();
+    assert_eq!(json["metadata"]["uri"], "ipfs://avatar");
+
+    // Clearing the account drops it with the rest.
+    processor
+        .apply_transaction(&signed(&signer, TxBuilder::new(own).nonce(1).clear()))
+        .unwrap();
+    assert!(db.get_account(&own).unwrap().metadata.is_empty());
+}
+
+#[test]
+fn metadata_is_capped_and_owner_only() {
+    let (signer, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let own = signer.address();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V12));
+
+    assert!(matches!(
+        processor.apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(own).set_metadata("x".repeat(MAX_NAME + 1), ""),
+        )),
+        Err(CanvasError::Metadata(MetadataError::TooLong {
+            field: "name",
+            ..
+        }))
+    ));
+    assert!(matches!(
+        processor.apply_transaction(&signed(
+            &other,
+            TxBuilder::new(own).set_metadata("Mine", ""),
+        )),
+        Err(CanvasError::Access(AccessError::NotOwner { .. }))
+    ));
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V11));
+    assert!(matches!(
+        processor.apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(own).set_metadata("Gallery", ""),
+        )),
+        Err(CanvasError::Metadata(MetadataError::NotCommitted))
+    ));
+    assert_eq!(db.get_account(&own).unwrap().metadata, Metadata::default());
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rlp::{Decodable, Encodable};
+use common::{config, signed};
+use program::{
+    batch::{encode_batch, BatchContext},
+    bridge::Inbox,
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    error::CanvasError,
+    limits::RateLimitState,
+    namespace::canvas_address,
+    smt,
+    witness::Witness,
+    AccountDB, CanvasProcessor, InMemoryDB, SignedTransaction, Transaction, TX_VERSION_CANVAS,
+};
+
+// A transaction appending `value` to the same address on `canvas`.
+fn canvas_tx(canvas: u64, nonce: u64, value: &str) -> TxBuilder {
+    TxBuilder::new(Address::with_last_byte(0xca))
+        .version(TX_VERSION_CANVAS)
+        .canvas(canvas)
+        .nonce(nonce)
+        .append(value)
+}
+
+fn batch(signer: &PrivateKeySigner) -> Vec<SignedTransaction> {
+    vec![
+        signed(signer, canvas_tx(0, 0, "a")),
+        signed(signer, canvas_tx(1, 1, "b")),
+        signed(signer, canvas_tx(7, 2, "c")),
+        signed(signer, canvas_tx(1, 3, "d")),
+    ]
+}
+
+#[test]
+fn canvas_round_trips() {
+    let tx = signed(&PrivateKeySigner::random(), canvas_tx(7, 0, "a")).tx;
+    let mut encoded = Vec::new();
+    tx.encode(&mut encoded);
+
+    assert_eq!(
+        Transaction::decode(&mut encoded.as_slice()).unwrap().canvas,
+        7
+    );
+}
+
+#[test]
+fn canvases_keep_their_own_data() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V8));
+    processor.process_batch(&batch(&signer)).unwrap();
+
+    let to = Address::with_last_byte(0xca);
+    let data = |canvas| db.get_account(&canvas_address(canvas, &to)).unwrap().data;
+    assert_eq!(
+        (data(0), data(1), data(7)),
+        ("a".into(), "bd".into(), "c".into())
+    );
+    assert_eq!(db.get_account(&signer.address()).unwrap().nonce, 4);
+
+    let root = processor.generate_state_root().unwrap();
+    let address = canvas_address(1, &to);
+    let account = db.get_account(&address).unwrap();
+    let canvas = processor.generate_canvas_proof(1).unwrap();
+    let leaf = program::commitment::leaf_hash(CommitmentVersion::V8, &address, &account);
+    assert!(canvas.verify(&root));
+    assert!(smt::verify(
+        &canvas.root,
+        &address,
+        Some(&leaf),
+        &processor.generate_sparse_proof(&address).unwrap()
+    ));
+}
+
+#[test]
+fn witness_matches_full_state() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let config = config(CommitmentVersion::V8);
+    CanvasProcessor::with_config(&db, config)
+        .process_batch(&[signed(&signer, canvas_tx(1, 0, "e"))])
+        .unwrap();
+
+    let transactions: Vec<SignedTransaction> = batch(&signer)
+        .into_iter()
+        .enumerate()
+        .map(|(i, tx)| signed(&signer, canvas_tx(tx.tx.canvas, i as u64 + 1, "f")))
+        .collect();
+    let witness = Witness::build(&db, &transactions, Inbox::default(), &config);
+    let expected = CanvasProcessor::with_config(&db, config)
+        .process_batch(&transactions)
+        .unwrap();
+    let report = witness
+        .process_batch(
+            &encode_batch(&transactions, config.codec, None).unwrap(),
+            config,
+            BatchContext::default(),
+            Inbox::default(),
+            RateLimitState::default(),
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        report.public_values.initialStateRoot,
+        expected.public_values.initialStateRoot
+    );
+    assert_eq!(
+        report.public_values.finalStateRoot,
+        expected.public_values.finalStateRoot
+    );
+}
+
+#[test]
+fn other_canvases_need_v8() {
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V7));
+
+    match processor.apply_transaction(&signed(&PrivateKeySigner::random(), canvas_tx(1, 0, "a"))) {
+        Err(CanvasError::NotNamespaced(1)) => {}
+        result => panic!("expected NotNamespaced, got {:?}", result),
+    }
+}
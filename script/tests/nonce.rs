@@ -0,0 +1,126 @@
+use alloy::{
+    primitives::{keccak256, Address},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use alloy_rlp::Encodable;
+use program::{
+    error::CanvasError, nonce::NonceError, AccountDB, CanvasProcessor, Data, InMemoryDB,
+    SignedTransaction, Transaction, TX_VERSION_LEGACY,
+};
+
+fn signed(signer: &PrivateKeySigner, to: Address, nonce: u64, value: &str) -> SignedTransaction {
+    let tx = Transaction {
+        to,
+        version: TX_VERSION_LEGACY,
+        data: vec![Data {
+            index: 0,
+            count: 0,
+            value: value.to_string(),
+        }],
+        ops: Vec::new(),
+        nonce,
+        extra: String::new(),
+        valid_until_batch: 0,
+        valid_until_timestamp: 0,
+        canvas: 0,
+        access_list: Vec::new(),
+    };
+
+    let mut encoded = Vec::<u8>::new();
+    tx.encode(&mut encoded);
+    let signature = signer
+        .sign_message_sync(keccak256(encoded).as_slice())
+        .unwrap();
+
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }
+}
+
+fn nonce_error(err: CanvasError) -> NonceError {
+    match err {
+        CanvasError::Nonce(err) => err,
+        err => panic!("expected a NonceError, got {}", err),
+    }
+}
+
+#[test]
+fn sequential_nonces_apply() {
+    let signer = PrivateKeySigner::random();
+    let canvas = Address::with_last_byte(1);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    for nonce in 0..3 {
+        processor
+            .apply_transaction(&signed(&signer, canvas, nonce, "a"))
+            .unwrap();
+    }
+
+    assert_eq!(db.get_account(&signer.address()).unwrap().nonce, 3);
+    assert_eq!(db.get_account(&canvas).unwrap().data, "aaa");
+}
+
+#[test]
+fn replayed_transaction_is_rejected() {
+    let signer = PrivateKeySigner::random();
+    let canvas = Address::with_last_byte(1);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let tx = signed(&signer, canvas, 0, "a");
+    processor.apply_transaction(&tx).unwrap();
+
+    let err = nonce_error(processor.apply_transaction(&tx).unwrap_err());
+    assert_eq!(
+        err,
+        NonceError {
+            address: signer.address(),
+            expected: 1,
+            got: 0,
+        }
+    );
+    assert_eq!(db.get_account(&canvas).unwrap().data, "a");
+}
+
+#[test]
+fn out_of_order_transaction_is_rejected() {
+    let signer = PrivateKeySigner::random();
+    let canvas = Address::with_last_byte(1);
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let err = nonce_error(
+        processor
+            .apply_transaction(&signed(&signer, canvas, 1, "b"))
+            .unwrap_err(),
+    );
+    assert_eq!((err.expected, err.got), (0, 1));
+    assert!(db.accounts.borrow().is_empty());
+
+    processor
+        .apply_transaction(&signed(&signer, canvas, 0, "a"))
+        .unwrap();
+    processor
+        .apply_transaction(&signed(&signer, canvas, 1, "b"))
+        .unwrap();
+    assert_eq!(db.get_account(&canvas).unwrap().data, "ba");
+}
+
+#[test]
+fn self_edit_cannot_be_replayed() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+
+    let tx = signed(&signer, signer.address(), 0, "a");
+    processor.apply_transaction(&tx).unwrap();
+    assert_eq!(db.get_account(&signer.address()).unwrap().nonce, 1);
+
+    let err = nonce_error(processor.apply_transaction(&tx).unwrap_err());
+    assert_eq!((err.expected, err.got), (1, 0));
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner, sol_types::SolValue};
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    public_values::{decode_public_values, layout_version, LAYOUT_WORDS, PUBLIC_VALUES_VERSION},
+    CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn every_layout_decodes_with_its_version() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let report = CanvasProcessor::new(&db)
+        .process_batch(&[signed(
+            &signer,
+            TxBuilder::new(Address::with_last_byte(1)).append("a"),
+        )])
+        .unwrap();
+    let values = report.public_values;
+    assert_eq!(values.publicValuesVersion, PUBLIC_VALUES_VERSION);
+
+    let bytes = values.abi_encode();
+    assert_eq!(bytes.len(), 32 * LAYOUT_WORDS[LAYOUT_WORDS.len() - 1]);
+    let latest = decode_public_values(&bytes).unwrap();
+    assert_eq!(latest.version, PUBLIC_VALUES_VERSION);
+    assert_eq!(latest.batch_hash, Some(values.batchHash));
+
+    // A proof posted under an older layout committed a prefix of today's values.
+    for (index, words) in LAYOUT_WORDS.iter().enumerate() {
+        let claimed = decode_public_values(&bytes[..32 * words]).unwrap();
+        assert_eq!(claimed.version, index as u64 + 1);
+        assert_eq!(claimed.final_state_root, values.finalStateRoot);
+        assert_eq!(claimed.transaction_commit, values.transaction_commit);
+    }
+    let previous = decode_public_values(&bytes[..32 * 17]).unwrap();
+    assert_eq!(previous.batch_hash, latest.batch_hash);
+    assert_eq!(previous.batch_timestamp, latest.batch_timestamp);
+    assert_eq!(layout_version(32 * 8), None);
+    assert!(decode_public_values(&bytes[..32 * 8]).is_err());
+
+    // The latest layout has to name itself.
+    let mut unknown = values.clone();
+    unknown.publicValuesVersion = PUBLIC_VALUES_VERSION + 1;
+    assert!(decode_public_values(&unknown.abi_encode()).is_err());
+}
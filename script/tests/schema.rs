@@ -0,0 +1,75 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use common::{config, signed};
+use program::{
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    edit::EditOp,
+    error::CanvasError,
+    schema::{Charset, Schema, SchemaError},
+    Account, AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+// Lines of two palette chars at most.
+fn schema() -> Schema {
+    Schema {
+        max_line_length: 2,
+        width: 0,
+        height: 0,
+        charset: Charset::Palette,
+    }
+}
+
+fn set_schema(to: Address) -> TxBuilder {
+    TxBuilder::new(to).op(EditOp::SetSchema {
+        schema: Some(schema()),
+    })
+}
+
+#[test]
+fn schemas_need_a_committed_policy() {
+    let signer = PrivateKeySigner::random();
+    let own = signer.address();
+    let db = InMemoryDB::default();
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V0));
+    assert!(matches!(
+        processor.apply_transaction(&signed(&signer, set_schema(own))),
+        Err(CanvasError::Schema(SchemaError::NotCommitted))
+    ));
+    assert_eq!(db.get_account(&own).unwrap().policy, B256::ZERO);
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V1));
+    processor
+        .apply_transaction(&signed(&signer, set_schema(own)))
+        .unwrap();
+    assert!(processor
+        .apply_transaction(&signed(&signer, TxBuilder::new(own).nonce(1).append("abc")))
+        .is_err());
+}
+
+#[test]
+fn uncommitted_policies_do_not_hold_back_edits() {
+    let signer = PrivateKeySigner::random();
+    let a = Address::with_last_byte(0xa);
+    // A schema the V0 leaf does not bind, as a prover could supply in a witness.
+    let db = InMemoryDB::default();
+    db.set_account(
+        &a,
+        &Account {
+            policy: schema().to_policy(),
+            ..Account::default()
+        },
+    )
+    .unwrap();
+
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V0));
+    processor
+        .apply_transaction(&signed(&signer, TxBuilder::new(a).append("abc")))
+        .unwrap();
+    assert_eq!(db.get_account(&a).unwrap().data, "abc");
+}
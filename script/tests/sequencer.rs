@@ -0,0 +1,116 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use common::signed;
+use program::{
+    batch::{commit, encode_batch, BatchContext},
+    builder::TxBuilder,
+    codec::Codec,
+    config::CanvasConfig,
+    sequencer::{equivocator, sequenced_batch, sequenced_batch_hash, SequencerSignature},
+    InMemoryDB, Input,
+};
+
+fn sign(key: &PrivateKeySigner, batch: BatchContext, transaction_data: &[u8]) -> BatchContext {
+    let hash = sequenced_batch_hash(
+        &batch,
+        commit(transaction_data),
+        &CanvasConfig::default().domain,
+    );
+    batch.signed(key.sign_hash_sync(&hash).unwrap().into())
+}
+
+fn process(transaction_data: Vec<u8>, batch: BatchContext) -> eyre::Result<()> {
+    Input {
+        transaction_data,
+        db: InMemoryDB::default(),
+        config: CanvasConfig {
+            require_sequencer_signature: true,
+            ..CanvasConfig::default()
+        },
+        batch,
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: Default::default(),
+        witness: None,
+        dictionary: None,
+    }
+    .process()
+    .map(|_| ())
+}
+
+#[test]
+fn program_checks_the_sequencer_signature() {
+    let key = PrivateKeySigner::random();
+    let posted = encode_batch(
+        &[signed(
+            &key,
+            TxBuilder::new(Address::with_last_byte(0xb)).append("ab"),
+        )],
+        Codec::Zlib,
+        None,
+    )
+    .unwrap();
+    let batch = BatchContext::default()
+        .timestamped(1_700_000_000)
+        .sequenced_by(key.address());
+
+    assert!(process(posted.clone(), sign(&key, batch, &posted)).is_ok());
+    assert!(process(posted.clone(), batch).is_err());
+
+    // Another key, or the same signature over other transaction data, does not hold.
+    let other = PrivateKeySigner::random();
+    assert!(process(posted.clone(), sign(&other, batch, &posted)).is_err());
+    let tampered = encode_batch(
+        &[signed(
+            &key,
+            TxBuilder::new(Address::with_last_byte(0xb)).append("cd"),
+        )],
+        Codec::Zlib,
+        None,
+    )
+    .unwrap();
+    assert!(process(tampered, sign(&key, batch, &posted)).is_err());
+
+    let signature = sign(&key, batch, &posted).sequencer_signature.unwrap();
+    assert_eq!(
+        SequencerSignature::from_bytes(&signature.to_bytes()).unwrap(),
+        signature
+    );
+}
+
+#[test]
+fn two_batches_at_one_index_convict_their_sequencer() {
+    let key = PrivateKeySigner::random();
+    let domain = CanvasConfig::default().domain;
+    let batch = BatchContext::default().sequenced_by(key.address());
+    let statement = |data: &[u8]| {
+        let signed = sign(&key, batch, data);
+        (
+            sequenced_batch(&signed, commit(data)),
+            signed.sequencer_signature.unwrap(),
+        )
+    };
+
+    let (a, a_signature) = statement(b"first");
+    let (b, b_signature) = statement(b"second");
+    assert_eq!(
+        equivocator((&a, &a_signature), (&b, &b_signature), &domain),
+        Some(key.address())
+    );
+    assert_eq!(
+        equivocator((&a, &a_signature), (&a, &a_signature), &domain),
+        None
+    );
+
+    let mut later = b.clone();
+    later.index = 1;
+    later.previousBatchHash = B256::repeat_byte(1);
+    assert_eq!(
+        equivocator((&a, &a_signature), (&later, &b_signature), &domain),
+        None
+    );
+}
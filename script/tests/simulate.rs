@@ -0,0 +1,86 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    config::{CanvasConfig, FailurePolicy},
+    cost::{estimate_l1_cost, PostingMode, GAS_PER_BLOB},
+    AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+#[test]
+fn simulation_predicts_the_batch() {
+    for commitment in [CommitmentVersion::V5, CommitmentVersion::V8] {
+        let signer = PrivateKeySigner::random();
+        let db = InMemoryDB::default();
+        let config = CanvasConfig {
+            commitment,
+            failure_policy: FailurePolicy::Skip,
+            ..CanvasConfig::default()
+        };
+        let mut processor = CanvasProcessor::with_config(&db, config);
+        processor
+            .process_batch(&[signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(0xa))
+                    .nonce(0)
+                    .append("1"),
+            )])
+            .unwrap();
+        let root = processor.generate_state_root().unwrap();
+
+        let transactions = vec![
+            signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(0xa))
+                    .nonce(1)
+                    .append("23"),
+            ),
+            signed(
+                &signer,
+                TxBuilder::new(Address::with_last_byte(0xa))
+                    .nonce(5)
+                    .append("4"),
+            ),
+        ];
+        let simulation = processor.simulate_batch(&transactions).unwrap();
+        assert_eq!(processor.generate_state_root().unwrap(), root);
+        assert_eq!(
+            db.get_account(&Address::with_last_byte(0xa)).unwrap().data,
+            "1"
+        );
+
+        assert_eq!(simulation.succeeded(), 1);
+        let failed = simulation
+            .transactions
+            .iter()
+            .find(|tx| tx.error.is_some())
+            .unwrap();
+        assert!(failed.error.as_ref().unwrap().contains("nonce"));
+        assert_eq!(simulation.changed_bytes, 2);
+        assert!(simulation.cycles > 0);
+        assert!(simulation.calldata_gas > 0);
+        assert_eq!(simulation.blob_gas, GAS_PER_BLOB);
+
+        let report = processor.process_batch(&transactions).unwrap();
+        assert_eq!(simulation.state_root, report.public_values.finalStateRoot);
+    }
+}
+
+#[test]
+fn l1_cost_counts_bytes_and_blobs() {
+    let data = [0, 1, 0, 0, 2];
+    let calldata = estimate_l1_cost(&data, PostingMode::Calldata, 10);
+    assert_eq!(calldata.zero_bytes, 3);
+    assert_eq!(calldata.gas, 3 * 4 + 2 * 16);
+    assert_eq!(calldata.wei, 440);
+
+    let blob = estimate_l1_cost(&data, PostingMode::Blob, 3);
+    assert_eq!(blob.blobs, 1);
+    assert_eq!(blob.wei, 3 * GAS_PER_BLOB as u128);
+    // A blob holds 4096 field elements of 31 bytes, behind a 4 byte length.
+    let full = vec![1; 4096 * 31 - 3];
+    assert_eq!(estimate_l1_cost(&full, PostingMode::Blob, 1).blobs, 2);
+}
@@ -0,0 +1,110 @@
+use alloy::primitives::{keccak256, Address};
+use program::{
+    hashing::HashScheme,
+    smt::{self, PartialTree, SparseMerkleTree},
+};
+
+fn leaf(seed: &str) -> [u8; 32] {
+    keccak256(seed).into()
+}
+
+fn tree(addresses: &[Address]) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::new();
+    for address in addresses {
+        tree.insert(address, leaf(&address.to_string()));
+    }
+    tree
+}
+
+fn addresses() -> Vec<Address> {
+    (0..40u8)
+        .map(|i| Address::from([i.wrapping_mul(37); 20]))
+        .chain([Address::with_last_byte(4), Address::with_last_byte(5)])
+        .collect()
+}
+
+#[test]
+fn proves_inclusion_and_exclusion() {
+    let addresses = addresses();
+    let mut tree = tree(&addresses);
+    let root = tree.root();
+
+    for address in &addresses {
+        let proof = tree.proof(address);
+        assert!(smt::verify(
+            &root,
+            address,
+            Some(&leaf(&address.to_string())),
+            &proof
+        ));
+        assert!(!smt::verify(&root, address, None, &proof));
+    }
+
+    let absent = Address::with_last_byte(6);
+    let proof = tree.proof(&absent);
+    assert!(smt::verify(&root, &absent, None, &proof));
+    assert!(!smt::verify(&root, &absent, Some(&leaf("forged")), &proof));
+}
+
+#[test]
+fn partial_tree_tracks_full_tree() {
+    let addresses = addresses();
+    let mut full = tree(&addresses);
+    let touched = [
+        addresses[0],
+        addresses[1],
+        Address::with_last_byte(4),
+        Address::with_last_byte(5),
+        Address::with_last_byte(6),
+    ];
+
+    let proofs: Vec<_> = touched.iter().map(|a| full.proof(a)).collect();
+    let mut partial =
+        PartialTree::from_proofs(touched.iter().zip(&proofs).map(|(address, proof)| {
+            let present = addresses.contains(address);
+            let leaf = present
+                .then(|| leaf(&address.to_string()))
+                .unwrap_or_default();
+            (address, leaf, proof)
+        }))
+        .unwrap();
+    assert_eq!(partial.root(), full.root());
+
+    for address in &touched[..4] {
+        partial.set(address, leaf("edited")).unwrap();
+        full.insert(address, leaf("edited"));
+    }
+    partial.set(&touched[4], [0; 32]).unwrap();
+    full.remove(&touched[4]);
+    assert_eq!(partial.root(), full.root());
+
+    assert!(partial.set(&addresses[2], leaf("edited")).is_err());
+}
+
+#[test]
+fn separated_scheme_keeps_leaves_and_nodes_apart() {
+    let addresses = addresses();
+    let scheme = HashScheme::SeparatedKeccak;
+    let mut separated = SparseMerkleTree::with_scheme(scheme);
+    for address in &addresses {
+        separated.insert(address, leaf(&address.to_string()));
+    }
+    let root = separated.root();
+    assert_ne!(root, tree(&addresses).root());
+    assert_ne!(
+        scheme.leaf(b"preimage"),
+        HashScheme::Keccak.leaf(b"preimage")
+    );
+
+    let address = &addresses[0];
+    let proof = separated.proof(address);
+    let hash = leaf(&address.to_string());
+    assert!(smt::verify_with(
+        scheme,
+        &root,
+        address,
+        Some(&hash),
+        &proof
+    ));
+    assert!(!smt::verify(&root, address, Some(&hash), &proof));
+}
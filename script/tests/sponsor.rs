@@ -0,0 +1,96 @@
+use alloy::{
+    primitives::{Address, U256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use alloy_rlp::{Decodable, Encodable};
+use program::{
+    builder::TxBuilder,
+    commitment::CommitmentVersion,
+    config::CanvasConfig,
+    fees::FeeSchedule,
+    signing::signing_hash,
+    sponsor::{recover_sponsor, sponsorship_hash, SponsorSignature},
+    Account, AccountDB, CanvasProcessor, InMemoryDB, SignedTransaction,
+};
+
+fn signed(signer: &PrivateKeySigner, sponsor: Option<&PrivateKeySigner>) -> SignedTransaction {
+    let domain = CanvasConfig::default().domain;
+    let tx = TxBuilder::new(Address::with_last_byte(1))
+        .append("a")
+        .build()
+        .unwrap();
+    let signature = signer.sign_hash_sync(&signing_hash(&tx, &domain)).unwrap();
+    let sponsor = sponsor.map(|sponsor| {
+        let signature = sponsor
+            .sign_hash_sync(&sponsorship_hash(&tx, &domain))
+            .unwrap();
+        SponsorSignature {
+            r: signature.r(),
+            s: signature.s(),
+            odd_y_parity: signature.v().y_parity(),
+        }
+    });
+
+    SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor,
+    }
+}
+
+#[test]
+fn sponsor_round_trips() {
+    let (signer, sponsor) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let domain = CanvasConfig::default().domain;
+
+    for tx in [signed(&signer, None), signed(&signer, Some(&sponsor))] {
+        let mut encoded = Vec::new();
+        tx.encode(&mut encoded);
+        assert_eq!(encoded.len(), tx.length());
+
+        let decoded = SignedTransaction::decode(&mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded.sponsor, tx.sponsor);
+        assert_eq!(
+            recover_sponsor(&decoded, &domain).unwrap(),
+            tx.sponsor.map(|_| sponsor.address())
+        );
+    }
+}
+
+#[test]
+fn sponsor_pays_the_fee() {
+    let (signer, sponsor) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let recipient = Address::with_last_byte(0xfe);
+    let db = InMemoryDB::default();
+    db.accounts.borrow_mut().insert(
+        sponsor.address(),
+        Account {
+            balance: U256::from(1_000),
+            ..Account::default()
+        },
+    );
+    let config = CanvasConfig {
+        commitment: CommitmentVersion::V1,
+        fees: FeeSchedule {
+            per_op: U256::from(10),
+            per_byte: U256::ZERO,
+            recipient,
+        },
+        ..CanvasConfig::default()
+    };
+    let mut processor = CanvasProcessor::with_config(&db, config);
+
+    assert!(processor.apply_transaction(&signed(&signer, None)).is_err());
+    processor
+        .apply_transaction(&signed(&signer, Some(&sponsor)))
+        .unwrap();
+
+    assert_eq!(db.get_account(&signer.address()).unwrap().nonce, 1);
+    assert_eq!(
+        db.get_account(&sponsor.address()).unwrap().balance,
+        U256::from(990)
+    );
+    assert_eq!(db.get_account(&recipient).unwrap().balance, U256::from(10));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use common::signed;
+use program::{
+    builder::TxBuilder,
+    commitment::{leaf_hash, CommitmentVersion},
+    config::CanvasConfig,
+    error::CanvasError,
+    snapshot,
+    undo::UndoError,
+    AccountDB, CanvasProcessor, InMemoryDB,
+};
+
+const CANVAS: Address = Address::with_last_byte(0xca);
+
+fn config(commitment: CommitmentVersion) -> CanvasConfig {
+    CanvasConfig {
+        commitment,
+        undo_window: 5,
+        ..CanvasConfig::default()
+    }
+}
+
+#[test]
+fn sender_reverts_their_last_edit() {
+    let (signer, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V11));
+
+    processor
+        .apply_transaction(&signed(&other, TxBuilder::new(CANVAS).append("--")))
+        .unwrap();
+    let edit = signed(&signer, TxBuilder::new(CANVAS).replace(1, 1, "abc"));
+    processor.apply_transaction(&edit).unwrap();
+    // Before and after the text the edit wrote, so it can still be reverted.
+    processor
+        .apply_transaction(&signed(
+            &other,
+            TxBuilder::new(CANVAS).nonce(1).insert(0, "x"),
+        ))
+        .unwrap();
+    processor
+        .apply_transaction(&signed(&other, TxBuilder::new(CANVAS).nonce(2).append("y")))
+        .unwrap();
+    assert_eq!(db.get_account(&CANVAS).unwrap().data, "x-abcy");
+
+    let revert = signed(
+        &signer,
+        TxBuilder::new(CANVAS).nonce(1).revert_own(edit.hash()),
+    );
+    processor.apply_transaction(&revert).unwrap();
+    let account = db.get_account(&CANVAS).unwrap();
+    assert_eq!(account.data, "x--y");
+
+    // Committed, and kept by snapshots.
+    let mut forged = account.clone();
+    forged.undos.clear();
+    assert_ne!(
+        leaf_hash(CommitmentVersion::V11, &CANVAS, &account),
+        leaf_hash(CommitmentVersion::V11, &CANVAS, &forged)
+    );
+    let restored = snapshot::decode(&db.snapshot_accounts().unwrap()).unwrap();
+    assert_eq!(restored[&CANVAS], account);
+
+    // The revert is the sender's last edit now, and reverting it redoes the edit.
+    processor
+        .apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(CANVAS).nonce(2).revert_own(revert.hash()),
+        ))
+        .unwrap();
+    assert_eq!(db.get_account(&CANVAS).unwrap().data, "x-abcy");
+}
+
+#[test]
+fn only_untouched_recent_edits_revert() {
+    let (signer, other) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V11));
+    let not_undoable = |result: Result<_, CanvasError>| {
+        matches!(result, Err(CanvasError::Undo(UndoError::NotUndoable(_))))
+    };
+
+    let edit = signed(&signer, TxBuilder::new(CANVAS).append("abc"));
+    processor.apply_transaction(&edit).unwrap();
+    assert!(matches!(
+        processor.apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(CANVAS)
+                .nonce(1)
+                .revert_own(edit.hash())
+                .append("d"),
+        )),
+        Err(CanvasError::Undo(UndoError::NotAlone))
+    ));
+    // Another sender has no edit of their own to revert.
+    assert!(not_undoable(processor.apply_transaction(&signed(
+        &other,
+        TxBuilder::new(CANVAS).revert_own(edit.hash()),
+    ))));
+
+    processor.batch.index = 5;
+    assert!(not_undoable(processor.apply_transaction(&signed(
+        &signer,
+        TxBuilder::new(CANVAS).nonce(1).revert_own(edit.hash()),
+    ))));
+
+    let edit = signed(&signer, TxBuilder::new(CANVAS).nonce(1).append("def"));
+    processor.apply_transaction(&edit).unwrap();
+    processor
+        .apply_transaction(&signed(&other, TxBuilder::new(CANVAS).replace(4, 1, "E")))
+        .unwrap();
+    assert!(not_undoable(processor.apply_transaction(&signed(
+        &signer,
+        TxBuilder::new(CANVAS).nonce(2).revert_own(edit.hash()),
+    ))));
+    assert_eq!(db.get_account(&CANVAS).unwrap().data, "abcdEf");
+}
+
+#[test]
+fn undo_window_needs_a_version_that_commits_it() {
+    let signer = PrivateKeySigner::random();
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::with_config(&db, config(CommitmentVersion::V10));
+
+    assert!(matches!(
+        processor.apply_transaction(&signed(&signer, TxBuilder::new(CANVAS).append("a"))),
+        Err(CanvasError::Undo(UndoError::NotCommitted))
+    ));
+}
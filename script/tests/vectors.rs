@@ -0,0 +1,50 @@
+use std::{fs, path::PathBuf, process::Command};
+
+// Runs `gen-vectors` into a directory of its own named after `name` and returns it.
+fn gen_vectors(name: &str, seed: &str) -> PathBuf {
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("vectors_{}", name));
+    let _ = fs::remove_dir_all(&out);
+    let output = Command::new(env!("CARGO_BIN_EXE_script"))
+        .arg("gen-vectors")
+        .arg("--out")
+        .arg(&out)
+        .args(["--seed", seed])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "gen-vectors failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    out
+}
+
+fn batches(dir: &PathBuf) -> Vec<serde_json::Value> {
+    serde_json::from_slice(&fs::read(dir.join("batches.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn a_seed_gives_the_same_vectors() {
+    let (first, again) = (gen_vectors("first", "7"), gen_vectors("again", "7"));
+    let mut files: Vec<_> = fs::read_dir(&first)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    files.sort();
+    assert!(files.iter().any(|file| file == "manifest.json"));
+    for file in files {
+        assert_eq!(
+            fs::read(first.join(&file)).unwrap(),
+            fs::read(again.join(&file)).unwrap(),
+            "{:?} differs",
+            file
+        );
+    }
+
+    // Another seed keeps the fixed batches and draws the rest anew.
+    let other = batches(&gen_vectors("other", "8"));
+    let first = batches(&first);
+    assert_eq!(first.len(), other.len());
+    assert_eq!(first[..2], other[..2]);
+    assert_ne!(first[2..], other[2..]);
+}
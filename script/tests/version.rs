@@ -0,0 +1,34 @@
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rlp::{encode, Decodable};
+use common::sign;
+use program::{
+    builder::TxBuilder, error::CanvasError, CanvasProcessor, InMemoryDB, SignedTransaction,
+    TX_VERSION_LATEST,
+};
+
+#[test]
+fn unknown_versions_are_rejected() {
+    let signer = PrivateKeySigner::random();
+    let builder = TxBuilder::new(Address::with_last_byte(1)).append("a");
+    let mut tx = builder.clone().version(TX_VERSION_LATEST).build().unwrap();
+    assert!(builder.version(TX_VERSION_LATEST + 1).build().is_err());
+
+    let latest = sign(&signer, tx.clone());
+    let decoded = SignedTransaction::decode(&mut encode(&latest).as_slice()).unwrap();
+    assert_eq!(decoded.hash(), latest.hash());
+
+    // Encoded with the layout of the latest version, which a later one need not share.
+    tx.version = TX_VERSION_LATEST + 1;
+    let unknown = sign(&signer, tx);
+    assert!(SignedTransaction::decode(&mut encode(&unknown).as_slice()).is_err());
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    assert!(matches!(
+        processor.apply_transaction(&unknown),
+        Err(CanvasError::UnknownVersion(version)) if version == TX_VERSION_LATEST + 1
+    ));
+    assert_eq!(db.accounts.borrow().len(), 0);
+}
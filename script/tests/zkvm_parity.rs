@@ -0,0 +1,188 @@
+//! Runs batches through the SP1 program in the executor, where keccak and secp256k1 recovery go
+//! through the precompiles, and checks that it commits exactly what the host computes. Needs the
+//! `prove` feature and the ELF from `cargo prove build`.
+#![cfg(feature = "prove")]
+
+mod common;
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner, sol_types::SolValue};
+use common::signed;
+use program::{
+    batch::encode_batch,
+    builder::TxBuilder,
+    chunked::{self, Encoding},
+    config::CanvasConfig,
+    limits::RateLimitState,
+    witness::Witness,
+    CanvasProcessor, InMemoryDB, Input, SignedTransaction, TX_VERSION_LEGACY,
+};
+use sp1_sdk::{ProverClient, SP1Stdin};
+
+const ELF: &str = "../program/elf/riscv32im-succinct-zkvm-elf";
+
+// The batch as the sequencer posts it.
+fn posted(transactions: &[SignedTransaction]) -> Vec<u8> {
+    encode_batch(transactions, CanvasConfig::default().codec, None).unwrap()
+}
+
+// A state with a few accounts and a batch of legacy and domain-signed edits on top of it.
+fn fixture() -> (InMemoryDB, Vec<SignedTransaction>) {
+    let signers: Vec<PrivateKeySigner> = (0..4).map(|_| PrivateKeySigner::random()).collect();
+    let canvas = Address::with_last_byte(0xca);
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    for (i, signer) in signers.iter().enumerate() {
+        let own = signer.address();
+        processor
+            .apply_transaction(&signed(
+                signer,
+                TxBuilder::new(own).insert(0, "0f".repeat(i + 1)),
+            ))
+            .unwrap();
+    }
+
+    let batch = signers
+        .iter()
+        .enumerate()
+        .map(|(i, signer)| {
+            let builder = TxBuilder::new(canvas).nonce(1).insert(0, "a1");
+            match i % 2 {
+                0 => signed(signer, builder.version(TX_VERSION_LEGACY)),
+                _ => signed(signer, builder),
+            }
+        })
+        .collect();
+
+    (db, batch)
+}
+
+// A canvas near the size limit and a batch of many small edits close to each other on it,
+// for comparing cycle counts of edit-heavy batches.
+fn edit_heavy_fixture() -> (InMemoryDB, Vec<SignedTransaction>) {
+    let signer = PrivateKeySigner::random();
+    let own = signer.address();
+
+    let db = InMemoryDB::default();
+    let mut processor = CanvasProcessor::new(&db);
+    processor
+        .apply_transaction(&signed(
+            &signer,
+            TxBuilder::new(own).append("0f".repeat(4000)),
+        ))
+        .unwrap();
+
+    let batch = (1..9)
+        .map(|nonce| {
+            let builder = (0..64).fold(TxBuilder::new(own).nonce(nonce), |builder, i| {
+                builder
+                    .replace(4000 + i * 8, 2, "a1")
+                    .insert(100 + i * 4, "b")
+            });
+            signed(&signer, builder)
+        })
+        .collect();
+
+    (db, batch)
+}
+
+// Executes the program on `input` and returns its cycle count, after checking it commits what
+// the host computes.
+fn assert_parity_with(input: &Input, encoding: Encoding) -> u64 {
+    let elf = std::fs::read(ELF).expect("build the program with `cargo prove build` first");
+    let expected = input.clone().process().unwrap().public_values.abi_encode();
+
+    let mut stdin = SP1Stdin::new();
+    let mut encoded = Vec::new();
+    chunked::write_input(input, encoding, &mut encoded).unwrap();
+    for buffer in encoded.chunks(chunked::FRAME_BYTES) {
+        stdin.write_slice(buffer);
+    }
+    let (public_values, report) = ProverClient::new().execute(&elf, stdin).run().unwrap();
+
+    assert_eq!(public_values.as_slice(), expected.as_slice());
+    report.total_instruction_count()
+}
+
+fn assert_parity(input: Input) {
+    let cycles = assert_parity_with(&input, Encoding::Bincode);
+    println!("{} cycles", cycles);
+}
+
+#[test]
+fn full_state_matches_host() {
+    let (db, transactions) = fixture();
+
+    assert_parity(Input {
+        transaction_data: posted(&transactions),
+        db,
+        config: CanvasConfig::default(),
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: RateLimitState::default(),
+        witness: None,
+        dictionary: None,
+    });
+}
+
+#[test]
+fn edit_heavy_matches_host() {
+    let (db, transactions) = edit_heavy_fixture();
+
+    assert_parity(Input {
+        transaction_data: posted(&transactions),
+        db,
+        config: CanvasConfig::default(),
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: RateLimitState::default(),
+        witness: None,
+        dictionary: None,
+    });
+}
+
+#[test]
+fn witness_matches_host() {
+    let (db, transactions) = fixture();
+    let config = CanvasConfig::default();
+
+    assert_parity(Input {
+        witness: Some(Witness::build(
+            &db,
+            &transactions,
+            Default::default(),
+            &config,
+        )),
+        transaction_data: posted(&transactions),
+        db: InMemoryDB::default(),
+        config,
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: RateLimitState::default(),
+        dictionary: None,
+    });
+}
+
+// Not a pass/fail benchmark: run with `--nocapture` to compare what decoding the accounts costs.
+#[test]
+fn borsh_input_matches_host() {
+    let (db, transactions) = fixture();
+    let input = Input {
+        transaction_data: posted(&transactions),
+        db,
+        config: CanvasConfig::default(),
+        batch: Default::default(),
+        deposits: Vec::new(),
+        forced_txs: Vec::new(),
+        limits: RateLimitState::default(),
+        witness: None,
+        dictionary: None,
+    };
+
+    let bincode = assert_parity_with(&input, Encoding::Bincode);
+    let borsh = assert_parity_with(&input, Encoding::Borsh);
+    println!("{} cycles with bincode, {} with borsh", bincode, borsh);
+}
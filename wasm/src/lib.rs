@@ -0,0 +1,288 @@
+use alloy_primitives::{Address, Signature, B256};
+use alloy_rlp::{Decodable, Encodable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+use canvas_core::builder::TxBuilder;
+use canvas_core::commitment::{leaf_hash, CommitmentVersion};
+use canvas_core::config::CanvasConfig;
+use canvas_core::edit::EditOp;
+use canvas_core::genesis::Genesis;
+use canvas_core::namespace::{canvas_address, CanvasProof};
+use canvas_core::proof::verify_proof;
+use canvas_core::receipt::Receipt;
+use canvas_core::signing::{is_legacy_signed, signing_hash, typed_transaction, SigningDomain};
+use canvas_core::smt::{self, SparseProof};
+use canvas_core::{
+    recover_address_from_tx, Account, CanvasProcessor, InMemoryDB, SignedTransaction, Transaction,
+};
+
+// What `buildTransaction` takes, named as in `TxBuilder`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TxRequest {
+    to: Address,
+    version: Option<u8>,
+    #[serde(default)]
+    nonce: u64,
+    #[serde(default)]
+    ops: Vec<EditOp>,
+    #[serde(default)]
+    extra: String,
+    #[serde(default)]
+    valid_until_batch: u64,
+    #[serde(default)]
+    valid_until_timestamp: u64,
+    #[serde(default)]
+    canvas: u64,
+    #[serde(default)]
+    access_list: Vec<Address>,
+}
+
+// What `previewTransaction` returns.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Preview {
+    receipt: Receipt,
+    accounts: BTreeMap<Address, Account>,
+}
+
+fn js(err: impl fmt::Display) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, JsError> {
+    hex::decode(hex.trim_start_matches("0x")).map_err(js)
+}
+
+fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn decode<T: Decodable>(hex: &str) -> Result<T, JsError> {
+    T::decode(&mut from_hex(hex)?.as_slice()).map_err(js)
+}
+
+fn encode(value: &impl Encodable) -> String {
+    let mut encoded = Vec::new();
+    value.encode(&mut encoded);
+    to_hex(encoded)
+}
+
+fn domain(chain_id: u64, verifying_contract: &str) -> Result<SigningDomain, JsError> {
+    Ok(SigningDomain {
+        chain_id,
+        verifying_contract: verifying_contract.parse().map_err(js)?,
+    })
+}
+
+/// Builds a transaction from JSON `{ to, version?, nonce?, ops?, extra?, validUntilBatch?,
+/// validUntilTimestamp?, canvas?, accessList? }` and returns its hex RLP, ready for `signingHash` or
+/// `typedData`.
+#[wasm_bindgen(js_name = buildTransaction)]
+pub fn build_transaction(request: &str) -> Result<String, JsError> {
+    let request: TxRequest = serde_json::from_str(request).map_err(js)?;
+    let mut builder = TxBuilder::new(request.to)
+        .nonce(request.nonce)
+        .ops(request.ops)
+        .extra(request.extra)
+        .valid_until_batch(request.valid_until_batch)
+        .valid_until_timestamp(request.valid_until_timestamp)
+        .canvas(request.canvas)
+        .access_list(request.access_list);
+    if let Some(version) = request.version {
+        builder = builder.version(version);
+    }
+
+    Ok(encode(&builder.build().map_err(js)?))
+}
+
+/// The prehash the sender signs for the hex RLP transaction `tx`.
+#[wasm_bindgen(js_name = signingHash)]
+pub fn signing_hash_js(
+    tx: &str,
+    chain_id: u64,
+    verifying_contract: &str,
+) -> Result<String, JsError> {
+    let tx: Transaction = decode(tx)?;
+    Ok(to_hex(signing_hash(
+        &tx,
+        &domain(chain_id, verifying_contract)?,
+    )))
+}
+
+/// `eth_signTypedData_v4` JSON for the hex RLP transaction `tx`. Legacy versions are
+/// personal-signed instead and have none.
+#[wasm_bindgen(js_name = typedData)]
+pub fn typed_data(tx: &str, chain_id: u64, verifying_contract: &str) -> Result<String, JsError> {
+    let tx: Transaction = decode(tx)?;
+    if is_legacy_signed(&tx) {
+        return Err(JsError::new(
+            "Legacy transactions are personal-signed over their payload hash",
+        ));
+    }
+    let eip712 = domain(chain_id, verifying_contract)?.eip712_domain();
+    let typed = typed_transaction(&tx);
+
+    let field = |name: &str, ty: &str| serde_json::json!({ "name": name, "type": ty });
+    let data = serde_json::json!({
+        "types": {
+            "EIP712Domain": [
+                field("name", "string"),
+                field("version", "string"),
+                field("chainId", "uint256"),
+                field("verifyingContract", "address"),
+            ],
+            "CanvasTransaction": [
+                field("to", "address"),
+                field("version", "uint8"),
+                field("nonce", "uint64"),
+                field("payloadHash", "bytes32"),
+            ],
+        },
+        "primaryType": "CanvasTransaction",
+        "domain": {
+            "name": eip712.name,
+            "version": eip712.version,
+            "chainId": eip712.chain_id,
+            "verifyingContract": eip712.verifying_contract,
+        },
+        "message": {
+            "to": typed.to,
+            "version": typed.version,
+            "nonce": typed.nonce,
+            "payloadHash": typed.payloadHash,
+        },
+    });
+
+    serde_json::to_string(&data).map_err(js)
+}
+
+/// Attaches a 65-byte hex `signature` to the hex RLP transaction `tx` and returns the hex RLP
+/// of the signed transaction, as `canvas_sendRawTransaction` takes it.
+#[wasm_bindgen(js_name = signTransaction)]
+pub fn sign_transaction(tx: &str, signature: &str) -> Result<String, JsError> {
+    let tx: Transaction = decode(tx)?;
+    let signature = Signature::try_from(from_hex(signature)?.as_slice()).map_err(js)?;
+
+    Ok(encode(&SignedTransaction {
+        tx,
+        r: signature.r(),
+        s: signature.s(),
+        odd_y_parity: signature.v().y_parity(),
+        sponsor: None,
+    }))
+}
+
+/// The sender of the hex RLP signed transaction `signed`, as the rollup recovers it.
+#[wasm_bindgen(js_name = recoverSender)]
+pub fn recover_sender(
+    signed: &str,
+    chain_id: u64,
+    verifying_contract: &str,
+) -> Result<String, JsError> {
+    let signed: SignedTransaction = decode(signed)?;
+    let sender =
+        recover_address_from_tx(&signed, &domain(chain_id, verifying_contract)?).map_err(js)?;
+    Ok(sender.to_string())
+}
+
+/// Checks a `canvas_getProof` proof, a JSON array of hex hashes, of the JSON `account` at
+/// `address` against `root` under commitment version `version`.
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof_js(
+    root: &str,
+    version: u8,
+    address: &str,
+    account: &str,
+    proof: &str,
+) -> Result<bool, JsError> {
+    let root: B256 = root.parse().map_err(js)?;
+    let version = CommitmentVersion::from_u8(version)
+        .ok_or_else(|| JsError::new(&format!("Unknown commitment version {}", version)))?;
+    let account: Account = serde_json::from_str(account).map_err(js)?;
+    let proof: Vec<B256> = serde_json::from_str(proof).map_err(js)?;
+    let proof: Vec<[u8; 32]> = proof.into_iter().map(B256::into).collect();
+
+    Ok(verify_proof(
+        &root.0,
+        version,
+        &address.parse().map_err(js)?,
+        &account,
+        &proof,
+    ))
+}
+
+/// Checks a `canvas_getSparseProof` proof of `address` against `root`: that it holds the JSON
+/// `account` there under commitment version `version`, or no account for `null`.
+#[wasm_bindgen(js_name = verifySparseProof)]
+pub fn verify_sparse_proof(
+    root: &str,
+    version: u8,
+    address: &str,
+    account: &str,
+    proof: &str,
+) -> Result<bool, JsError> {
+    let root: B256 = root.parse().map_err(js)?;
+    let version = CommitmentVersion::from_u8(version)
+        .ok_or_else(|| JsError::new(&format!("Unknown commitment version {}", version)))?;
+    let address: Address = address.parse().map_err(js)?;
+    let account: Option<Account> = serde_json::from_str(account).map_err(js)?;
+    let proof: SparseProof = serde_json::from_str(proof).map_err(js)?;
+    let leaf = account.map(|account| leaf_hash(version, &address, &account));
+
+    Ok(smt::verify_with(
+        version.hash_scheme(),
+        &root.0,
+        &address,
+        leaf.as_ref(),
+        &proof,
+    ))
+}
+
+/// The address the account `address` of canvas `canvas` is stored and proven under, for
+/// `canvas_getAccount` and `canvas_getSparseProof`.
+#[wasm_bindgen(js_name = canvasAddress)]
+pub fn canvas_address_js(canvas: u64, address: &str) -> Result<String, JsError> {
+    let address: Address = address.parse().map_err(js)?;
+    Ok(canvas_address(canvas, &address).to_string())
+}
+
+/// Checks a `canvas_getCanvasProof` proof against the state root `root` under commitment
+/// version `version`: that the sparse proofs of the canvas's accounts are against a root the
+/// state commits to.
+#[wasm_bindgen(js_name = verifyCanvasProof)]
+pub fn verify_canvas_proof(root: &str, version: u8, proof: &str) -> Result<bool, JsError> {
+    let root: B256 = root.parse().map_err(js)?;
+    let version = CommitmentVersion::from_u8(version)
+        .ok_or_else(|| JsError::new(&format!("Unknown commitment version {}", version)))?;
+    let proof: CanvasProof = serde_json::from_str(proof).map_err(js)?;
+    Ok(proof.verify_with(version.hash_scheme(), &root.0))
+}
+
+/// Applies the hex RLP signed transaction `signed` to the accounts in the JSON object
+/// `accounts`, keyed by address, with the processor the rollup runs under the JSON
+/// `CanvasConfig` `config`, or the default one for `null`. Accounts left out are taken to be empty. Returns JSON
+/// `{ receipt, accounts }` with every account after the transaction, or the error the rollup
+/// would reject it with.
+#[wasm_bindgen(js_name = previewTransaction)]
+pub fn preview_transaction(signed: &str, accounts: &str, config: &str) -> Result<String, JsError> {
+    let signed: SignedTransaction = decode(signed)?;
+    let accounts: BTreeMap<Address, Account> = serde_json::from_str(accounts).map_err(js)?;
+    let config: Option<CanvasConfig> = serde_json::from_str(config).map_err(js)?;
+
+    let db = InMemoryDB::from_genesis(&Genesis { accounts });
+    let mut processor = CanvasProcessor::with_config(db, config.unwrap_or_default());
+    let receipt = processor.apply_transaction(&signed).map_err(js)?;
+    let accounts = processor
+        .db
+        .accounts
+        .borrow()
+        .iter()
+        .map(|(address, account)| (*address, account.clone()))
+        .collect();
+
+    serde_json::to_string(&Preview { receipt, accounts }).map_err(js)
+}